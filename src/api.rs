@@ -1,25 +1,429 @@
 // src/api.rs
 
-use reqwest::blocking;
+// The Scavenger Mine API, async on a shared tokio runtime. Synchronous code (the mining loops, the
+// submitter and command handlers) runs a call with `block_on`; `fetch_statistics_many` fetches the
+// statistics of many wallets at once. `api_url` may list several endpoints ('--api-url a,b'): every call
+// fails over to the next one on a connection error or 5xx and returns to the first once it recovers.
+// Connection errors and 5xx are retried with jittered exponential backoff ('--api-retries'); every call
+// fails with an ApiError, whose `is_transient` tells what is worth trying again later.
+// A 429 or Retry-After holds every later call to that endpoint (see RATE LIMITS).
+// Challenge polls are conditional (ETag/Last-Modified), so an unchanged challenge isn't downloaded again.
+
+use crate::api_trace;
+use crate::error::ApiError;
+use crate::proxies::WalletClients;
+use reqwest::{Client, Response};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 // FIX: Import structs from the new module location
 use crate::data_types::{
-    TandCResponse, RegistrationReceipt, ChallengeData, ChallengeResponse,
+    TandCResponse, RegistrationReceipt, ChallengeData, ChallengeResponse, ChallengeHistoryPage, HistoricalChallenge,
     SolutionReceipt, DonateResponse, Statistics, StatisticsApiResponse, CliChallengeData, ApiErrorResponse
 };
 
+// Challenges asked for per GET /challenges page
+const HISTORY_PAGE_SIZE: usize = 100;
+// How many API calls `fetch_statistics_many` keeps in flight
+const MAX_CONCURRENT_REQUESTS: usize = 16;
+const RUNTIME_THREADS: usize = 2;
+
+// ===============================================
+// RUNTIME
+// ===============================================
+
+static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+
+/// Runs `future` (an API call) to completion on the shared runtime. Not for use from async code.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    RUNTIME.get_or_init(|| tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(RUNTIME_THREADS)
+        .thread_name("api")
+        .enable_all()
+        .build()
+        .expect("Could not start the API runtime"))
+        .block_on(future)
+}
+
+// ===============================================
+// ENDPOINT FAILOVER
+// ===============================================
+
+// A failed endpoint is skipped for this long, then tried again in its place in the list
+const ENDPOINT_RETRY: Duration = Duration::from_secs(60);
+
+// When each endpoint of an '--api-url' list failed last, keyed by the list
+static FAILED_ENDPOINTS: Mutex<Vec<(String, String, Instant)>> = Mutex::new(Vec::new());
+
+/// The endpoints an '--api-url' value lists.
+pub fn endpoints(api_url: &str) -> Vec<String> {
+    api_url.split(',').map(|url| url.trim().trim_end_matches('/').to_string()).filter(|url| !url.is_empty()).collect()
+}
+
+/// The endpoints of `api_url` in the order to try them: those that didn't fail lately as listed, then
+/// the others, least recently failed first.
+fn endpoint_order(api_url: &str) -> Vec<String> {
+    let endpoints = endpoints(api_url);
+    let failed = FAILED_ENDPOINTS.lock().unwrap_or_else(|e| e.into_inner());
+    let failed_at = |endpoint: &str| failed.iter()
+        .find(|(list, url, at)| list == api_url && url == endpoint && at.elapsed() < ENDPOINT_RETRY)
+        .map(|(_, _, at)| *at);
+    let (mut healthy, mut down): (Vec<String>, Vec<String>) = endpoints.into_iter().partition(|endpoint| failed_at(endpoint).is_none());
+    down.sort_by_key(|endpoint| failed_at(endpoint));
+    healthy.append(&mut down);
+    healthy
+}
+
+fn record_outcome(api_url: &str, endpoint: &str, ok: bool) {
+    let mut failed = FAILED_ENDPOINTS.lock().unwrap_or_else(|e| e.into_inner());
+    let known = failed.iter().position(|(list, url, _)| list == api_url && url == endpoint);
+    match (known, ok) {
+        (Some(index), true) => {
+            failed.remove(index);
+            println!("✅ API endpoint {} answers again.", endpoint);
+        },
+        (Some(index), false) => failed[index].2 = Instant::now(),
+        (None, false) => failed.push((api_url.to_string(), endpoint.to_string(), Instant::now())),
+        (None, true) => {},
+    }
+}
+
+/// A 5xx other than a closed submission window.
+fn is_server_failure(response: &Response) -> bool {
+    response.status().is_server_error() && closed_window_retry_after(response).is_none()
+}
+
+/// Sends the request `build` makes for an endpoint's base URL, trying the endpoints of `api_url` in turn
+/// until one answers without a connection error or 5xx.
+async fn send_to_any(api_url: &str, build: &impl Fn(&str) -> reqwest::RequestBuilder) -> Result<Response, String> {
+    let endpoints = endpoint_order(api_url);
+    for (index, endpoint) in endpoints.iter().enumerate() {
+        let result = send_to(endpoint, build).await;
+        let failure = match &result {
+            Err(e) => Some(e.clone()),
+            Ok(response) if is_server_failure(response) => Some(response.status().to_string()),
+            Ok(_) => None,
+        };
+        if endpoints.len() > 1 {
+            record_outcome(api_url, endpoint, failure.is_none());
+        }
+        match (failure, endpoints.get(index + 1)) {
+            (Some(e), Some(next)) => eprintln!("⚠️ API endpoint {} failed ({}). Failing over to {}.", endpoint, e, next),
+            _ => return result,
+        }
+    }
+    // No endpoint listed
+    send_to(api_url, build).await
+}
+
+// ===============================================
+// RETRIES
+// ===============================================
+
+// Cap of the exponential delay between retries
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// How often and how patiently a failed call is retried ('--api-retries', '--api-retry-delay').
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { retries: 2, base_delay: Duration::from_secs(1) }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay before retry `attempt` (0-based): the doubled base delay, capped, minus up to half of it
+    /// at random so that many instances don't retry in step.
+    fn delay(&self, attempt: u32) -> Duration {
+        let full = self.base_delay.saturating_mul(2u32.saturating_pow(attempt)).min(RETRY_MAX_DELAY);
+        let jitter = rand_core::RngCore::next_u32(&mut rand_core::OsRng) as f64 / u32::MAX as f64;
+        full.mul_f64(1.0 - jitter / 2.0)
+    }
+}
+
+static RETRY_POLICY: OnceLock<RetryPolicy> = OnceLock::new();
+
+/// Sets the retry policy of every API call. Only the first call has an effect.
+pub fn set_retry_policy(policy: RetryPolicy) {
+    let _ = RETRY_POLICY.set(policy);
+}
+
+/// Sends the request `build` makes for an endpoint's base URL (see `send_to_any`), retried under the
+/// retry policy. Writes carry an idempotency key, so every call is safe to resend.
+async fn send(api_url: &str, build: impl Fn(&str) -> reqwest::RequestBuilder) -> Result<Response, ApiError> {
+    let policy = RETRY_POLICY.get().copied().unwrap_or_default();
+    let mut attempt = 0;
+    loop {
+        let error = match send_to_any(api_url, &build).await {
+            Ok(response) if !is_server_failure(&response) => return Ok(response),
+            Ok(response) => status_error(response).await,
+            Err(e) => ApiError::Network(e),
+        };
+        if attempt == policy.retries {
+            return Err(error);
+        }
+        let delay = policy.delay(attempt);
+        attempt += 1;
+        eprintln!("⚠️ {}. Retry {}/{} in {:.1}s...", error.to_string().trim_end(), attempt, policy.retries, delay.as_secs_f64());
+        tokio::time::sleep(delay).await;
+    }
+}
+
+// ===============================================
+// RATE LIMITS
+// ===============================================
+
+// Longest wait a rate limit gets by holding and resending the call; a longer one is the caller's to handle
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(5 * 60);
+const RATE_LIMIT_RETRIES: usize = 3;
+// X-RateLimit-Reset values above this are Unix timestamps rather than seconds from now
+const RESET_EPOCH_THRESHOLD: u64 = 1_000_000_000;
+
+// Until when each endpoint asked not to be called, keyed by base URL
+static RATE_LIMITED_UNTIL: LazyLock<Mutex<HashMap<String, Instant>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Parses a Retry-After value: seconds, or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default())
+}
+
+/// How long the response asks for no further calls: its Retry-After on a 429 or 503, or the reset time
+/// of a used-up RateLimit-* / X-RateLimit-* quota.
+fn rate_limit_wait(response: &Response) -> Option<Duration> {
+    let header = |name: &str| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+    let retry_after = header("retry-after").and_then(parse_retry_after);
+    let status = response.status().as_u16();
+    if status == 429 || (status == 503 && retry_after.is_some()) {
+        return Some(retry_after.unwrap_or(Duration::from_secs(DEFAULT_WINDOW_RETRY_SECS)));
+    }
+    ["ratelimit", "x-ratelimit"].iter().find_map(|prefix| {
+        let remaining = header(&format!("{}-remaining", prefix))?.parse::<u64>().ok()?;
+        let reset = header(&format!("{}-reset", prefix))?.parse::<u64>().ok()?;
+        if remaining > 0 {
+            return None;
+        }
+        if reset > RESET_EPOCH_THRESHOLD {
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+            return Some(Duration::from_secs(reset.saturating_sub(now)));
+        }
+        Some(Duration::from_secs(reset))
+    })
+}
+
+/// Sends the request `build` makes for `endpoint` once the endpoint's rate limit allows, and resends it
+/// when rate limited for up to MAX_RATE_LIMIT_WAIT.
+async fn send_to(endpoint: &str, build: &impl Fn(&str) -> reqwest::RequestBuilder) -> Result<Response, String> {
+    let mut attempt = 0;
+    loop {
+        let until = RATE_LIMITED_UNTIL.lock().unwrap_or_else(|e| e.into_inner()).get(endpoint).copied();
+        if let Some(wait) = until.map(|until| until.saturating_duration_since(Instant::now())).filter(|wait| !wait.is_zero()) {
+            tokio::time::sleep(wait.min(MAX_RATE_LIMIT_WAIT)).await;
+        }
+
+        let response = execute(endpoint, build(endpoint)).await?;
+        let Some(wait) = rate_limit_wait(&response) else { return Ok(response) };
+        RATE_LIMITED_UNTIL.lock().unwrap_or_else(|e| e.into_inner()).insert(endpoint.to_string(), Instant::now() + wait);
+        let limited = matches!(response.status().as_u16(), 429 | 503);
+        if !limited || wait > MAX_RATE_LIMIT_WAIT || attempt == RATE_LIMIT_RETRIES {
+            return Ok(response);
+        }
+        attempt += 1;
+        println!("⏳ API endpoint {} is rate limiting (Status {}). Holding API calls for {}s.", endpoint, response.status().as_u16(), wait.as_secs());
+    }
+}
+
+/// Sends `request` to `endpoint` once, without retries or failover, and checks the endpoint's certificate
+/// against '--api-pin', recording the exchange if '--api-trace' is on.
+pub async fn execute(endpoint: &str, request: reqwest::RequestBuilder) -> Result<Response, String> {
+    if PIN_MISMATCHES.lock().unwrap_or_else(|e| e.into_inner()).iter().any(|refused| refused == endpoint) {
+        return Err(format!("Refusing to call {}: its TLS certificate did not match '--api-pin'", endpoint));
+    }
+    if !api_trace::enabled() {
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        check_pin(endpoint, &response)?;
+        return Ok(response);
+    }
+    let (client, request) = request.build_split();
+    let request = request.map_err(|e| e.to_string())?;
+    let (method, url, started) = (request.method().to_string(), request.url().clone(), Instant::now());
+    let response = match client.execute(request).await {
+        Ok(response) => response,
+        Err(e) => {
+            api_trace::record(&method, &url, Err(&e.to_string()), started.elapsed());
+            return Err(e.to_string());
+        },
+    };
+    check_pin(endpoint, &response)?;
+    // The body is read for the trace, then handed on in a response rebuilt around it
+    let (status, version, headers) = (response.status(), response.version(), response.headers().clone());
+    let body = match response.bytes().await {
+        Ok(body) => body,
+        Err(e) => {
+            api_trace::record(&method, &url, Err(&e.to_string()), started.elapsed());
+            return Err(e.to_string());
+        },
+    };
+    api_trace::record(&method, &url, Ok((status.as_u16(), &body)), started.elapsed());
+    let mut rebuilt = http::Response::new(body);
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+    Ok(Response::from(rebuilt))
+}
+
+// ===============================================
+// TLS
+// ===============================================
+
+/// '--api-ca-file' and '--api-pin': CA certificates the API clients trust besides the system's, and the
+/// SHA-256 fingerprints of which the API's certificate must have one (none: any valid certificate).
+#[derive(Debug, Clone, Default)]
+pub struct ApiTls {
+    pub ca_certificates: Vec<reqwest::Certificate>,
+    pub pins: Vec<[u8; 32]>,
+}
+
+static TLS: OnceLock<ApiTls> = OnceLock::new();
+
+// Endpoints whose certificate matched no pin; no further request goes to them
+static PIN_MISMATCHES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Sets the TLS trust of the API clients. Only the first call has an effect; set it before creating clients.
+pub fn set_tls(tls: ApiTls) {
+    let _ = TLS.set(tls);
+}
+
+pub fn tls() -> ApiTls {
+    TLS.get().cloned().unwrap_or_default()
+}
+
+/// Every certificate of a PEM bundle.
+pub fn load_ca_file(path: &str) -> Result<Vec<reqwest::Certificate>, String> {
+    let pem = std::fs::read_to_string(path).map_err(|e| format!("Could not read CA file '{}': {}", path, e))?;
+    const END: &str = "-----END CERTIFICATE-----";
+    let certificates = pem.split_inclusive(END)
+        .filter(|block| block.contains("-----BEGIN CERTIFICATE-----"))
+        .map(|block| reqwest::Certificate::from_pem(block.trim().as_bytes()).map_err(|e| format!("Invalid certificate in '{}': {}", path, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if certificates.is_empty() {
+        return Err(format!("CA file '{}' contains no PEM certificate.", path));
+    }
+    Ok(certificates)
+}
+
+/// Parses an '--api-pin' fingerprint: 64 hex digits, colons optional (as `openssl x509 -fingerprint -sha256`
+/// prints them).
+pub fn parse_pin(pin: &str) -> Result<[u8; 32], String> {
+    let digits: String = pin.trim().trim_start_matches("sha256:").chars().filter(|c| *c != ':').collect();
+    hex::decode(&digits).ok().and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| format!("Invalid '--api-pin' '{}': expected a SHA-256 fingerprint (64 hex digits, colons optional).", pin))
+}
+
+/// Checks the certificate `endpoint` answered with against the pins. A mismatch refuses the endpoint for the
+/// rest of the run.
+fn check_pin(endpoint: &str, response: &Response) -> Result<(), String> {
+    let Some(pins) = TLS.get().map(|tls| &tls.pins).filter(|pins| !pins.is_empty()) else { return Ok(()) };
+    let fingerprint = response.extensions().get::<reqwest::tls::TlsInfo>()
+        .and_then(|info| info.peer_certificate())
+        .map(|der| cryptoxide::hashing::sha2::Sha256::new().update(der).finalize());
+    if fingerprint.is_some_and(|fingerprint| pins.contains(&fingerprint)) {
+        return Ok(());
+    }
+    let presented = fingerprint.map_or("no TLS certificate".to_string(), |fingerprint| format!("certificate SHA-256 {}", hex::encode(fingerprint)));
+    eprintln!("❌ API endpoint {} presented {}, which matches no '--api-pin'. Refusing further calls to it.", endpoint, presented);
+    PIN_MISMATCHES.lock().unwrap_or_else(|e| e.into_inner()).push(endpoint.to_string());
+    Err(format!("TLS certificate of {} does not match '--api-pin'", endpoint))
+}
+
+// ===============================================
+// TIMEOUTS
+// ===============================================
+
+/// How long one kind of call may take to connect and then to answer (`[timeouts.*]` in the config file).
+/// reqwest sets the connect timeout per client, so the clients use the longest one configured; each call
+/// is cut after its own connect + read time.
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    pub connect: Duration,
+    pub read: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self { connect: Duration::from_secs(10), read: Duration::from_secs(20) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiTimeouts {
+    pub challenge: Timeouts,
+    pub statistics: Timeouts,
+    pub registration: Timeouts,
+    pub submission: Timeouts,
+}
+
+impl Timeouts {
+    /// The limit of a whole call.
+    pub fn total(&self) -> Duration {
+        self.connect + self.read
+    }
+}
+
+impl ApiTimeouts {
+    pub fn longest_connect(&self) -> Duration {
+        [self.challenge, self.statistics, self.registration, self.submission].iter().map(|t| t.connect).max().unwrap_or_default()
+    }
+}
+
+static TIMEOUTS: OnceLock<ApiTimeouts> = OnceLock::new();
+
+/// Sets the timeouts of the API calls. Only the first call has an effect; set them before creating clients.
+pub fn set_timeouts(timeouts: ApiTimeouts) {
+    let _ = TIMEOUTS.set(timeouts);
+}
+
+pub fn timeouts() -> ApiTimeouts {
+    TIMEOUTS.get().copied().unwrap_or_default()
+}
+
+// ===============================================
+// CHALLENGE CACHE
+// ===============================================
+
+// The last challenge body each endpoint sent with a validator, keyed by request URL
+struct CachedChallenge {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+static CHALLENGE_CACHE: LazyLock<Mutex<HashMap<String, CachedChallenge>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
 // --- API FUNCTIONS ---
 
 /// Fetches the T&C from the API, returning the full response object.
-pub fn fetch_tandc(client: &blocking::Client, api_url: &str) -> Result<TandCResponse, reqwest::Error> {
-    let url = format!("{}/TandC/1-0", api_url);
-    println!("-> Fetching Terms and Conditions from: {}", url);
+pub async fn fetch_tandc(client: &Client, api_url: &str) -> Result<TandCResponse, ApiError> {
+    println!("-> Fetching Terms and Conditions from: {}/TandC/1-0", api_url);
 
-    let response = client.get(url).send()?;
+    let response = send(api_url, |base| client.get(format!("{}/TandC/1-0", base))).await?;
 
-    let response = response.error_for_status()?;
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
 
-    response.json()
+    response.json().await.map_err(|e| ApiError::Decode(format!("T&C: {}", e)))
 }
 
 pub fn parse_cli_challenge_string(challenge_str: &str) -> Result<CliChallengeData, String> {
@@ -42,33 +446,33 @@ pub fn parse_cli_challenge_string(challenge_str: &str) -> Result<CliChallengeDat
 }
 
 
+// Sent with every write so a retry after a timeout is recognised as the same request
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
 /// Performs the POST /register call using key/signature arguments.
-pub fn register_address(
-    client: &blocking::Client,
+pub async fn register_address(
+    client: &Client,
     api_url: &str,
     address: &str,
     _tc_message: &str,
     signature: &str,
     pubkey: &str,
-) -> Result<(), reqwest::Error> {
-    let url = format!(
-        "{}/register/{}/{}/{}",
-        api_url,
-        address,
-        signature,
-        pubkey
-    );
-
+    idempotency_key: &str,
+) -> Result<(), ApiError> {
     println!("-> Attempting address registration for address: {}", address);
 
-    let response = client
-        .post(url)
+    let response = send(api_url, |base| client
+        .post(format!("{}/register/{}/{}/{}", base, address, signature, pubkey))
+        .timeout(timeouts().registration.total())
         .header("Content-Type", "application/json; charset=utf-8")
-        .send()?;
+        .header(IDEMPOTENCY_KEY_HEADER, idempotency_key))
+        .await?;
 
-    let response = response.error_for_status()?;
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
 
-    let registration_receipt: RegistrationReceipt = response.json()?;
+    let registration_receipt: RegistrationReceipt = response.json().await.map_err(|e| ApiError::Decode(format!("registration receipt: {}", e)))?;
     println!("✅ Address registered successfully.");
     println!("Receipt: {}", registration_receipt.registration_receipt);
 
@@ -76,8 +480,8 @@ pub fn register_address(
 }
 
 /// Helper to format a detailed error message from the API response body.
-fn format_detailed_api_error(err: ApiErrorResponse, status: reqwest::StatusCode) -> String {
-    let mut msg = format!("(Status {}) {}", status.as_u16(), err.message);
+fn format_detailed_api_error(err: ApiErrorResponse) -> String {
+    let mut msg = err.message;
 
     if let Some(e) = err.error {
         msg.push_str(&format!(" [Type: {}]", e));
@@ -88,118 +492,176 @@ fn format_detailed_api_error(err: ApiErrorResponse, status: reqwest::StatusCode)
     msg
 }
 
+// Wait used when a closed window response carries no Retry-After header
+const DEFAULT_WINDOW_RETRY_SECS: u64 = 30;
+
+/// Seconds to hold calls if the response is a rate limit or says the submission window is closed: 425 Too
+/// Early, 429 Too Many Requests, or 503 with a Retry-After header (a plain 503 is a transient server error).
+fn closed_window_retry_after(response: &Response) -> Option<u64> {
+    let retry_after = response.headers().get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_retry_after)
+        .map(|wait| wait.as_secs());
+    match response.status().as_u16() {
+        425 | 429 => Some(retry_after.unwrap_or(DEFAULT_WINDOW_RETRY_SECS)),
+        503 => retry_after,
+        _ => None,
+    }
+}
+
+/// The error for a response the call doesn't accept: a rate limit or closed submission window with its
+/// wait, or the status with the API's error details or raw body.
+async fn status_error(response: Response) -> ApiError {
+    let status = response.status().as_u16();
+    let retry_after = closed_window_retry_after(&response);
+    let body = response.text().await.unwrap_or_else(|_| "(unreadable body)".to_string());
+    let message = match serde_json::from_str::<ApiErrorResponse>(&body) {
+        Ok(err) => format_detailed_api_error(err),
+        Err(_) => body,
+    };
+    match retry_after {
+        Some(secs) => ApiError::RateLimited { status, retry_after: Duration::from_secs(secs), message },
+        None => ApiError::HttpStatus { status, message },
+    }
+}
+
 /// Performs the POST /solution call.
-pub fn submit_solution(
-    client: &blocking::Client,
+pub async fn submit_solution(
+    client: &Client,
     api_url: &str,
     address: &str,
     challenge_id: &str,
     nonce: &str,
-) -> Result<serde_json::Value, String> {
-    let url = format!(
-        "{}/solution/{}/{}/{}",
-        api_url,
-        address,
-        challenge_id,
-        nonce
-    );
-
+    idempotency_key: &str,
+) -> Result<serde_json::Value, ApiError> {
     println!("-> Submitting solution (Nonce: {})", nonce);
 
-    let response = client
-        .post(url)
+    let response = send(api_url, |base| client
+        .post(format!("{}/solution/{}/{}/{}", base, address, challenge_id, nonce))
+        .timeout(timeouts().submission.total())
         .header("Content-Type", "application/json; charset=utf-8")
-        .send().map_err(|e| format!("Network/Client Error: {}", e))?;
+        .header(IDEMPOTENCY_KEY_HEADER, idempotency_key))
+        .await?;
 
-    let status = response.status();
-
-    if status.is_success() {
+    if response.status().is_success() {
         // Successful submission
-        let receipt: SolutionReceipt = response.json().map_err(|e| format!("Failed to parse successful receipt JSON: {}", e))?;
+        let receipt: SolutionReceipt = response.json().await.map_err(|e| ApiError::Decode(format!("solution receipt: {}", e)))?;
         Ok(receipt.crypto_receipt)
     } else {
-        // Submission failed (4xx or 5xx)
-        let body_text = response.text().unwrap_or_else(|_| format!("Could not read response body for status {}", status));
-
-        let api_error: Result<ApiErrorResponse, _> = serde_json::from_str(&body_text);
-
-        match api_error {
-            Ok(err) => {
-                // FIX: Use all error fields for detailed reporting
-                Err(format!("API Validation Failed: {}", format_detailed_api_error(err, status)))
-            }
-            Err(_) => {
-                // API returned a non-structured error (e.g., plain text or unreadable JSON)
-                Err(format!("HTTP Error {} with unparseable body: {}", status.as_u16(), body_text))
-            }
+        // Submission failed: closed window, already solved, validation (4xx) or 5xx
+        match status_error(response).await {
+            ApiError::HttpStatus { status: 409, message } => Err(ApiError::AlreadySolved(message)),
+            error => Err(error),
         }
     }
 }
 
 /// Performs the POST /donate_to call.
-pub fn donate_to(
-    client: &blocking::Client,
+pub async fn donate_to(
+    client: &Client,
     api_url: &str,
     original_address: &str,
     destination_address: &str,
     donation_signature: &str,
-) -> Result<String, String> {
-
-    let url = format!(
-        "{}/donate_to/{}/{}/{}",
-        api_url,
-        destination_address,
-        original_address,
-        donation_signature
-    );
+    idempotency_key: &str,
+) -> Result<String, ApiError> {
 
     println!("-> Donating funds from {} to {}", original_address, destination_address);
 
-    let response = client
-        .post(&url)
+    let response = send(api_url, |base| client
+        .post(format!("{}/donate_to/{}/{}/{}", base, destination_address, original_address, donation_signature))
         .header("Content-Type", "application/json; charset=utf-8")
-        .json(&serde_json::json!({}))
-        .send().map_err(|e| format!("Network/Client Error: {}", e))?;
-
-    let status = response.status();
+        .header(IDEMPOTENCY_KEY_HEADER, idempotency_key)
+        .json(&serde_json::json!({})))
+        .await?;
 
-    if status.is_success() {
-        let donation_response: DonateResponse = response.json().map_err(|e| format!("Failed to parse successful donation JSON: {}", e))?;
+    if response.status().is_success() {
+        let donation_response: DonateResponse = response.json().await.map_err(|e| ApiError::Decode(format!("donation: {}", e)))?;
         println!("✅ Donation successful. Donation ID: {}", donation_response.donation_id);
         Ok(donation_response.donation_id)
     } else {
-        let body_text = response.text().unwrap_or_else(|_| format!("Could not read response body for status {}", status));
-        let api_error: Result<ApiErrorResponse, _> = serde_json::from_str(&body_text);
-
-        match api_error {
-            Ok(err) => {
-                // FIX: Use all error fields for detailed reporting
-                Err(format!("Donation Failed: {}", format_detailed_api_error(err, status)))
-            }
-            Err(_) => {
-                Err(format!("HTTP Error {} with unparseable body: {}", status.as_u16(), body_text))
-            }
-        }
+        Err(status_error(response).await)
     }
 }
 
 /// Fetches the raw Challenge Response object from the API.
-pub fn fetch_challenge_status(client: &blocking::Client, api_url: &str) -> Result<ChallengeResponse, String> {
-    let url = format!("{}/challenge", api_url);
+pub async fn fetch_challenge_status(client: &Client, api_url: &str) -> Result<ChallengeResponse, ApiError> {
+    // The endpoint the final request went to (see `send`)
+    let answered = Mutex::new(String::new());
+    let response = send(api_url, |base| {
+        let url = format!("{}/challenge", base);
+        let mut request = client.get(&url).timeout(timeouts().challenge.total());
+        if let Some(cached) = CHALLENGE_CACHE.lock().unwrap_or_else(|e| e.into_inner()).get(&url) {
+            if let Some(etag) = &cached.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        *answered.lock().unwrap_or_else(|e| e.into_inner()) = url;
+        request
+    }).await?;
+
+    let url = answered.into_inner().unwrap_or_else(|e| e.into_inner());
+    let body = if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        CHALLENGE_CACHE.lock().unwrap_or_else(|e| e.into_inner()).get(&url).map(|cached| cached.body.clone())
+            .ok_or_else(|| ApiError::Decode("challenge: 304 Not Modified to an unconditional request".to_string()))?
+    } else if response.status().is_success() {
+        let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let (etag, last_modified) = (header(reqwest::header::ETAG), header(reqwest::header::LAST_MODIFIED));
+        let body = response.text().await.map_err(|e| ApiError::Network(format!("Could not read the challenge response: {}", e)))?;
+        if etag.is_some() || last_modified.is_some() {
+            CHALLENGE_CACHE.lock().unwrap_or_else(|e| e.into_inner())
+                .insert(url, CachedChallenge { etag, last_modified, body: body.clone() });
+        }
+        body
+    } else {
+        return Err(status_error(response).await);
+    };
 
-    let response = client.get(url).send().map_err(|e| format!("API request failed: {}", e))?;
+    let challenge_response: ChallengeResponse = serde_json::from_str(&body).map_err(|e| ApiError::Decode(format!("challenge: {}", e)))?;
+    Ok(challenge_response)
+}
 
-    if !response.status().is_success() {
-        return Err(format!("Challenge API returned non-success status: {}", response.status()));
+/// Fetches the published challenges page by page, oldest first, up to `limit`. The filters are passed to
+/// the API and applied again here, for an API that ignores them.
+pub async fn fetch_challenge_history(client: &Client, api_url: &str, day: Option<u8>, status: Option<&str>, limit: usize) -> Result<Vec<HistoricalChallenge>, ApiError> {
+    let mut challenges = Vec::new();
+    let mut page = 1;
+    loop {
+        let response = send(api_url, |base| {
+            let mut request = client.get(format!("{}/challenges", base))
+                .timeout(timeouts().challenge.total())
+                .query(&[("page", page.to_string()), ("limit", HISTORY_PAGE_SIZE.to_string())]);
+            if let Some(day) = day {
+                request = request.query(&[("day", day.to_string())]);
+            }
+            if let Some(status) = status {
+                request = request.query(&[("status", status)]);
+            }
+            request
+        }).await?;
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+        let body: ChallengeHistoryPage = response.json().await.map_err(|e| ApiError::Decode(format!("challenges page {}: {}", page, e)))?;
+
+        let received = body.challenges.len();
+        challenges.extend(body.challenges.into_iter()
+            .filter(|challenge| day.is_none_or(|day| challenge.day == day))
+            .filter(|challenge| status.is_none_or(|status| challenge.status() == status)));
+        if challenges.len() >= limit || received == 0 || body.page >= body.total_pages {
+            challenges.truncate(limit);
+            return Ok(challenges);
+        }
+        page = body.page + 1;
     }
-
-    let challenge_response: ChallengeResponse = response.json().map_err(|e| format!("JSON parsing failed: {}", e))?;
-    Ok(challenge_response)
 }
 
 /// Fetches and validates the active challenge parameters, returning data only if active.
-pub fn get_active_challenge_data(client: &blocking::Client, api_url: &str) -> Result<ChallengeData, String> {
-    let challenge_response = fetch_challenge_status(client, api_url)?;
+pub async fn get_active_challenge_data(client: &Client, api_url: &str) -> Result<ChallengeData, ApiError> {
+    let challenge_response = fetch_challenge_status(client, api_url).await?;
 
     match challenge_response.code.as_str() {
         "active" => {
@@ -208,33 +670,28 @@ pub fn get_active_challenge_data(client: &blocking::Client, api_url: &str) -> Re
         }
         "before" => {
             let start_time = challenge_response.starts_at.unwrap_or_default();
-            Err(format!("MINING IS NOT YET ACTIVE. Starts at: {}", start_time))
+            Err(ApiError::NotActive(format!("MINING IS NOT YET ACTIVE. Starts at: {}", start_time)))
         }
         "after" => {
-            Err("MINING PERIOD HAS ENDED.".to_string())
+            Err(ApiError::NotActive("MINING PERIOD HAS ENDED.".to_string()))
         }
         _ => {
-            Err(format!("Received unexpected challenge code: {}", challenge_response.code))
+            Err(ApiError::Decode(format!("unexpected challenge code: {}", challenge_response.code)))
         }
     }
 }
 
 
-// ... (existing API FUNCTIONS)
-
-pub fn fetch_statistics(client: &blocking::Client, api_url: &str, address: &str) -> Result<Statistics, String> {
-    let url = format!("{}/statistics/{}", api_url, address);
+pub async fn fetch_statistics(client: &Client, api_url: &str, address: &str) -> Result<Statistics, ApiError> {
     println!("\n📊 Fetching statistics for address: {}", address);
 
-    let response = client.get(url)
-        .header("Accept", "application/json")
-        .send()
-        .map_err(|e| format!("Network/Client Error: {}", e))?;
-
-    let status = response.status();
+    let response = send(api_url, |base| client.get(format!("{}/statistics/{}", base, address))
+        .timeout(timeouts().statistics.total())
+        .header("Accept", "application/json"))
+        .await?;
 
-    if status.is_success() {
-        let api_data: StatisticsApiResponse = response.json().map_err(|e| format!("JSON parsing failed: {}", e))?;
+    if response.status().is_success() {
+        let api_data: StatisticsApiResponse = response.json().await.map_err(|e| ApiError::Decode(format!("statistics: {}", e)))?;
 
         // Transform nested API response into the desired flat Statistics struct
         Ok(Statistics {
@@ -248,17 +705,107 @@ pub fn fetch_statistics(client: &blocking::Client, api_url: &str, address: &str)
             night_allocation: api_data.local.night_allocation,
         })
     } else {
-        let body_text = response.text().unwrap_or_else(|_| format!("(Could not read response body for status {})", status));
-        let api_error: Result<ApiErrorResponse, _> = serde_json::from_str(&body_text);
+        Err(status_error(response).await)
+    }
+}
 
-        match api_error {
-            Ok(err) => {
-                // FIX: Use all error fields for detailed reporting
-                Err(format!("API Error: {}", format_detailed_api_error(err, status)))
-            }
-            Err(_) => {
-                Err(format!("HTTP Error {} with unparseable body: {}", status.as_u16(), body_text))
-            }
+/// The STAR one solution earns on each mining day so far, day 1 first.
+pub async fn fetch_work_to_star_rate(client: &Client, api_url: &str) -> Result<Vec<f64>, ApiError> {
+    let response = send(api_url, |base| client.get(format!("{}/work_to_star_rate", base))
+        .timeout(timeouts().statistics.total())
+        .header("Accept", "application/json"))
+        .await?;
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+    response.json().await.map_err(|e| ApiError::Decode(format!("work to star rate: {}", e)))
+}
+
+/// Statistics of every address in `addresses`, fetched concurrently, e.g. for a pool of wallets.
+pub async fn fetch_statistics_many(clients: &WalletClients, api_url: &str, addresses: &[String]) -> HashMap<String, Result<Statistics, ApiError>> {
+    let mut pending = addresses.iter().cloned();
+    let mut running = tokio::task::JoinSet::new();
+    let mut results = HashMap::new();
+    loop {
+        while running.len() < MAX_CONCURRENT_REQUESTS {
+            let Some(address) = pending.next() else { break };
+            let (client, api_url) = (clients.for_wallet(&address).clone(), api_url.to_string());
+            running.spawn(async move {
+                let stats = fetch_statistics(&client, &api_url, &address).await;
+                (address, stats)
+            });
         }
+        match running.join_next().await {
+            Some(Ok((address, stats))) => { results.insert(address, stats); },
+            Some(Err(e)) => eprintln!("⚠️ Statistics request failed: {}", e),
+            None => return results,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serves `scenario` (see mock_api) and returns its URL.
+    fn mock_api(name: &str, scenario: &str) -> String {
+        let path = std::env::temp_dir().join(format!("shadow-harvester-api-{}-{}.json", name, std::process::id()));
+        std::fs::write(&path, scenario).unwrap();
+        let api_url = crate::mock_api::spawn_mock_api(path.to_str().unwrap()).unwrap();
+        let _ = std::fs::remove_file(&path);
+        api_url
+    }
+
+    #[test]
+    fn endpoints_that_keep_failing_move_to_the_back() {
+        let api_url = "https://a.test,https://b.test,https://c.test";
+        assert_eq!(endpoint_order(api_url), ["https://a.test", "https://b.test", "https://c.test"]);
+
+        record_outcome(api_url, "https://a.test", false);
+        assert_eq!(endpoint_order(api_url), ["https://b.test", "https://c.test", "https://a.test"]);
+
+        // The endpoint that failed longest ago is tried first among the failed ones
+        record_outcome(api_url, "https://b.test", false);
+        assert_eq!(endpoint_order(api_url), ["https://c.test", "https://a.test", "https://b.test"]);
+        record_outcome(api_url, "https://a.test", false);
+        assert_eq!(endpoint_order(api_url), ["https://c.test", "https://b.test", "https://a.test"]);
+
+        // A recovered endpoint gets its place in the list back
+        record_outcome(api_url, "https://a.test", true);
+        assert_eq!(endpoint_order(api_url), ["https://a.test", "https://c.test", "https://b.test"]);
+    }
+
+    #[test]
+    fn fails_over_to_the_next_endpoint() {
+        let mock = mock_api("failover", "{}");
+        // Nothing listens on port 1
+        let api_url = format!("http://127.0.0.1:1,{}", mock);
+        let tandc = block_on(fetch_tandc(&Client::new(), &api_url)).unwrap();
+        assert_eq!(tandc.version, "1-0");
+        assert_eq!(endpoint_order(&api_url), [mock, "http://127.0.0.1:1".to_string()]);
+    }
+
+    #[test]
+    fn retries_server_errors_but_not_rejections() {
+        let api_url = mock_api("retries", r#"{ "responses": {
+            "statistics": [{ "status": 503 }, { "status": 200 }],
+            "register": [{ "status": 400, "body": { "message": "bad sig", "statusCode": 400 } }, { "status": 201 }]
+        } }"#);
+        let client = Client::new();
+
+        assert!(block_on(fetch_statistics(&client, &api_url, "addr1test")).is_ok());
+
+        // Not retried: a retry would have been registered
+        let rejected = block_on(register_address(&client, &api_url, "addr1test", "", "sig", "key", "id")).unwrap_err();
+        assert!(matches!(rejected, ApiError::HttpStatus { status: 400, .. }), "{:?}", rejected);
+        assert!(!rejected.is_transient());
+    }
+
+    #[test]
+    fn a_closed_submission_window_is_a_transient_rate_limit() {
+        let api_url = mock_api("window", r#"{ "responses": { "statistics": [{ "status": 425, "retry_after": 90 }] } }"#);
+        let error = block_on(fetch_statistics(&Client::new(), &api_url, "addr1test")).unwrap_err();
+        assert!(matches!(error, ApiError::RateLimited { status: 425, retry_after, .. } if retry_after == Duration::from_secs(90)), "{:?}", error);
+        assert!(error.is_transient());
     }
 }
@@ -0,0 +1,223 @@
+// src/api_check.rs
+
+// 'api-check': calls each route the miner uses on every '--api-url' endpoint, once and without retries, and
+// reports status and latency with a diagnosis of what went wrong: WAF blocks, a refused User-Agent, rate
+// limits, TLS, DNS and timeouts. The solution route gets a dry run with an address that can't be registered,
+// which a healthy API rejects with a JSON error, so nothing is ever credited. Any failed check makes the
+// command fail, so it can gate a fleet deployment.
+
+use crate::api;
+use crate::data_types::ChallengeResponse;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, RequestBuilder};
+use std::time::{Duration, Instant};
+
+// Calls slower than this are flagged
+const SLOW_CALL: Duration = Duration::from_secs(2);
+// Address of the solution dry run, and of the statistics call without '--address'; the API can't know it
+const PROBE_ADDRESS: &str = "addr1_api_check";
+const PROBE_NONCE: &str = "0000000000000000";
+// Sent once the miner's User-Agent is refused, to tell a User-Agent block from an IP block
+const PLAIN_USER_AGENT: &str = concat!("shadow-harvester/", env!("CARGO_PKG_VERSION"));
+// Response characters shown in a diagnosis
+const BODY_EXCERPT_CHARS: usize = 120;
+
+struct Outcome {
+    status: u16,
+    headers: HeaderMap,
+    body: String,
+    latency: Duration,
+}
+
+enum Verdict {
+    Pass(String),
+    Warn(String, String),
+    Fail(String, String),
+}
+
+// ===============================================
+// PROBES
+// ===============================================
+
+async fn probe(endpoint: &str, request: RequestBuilder) -> Result<Outcome, (String, Duration)> {
+    let started = Instant::now();
+    let response = api::execute(endpoint, request).await.map_err(|e| (e, started.elapsed()))?;
+    let (status, headers) = (response.status().as_u16(), response.headers().clone());
+    let body = response.text().await.map_err(|e| (e.to_string(), started.elapsed()))?;
+    Ok(Outcome { status, headers, body, latency: started.elapsed() })
+}
+
+fn excerpt(body: &str) -> String {
+    let body = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    match body.char_indices().nth(BODY_EXCERPT_CHARS) {
+        Some((cut, _)) => format!("{}...", &body[..cut]),
+        None => body,
+    }
+}
+
+fn is_html(outcome: &Outcome) -> bool {
+    let content_type = outcome.headers.get(reqwest::header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    content_type.contains("text/html") || outcome.body.trim_start().starts_with('<')
+}
+
+/// The WAF or CDN that answered, recognised by its headers or block page.
+fn waf_name(outcome: &Outcome) -> Option<&'static str> {
+    let header = |name: &str| outcome.headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_ascii_lowercase);
+    let body = outcome.body.to_ascii_lowercase();
+    if outcome.headers.contains_key("cf-ray") || header("server").is_some_and(|s| s.contains("cloudflare")) {
+        Some("Cloudflare")
+    } else if outcome.headers.keys().any(|name| name.as_str().starts_with("x-amzn-waf")) || body.contains("aws waf") {
+        Some("AWS WAF")
+    } else if header("server").is_some_and(|s| s.contains("akamai")) {
+        Some("Akamai")
+    } else if body.contains("captcha") || body.contains("access denied") || body.contains("request blocked") {
+        Some("a WAF")
+    } else {
+        None
+    }
+}
+
+/// What a request that never got a response tells about the network path.
+fn diagnose_error(error: &str) -> String {
+    let lower = error.to_ascii_lowercase();
+    if lower.contains("api-pin") {
+        "The certificate isn't the pinned one: check '--api-pin', or a TLS-intercepting proxy is in the path.".to_string()
+    } else if lower.contains("certificate") || lower.contains("ssl") || lower.contains("tls") {
+        "TLS verification failed. Behind a TLS-intercepting proxy, trust its CA with '--api-ca-file'.".to_string()
+    } else if lower.contains("dns") || lower.contains("resolve") || lower.contains("lookup") {
+        "The host name didn't resolve. Check DNS, the '--api-url' host or '--proxy'.".to_string()
+    } else if lower.contains("timed out") || lower.contains("timeout") {
+        "The call timed out. Check the route to the API, or raise the [timeouts] in the config file.".to_string()
+    } else if lower.contains("refused") {
+        "The connection was refused. Check the '--api-url' port, or '--proxy'.".to_string()
+    } else {
+        "The API couldn't be reached. Check the network, '--proxy' and 'doctor'.".to_string()
+    }
+}
+
+/// The verdict on a response whose expected statuses are `expected`.
+fn diagnose(outcome: &Outcome, expected: impl Fn(u16) -> bool) -> Verdict {
+    let summary = format!("{} in {} ms", outcome.status, outcome.latency.as_millis());
+    let status = outcome.status;
+    if status == 429 {
+        let retry_after = outcome.headers.get(reqwest::header::RETRY_AFTER).and_then(|v| v.to_str().ok()).unwrap_or("not given");
+        return Verdict::Warn(summary, format!("Rate limited (Retry-After: {}). Stagger the fleet's starts or spread wallets with '--proxies-file'.", retry_after));
+    }
+    if matches!(status, 401 | 403 | 406) || (is_html(outcome) && !expected(status)) {
+        let blocker = waf_name(outcome).map_or("The API".to_string(), |name| format!("{} in front of the API", name));
+        return Verdict::Fail(summary, format!("{} blocked the request: {}", blocker, excerpt(&outcome.body)));
+    }
+    if status >= 500 {
+        return Verdict::Fail(summary, format!("Server error: {}", excerpt(&outcome.body)));
+    }
+    if !expected(status) {
+        return Verdict::Fail(summary, format!("Unexpected status: {}", excerpt(&outcome.body)));
+    }
+    if is_html(outcome) {
+        return Verdict::Fail(summary, format!("Answered with HTML instead of JSON, e.g. a captive portal or proxy error page: {}", excerpt(&outcome.body)));
+    }
+    if outcome.latency > SLOW_CALL {
+        return Verdict::Warn(summary, format!("Slower than {}s: expect late submissions near the end of a challenge.", SLOW_CALL.as_secs()));
+    }
+    Verdict::Pass(summary)
+}
+
+// ===============================================
+// API CHECK COMMAND
+// ===============================================
+
+/// Checks every route on every endpoint of `api_url`. Statistics are fetched for `address` when given.
+pub fn run_api_check(client: &Client, api_url: &str, address: Option<&str>) -> Result<(), String> {
+    println!("\n==============================================");
+    println!("🩺 Shadow Harvester: API Check");
+    println!("==============================================");
+    let (mut failures, mut warnings) = (0, 0);
+    for endpoint in api::endpoints(api_url) {
+        println!("** {} **", endpoint);
+        for (label, verdict) in api::block_on(check_endpoint(client, &endpoint, address)) {
+            match verdict {
+                Verdict::Pass(summary) => println!("  {:<17} ✅ {}", label, summary),
+                Verdict::Warn(summary, hint) => {
+                    warnings += 1;
+                    println!("  {:<17} ⚠️ {}\n  {:<17}    {}", label, summary, "", hint);
+                },
+                Verdict::Fail(summary, hint) => {
+                    failures += 1;
+                    println!("  {:<17} ❌ {}\n  {:<17}    {}", label, summary, "", hint);
+                },
+            }
+        }
+        println!("----------------------------------------------");
+    }
+    if failures > 0 {
+        return Err(format!("API check failed: {} failed and {} warning check(s).", failures, warnings));
+    }
+    println!("✅ API check passed{}.", if warnings > 0 { format!(" with {} warning(s)", warnings) } else { String::new() });
+    Ok(())
+}
+
+async fn check_endpoint(client: &Client, endpoint: &str, address: Option<&str>) -> Vec<(&'static str, Verdict)> {
+    let timeouts = api::timeouts();
+    let mut verdicts = Vec::new();
+
+    // --- Challenge, retried with a plain User-Agent if refused ---
+    let challenge_url = format!("{}/challenge", endpoint);
+    let mut challenge_id = None;
+    let verdict = match probe(endpoint, client.get(&challenge_url).timeout(timeouts.challenge.total())).await {
+        Err((e, latency)) => Verdict::Fail(format!("no response after {} ms: {}", latency.as_millis(), e), diagnose_error(&e)),
+        Ok(outcome) => match diagnose(&outcome, |status| status == 200) {
+            Verdict::Fail(summary, hint) if matches!(outcome.status, 401 | 403 | 406) => {
+                let plain = client.get(&challenge_url).timeout(timeouts.challenge.total()).header(reqwest::header::USER_AGENT, PLAIN_USER_AGENT);
+                let hint = match probe(endpoint, plain).await {
+                    Ok(retry) if retry.status == 200 => format!("{}\n  {:<17}    The same call with User-Agent '{}' succeeded: the miner's User-Agent is refused.", hint, "", PLAIN_USER_AGENT),
+                    _ => format!("{}\n  {:<17}    A plain User-Agent is refused too: the IP is likely blocked. Try another '--proxy'.", hint, ""),
+                };
+                Verdict::Fail(summary, hint)
+            },
+            Verdict::Fail(summary, hint) => Verdict::Fail(summary, hint),
+            verdict => match serde_json::from_str::<ChallengeResponse>(&outcome.body) {
+                Ok(status) => {
+                    challenge_id = status.challenge.map(|challenge| challenge.challenge_id);
+                    verdict
+                },
+                Err(e) => Verdict::Fail(format!("{} in {} ms", outcome.status, outcome.latency.as_millis()), format!("Not challenge JSON ({}): {}", e, excerpt(&outcome.body))),
+            },
+        },
+    };
+    verdicts.push(("Challenge:", verdict));
+
+    // --- Terms and conditions ---
+    let verdict = match probe(endpoint, client.get(format!("{}/TandC/1-0", endpoint))).await {
+        Err((e, latency)) => Verdict::Fail(format!("no response after {} ms: {}", latency.as_millis(), e), diagnose_error(&e)),
+        Ok(outcome) => diagnose(&outcome, |status| status == 200),
+    };
+    verdicts.push(("T&C:", verdict));
+
+    // --- Statistics: an unknown address gets a JSON error from a healthy API ---
+    let statistics = client.get(format!("{}/statistics/{}", endpoint, address.unwrap_or(PROBE_ADDRESS)))
+        .timeout(timeouts.statistics.total())
+        .header("Accept", "application/json");
+    let verdict = match probe(endpoint, statistics).await {
+        Err((e, latency)) => Verdict::Fail(format!("no response after {} ms: {}", latency.as_millis(), e), diagnose_error(&e)),
+        Ok(outcome) if address.is_some() => diagnose(&outcome, |status| status == 200),
+        Ok(outcome) => diagnose(&outcome, |status| status == 200 || (400..500).contains(&status)),
+    };
+    verdicts.push(("Statistics:", verdict));
+
+    // --- Solution dry run: an unregistered address is rejected before anything is credited ---
+    let solution = client.post(format!("{}/solution/{}/{}/{}", endpoint, PROBE_ADDRESS, challenge_id.as_deref().unwrap_or("**D00C00"), PROBE_NONCE))
+        .timeout(timeouts.submission.total())
+        .header("Content-Type", "application/json; charset=utf-8");
+    let verdict = match probe(endpoint, solution).await {
+        Err((e, latency)) => Verdict::Fail(format!("no response after {} ms: {}", latency.as_millis(), e), diagnose_error(&e)),
+        Ok(outcome) if (200..300).contains(&outcome.status) => Verdict::Warn(
+            format!("{} in {} ms", outcome.status, outcome.latency.as_millis()),
+            "Accepted a dry-run solution from an unregistered address: is '--api-url' a real Scavenger Mine API?".to_string()),
+        Ok(outcome) => match diagnose(&outcome, |status| (400..500).contains(&status)) {
+            Verdict::Pass(summary) => Verdict::Pass(format!("{} (dry run rejected as expected)", summary)),
+            verdict => verdict,
+        },
+    };
+    verdicts.push(("Submit (dry run):", verdict));
+    verdicts
+}
@@ -0,0 +1,119 @@
+// src/api_trace.rs
+
+// '--api-trace <file>': a JSON line per HTTP exchange with the API (method, URL, status or error, latency
+// and the start of the response body), for debugging 403s and WAF blocks. Signatures and URL credentials
+// are redacted, so a trace can be attached to a bug report. The file is rotated at MAX_TRACE_BYTES into
+// <file>.1 ... <file>.TRACE_BACKUPS.
+
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+const MAX_TRACE_BYTES: u64 = 10 * 1024 * 1024;
+const TRACE_BACKUPS: usize = 3;
+// Response characters kept per entry
+const MAX_BODY_CHARS: usize = 512;
+
+struct Trace {
+    path: String,
+    file: File,
+    written: u64,
+}
+
+static TRACE: OnceLock<Mutex<Trace>> = OnceLock::new();
+
+#[derive(Debug, Serialize)]
+struct TraceEntry<'a> {
+    timestamp: String,
+    method: &'a str,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+    latency_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+}
+
+/// Starts tracing to `path`, appending to an existing trace.
+pub fn open(path: &str) -> Result<(), String> {
+    let file = OpenOptions::new().create(true).append(true).open(path)
+        .map_err(|e| format!("Could not open API trace {}: {}", path, e))?;
+    let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let _ = TRACE.set(Mutex::new(Trace { path: path.to_string(), file, written }));
+    println!("🔎 Tracing API requests to {} (signatures redacted).", path);
+    Ok(())
+}
+
+pub fn enabled() -> bool {
+    TRACE.get().is_some()
+}
+
+/// Records one exchange: the response's status and body, or the error that prevented one.
+pub fn record(method: &str, url: &reqwest::Url, outcome: Result<(u16, &[u8]), &str>, latency: Duration) {
+    let Some(trace) = TRACE.get() else { return };
+    let body = outcome.ok().map(|(_, body)| {
+        let text = String::from_utf8_lossy(body);
+        match text.char_indices().nth(MAX_BODY_CHARS) {
+            Some((cut, _)) => format!("{}... ({} bytes)", &text[..cut], body.len()),
+            None => text.into_owned(),
+        }
+    });
+    let entry = TraceEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        method,
+        url: redact(url),
+        status: outcome.ok().map(|(status, _)| status),
+        error: outcome.err(),
+        latency_ms: latency.as_millis(),
+        body,
+    };
+    let Ok(line) = serde_json::to_string(&entry) else { return };
+
+    let mut trace = trace.lock().unwrap_or_else(|e| e.into_inner());
+    if trace.written + line.len() as u64 >= MAX_TRACE_BYTES
+        && let Err(e) = trace.rotate()
+    {
+        eprintln!("⚠️ Could not rotate API trace {}: {}", trace.path, e);
+    }
+    match writeln!(trace.file, "{}", line) {
+        Ok(()) => trace.written += line.len() as u64 + 1,
+        Err(e) => eprintln!("⚠️ Could not append to API trace {}: {}", trace.path, e),
+    }
+}
+
+impl Trace {
+    fn rotate(&mut self) -> std::io::Result<()> {
+        for index in (1..TRACE_BACKUPS).rev() {
+            let from = format!("{}.{}", self.path, index);
+            if std::path::Path::new(&from).exists() {
+                std::fs::rename(&from, format!("{}.{}", self.path, index + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, format!("{}.1", self.path))?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+/// `url` without credentials, and with the signature of a registration or donation replaced.
+fn redact(url: &reqwest::Url) -> String {
+    let mut url = url.clone();
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    // /register/{address}/{signature}/{pubkey} and /donate_to/{destination}/{original}/{signature}
+    let segments: Vec<String> = url.path_segments().map(|s| s.map(str::to_string).collect()).unwrap_or_default();
+    let signature = segments.iter().rposition(|s| s == "register" || s == "donate_to")
+        .map(|route| if segments[route] == "register" { route + 2 } else { route + 3 })
+        .filter(|&index| index < segments.len());
+    if let Some(index) = signature {
+        let mut redacted = segments;
+        redacted[index] = format!("REDACTED-{}-CHARS", redacted[index].len());
+        url.set_path(&format!("/{}", redacted.join("/")));
+    }
+    url.to_string()
+}
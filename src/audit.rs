@@ -0,0 +1,49 @@
+// src/audit.rs
+
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+
+// Append-only JSON lines in '--data-dir', one per API write attempt
+pub const AUDIT_LOG_FILE_NAME: &str = "audit.log";
+
+#[derive(Debug, Serialize)]
+struct AuditEntry<'a> {
+    timestamp: String,
+    action: &'a str,
+    address: &'a str,
+    idempotency_key: &'a str,
+    outcome: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Records the outcome of a registration, donation or submission with the idempotency key it was sent with,
+/// so a request can be correlated with the API operator's logs. Without a data dir nothing is recorded.
+pub fn record<T, E: std::fmt::Display>(data_dir: Option<&str>, action: &str, address: &str, idempotency_key: &str, result: &Result<T, E>) {
+    let base_dir = match data_dir {
+        Some(d) => d,
+        None => return,
+    };
+
+    let entry = AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        action,
+        address,
+        idempotency_key,
+        outcome: if result.is_ok() { "ok" } else { "error" },
+        error: result.as_ref().err().map(|e| e.to_string()),
+    };
+
+    let path = PathBuf::from(base_dir).join(AUDIT_LOG_FILE_NAME);
+    let written = serde_json::to_string(&entry)
+        .map_err(|e| e.to_string())
+        .and_then(|line| {
+            std::fs::OpenOptions::new().create(true).append(true).open(&path)
+                .and_then(|mut file| writeln!(file, "{}", line))
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = written {
+        eprintln!("⚠️ Could not append to audit log {:?}: {}", path, e);
+    }
+}
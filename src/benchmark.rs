@@ -0,0 +1,148 @@
+// src/benchmark.rs
+
+use crate::cardano;
+use crate::cli::Cli;
+use crate::data_types::{ChallengeData, load_cached_challenge};
+use crate::utils::{CpuTopology, format_duration};
+use shadow_harvester_lib::{ChallengeParams, benchmark_hashes, challenge_rom, difficulty_to_zero_bits};
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Stand-in parameters when no saved challenge is replayed; only the ROM key and preimage length matter for speed
+const SYNTHETIC_CHALLENGE_ID: &str = "**BENCH";
+const SYNTHETIC_DIFFICULTY: &str = "000FFFFF";
+const SYNTHETIC_NO_PRE_MINE_KEY: &str = "e8a195800b3bd5e4a5c3e7ad3c2cdc6b1e7e2a64f28d1c1e5e7c1e3b4a5d6f70";
+const SYNTHETIC_NO_PRE_MINE_HOUR: &str = "509681483";
+const SYNTHETIC_LATEST_SUBMISSION: &str = "2099-12-31T23:59:59.000Z";
+
+// Counts within this fraction of the best rate are considered equivalent; the smallest one is recommended
+const RECOMMENDATION_TOLERANCE: f64 = 0.02;
+
+#[derive(Debug)]
+struct BenchmarkRun {
+    threads: u32,
+    hashes: u64,
+    elapsed_secs: f64,
+    rss_mb: Option<u64>,
+}
+
+impl BenchmarkRun {
+    fn hash_rate(&self) -> f64 {
+        if self.elapsed_secs > 0.0 { self.hashes as f64 / self.elapsed_secs } else { 0.0 }
+    }
+}
+
+/// Reads a `kB` field from /proc/self/status and returns it in MiB (Linux only).
+fn process_memory_mb(field: &str) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status.lines()
+        .find(|line| line.starts_with(field))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb / 1024)
+}
+
+fn synthetic_challenge() -> ChallengeData {
+    ChallengeData {
+        challenge_id: SYNTHETIC_CHALLENGE_ID.to_string(),
+        difficulty: SYNTHETIC_DIFFICULTY.to_string(),
+        no_pre_mine_key: SYNTHETIC_NO_PRE_MINE_KEY.to_string(),
+        no_pre_mine_hour_str: SYNTHETIC_NO_PRE_MINE_HOUR.to_string(),
+        latest_submission: SYNTHETIC_LATEST_SUBMISSION.to_string(),
+        challenge_number: 0,
+        day: 0,
+        issued_at: String::new(),
+    }
+}
+
+/// Parses '--thread-counts' ("1,2,4"), or defaults to powers of two plus the physical and logical core counts.
+fn thread_counts(thread_counts_str: Option<&str>, topology: CpuTopology) -> Result<Vec<u32>, String> {
+    let mut counts: Vec<u32> = match thread_counts_str {
+        Some(s) => s.split(',')
+            .map(|t| match t.trim().parse::<u32>() {
+                Ok(n) if n > 0 => Ok(n),
+                _ => Err(format!("Invalid thread count '{}' in '--thread-counts {}'.", t.trim(), s)),
+            })
+            .collect::<Result<_, _>>()?,
+        None => std::iter::successors(Some(1u32), |n| n.checked_mul(2))
+            .take_while(|&n| n < topology.logical)
+            .chain([topology.physical, topology.logical])
+            .collect(),
+    };
+    counts.sort_unstable();
+    counts.dedup();
+    Ok(counts)
+}
+
+/// Picks the smallest thread count whose rate is within the tolerance of the best one.
+fn recommend(runs: &[BenchmarkRun]) -> Option<&BenchmarkRun> {
+    let best_rate = runs.iter().map(BenchmarkRun::hash_rate).fold(0.0, f64::max);
+    runs.iter().find(|run| run.hash_rate() >= best_rate * (1.0 - RECOMMENDATION_TOLERANCE))
+}
+
+/// Measures the hash rate for each thread count on a synthetic (or saved) challenge. No API access is needed.
+pub fn run_benchmark(cli: &Cli, duration: Duration, thread_counts_str: Option<&str>, challenge_id: Option<&str>) -> Result<(), String> {
+    if duration < Duration::from_secs(1) {
+        return Err("'--duration' must be at least 1 second.".to_string());
+    }
+    let topology = CpuTopology::detect();
+    let counts = thread_counts(thread_counts_str, topology)?;
+
+    let challenge = match challenge_id {
+        Some(id) => {
+            let data_dir = cli.data_dir.as_deref()
+                .ok_or("Replaying a saved challenge with '--challenge-id' requires '--data-dir'.")?;
+            load_cached_challenge(data_dir, id, cli.allow_stale_challenge)?
+                .ok_or_else(|| format!("No saved challenge {} in {}.", id, data_dir))?
+        },
+        None => synthetic_challenge(),
+    };
+
+    println!("\n==============================================");
+    println!("⏱️  Shadow Harvester: Benchmark");
+    println!("==============================================");
+    println!("Challenge:      {}{}", challenge.challenge_id, if challenge_id.is_none() { " (synthetic)" } else { "" });
+    println!("CPU Cores:      {} logical, {} physical", topology.logical, topology.physical);
+    println!("Thread Counts:  {:?} ({} each)", counts, format_duration(duration.as_secs_f64()));
+
+    println!("\n🧱 Generating ROM...");
+    let rom_start = Instant::now();
+    let rom = challenge_rom(&challenge.no_pre_mine_key);
+    println!("ROM ready in {} (RSS: {} MiB)", format_duration(rom_start.elapsed().as_secs_f64()),
+        process_memory_mb("VmRSS:").map_or("unknown".to_string(), |mb| mb.to_string()));
+
+    let address = cardano::generate_cardano_key_and_address().2.to_bech32()
+        .map_err(|e| format!("Could not encode benchmark address: {}", e))?;
+    let params = ChallengeParams {
+        rom_key: challenge.no_pre_mine_key.clone(),
+        difficulty_mask: challenge.difficulty.clone(),
+        address,
+        challenge_id: challenge.challenge_id.clone(),
+        latest_submission: challenge.latest_submission.clone(),
+        no_pre_mine_hour: challenge.no_pre_mine_hour_str.clone(),
+        required_zero_bits: difficulty_to_zero_bits(&challenge.difficulty),
+        rom: Arc::new(rom),
+    };
+
+    let mut runs = Vec::new();
+    for threads in counts {
+        print!("  {:>3} thread(s): ", threads);
+        let _ = std::io::stdout().flush();
+        let start = Instant::now();
+        let hashes = benchmark_hashes(&params, threads, duration);
+        let run = BenchmarkRun { threads, hashes, elapsed_secs: start.elapsed().as_secs_f64(), rss_mb: process_memory_mb("VmHWM:") };
+        println!("{:>10.2} H/s ({:.2} H/s per thread)", run.hash_rate(), run.hash_rate() / threads as f64);
+        runs.push(run);
+    }
+
+    println!("----------------------------------------------");
+    if let Some(peak_mb) = runs.iter().filter_map(|run| run.rss_mb).max() {
+        println!("Peak Memory:    {} MiB", peak_mb);
+    }
+    if let Some(best) = recommend(&runs) {
+        println!("✅ Recommended: --threads {} ({:.2} H/s)", best.threads, best.hash_rate());
+    }
+    println!("==============================================");
+    Ok(())
+}
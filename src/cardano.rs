@@ -13,6 +13,9 @@ use minicbor::*;
 use rand_core::{OsRng};
 use bip39::Mnemonic;
 use ed25519_bip32::{self, XPrv, XPRV_SIZE};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 pub enum FlexibleSecretKey {
     Standard(SecretKey),
@@ -80,6 +83,66 @@ pub fn derive_key_pair_from_mnemonic(mnemonic: &str, account: u32, index: u32) -
 
 }
 
+/// Keys of the next mnemonic indices, derived ahead on a background thread so that moving on to the next
+/// wallet doesn't wait for derivation (seconds on slow ARM boards). Indices not derived yet are derived
+/// inline.
+pub struct KeyCache {
+    mnemonic: Arc<str>,
+    account: u32,
+    ahead: u32,
+    keys: Arc<Mutex<BTreeMap<u32, Arc<KeyPairAndAddress>>>>,
+    deriving: Arc<AtomicBool>,
+}
+
+impl KeyCache {
+    /// `ahead` is how many indices after the last requested one to keep derived; 0 disables prefetching.
+    pub fn new(mnemonic: &str, account: u32, ahead: u32) -> Self {
+        Self {
+            mnemonic: Arc::from(mnemonic),
+            account,
+            ahead,
+            keys: Arc::new(Mutex::new(BTreeMap::new())),
+            deriving: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// The key pair of `index`, then starts deriving the indices after it.
+    pub fn key_pair(&self, index: u32) -> Arc<KeyPairAndAddress> {
+        let cached = self.keys.lock().unwrap().get(&index).cloned();
+        let key_pair = cached.unwrap_or_else(|| {
+            let key_pair = Arc::new(derive_key_pair_from_mnemonic(&self.mnemonic, self.account, index));
+            self.keys.lock().unwrap().insert(index, key_pair.clone());
+            key_pair
+        });
+        self.prefetch(index);
+        key_pair
+    }
+
+    /// Drops the keys of indices below `index`, which won't be mined again.
+    pub fn forget_below(&self, index: u32) {
+        let mut keys = self.keys.lock().unwrap();
+        *keys = keys.split_off(&index);
+    }
+
+    fn prefetch(&self, index: u32) {
+        if self.ahead == 0 || self.deriving.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let (mnemonic, account, keys, deriving) = (self.mnemonic.clone(), self.account, self.keys.clone(), self.deriving.clone());
+        let upcoming: Vec<u32> = (1..=self.ahead).filter_map(|offset| index.checked_add(offset)).collect();
+        std::thread::spawn(move || {
+            for next in upcoming {
+                if keys.lock().unwrap().contains_key(&next) {
+                    continue;
+                }
+                let key_pair = Arc::new(derive_key_pair_from_mnemonic(&mnemonic, account, next));
+                keys.lock().unwrap().insert(next, key_pair);
+            }
+            deriving.store(false, Ordering::Release);
+        });
+    }
+}
+
 pub fn generate_cardano_key_pair_from_skey(sk_hex: &String) -> KeyPairAndAddress {
     let skey_bytes = hex::decode(sk_hex).expect("Invalid secret key hex");
     let skey_array: [u8; 32] = skey_bytes
@@ -214,3 +277,29 @@ pub fn cip8_sign(kp: &KeyPairAndAddress, message: &str) -> (String, String) {
 
     (hex::encode(&cose_sign1_cbor).to_string(), hex::encode(pubkey).to_string())
 }
+
+/// Extracts the raw Ed25519 public key (hex) from the `key` output of external CIP-8/CIP-30 signers,
+/// which is either the 32-byte key itself or a COSE_Key map holding it under label -2 (x).
+pub fn cose_key_public_key(key_hex: &str) -> Result<String, String> {
+    let key_bytes = hex::decode(key_hex.trim()).map_err(|e| format!("Public key is not valid hex: {}", e))?;
+    if key_bytes.len() == 32 {
+        return Ok(hex::encode(key_bytes));
+    }
+
+    let mut decoder = Decoder::new(&key_bytes);
+    let entries = decoder.map()
+        .map_err(|e| format!("Public key is neither 32 bytes nor a COSE_Key: {}", e))?
+        .ok_or("Indefinite-length COSE_Key maps are not supported")?;
+    for _ in 0..entries {
+        let label = decoder.i64().map_err(|e| format!("Invalid COSE_Key label: {}", e))?;
+        if label == -2 {
+            let x = decoder.bytes().map_err(|e| format!("Invalid COSE_Key x coordinate: {}", e))?;
+            if x.len() != 32 {
+                return Err(format!("COSE_Key x coordinate is {} bytes, expected 32", x.len()));
+            }
+            return Ok(hex::encode(x));
+        }
+        decoder.skip().map_err(|e| format!("Invalid COSE_Key value: {}", e))?;
+    }
+    Err("COSE_Key has no public key (label -2)".to_string())
+}
@@ -0,0 +1,128 @@
+// src/checklist.rs
+
+use crate::cardano;
+use crate::cli::Cli;
+use crate::data_types::mnemonic_dir_id;
+use crate::init::prompt_yes_no;
+use serde::{Deserialize, Serialize};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+// One record per wallet source and donation target that went through the checklist
+pub const CHECKLIST_FILE_NAME: &str = "first_run_checklist.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChecklistRecord {
+    /// A payment address, a mnemonic's directory id, or "ephemeral"; never key material.
+    wallet: String,
+    donate_to: Option<String>,
+    confirmed: Vec<String>,
+    confirmed_at: String,
+}
+
+/// A question that has to be answered 'yes', and what goes wrong for good if it isn't true.
+struct ChecklistItem {
+    question: String,
+    why: &'static str,
+}
+
+fn checklist_items(cli: &Cli, data_dir: &str, mnemonic_mode: bool) -> Vec<ChecklistItem> {
+    let mut items = Vec::new();
+    if mnemonic_mode {
+        items.push(ChecklistItem {
+            question: "Is the mnemonic backed up offline, somewhere other than this machine?".to_string(),
+            why: "The mnemonic is the only way to claim the rewards of its wallets. Lose it and they are gone.",
+        });
+    } else if cli.payment_key.is_some() {
+        items.push(ChecklistItem {
+            question: "Is the payment key backed up offline, somewhere other than this machine?".to_string(),
+            why: "The key is the only way to claim the rewards of its address. Lose it and they are gone.",
+        });
+    } else if cli.ephemeral_key && cli.donate_to.is_none() {
+        items.push(ChecklistItem {
+            question: "Ephemeral keys are never saved. Mine anyway, knowing their rewards can't be claimed without '--donate-to'?".to_string(),
+            why: "Without a donation target, rewards stay with throwaway addresses nobody holds the key to.",
+        });
+    }
+    if let Some(donate_to) = &cli.donate_to {
+        items.push(ChecklistItem {
+            question: format!("Did you check the donation address {} on a second device (e.g. your wallet app)?", donate_to),
+            why: "Donations assign rewards for good. Clipboard malware and typos swap addresses.",
+        });
+    }
+    items.push(ChecklistItem {
+        question: format!("Is the data dir {} on persistent storage (not tmpfs or a container's throwaway disk)?", data_dir),
+        why: "Queued solutions and receipts live in the data dir. Solutions not yet submitted are lost with it.",
+    });
+    items
+}
+
+/// The wallet source a run mines with, identified without key material, and whether it is a mnemonic.
+/// `None` when no mining mode is selected.
+fn wallet_source(cli: &Cli) -> Result<Option<(String, bool)>, String> {
+    let mnemonic = match (&cli.mnemonic, &cli.mnemonic_file) {
+        (Some(mnemonic), _) => Some(mnemonic.clone()),
+        (None, Some(path)) => Some(std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read mnemonic from file: {}", e))?),
+        (None, None) => None,
+    };
+    Ok(if let Some(skey_hex) = cli.payment_key.as_ref() {
+        Some((cardano::generate_cardano_key_pair_from_skey(skey_hex).2.to_bech32().unwrap(), false))
+    } else if let Some(mnemonic) = mnemonic {
+        Some((format!("mnemonic {}", mnemonic_dir_id(&mnemonic)), true))
+    } else if cli.external_signer.is_some() {
+        cli.address.clone().map(|address| (address, false))
+    } else if cli.ephemeral_key {
+        Some(("ephemeral".to_string(), false))
+    } else {
+        None
+    })
+}
+
+/// One-time checklist before the first run of a wallet source (and donation target) in a data dir, with
+/// the confirmations recorded there. Skipped with '--headless', without a data dir to record into, and
+/// when stdin is not a terminal. Any 'no' stops the run before anything is mined or donated.
+pub fn run_first_run_checklist(cli: &Cli) -> Result<(), String> {
+    if cli.headless || cli.watch {
+        return Ok(());
+    }
+    let Some(data_dir) = cli.data_dir.as_deref() else { return Ok(()) };
+    let Some((wallet, mnemonic_mode)) = wallet_source(cli)? else { return Ok(()) };
+    let path = PathBuf::from(data_dir).join(CHECKLIST_FILE_NAME);
+    let mut records: Vec<ChecklistRecord> = std::fs::read_to_string(&path).ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    if records.iter().any(|record| record.wallet == wallet && record.donate_to == cli.donate_to) {
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        eprintln!("⚠️ First run of this wallet in {}: the safety checklist needs a terminal and was skipped. Pass '--headless' to skip it on purpose.", data_dir);
+        return Ok(());
+    }
+
+    println!("\n==============================================");
+    println!("🛟 Shadow Harvester: First-Run Checklist");
+    println!("==============================================");
+    println!("Mistakes on a first run can't be undone. Please confirm:\n");
+    let items = checklist_items(cli, data_dir, mnemonic_mode);
+    for item in &items {
+        if !prompt_yes_no(&item.question, false)? {
+            return Err(format!("First-run checklist not completed. {} Nothing was mined or donated.", item.why));
+        }
+    }
+
+    records.push(ChecklistRecord {
+        wallet: wallet.to_string(),
+        donate_to: cli.donate_to.clone(),
+        confirmed: items.into_iter().map(|item| item.question).collect(),
+        confirmed_at: chrono::Utc::now().to_rfc3339(),
+    });
+    let saved = std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())
+        .and_then(|_| serde_json::to_string_pretty(&records).map_err(|e| e.to_string()))
+        .and_then(|json| std::fs::write(&path, json).map_err(|e| e.to_string()));
+    match saved {
+        Ok(()) => println!("✅ Checklist confirmed and recorded in {:?}.", path),
+        Err(e) => eprintln!("⚠️ Checklist confirmed, but could not be recorded in {:?}: {}", path, e),
+    }
+    Ok(())
+}
@@ -0,0 +1,897 @@
+// src/cluster.rs
+
+// '--coordinator' / '--worker': one machine talks to the API, holds the keys and queues the solutions;
+// workers on other machines only hash the nonce ranges it hands out. The protocol is one JSON message
+// per line over TCP; third-party workers can use the gRPC service (grpc.rs) or the Stratum-style
+// protocol (stratum.rs) instead. Every reported solution is hashed again before it is used, but without
+// '--cluster-token' (auth.rs) the coordinator should still only listen on a network you control. Workers on the same LAN can find an announcing
+// coordinator over mDNS (discovery.rs).
+
+mod auth;
+mod dashboard;
+mod discovery;
+mod grpc;
+mod stratum;
+
+pub use auth::load_token;
+
+use crate::backoff::Backoff;
+use chrono::{DateTime, Utc};
+use dashboard::{Protocol, WorkerHealth};
+use crate::data_types::{ChallengeData, PendingSolution, challenge_checksum, new_idempotency_key};
+use crate::store::StateStore;
+use crate::utils::{format_duration, format_hashrate, format_thousands, submission_deadline_near};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use shadow_harvester_lib::hashcore::{NonceRange, Preimage, Searcher, Target};
+use shadow_harvester_lib::{NoncePartition, Rom, ScavengeControl};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, channel};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Units are sized to take about this long at the worker's rate, so a challenge or address switch wastes little
+const UNIT_SECS: f64 = 30.0;
+// Nonces per thread in a worker's first unit, before it has measured its hash rate
+const FIRST_UNIT_NONCES_PER_THREAD: u64 = 64;
+// Bounds the unit of a worker reporting an absurd hash rate (~30 hours at 10 MH/s)
+const MAX_UNIT_NONCES: u64 = 1 << 40;
+// How long a worker waits before asking again when there is nothing to mine
+const WAIT_SECS: u64 = 2;
+const STATUS_SECS: u64 = 30;
+// A worker's unit is handed to others once it has been silent for this many units' time at its rate,
+// and at least RECLAIM_MIN_SECS (which covers building the ROM of a new challenge)
+const RECLAIM_UNITS: f64 = 3.0;
+const RECLAIM_MIN_SECS: u64 = 5 * 60;
+// Finished jobs whose late solutions are still queued
+const FINISHED_JOBS: usize = 32;
+// gRPC workers don't hold a connection; one counts as connected while it asked for work this recently
+const GRPC_WORKER_ACTIVE_SECS: u64 = 5 * 60;
+// How long a new connection has for the TLS handshake and its first message
+const HANDSHAKE_SECS: u64 = 10;
+// Messages are a few hundred bytes; a longer line is refused rather than buffered, authenticated or not
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WorkerMessage {
+    /// First message: the cluster token, if the worker has one.
+    Auth { token: Option<String> },
+    /// Asks for a unit of about `nonces` nonces (the coordinator sizes it once it knows the worker's rate).
+    Request { nonces: u64 },
+    /// A finished unit: the nonce that solved it, if any, how many nonces were hashed and in how long
+    /// (workers before 'elapsed_ms' was added leave it out).
+    Result {
+        job_id: u64,
+        nonce: Option<u64>,
+        hashes: u64,
+        #[serde(default)]
+        elapsed_ms: Option<u64>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CoordinatorMessage {
+    Work(WorkUnit),
+    /// Nothing to mine right now (between cycles, or paused); ask again later.
+    Wait { secs: u64 },
+    /// The worker's token was accepted.
+    Welcome,
+    /// The worker's token was missing or wrong; the connection is closed.
+    Rejected { reason: String },
+}
+
+/// `count` consecutive nonces from `start` of one address and challenge.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WorkUnit {
+    job_id: u64,
+    address: String,
+    challenge: ChallengeData,
+    start: u64,
+    count: u64,
+}
+
+/// A worker connection: plain TCP, or TLS over it.
+trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+fn send_line<T: Serialize>(stream: &mut impl Write, message: &T) -> Result<(), String> {
+    let mut line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).map_err(|e| e.to_string())
+}
+
+fn read_line<T: for<'de> Deserialize<'de>>(reader: &mut impl BufRead) -> Result<Option<T>, String> {
+    let mut line = Vec::new();
+    if reader.take(MAX_LINE_BYTES as u64).read_until(b'\n', &mut line).map_err(|e| e.to_string())? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with(b"\n") && line.len() >= MAX_LINE_BYTES {
+        return Err(format!("Message longer than {} bytes", MAX_LINE_BYTES));
+    }
+    serde_json::from_slice(&line).map(Some).map_err(|e| format!("Malformed message: {}", e))
+}
+
+// ===============================================
+// COORDINATOR ('--coordinator')
+// ===============================================
+
+/// The address and challenge being searched, and where the next unit starts.
+#[derive(Debug)]
+struct Job {
+    id: u64,
+    address: String,
+    challenge: ChallengeData,
+    next_nonce: u64,
+    /// The unit each worker is hashing, by worker key.
+    outstanding: HashMap<String, Outstanding>,
+    /// Units of workers that dropped or went silent, handed out again before new nonces.
+    reclaimed: VecDeque<(u64, u64)>,
+}
+
+#[derive(Debug)]
+struct Outstanding {
+    start: u64,
+    count: u64,
+    /// When the unit is reclaimed if the worker hasn't asked for the next one. None: only on disconnect.
+    deadline: Option<Instant>,
+}
+
+/// A job that is no longer handed out, kept so a solution reported after its end can still be queued.
+#[derive(Debug)]
+struct FinishedJob {
+    id: u64,
+    address: String,
+    challenge: ChallengeData,
+    solved: bool,
+}
+
+/// Why a worker's report was refused.
+#[derive(Debug)]
+enum ReportError {
+    /// The nonce doesn't solve its job: the worker is buggy or hostile.
+    Invalid(String),
+    /// The solution came too late to use: unknown or solved job, closed challenge, nowhere to queue it.
+    Stale(String),
+}
+
+impl std::fmt::Display for ReportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportError::Invalid(e) => write!(f, "Invalid solution: {}", e),
+            ReportError::Stale(e) => write!(f, "Stale solution: {}", e),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Report {
+    job_id: u64,
+    nonce: Option<u64>,
+    hashes: u64,
+    /// How long the worker took to hash `hashes`, if it says.
+    elapsed: Option<Duration>,
+}
+
+pub struct Coordinator {
+    job: Mutex<Option<Job>>,
+    next_job_id: AtomicU64,
+    reports: Mutex<Receiver<Report>>,
+    report_sender: Sender<Report>,
+    /// Every worker's health, by peer address (gRPC workers: by worker id).
+    fleet: Mutex<HashMap<String, WorkerHealth>>,
+    /// Solutions found by the workers since the coordinator started.
+    solutions: AtomicU64,
+    /// The last `FINISHED_JOBS` jobs, newest last.
+    finished: Mutex<VecDeque<FinishedJob>>,
+    /// The submission queue ('--data-dir') late solutions go to, and their donation address.
+    queue: Option<Arc<dyn StateStore>>,
+    donate_to: Option<String>,
+    /// '--cluster-token': workers must present it.
+    token: Option<String>,
+    /// TLS on the line protocol listener.
+    tls: Option<native_tls::TlsAcceptor>,
+    control: Arc<ScavengeControl>,
+}
+
+static COORDINATOR: OnceLock<Arc<Coordinator>> = OnceLock::new();
+
+/// The running coordinator, if '--coordinator' was given: mining cycles hand their search to it.
+pub fn coordinator() -> Option<&'static Arc<Coordinator>> {
+    COORDINATOR.get()
+}
+
+/// '--coordinator' and the options going with it.
+pub struct CoordinatorOptions<'a> {
+    pub bind: &'a str,
+    pub grpc_bind: Option<&'a str>,
+    pub stratum_bind: Option<&'a str>,
+    pub announce: bool,
+    pub token: Option<String>,
+    /// PEM certificate (chain) and PKCS#8 PEM key for TLS on the line protocol listener.
+    pub tls: Option<(&'a str, &'a str)>,
+    /// The submission queue solutions reported after their cycle ended go to straight away.
+    pub queue: Option<Arc<dyn StateStore>>,
+    pub donate_to: Option<String>,
+}
+
+/// Listens for workers on `bind` and serves each connection on its own thread, and for gRPC and Stratum
+/// workers on `grpc_bind` and `stratum_bind` if given. Workers are only handed units while a mining cycle
+/// runs and the control allows hashing.
+pub fn start_coordinator(options: CoordinatorOptions, control: Arc<ScavengeControl>) -> Result<(), String> {
+    let CoordinatorOptions { bind, grpc_bind, stratum_bind, announce, token, tls, queue, donate_to } = options;
+    let tls = tls.map(|(cert, key)| auth::acceptor(cert, key)).transpose()?;
+    let listener = TcpListener::bind(bind).map_err(|e| format!("Could not listen for workers on {}: {}", bind, e))?;
+    let (report_sender, receiver) = channel();
+    let coordinator = Arc::new(Coordinator {
+        job: Mutex::new(None),
+        next_job_id: AtomicU64::new(1),
+        reports: Mutex::new(receiver),
+        report_sender,
+        fleet: Mutex::new(HashMap::new()),
+        solutions: AtomicU64::new(0),
+        finished: Mutex::new(VecDeque::new()),
+        queue,
+        donate_to,
+        token,
+        tls,
+        control,
+    });
+    COORDINATOR.set(coordinator.clone()).map_err(|_| "The coordinator is already running.".to_string())?;
+    println!("📡 Coordinator listening for workers on {}{}{}.", listener.local_addr().map_or(bind.to_string(), |a| a.to_string()),
+        if coordinator.tls.is_some() { " (TLS)" } else { "" },
+        if coordinator.token.is_some() { ", token required" } else { "" });
+    if announce {
+        discovery::announce(listener.local_addr().map_err(|e| e.to_string())?.port())?;
+    }
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let coordinator = coordinator.clone();
+                    thread::spawn(move || coordinator.serve(stream));
+                },
+                Err(e) => eprintln!("⚠️ Worker connection failed: {}", e),
+            }
+        }
+    });
+    if let Some(grpc_bind) = grpc_bind {
+        grpc::serve(grpc_bind, COORDINATOR.get().unwrap().clone())?;
+    }
+    if let Some(stratum_bind) = stratum_bind {
+        stratum::serve(stratum_bind, COORDINATOR.get().unwrap().clone())?;
+    }
+    Ok(())
+}
+
+impl Coordinator {
+    fn serve(&self, stream: TcpStream) {
+        let peer = stream.peer_addr().map_or("unknown".to_string(), |a| a.to_string());
+        // Until it is accepted, a connection mustn't hold its thread forever
+        let Ok(socket) = stream.try_clone() else { return };
+        if socket.set_read_timeout(Some(Duration::from_secs(HANDSHAKE_SECS))).is_err() {
+            return;
+        }
+        let stream: Box<dyn Stream> = match &self.tls {
+            None => Box::new(stream),
+            Some(acceptor) => match acceptor.accept(stream) {
+                Ok(stream) => Box::new(stream),
+                Err(e) => return eprintln!("⚠️ TLS handshake with {} failed: {}", peer, e),
+            },
+        };
+        let mut connection = BufReader::new(stream);
+        let mut accepted = false;
+
+        let outcome = loop {
+            let message = match read_line::<WorkerMessage>(&mut connection) {
+                Ok(Some(message)) => message,
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            };
+            if !accepted {
+                let rejection = match &message {
+                    WorkerMessage::Auth { token } if self.authorized(token.as_deref()) => None,
+                    WorkerMessage::Auth { token: Some(_) } => Some("wrong cluster token"),
+                    _ if self.token.is_none() => None,
+                    _ => Some("cluster token required"),
+                };
+                if let Some(reason) = rejection {
+                    eprintln!("⚠️ Worker {} rejected: {}.", peer, reason);
+                    let _ = send_line(connection.get_mut(), &CoordinatorMessage::Rejected { reason: reason.to_string() });
+                    return;
+                }
+                // Accepted workers may take their time between messages (ROM builds, long units)
+                accepted = true;
+                let _ = socket.set_read_timeout(None);
+                println!("🔗 Worker {} connected ({} connected).", peer, self.connected(&peer, Protocol::Line));
+            }
+            self.seen(&peer, Protocol::Line);
+            let sent = match message {
+                WorkerMessage::Auth { .. } => send_line(connection.get_mut(), &CoordinatorMessage::Welcome),
+                WorkerMessage::Request { nonces } => send_line(connection.get_mut(), &self.next_unit(&peer, Protocol::Line, nonces)),
+                WorkerMessage::Result { job_id, nonce, hashes, elapsed_ms } => {
+                    let elapsed = elapsed_ms.map(Duration::from_millis);
+                    if let Err(e) = self.report(&peer, Protocol::Line, Report { job_id, nonce, hashes, elapsed }) {
+                        self.worker_error(&peer, Protocol::Line, &e.to_string());
+                    }
+                    Ok(())
+                },
+            };
+            if let Err(e) = sent {
+                break Err(e);
+            }
+        };
+        if accepted {
+            self.disconnected(&peer, Protocol::Line, outcome);
+        }
+    }
+
+    /// Hands a worker's report to the mining cycle, or its solution to the queue if the job has ended, and
+    /// accounts it on the worker's dashboard line. A solution is hashed again first: one that doesn't solve
+    /// its job is refused with the whole report, counted against the worker, and its unit handed out again.
+    /// Fails if the report's solution was refused or dropped.
+    fn report(&self, worker: &str, protocol: Protocol, report: Report) -> Result<(), ReportError> {
+        if let Some(nonce) = report.nonce
+            && let Err(e) = self.verify(report.job_id, nonce)
+        {
+            if let ReportError::Invalid(reason) = &e {
+                eprintln!("❌ {} {} reported solution {:016x}, which doesn't solve job {}: {}.", protocol.title(), worker, nonce, report.job_id, reason);
+                self.record_invalid_solution(worker, protocol);
+                if let Some(job) = self.job.lock().unwrap_or_else(|e| e.into_inner()).as_mut().filter(|job| job.id == report.job_id) {
+                    Self::reclaim(job, worker, "reported an invalid solution");
+                }
+            }
+            return Err(e);
+        }
+        let mut result = Ok(());
+        let mut solved = report.nonce.is_some() && self.is_current(report.job_id);
+        if let Some(nonce) = report.nonce.filter(|_| !solved) {
+            result = self.queue_late_solution(report.job_id, nonce).map_err(ReportError::Stale);
+            solved = result.is_ok();
+        }
+        self.record_report(worker, protocol, report.hashes, report.elapsed, solved);
+        result?;
+        // Nobody listens between mining cycles; the next one drains reports of a finished job
+        let _ = self.report_sender.send(report);
+        Ok(())
+    }
+
+    /// Hashes `nonce` for its job's address and challenge, with the challenge's ROM, and checks it against
+    /// the difficulty. Builds the ROM on the coordinator if it isn't loaded yet.
+    fn verify(&self, job_id: u64, nonce: u64) -> Result<(), ReportError> {
+        let (address, challenge) = self.job_target(job_id).ok_or_else(|| ReportError::Stale("unknown job".to_string()))?;
+        let rom = shadow_harvester_lib::shared_challenge_rom(&challenge.no_pre_mine_key);
+        match solves(&rom, &address, &challenge, nonce) {
+            true => Ok(()),
+            false => Err(ReportError::Invalid(format!("its hash doesn't meet difficulty {}", challenge.difficulty))),
+        }
+    }
+
+    /// The address and challenge of the current job or a finished one.
+    fn job_target(&self, job_id: u64) -> Option<(String, ChallengeData)> {
+        if let Some(job) = self.job.lock().unwrap_or_else(|e| e.into_inner()).as_ref().filter(|job| job.id == job_id) {
+            return Some((job.address.clone(), job.challenge.clone()));
+        }
+        self.finished.lock().unwrap_or_else(|e| e.into_inner()).iter()
+            .find(|job| job.id == job_id)
+            .map(|job| (job.address.clone(), job.challenge.clone()))
+    }
+
+    /// The job being mined, if a mining cycle runs.
+    fn current_job_id(&self) -> Option<u64> {
+        self.job.lock().unwrap_or_else(|e| e.into_inner()).as_ref().map(|job| job.id)
+    }
+
+    /// True if `job_id` is the job being mined.
+    fn is_current(&self, job_id: u64) -> bool {
+        self.current_job_id() == Some(job_id)
+    }
+
+    /// The next unit for `worker`, whose previous unit is done by now, sized by `unit_size`. Reclaimed
+    /// units come first, except for Stratum workers: they get one unit per job and can't ask for more.
+    fn next_unit(&self, worker: &str, protocol: Protocol, nonces: u64) -> CoordinatorMessage {
+        if self.control.allowed_threads() == 0 {
+            return CoordinatorMessage::Wait { secs: WAIT_SECS };
+        }
+        let mut job = self.job.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(job) = job.as_mut() else { return CoordinatorMessage::Wait { secs: WAIT_SECS } };
+        job.outstanding.remove(worker);
+        self.reclaim_expired(job);
+
+        let count = self.unit_size(worker, protocol, nonces);
+        let (start, count) = match job.reclaimed.pop_front() {
+            Some((start, reclaimed)) if protocol != Protocol::Stratum => {
+                if reclaimed > count {
+                    job.reclaimed.push_front((start.wrapping_add(count), reclaimed - count));
+                }
+                (start, count.min(reclaimed))
+            },
+            other => {
+                if let Some(reclaimed) = other {
+                    job.reclaimed.push_front(reclaimed);
+                }
+                let start = job.next_nonce;
+                job.next_nonce = job.next_nonce.wrapping_add(count);
+                (start, count)
+            },
+        };
+        // A live worker asks again within a few units' time, at its measured rate
+        let deadline = match protocol {
+            Protocol::Stratum => None,
+            _ => {
+                let expected = self.worker_hash_rate(worker).map_or(0.0, |rate| count as f64 / rate);
+                Some(Instant::now() + Duration::from_secs_f64((expected * RECLAIM_UNITS).max(RECLAIM_MIN_SECS as f64)))
+            },
+        };
+        job.outstanding.insert(worker.to_string(), Outstanding { start, count, deadline });
+        CoordinatorMessage::Work(WorkUnit { job_id: job.id, address: job.address.clone(), challenge: job.challenge.clone(), start, count })
+    }
+
+    /// About `UNIT_SECS` of work at the worker's measured hash rate, so fast machines get proportionally
+    /// larger ranges than slow ones. The `requested` nonces until it has reported hashes, and for Stratum
+    /// workers, which don't.
+    fn unit_size(&self, worker: &str, protocol: Protocol, requested: u64) -> u64 {
+        match self.worker_hash_rate(worker) {
+            Some(rate) if protocol != Protocol::Stratum => (rate * UNIT_SECS) as u64,
+            _ if requested == 0 => FIRST_UNIT_NONCES_PER_THREAD,
+            _ => requested,
+        }.clamp(1, MAX_UNIT_NONCES)
+    }
+
+    /// Moves the units of workers that stopped asking for work in time to `reclaimed`.
+    fn reclaim_expired(&self, job: &mut Job) {
+        let now = Instant::now();
+        let expired: Vec<String> = job.outstanding.iter()
+            .filter(|(_, unit)| unit.deadline.is_some_and(|deadline| deadline <= now))
+            .map(|(worker, _)| worker.clone())
+            .collect();
+        for worker in expired {
+            Self::reclaim(job, &worker, "went silent");
+        }
+    }
+
+    fn reclaim(job: &mut Job, worker: &str, reason: &str) {
+        if let Some(unit) = job.outstanding.remove(worker) {
+            println!("♻️ Worker {} {}. Handing its {} nonces from {:016x} to other workers.", worker, reason, format_thousands(unit.count), unit.start);
+            job.reclaimed.push_back((unit.start, unit.count));
+        }
+    }
+
+    /// Hands the unit of a disconnected worker to the others.
+    fn reclaim_disconnected(&self, worker: &str) {
+        if let Some(job) = self.job.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            Self::reclaim(job, worker, "disconnected");
+        }
+    }
+
+    /// Has the workers search `address`'s nonces for the challenge, from the partition's start, until one
+    /// of them solves it, the time slice or submission deadline runs out, or a newer challenge is pushed.
+    /// Same result as `scavenge`.
+    pub fn mine(&self, address: &str, challenge: &ChallengeData, control: &ScavengeControl, partition: NoncePartition) -> (Option<String>, u64, f64) {
+        let reports = self.reports.lock().unwrap_or_else(|e| e.into_inner());
+        // Reports of earlier cycles' units, received as their cycle ended
+        while let Ok(report) = reports.try_recv() {
+            if let Some(nonce) = report.nonce
+                && let Err(e) = self.queue_late_solution(report.job_id, nonce)
+            {
+                eprintln!("⚠️ Dropping solution {:016x} reported at the end of a cycle: {}.", nonce, e);
+            }
+        }
+        let job_id = self.next_job_id.fetch_add(1, Ordering::Relaxed);
+        *self.job.lock().unwrap_or_else(|e| e.into_inner()) = Some(Job {
+            id: job_id,
+            address: address.to_string(),
+            challenge: challenge.clone(),
+            next_nonce: partition.base(),
+            outstanding: HashMap::new(),
+            reclaimed: VecDeque::new(),
+        });
+        println!("📡 Handing out {} / challenge {} to {} connected worker(s).", address, challenge.challenge_id, self.worker_count());
+        let _search = control.start_search();
+
+        // The cluster's totals on a live line like `scavenge`'s, and the per-worker dashboard now and then
+        let pb = if control.is_live() { ProgressBar::new(u64::MAX) } else { ProgressBar::hidden() };
+        pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] {human_pos} hashes | {msg}").unwrap());
+        // '--no-live', or output that isn't a terminal: the line isn't drawn, so the totals are logged with the dashboard
+        let log_status = pb.is_hidden();
+        let start = Instant::now();
+        let mut last_status = Instant::now();
+        let mut total_hashes: u64 = 0;
+        let superseded_at_start = control.challenge_superseded(&challenge.challenge_id);
+        let found = loop {
+            match reports.recv_timeout(Duration::from_secs(1)) {
+                Ok(report) if report.job_id == job_id => {
+                    total_hashes += report.hashes;
+                    control.hashes.fetch_add(report.hashes, Ordering::Relaxed);
+                    if let Some(nonce) = report.nonce {
+                        self.solutions.fetch_add(1, Ordering::Relaxed);
+                        break Some(format!("{:016x}", nonce));
+                    }
+                },
+                Ok(_) | Err(RecvTimeoutError::Timeout) => {},
+                Err(RecvTimeoutError::Disconnected) => break None,
+            }
+            if control.time_slice_expired() {
+                break None;
+            }
+            // As in `scavenge`, only a push during the search stops it
+            if !superseded_at_start && control.challenge_superseded(&challenge.challenge_id) {
+                pb.suspend(|| println!("\n🔔 A new challenge was published, stopping the search on {}.", challenge.challenge_id));
+                break None;
+            }
+            if submission_deadline_near(challenge, control) {
+                pb.suspend(|| println!("\n⏰ Submission deadline in less than {}, giving up on this challenge.", format_duration(control.deadline_margin().unwrap_or_default().as_secs_f64())));
+                break None;
+            }
+            let elapsed = start.elapsed().as_secs_f64();
+            let status = format!("{} from {} worker(s) | found: {} this session",
+                format_hashrate(total_hashes as f64 / elapsed), self.worker_count(), self.solutions.load(Ordering::Relaxed));
+            pb.set_position(total_hashes);
+            pb.set_message(status.clone());
+            if last_status.elapsed() >= Duration::from_secs(STATUS_SECS) {
+                last_status = Instant::now();
+                let dashboard = self.dashboard();
+                pb.suspend(|| {
+                    if log_status {
+                        println!("⛏️ [{}] {} hashes | {}", format_duration(elapsed), format_thousands(total_hashes), status);
+                    }
+                    println!("{}", dashboard.join("\n"));
+                });
+            }
+        };
+        pb.finish_and_clear();
+        if let Some(job) = self.job.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            let mut finished = self.finished.lock().unwrap_or_else(|e| e.into_inner());
+            if finished.len() >= FINISHED_JOBS {
+                finished.pop_front();
+            }
+            finished.push_back(FinishedJob { id: job.id, address: job.address, challenge: job.challenge, solved: found.is_some() });
+        }
+        (found, total_hashes, start.elapsed().as_secs_f64())
+    }
+
+    /// Queues a solution of a finished job that ended without one, e.g. when its time slice ran out while
+    /// the worker's unit was still hashing. Fails for unknown or solved jobs and closed challenges.
+    fn queue_late_solution(&self, job_id: u64, nonce: u64) -> Result<(), String> {
+        let mut finished = self.finished.lock().unwrap_or_else(|e| e.into_inner());
+        let job = finished.iter_mut().find(|job| job.id == job_id).ok_or("unknown job")?;
+        if job.solved {
+            return Err("the job already has a solution".to_string());
+        }
+        if DateTime::parse_from_rfc3339(&job.challenge.latest_submission).is_ok_and(|deadline| deadline.with_timezone(&Utc) <= Utc::now()) {
+            return Err("the challenge is closed".to_string());
+        }
+        let queue = self.queue.as_deref().ok_or("no '--data-dir' to queue it in")?;
+        queue.save_pending_solution(&PendingSolution {
+            address: job.address.clone(),
+            challenge_id: job.challenge.challenge_id.clone(),
+            nonce: format!("{:016x}", nonce),
+            donation_address: self.donate_to.clone(),
+            challenge_checksum: challenge_checksum(&job.challenge),
+            idempotency_key: Some(new_idempotency_key()),
+        })?;
+        job.solved = true;
+        self.solutions.fetch_add(1, Ordering::Relaxed);
+        println!("📥 Late solution {:016x} for {} / challenge {} queued for submission.", nonce, job.address, job.challenge.challenge_id);
+        Ok(())
+    }
+}
+
+/// True if `nonce` solves `challenge` for `address`: the same hash and target as the workers' search.
+fn solves(rom: &Rom, address: &str, challenge: &ChallengeData, nonce: u64) -> bool {
+    let preimage = Preimage::new(address, &challenge.challenge_id, &challenge.difficulty,
+        &challenge.no_pre_mine_key, &challenge.latest_submission, &challenge.no_pre_mine_hour_str);
+    let target = Target { zero_bits: shadow_harvester_lib::difficulty_to_zero_bits(&challenge.difficulty) };
+    target.is_met(&Searcher::new(preimage).hash(rom, nonce))
+}
+
+// ===============================================
+// WORKER ('--worker')
+// ===============================================
+
+/// '--worker' and the options going with it.
+pub struct WorkerOptions<'a> {
+    /// The coordinator's address (host:port), or None to look for one on the LAN before each connection.
+    pub coordinator: Option<&'a str>,
+    pub threads: u32,
+    pub token: Option<String>,
+    /// Connect over TLS, trusting the system's roots and this PEM certificate if given.
+    pub tls: bool,
+    pub tls_ca: Option<&'a str>,
+}
+
+/// Hashes units for the coordinator on `threads` threads until the process is stopped, reconnecting
+/// whenever the connection drops. Needs no keys, API or data dir. Fails if the coordinator rejects the token.
+pub fn run_worker(options: WorkerOptions) -> Result<(), String> {
+    let threads = options.threads.max(1);
+    let tls = options.tls.then(|| auth::connector(options.tls_ca)).transpose()?;
+    println!("\n==============================================");
+    println!("⛏️  Shadow Harvester: CLUSTER WORKER Mode ({} thread(s))", threads);
+    println!("==============================================");
+
+    let mut backoff = Backoff::new(5, 60, 2.0);
+    let mut unit_nonces = threads as u64 * FIRST_UNIT_NONCES_PER_THREAD;
+    let mut rom: Option<(String, Arc<Rom>)> = None;
+    loop {
+        let coordinator = match options.coordinator {
+            Some(coordinator) => coordinator.to_string(),
+            None => match discovery::discover() {
+                Ok(coordinator) => coordinator,
+                Err(e) => {
+                    eprintln!("⚠️ {}. Retrying...", e);
+                    backoff.sleep();
+                    continue;
+                },
+            },
+        };
+        let mut connection = match connect(&coordinator, tls.as_ref(), options.token.as_deref()) {
+            Ok(connection) => connection,
+            Err(ConnectError::Rejected(reason)) => return Err(format!("Coordinator {} rejected this worker: {}.", coordinator, reason)),
+            Err(ConnectError::Failed(e)) => {
+                eprintln!("⚠️ Could not connect to coordinator {}: {}. Retrying...", coordinator, e);
+                backoff.sleep();
+                continue;
+            },
+        };
+        println!("🔗 Connected to coordinator {}{}.", coordinator, if tls.is_some() { " (TLS)" } else { "" });
+        backoff.reset();
+        if let Err(e) = work_for(&mut connection, threads, &mut unit_nonces, &mut rom) {
+            eprintln!("⚠️ Connection to coordinator {} lost: {}. Reconnecting...", coordinator, e);
+            backoff.sleep();
+        }
+    }
+}
+
+enum ConnectError {
+    Failed(String),
+    Rejected(String),
+}
+
+/// Connects to `coordinator` (over TLS if given a connector) and presents the token.
+fn connect(coordinator: &str, tls: Option<&native_tls::TlsConnector>, token: Option<&str>) -> Result<BufReader<Box<dyn Stream>>, ConnectError> {
+    let failed = |e: String| ConnectError::Failed(e);
+    let stream = TcpStream::connect(coordinator).map_err(|e| failed(e.to_string()))?;
+    let stream: Box<dyn Stream> = match tls {
+        None => Box::new(stream),
+        Some(connector) => {
+            let host = coordinator.rsplit_once(':').map_or(coordinator, |(host, _)| host).trim_matches(['[', ']']);
+            Box::new(connector.connect(host, stream).map_err(|e| failed(format!("TLS handshake failed: {}", e)))?)
+        },
+    };
+    let mut connection = BufReader::new(stream);
+    send_line(connection.get_mut(), &WorkerMessage::Auth { token: token.map(str::to_string) }).map_err(failed)?;
+    match read_line::<CoordinatorMessage>(&mut connection).map_err(failed)? {
+        Some(CoordinatorMessage::Welcome) => Ok(connection),
+        Some(CoordinatorMessage::Rejected { reason }) => Err(ConnectError::Rejected(reason)),
+        _ => Err(failed("unexpected answer to the auth message".to_string())),
+    }
+}
+
+fn work_for(connection: &mut BufReader<Box<dyn Stream>>, threads: u32, unit_nonces: &mut u64, rom: &mut Option<(String, Arc<Rom>)>) -> Result<(), String> {
+    let mut waiting = false;
+    let mut current: Option<(String, String)> = None;
+    loop {
+        send_line(connection.get_mut(), &WorkerMessage::Request { nonces: *unit_nonces })?;
+        let unit = match read_line::<CoordinatorMessage>(connection)? {
+            None => return Err("closed by the coordinator".to_string()),
+            Some(CoordinatorMessage::Welcome | CoordinatorMessage::Rejected { .. }) => return Err("unexpected message".to_string()),
+            Some(CoordinatorMessage::Wait { secs }) => {
+                if !waiting {
+                    println!("⏳ Coordinator has no work right now. Waiting...");
+                    waiting = true;
+                }
+                thread::sleep(Duration::from_secs(secs));
+                continue;
+            },
+            Some(CoordinatorMessage::Work(unit)) => unit,
+        };
+        waiting = false;
+        if current.as_ref().is_none_or(|(address, challenge_id)| *address != unit.address || *challenge_id != unit.challenge.challenge_id) {
+            println!("\n⛏️ Mining {} / challenge {}.", unit.address, unit.challenge.challenge_id);
+            current = Some((unit.address.clone(), unit.challenge.challenge_id.clone()));
+        }
+
+        // The ROM build isn't part of the unit's hash rate
+        let key = &unit.challenge.no_pre_mine_key;
+        if rom.as_ref().is_none_or(|(loaded, _)| loaded != key) {
+            *rom = None;
+            *rom = Some((key.clone(), shadow_harvester_lib::shared_challenge_rom(key)));
+        }
+        let start = Instant::now();
+        let (nonce, hashes) = search_unit(&rom.as_ref().unwrap().1, &unit, threads);
+        let elapsed_ms = start.elapsed().as_millis() as u64;
+        let elapsed = elapsed_ms as f64 / 1000.0;
+        if let Some(nonce) = nonce {
+            println!("✅ Solution found: {:016x}. Reported to the coordinator.", nonce);
+        }
+        send_line(connection.get_mut(), &WorkerMessage::Result { job_id: unit.job_id, nonce, hashes, elapsed_ms: Some(elapsed_ms) })?;
+
+        if elapsed > 0.0 && nonce.is_none() {
+            let rate = hashes as f64 / elapsed;
+            *unit_nonces = ((rate * UNIT_SECS) as u64).clamp(threads as u64, MAX_UNIT_NONCES);
+            println!("⛏️ {} hashes in {} ({})", format_thousands(hashes), format_duration(elapsed), format_hashrate(rate));
+        }
+    }
+}
+
+/// Searches a unit on `threads` threads, thread `t` taking every `threads`-th nonce from `start + t`.
+/// The first solution stops the others. Returns it and (about) how many nonces were hashed.
+fn search_unit(rom: &Rom, unit: &WorkUnit, threads: u32) -> (Option<u64>, u64) {
+    let challenge = &unit.challenge;
+    let preimage = Preimage::new(&unit.address, &challenge.challenge_id, &challenge.difficulty,
+        &challenge.no_pre_mine_key, &challenge.latest_submission, &challenge.no_pre_mine_hour_str);
+    let target = Target { zero_bits: shadow_harvester_lib::difficulty_to_zero_bits(&challenge.difficulty) };
+    let step = (threads as u64).min(unit.count.max(1));
+    let cancel = AtomicBool::new(false);
+
+    let found = thread::scope(|scope| {
+        let handles: Vec<_> = (0..step).map(|t| {
+            let (preimage, cancel) = (preimage.clone(), &cancel);
+            scope.spawn(move || {
+                let range = NonceRange { start: unit.start.wrapping_add(t), step, count: (unit.count - t).div_ceil(step) };
+                let solution = Searcher::new(preimage).search(rom, target, range, cancel);
+                if solution.is_some() {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+                solution
+            })
+        }).collect();
+        handles.into_iter().filter_map(|handle| handle.join().ok().flatten()).next()
+    });
+    match found {
+        Some(solution) => (Some(solution.nonce), solution.nonce.wrapping_sub(unit.start).wrapping_add(1)),
+        None => (None, unit.count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A coordinator that isn't listening, with `job` being mined.
+    pub(super) fn coordinator(token: Option<&str>, job: Option<(u64, &str)>) -> Arc<Coordinator> {
+        let (report_sender, receiver) = channel();
+        Arc::new(Coordinator {
+            job: Mutex::new(job.map(|(id, address)| Job {
+                id,
+                address: address.to_string(),
+                challenge: challenge(),
+                next_nonce: 0x1000,
+                outstanding: HashMap::new(),
+                reclaimed: VecDeque::new(),
+            })),
+            next_job_id: AtomicU64::new(1),
+            reports: Mutex::new(receiver),
+            report_sender,
+            fleet: Mutex::new(HashMap::new()),
+            solutions: AtomicU64::new(0),
+            finished: Mutex::new(VecDeque::new()),
+            queue: None,
+            donate_to: None,
+            token: token.map(str::to_string),
+            tls: None,
+            control: Arc::new(ScavengeControl::new(1)),
+        })
+    }
+
+    pub(super) fn challenge() -> ChallengeData {
+        ChallengeData {
+            challenge_id: "**D01C05".to_string(),
+            difficulty: "000FFFFF".to_string(),
+            no_pre_mine_key: "e8a1958".to_string(),
+            no_pre_mine_hour_str: "123456".to_string(),
+            latest_submission: "2025-11-01T00:00:00Z".to_string(),
+            challenge_number: 5,
+            day: 1,
+            issued_at: "2025-10-31T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn worker_messages_round_trip_one_per_line() {
+        let mut wire = Vec::new();
+        send_line(&mut wire, &WorkerMessage::Auth { token: Some("secret".to_string()) }).unwrap();
+        send_line(&mut wire, &WorkerMessage::Request { nonces: 1000 }).unwrap();
+        send_line(&mut wire, &WorkerMessage::Result { job_id: 7, nonce: Some(0xabc), hashes: 42, elapsed_ms: Some(1500) }).unwrap();
+        assert_eq!(wire.iter().filter(|&&b| b == b'\n').count(), 3);
+
+        let mut reader = Cursor::new(wire);
+        assert!(matches!(read_line(&mut reader), Ok(Some(WorkerMessage::Auth { token: Some(t) })) if t == "secret"));
+        assert!(matches!(read_line(&mut reader), Ok(Some(WorkerMessage::Request { nonces: 1000 }))));
+        assert!(matches!(read_line(&mut reader),
+            Ok(Some(WorkerMessage::Result { job_id: 7, nonce: Some(0xabc), hashes: 42, elapsed_ms: Some(1500) }))));
+        assert!(matches!(read_line::<WorkerMessage>(&mut reader), Ok(None)));
+    }
+
+    #[test]
+    fn coordinator_messages_round_trip() {
+        let unit = WorkUnit { job_id: 3, address: "addr1test".to_string(), challenge: challenge(), start: u64::MAX - 1, count: 300_000 };
+        let mut wire = Vec::new();
+        send_line(&mut wire, &CoordinatorMessage::Work(unit)).unwrap();
+        send_line(&mut wire, &CoordinatorMessage::Wait { secs: 5 }).unwrap();
+        send_line(&mut wire, &CoordinatorMessage::Rejected { reason: "bad token".to_string() }).unwrap();
+
+        let mut reader = Cursor::new(wire);
+        match read_line(&mut reader) {
+            Ok(Some(CoordinatorMessage::Work(unit))) => {
+                assert_eq!((unit.job_id, unit.address.as_str(), unit.start, unit.count), (3, "addr1test", u64::MAX - 1, 300_000));
+                assert_eq!(unit.challenge.challenge_id, "**D01C05");
+                assert_eq!(unit.challenge.no_pre_mine_key, "e8a1958");
+            },
+            other => panic!("expected a work unit, got {:?}", other),
+        }
+        assert!(matches!(read_line(&mut reader), Ok(Some(CoordinatorMessage::Wait { secs: 5 }))));
+        assert!(matches!(read_line(&mut reader), Ok(Some(CoordinatorMessage::Rejected { reason })) if reason == "bad token"));
+    }
+
+    #[test]
+    fn reads_known_wire_format() {
+        // Workers that predate 'elapsed_ms' leave it out
+        let mut reader = Cursor::new(b"{\"type\":\"result\",\"job_id\":1,\"nonce\":null,\"hashes\":9}\n{\"type\":\"welcome\"}\n".to_vec());
+        assert!(matches!(read_line(&mut reader), Ok(Some(WorkerMessage::Result { job_id: 1, nonce: None, hashes: 9, elapsed_ms: None }))));
+        assert!(matches!(read_line(&mut reader), Ok(Some(CoordinatorMessage::Welcome))));
+        assert_eq!(serde_json::to_string(&WorkerMessage::Request { nonces: 5 }).unwrap(), "{\"type\":\"request\",\"nonces\":5}");
+    }
+
+    #[test]
+    fn solutions_are_checked_against_the_difficulty() {
+        use shadow_harvester_lib::RomGenerationType;
+        let rom = Rom::new(b"cluster verify", RomGenerationType::TwoStep { pre_size: 16 * 1024, mixing_numbers: 4 }, 256 * 1024);
+        let easy = ChallengeData { difficulty: "FFFFFFFF".to_string(), ..challenge() };
+        assert!((0..4).all(|nonce| solves(&rom, "addr1test", &easy, nonce)));
+        // 32 leading zero bits: no small nonce gets there
+        let hard = ChallengeData { difficulty: "00000000".to_string(), ..challenge() };
+        assert!((0..4).all(|nonce| !solves(&rom, "addr1test", &hard, nonce)));
+    }
+
+    #[test]
+    fn refuses_solutions_of_unknown_jobs_before_hashing() {
+        let coordinator = coordinator(None, Some((7, "addr1test")));
+        let report = Report { job_id: 3, nonce: Some(0xff), hashes: 10, elapsed: None };
+        assert!(matches!(coordinator.report("w1", Protocol::Line, report), Err(ReportError::Stale(e)) if e == "unknown job"));
+        assert!(coordinator.reports.lock().unwrap().try_recv().is_err());
+        assert_eq!(coordinator.current_job_id(), Some(7));
+    }
+
+    #[test]
+    fn a_pushed_challenge_stops_the_search() {
+        let coordinator = coordinator(None, None);
+        let control = Arc::new(ScavengeControl::new(1));
+        let challenge = ChallengeData { latest_submission: "2099-12-31T23:59:59Z".to_string(), ..challenge() };
+        let pusher = {
+            let control = control.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(300));
+                control.set_latest_challenge("**D01C06");
+            })
+        };
+        let (found, hashes, elapsed) = coordinator.mine("addr1test", &challenge, &control, NoncePartition::default());
+        pusher.join().unwrap();
+        assert_eq!((found, hashes), (None, 0));
+        assert!((0.3..10.0).contains(&elapsed), "stopped after {}s", elapsed);
+        assert_eq!(coordinator.current_job_id(), None);
+
+        // A search that starts after the push isn't stopped by it
+        control.start_time_slice(Some(Duration::from_millis(1500)));
+        let (_, _, elapsed) = coordinator.mine("addr1test", &challenge, &control, NoncePartition::default());
+        assert!(elapsed >= 1.5, "stopped after {}s", elapsed);
+    }
+
+    #[test]
+    fn rejects_malformed_and_oversized_lines() {
+        let mut reader = Cursor::new(b"{\"type\":\"unknown\"}\nnot json\n".to_vec());
+        assert!(read_line::<WorkerMessage>(&mut reader).is_err());
+        assert!(read_line::<WorkerMessage>(&mut reader).is_err());
+
+        let mut reader = Cursor::new(vec![b'x'; MAX_LINE_BYTES + 10]);
+        assert!(read_line::<WorkerMessage>(&mut reader).unwrap_err().contains("longer than"));
+    }
+}
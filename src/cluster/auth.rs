@@ -0,0 +1,59 @@
+// src/cluster/auth.rs
+
+// '--cluster-token' and TLS for cluster connections. With a token, the coordinator only serves workers
+// presenting it: in the line protocol's first message, as a gRPC 'authorization: Bearer' header, or as the
+// Stratum mining.authorize password. TLS ('--coordinator-tls-cert'/'--coordinator-tls-key', '--worker-tls')
+// covers the line protocol listener.
+
+use super::Coordinator;
+use native_tls::{Certificate, Identity, TlsAcceptor, TlsConnector};
+
+/// The cluster token from '--cluster-token' or the first line of '--cluster-token-file', if either is given.
+pub fn load_token(token: Option<&str>, token_file: Option<&str>) -> Result<Option<String>, String> {
+    let token = match (token, token_file) {
+        (Some(token), _) => token.to_string(),
+        (None, Some(path)) => std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read the cluster token from '{}': {}", path, e))?
+            .lines().next().unwrap_or_default().to_string(),
+        (None, None) => return Ok(None),
+    };
+    let token = token.trim().to_string();
+    if token.is_empty() {
+        return Err("The cluster token is empty.".to_string());
+    }
+    Ok(Some(token))
+}
+
+/// A TLS acceptor from a PEM certificate (chain) and PKCS#8 PEM key.
+pub(super) fn acceptor(cert_path: &str, key_path: &str) -> Result<TlsAcceptor, String> {
+    let read = |path: &str| std::fs::read(path).map_err(|e| format!("Could not read '{}': {}", path, e));
+    let identity = Identity::from_pkcs8(&read(cert_path)?, &read(key_path)?)
+        .map_err(|e| format!("Invalid TLS certificate or key ('{}', '{}'): {}", cert_path, key_path, e))?;
+    TlsAcceptor::new(identity).map_err(|e| format!("Could not set up TLS: {}", e))
+}
+
+/// A TLS connector trusting the system's roots, plus `ca_path` (a PEM CA or self-signed certificate) if given.
+pub(super) fn connector(ca_path: Option<&str>) -> Result<TlsConnector, String> {
+    let mut builder = TlsConnector::builder();
+    if let Some(path) = ca_path {
+        let pem = std::fs::read(path).map_err(|e| format!("Could not read '{}': {}", path, e))?;
+        builder.add_root_certificate(Certificate::from_pem(&pem).map_err(|e| format!("Invalid certificate '{}': {}", path, e))?);
+    }
+    builder.build().map_err(|e| format!("Could not set up TLS: {}", e))
+}
+
+impl Coordinator {
+    /// True if `token` is the cluster token, or the coordinator has none.
+    pub(super) fn authorized(&self, token: Option<&str>) -> bool {
+        match (&self.token, token) {
+            (None, _) => true,
+            (Some(expected), Some(token)) => constant_time_eq(expected.as_bytes(), token.as_bytes()),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Compares without stopping at the first difference, so response times don't leak the token.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
@@ -0,0 +1,199 @@
+// src/cluster/dashboard.rs
+
+// The coordinator's view of its workers across all three protocols: last heartbeat, hash rate, solutions
+// and errors per worker, shown next to the cluster's totals while it mines.
+
+use super::{Coordinator, GRPC_WORKER_ACTIVE_SECS, UNIT_SECS};
+use crate::utils::{format_duration, format_hashrate, format_thousands};
+use std::time::{Duration, Instant};
+
+// Disconnected workers stay on the dashboard this long
+const FORGET_SECS: u64 = 10 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Protocol {
+    Line,
+    Grpc,
+    Stratum,
+}
+
+impl Protocol {
+    fn name(self) -> &'static str {
+        match self {
+            Protocol::Line => "worker",
+            Protocol::Grpc => "gRPC",
+            Protocol::Stratum => "Stratum",
+        }
+    }
+
+    pub(super) fn title(self) -> &'static str {
+        match self {
+            Protocol::Line => "Worker",
+            Protocol::Grpc => "gRPC worker",
+            Protocol::Stratum => "Stratum worker",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct WorkerHealth {
+    protocol: Protocol,
+    /// A name the worker gave itself (gRPC worker id, Stratum worker), if different from its key.
+    name: Option<String>,
+    connected: bool,
+    last_seen: Instant,
+    last_report: Instant,
+    hashes: u64,
+    /// Rate of the last reported unit; Stratum workers don't report hashes.
+    hash_rate: Option<f64>,
+    solutions: u32,
+    /// Reported solutions that didn't solve their job when hashed again.
+    invalid_solutions: u32,
+    errors: u32,
+    last_error: Option<String>,
+}
+
+impl WorkerHealth {
+    fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            name: None,
+            connected: true,
+            last_seen: Instant::now(),
+            last_report: Instant::now(),
+            hashes: 0,
+            hash_rate: None,
+            solutions: 0,
+            invalid_solutions: 0,
+            errors: 0,
+            last_error: None,
+        }
+    }
+
+    /// Connected workers, and gRPC workers (which don't hold a connection) that asked for work recently.
+    fn active(&self) -> bool {
+        match self.protocol {
+            Protocol::Grpc => self.last_seen.elapsed() < Duration::from_secs(GRPC_WORKER_ACTIVE_SECS),
+            _ => self.connected,
+        }
+    }
+
+    /// The worker's line; `total_rate` is the cluster's, for the worker's share of the work.
+    fn line(&self, key: &str, total_rate: f64) -> String {
+        let seen = self.last_seen.elapsed().as_secs_f64();
+        let health = match () {
+            _ if !self.active() => "🔴",
+            _ if seen > 2.0 * UNIT_SECS => "🟡",
+            _ => "🟢",
+        };
+        let name = match &self.name {
+            Some(name) => format!("{} ({})", name, key),
+            None => key.to_string(),
+        };
+        let rate = match self.hash_rate.filter(|_| self.active()) {
+            Some(rate) if total_rate > 0.0 => format!("{} ({:.0}% of the work)", format_hashrate(rate), 100.0 * rate / total_rate),
+            Some(rate) => format_hashrate(rate),
+            None => "-".to_string(),
+        };
+        let mut line = format!("   {} {} [{}] seen {} ago | {} | {} hashes | {} solution(s) | {} error(s)",
+            health, name, self.protocol.name(), format_duration(seen), rate,
+            format_thousands(self.hashes), self.solutions, self.errors);
+        if self.invalid_solutions > 0 {
+            line.push_str(&format!(" | ❌ {} invalid solution(s)", self.invalid_solutions));
+        }
+        if let Some(error) = &self.last_error {
+            line.push_str(&format!(" (last: {})", error));
+        }
+        line
+    }
+}
+
+impl Coordinator {
+    fn with_worker<T>(&self, key: &str, protocol: Protocol, update: impl FnOnce(&mut WorkerHealth) -> T) -> T {
+        let mut fleet = self.fleet.lock().unwrap_or_else(|e| e.into_inner());
+        update(fleet.entry(key.to_string()).or_insert_with(|| WorkerHealth::new(protocol)))
+    }
+
+    /// Records a new connection and returns the number of active workers.
+    pub(super) fn connected(&self, key: &str, protocol: Protocol) -> usize {
+        self.fleet.lock().unwrap_or_else(|e| e.into_inner()).insert(key.to_string(), WorkerHealth::new(protocol));
+        self.worker_count()
+    }
+
+    /// Records a heartbeat. True on a worker's first contact (gRPC workers have no connection).
+    pub(super) fn seen(&self, key: &str, protocol: Protocol) -> bool {
+        let mut fleet = self.fleet.lock().unwrap_or_else(|e| e.into_inner());
+        let first_contact = !fleet.contains_key(key);
+        fleet.entry(key.to_string()).or_insert_with(|| WorkerHealth::new(protocol)).last_seen = Instant::now();
+        first_contact
+    }
+
+    pub(super) fn set_worker_name(&self, key: &str, protocol: Protocol, name: &str) {
+        self.with_worker(key, protocol, |health| health.name = Some(name.to_string()));
+    }
+
+    pub(super) fn worker_error(&self, key: &str, protocol: Protocol, error: &str) {
+        self.with_worker(key, protocol, |health| {
+            health.errors += 1;
+            health.last_error = Some(error.to_string());
+        });
+    }
+
+    pub(super) fn record_invalid_solution(&self, key: &str, protocol: Protocol) {
+        self.with_worker(key, protocol, |health| health.invalid_solutions += 1);
+    }
+
+    /// Logs the end of a worker connection, and hands its unit to the other workers.
+    pub(super) fn disconnected(&self, key: &str, protocol: Protocol, outcome: Result<(), String>) {
+        self.with_worker(key, protocol, |health| health.connected = false);
+        self.reclaim_disconnected(key);
+        if let Err(e) = &outcome {
+            self.worker_error(key, protocol, e);
+        }
+        let connected = self.worker_count();
+        match outcome {
+            Ok(()) => println!("🔗 {} {} disconnected ({} connected).", protocol.title(), key, connected),
+            Err(e) => eprintln!("⚠️ {} {} dropped: {} ({} connected).", protocol.title(), key, e, connected),
+        }
+    }
+
+    /// Accounts a worker's report: `hashes` hashed in `elapsed`, and a solution if it found one. Workers that
+    /// don't say how long they hashed are measured from their last report, which counts their idle time too.
+    pub(super) fn record_report(&self, key: &str, protocol: Protocol, hashes: u64, elapsed: Option<Duration>, solved: bool) {
+        self.with_worker(key, protocol, |health| {
+            health.last_seen = Instant::now();
+            if hashes > 0 {
+                let elapsed = elapsed.map_or_else(|| health.last_report.elapsed().as_secs_f64().max(1.0), |elapsed| elapsed.as_secs_f64().max(0.001));
+                health.hash_rate = Some(hashes as f64 / elapsed);
+                health.hashes += hashes;
+                health.last_report = Instant::now();
+            }
+            if solved {
+                health.solutions += 1;
+            }
+        });
+    }
+
+    /// The rate of `key`'s last reported unit, if it reports hashes.
+    pub(super) fn worker_hash_rate(&self, key: &str) -> Option<f64> {
+        self.fleet.lock().unwrap_or_else(|e| e.into_inner()).get(key).and_then(|health| health.hash_rate)
+    }
+
+    pub(super) fn worker_count(&self) -> usize {
+        self.fleet.lock().unwrap_or_else(|e| e.into_inner()).values().filter(|health| health.active()).count()
+    }
+
+    /// The per-worker dashboard, most recently seen first. Forgets workers gone for a while.
+    pub(super) fn dashboard(&self) -> Vec<String> {
+        let mut fleet = self.fleet.lock().unwrap_or_else(|e| e.into_inner());
+        fleet.retain(|_, health| health.active() || health.last_seen.elapsed() < Duration::from_secs(FORGET_SECS));
+        let mut workers: Vec<_> = fleet.iter().collect();
+        workers.sort_by_key(|(_, health)| health.last_seen.elapsed());
+        let active: Vec<_> = workers.iter().filter(|(_, health)| health.active()).collect();
+        let total_rate = active.iter().filter_map(|(_, health)| health.hash_rate).sum();
+        let mut lines = vec![format!("👷 Workers: {} active, {} reporting {}", active.len(),
+            active.iter().filter(|(_, health)| health.hash_rate.is_some()).count(), format_hashrate(total_rate))];
+        lines.extend(workers.iter().map(|(key, health)| health.line(key, total_rate)));
+        lines
+    }
+}
@@ -0,0 +1,312 @@
+// src/cluster/discovery.rs
+
+// '--coordinator-announce' / '--worker --auto-discover': the coordinator answers mDNS (RFC 6762) queries for
+// _shadow-harvester._tcp.local with a PTR, SRV and A record, so workers on the same LAN find it without an
+// address. Only what DNS-SD browsers and our workers need is implemented: IPv4, one-shot queries.
+
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SERVICE: &str = "_shadow-harvester._tcp.local";
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+// How long a worker collects answers to one query
+const DISCOVERY_SECS: u64 = 3;
+const RECORD_TTL_SECS: u32 = 120;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+// Unicast-response bit of the question class, cache-flush bit of the record class
+const CLASS_TOP_BIT: u16 = 0x8000;
+
+// ===============================================
+// COORDINATOR (responder)
+// ===============================================
+
+/// Answers queries for the coordinator listening on `port` from a background thread.
+pub(super) fn announce(port: u16) -> Result<(), String> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+        .map_err(|e| format!("Could not open the mDNS socket: {}", e))?;
+    // Shared with the system's mDNS daemon, if any
+    socket.set_reuse_address(true).map_err(|e| format!("Could not share the mDNS port: {}", e))?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true).map_err(|e| format!("Could not share the mDNS port: {}", e))?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, MDNS_PORT).into())
+        .map_err(|e| format!("Could not listen for mDNS queries on port {}: {}", MDNS_PORT, e))?;
+    let socket: UdpSocket = socket.into();
+    socket.join_multicast_v4(&MDNS_GROUP, &Ipv4Addr::UNSPECIFIED)
+        .map_err(|e| format!("Could not join the mDNS group: {}", e))?;
+
+    let host = crate::utils::hostname().replace('.', "-");
+    let instance = format!("{}.{}", host, SERVICE);
+    println!("📡 Announcing the coordinator on the LAN as '{}' (mDNS).", instance);
+    thread::spawn(move || {
+        let mut packet = [0u8; 1500];
+        loop {
+            let (len, SocketAddr::V4(peer)) = (match socket.recv_from(&mut packet) {
+                Ok(received) => received,
+                Err(e) => {
+                    eprintln!("⚠️ mDNS receive failed: {}", e);
+                    thread::sleep(Duration::from_secs(1));
+                    continue;
+                },
+            }) else { continue };
+            let Some(id) = parse_query(&packet[..len]) else { continue };
+            let Some(ip) = local_ip_towards(*peer.ip()) else { continue };
+            // Queries from other ports are one-shot (legacy unicast) and are answered directly
+            let (id, destination) = match peer.port() {
+                MDNS_PORT => (0, SocketAddrV4::new(MDNS_GROUP, MDNS_PORT)),
+                _ => (id, peer),
+            };
+            let response = build_response(id, &instance, &format!("{}.local", host), ip, port);
+            if let Err(e) = socket.send_to(&response, destination) {
+                eprintln!("⚠️ mDNS answer to {} failed: {}", peer, e);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// The id of a query asking for our service, if `packet` is one.
+fn parse_query(packet: &[u8]) -> Option<u16> {
+    let (id, flags, questions) = (read_u16(packet, 0)?, read_u16(packet, 2)?, read_u16(packet, 4)?);
+    if flags & 0x8000 != 0 {
+        return None;
+    }
+    let mut offset = 12;
+    for _ in 0..questions {
+        let (name, next) = read_name(packet, offset)?;
+        let qtype = read_u16(packet, next)?;
+        offset = next + 4;
+        if name.eq_ignore_ascii_case(SERVICE) && matches!(qtype, TYPE_PTR | TYPE_ANY) {
+            return Some(id);
+        }
+    }
+    None
+}
+
+/// The address of this machine's interface on the route to `peer`.
+fn local_ip_towards(peer: Ipv4Addr) -> Option<Ipv4Addr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((peer, MDNS_PORT)).ok()?;
+    match socket.local_addr().ok()? {
+        SocketAddr::V4(local) if !local.ip().is_unspecified() => Some(*local.ip()),
+        _ => None,
+    }
+}
+
+fn build_response(id: u16, instance: &str, target: &str, ip: Ipv4Addr, port: u16) -> Vec<u8> {
+    let mut packet = Vec::new();
+    // Authoritative answer: one PTR, with the SRV and A records as additional records
+    for field in [id, 0x8400, 0, 1, 0, 2] {
+        packet.extend_from_slice(&field.to_be_bytes());
+    }
+    let mut srv = vec![0, 0, 0, 0];
+    srv.extend_from_slice(&port.to_be_bytes());
+    srv.extend(encode_name(target));
+    push_record(&mut packet, SERVICE, TYPE_PTR, CLASS_IN, &encode_name(instance));
+    push_record(&mut packet, instance, TYPE_SRV, CLASS_IN | CLASS_TOP_BIT, &srv);
+    push_record(&mut packet, target, TYPE_A, CLASS_IN | CLASS_TOP_BIT, &ip.octets());
+    packet
+}
+
+fn push_record(packet: &mut Vec<u8>, name: &str, rtype: u16, class: u16, data: &[u8]) {
+    packet.extend(encode_name(name));
+    packet.extend_from_slice(&rtype.to_be_bytes());
+    packet.extend_from_slice(&class.to_be_bytes());
+    packet.extend_from_slice(&RECORD_TTL_SECS.to_be_bytes());
+    packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    packet.extend_from_slice(data);
+}
+
+// ===============================================
+// WORKER (query)
+// ===============================================
+
+/// The address (ip:port) of a coordinator announcing itself on the LAN.
+pub(super) fn discover() -> Result<String, String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(|e| format!("Could not open the mDNS socket: {}", e))?;
+    let mut query = Vec::new();
+    let id = std::process::id() as u16;
+    for field in [id, 0, 1, 0, 0, 0] {
+        query.extend_from_slice(&field.to_be_bytes());
+    }
+    query.extend(encode_name(SERVICE));
+    query.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    query.extend_from_slice(&CLASS_IN.to_be_bytes());
+    socket.send_to(&query, (MDNS_GROUP, MDNS_PORT)).map_err(|e| format!("Could not send the mDNS query: {}", e))?;
+
+    let deadline = Instant::now() + Duration::from_secs(DISCOVERY_SECS);
+    let mut packet = [0u8; 1500];
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) {
+        socket.set_read_timeout(Some(remaining)).map_err(|e| e.to_string())?;
+        let Ok((len, SocketAddr::V4(peer))) = socket.recv_from(&mut packet) else { continue };
+        if let Some((instance, address)) = parse_response(&packet[..len], *peer.ip()) {
+            println!("🔎 Found coordinator '{}' at {}.", instance, address);
+            return Ok(address.to_string());
+        }
+    }
+    Err(format!("No coordinator answered on the LAN within {}s (is it running with '--coordinator-announce'?)", DISCOVERY_SECS))
+}
+
+/// The instance and address of the first coordinator in an answer. The A record's address is preferred
+/// over the sender's.
+fn parse_response(packet: &[u8], sender: Ipv4Addr) -> Option<(String, SocketAddrV4)> {
+    if read_u16(packet, 2)? & 0x8000 == 0 {
+        return None;
+    }
+    let questions = read_u16(packet, 4)?;
+    let records = read_u16(packet, 6)? as usize + read_u16(packet, 8)? as usize + read_u16(packet, 10)? as usize;
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = read_name(packet, offset)?.1 + 4;
+    }
+    let mut instance = None;
+    let mut srv = None;
+    let mut addresses = Vec::new();
+    for _ in 0..records {
+        let (name, next) = read_name(packet, offset)?;
+        let rtype = read_u16(packet, next)?;
+        let len = read_u16(packet, next + 8)? as usize;
+        let data = next + 10;
+        packet.get(data..data + len)?;
+        match rtype {
+            TYPE_PTR if name.eq_ignore_ascii_case(SERVICE) => instance = Some(read_name(packet, data)?.0),
+            TYPE_SRV => srv = Some((name, read_u16(packet, data + 4)?, read_name(packet, data + 6)?.0)),
+            TYPE_A if len == 4 => addresses.push((name, Ipv4Addr::new(packet[data], packet[data + 1], packet[data + 2], packet[data + 3]))),
+            _ => {},
+        }
+        offset = data + len;
+    }
+    let instance = instance?;
+    let (_, port, target) = srv.filter(|(name, _, _)| name.eq_ignore_ascii_case(&instance))?;
+    let ip = addresses.iter().find(|(name, _)| name.eq_ignore_ascii_case(&target)).map_or(sender, |(_, ip)| *ip);
+    Some((instance, SocketAddrV4::new(ip, port)))
+}
+
+// ===============================================
+// DNS WIRE FORMAT
+// ===============================================
+
+fn encode_name(name: &str) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(name.len() + 2);
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        encoded.push(label.len().min(63) as u8);
+        encoded.extend_from_slice(&label.as_bytes()[..label.len().min(63)]);
+    }
+    encoded.push(0);
+    encoded
+}
+
+/// The dotted name at `offset` (following compression pointers) and the offset after it.
+fn read_name(packet: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut end = None;
+    // Bounds the pointer chain, so a malicious loop can't hang the reader
+    for _ in 0..128 {
+        let len = *packet.get(offset)? as usize;
+        match len {
+            0 => return Some((labels.join("."), end.unwrap_or(offset + 1))),
+            _ if len & 0xC0 == 0xC0 => {
+                end.get_or_insert(offset + 2);
+                offset = (read_u16(packet, offset)? & 0x3FFF) as usize;
+            },
+            _ => {
+                labels.push(String::from_utf8_lossy(packet.get(offset + 1..offset + 1 + len)?).into_owned());
+                offset += 1 + len;
+            },
+        }
+    }
+    None
+}
+
+fn read_u16(packet: &[u8], offset: usize) -> Option<u16> {
+    packet.get(offset..offset + 2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INSTANCE: &str = "rig-1._shadow-harvester._tcp.local";
+
+    // A one-shot PTR query for the service, as 'discover' sends it, with id 0x1234
+    fn query(qtype: u16) -> Vec<u8> {
+        let mut packet = vec![0x12, 0x34, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        packet.extend(b"\x11_shadow-harvester\x04_tcp\x05local\x00");
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&(CLASS_IN | CLASS_TOP_BIT).to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn encodes_names_as_labels() {
+        assert_eq!(encode_name(SERVICE), b"\x11_shadow-harvester\x04_tcp\x05local\x00".to_vec());
+        assert_eq!(encode_name("rig.local."), b"\x03rig\x05local\x00".to_vec());
+        assert_eq!(read_name(&encode_name(INSTANCE), 0), Some((INSTANCE.to_string(), INSTANCE.len() + 2)));
+    }
+
+    #[test]
+    fn answers_queries_for_the_service_only() {
+        assert_eq!(parse_query(&query(TYPE_PTR)), Some(0x1234));
+        assert_eq!(parse_query(&query(TYPE_ANY)), Some(0x1234));
+        assert_eq!(parse_query(&query(TYPE_A)), None);
+
+        let mut other = vec![0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        other.extend(encode_name("_http._tcp.local"));
+        other.extend_from_slice(&[0, 12, 0, 1]);
+        assert_eq!(parse_query(&other), None);
+
+        // A response, even for the service, isn't a query
+        let mut response = query(TYPE_PTR);
+        response[2] = 0x84;
+        assert_eq!(parse_query(&response), None);
+    }
+
+    #[test]
+    fn response_round_trips() {
+        let packet = build_response(0x1234, INSTANCE, "rig-1.local", Ipv4Addr::new(192, 168, 1, 20), 7890);
+        assert_eq!(&packet[..12], &[0x12, 0x34, 0x84, 0x00, 0, 0, 0, 1, 0, 0, 0, 2]);
+        let found = parse_response(&packet, Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(found, Some((INSTANCE.to_string(), SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 20), 7890))));
+    }
+
+    #[test]
+    fn falls_back_to_the_sender_without_an_a_record() {
+        let mut packet = Vec::new();
+        for field in [0u16, 0x8400, 0, 1, 0, 1] {
+            packet.extend_from_slice(&field.to_be_bytes());
+        }
+        let mut srv = vec![0, 0, 0, 0];
+        srv.extend_from_slice(&7890u16.to_be_bytes());
+        srv.extend(encode_name("rig-1.local"));
+        push_record(&mut packet, SERVICE, TYPE_PTR, CLASS_IN, &encode_name(INSTANCE));
+        push_record(&mut packet, INSTANCE, TYPE_SRV, CLASS_IN, &srv);
+        let sender = Ipv4Addr::new(10, 0, 0, 7);
+        assert_eq!(parse_response(&packet, sender), Some((INSTANCE.to_string(), SocketAddrV4::new(sender, 7890))));
+    }
+
+    #[test]
+    fn follows_compression_pointers() {
+        // "rig-1" then a pointer to the service name at offset 12
+        let mut packet = query(TYPE_PTR);
+        let pointer_at = packet.len();
+        packet.extend(b"\x05rig-1\xc0\x0c");
+        assert_eq!(read_name(&packet, pointer_at), Some((INSTANCE.to_string(), packet.len())));
+    }
+
+    #[test]
+    fn rejects_truncated_and_looping_packets() {
+        let packet = build_response(1, INSTANCE, "rig-1.local", Ipv4Addr::LOCALHOST, 7890);
+        for len in [0, 5, 12, 40, packet.len() - 1] {
+            assert_eq!(parse_response(&packet[..len], Ipv4Addr::LOCALHOST), None, "truncated to {} bytes", len);
+        }
+        // A pointer to itself
+        assert_eq!(read_name(&[0xc0, 0x00], 0), None);
+    }
+}
@@ -0,0 +1,316 @@
+// src/cluster/grpc.rs
+
+// '--coordinator-grpc': the coordinator's work units over the versioned gRPC service of
+// proto/shadowharvester/work/v1/work.proto, for workers that aren't shadow-harvester (e.g. GPU-only
+// clients). Unary calls only, served directly over HTTP/2 without compression. With a cluster token, calls
+// need an 'authorization: Bearer <token>' header.
+
+use super::{Coordinator, CoordinatorMessage, Protocol, Report};
+use bytes::{BufMut, Bytes, BytesMut};
+use http::{HeaderMap, HeaderValue, Request, Response};
+use prost::Message;
+use std::sync::Arc;
+use std::time::Duration;
+
+const SERVICE_PATH: &str = "/shadowharvester.work.v1.WorkDistribution/";
+
+// ===============================================
+// MESSAGES (mirror proto/shadowharvester/work/v1/work.proto)
+// ===============================================
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetWorkRequest {
+    #[prost(string, tag = "1")]
+    pub worker_id: String,
+    #[prost(uint64, tag = "2")]
+    pub nonces: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Challenge {
+    #[prost(string, tag = "1")]
+    pub challenge_id: String,
+    #[prost(string, tag = "2")]
+    pub difficulty: String,
+    #[prost(string, tag = "3")]
+    pub no_pre_mine: String,
+    #[prost(string, tag = "4")]
+    pub no_pre_mine_hour: String,
+    #[prost(string, tag = "5")]
+    pub latest_submission: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct WorkUnit {
+    #[prost(uint64, tag = "1")]
+    pub job_id: u64,
+    #[prost(string, tag = "2")]
+    pub address: String,
+    #[prost(message, optional, tag = "3")]
+    pub challenge: Option<Challenge>,
+    #[prost(uint64, tag = "4")]
+    pub start_nonce: u64,
+    #[prost(uint64, tag = "5")]
+    pub nonce_count: u64,
+    #[prost(uint32, tag = "6")]
+    pub required_zero_bits: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetWorkResponse {
+    #[prost(oneof = "Work", tags = "1, 2")]
+    pub work: Option<Work>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum Work {
+    #[prost(message, tag = "1")]
+    Unit(WorkUnit),
+    #[prost(uint64, tag = "2")]
+    WaitSecs(u64),
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SubmitSolutionRequest {
+    #[prost(string, tag = "1")]
+    pub worker_id: String,
+    #[prost(uint64, tag = "2")]
+    pub job_id: u64,
+    #[prost(uint64, tag = "3")]
+    pub nonce: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SubmitSolutionResponse {
+    #[prost(bool, tag = "1")]
+    pub accepted: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ReportStatsRequest {
+    #[prost(string, tag = "1")]
+    pub worker_id: String,
+    #[prost(uint64, tag = "2")]
+    pub job_id: u64,
+    #[prost(uint64, tag = "3")]
+    pub hashes: u64,
+    #[prost(uint64, tag = "4")]
+    pub elapsed_ms: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ReportStatsResponse {}
+
+// ===============================================
+// SERVICE
+// ===============================================
+
+impl Coordinator {
+    fn grpc_get_work(&self, request: GetWorkRequest) -> GetWorkResponse {
+        if self.seen(&request.worker_id, Protocol::Grpc) {
+            println!("🔗 gRPC worker '{}' checked in.", request.worker_id);
+        }
+        let work = match self.next_unit(&request.worker_id, Protocol::Grpc, request.nonces) {
+            CoordinatorMessage::Wait { secs } => Work::WaitSecs(secs),
+            CoordinatorMessage::Work(unit) => Work::Unit(WorkUnit {
+                job_id: unit.job_id,
+                address: unit.address,
+                required_zero_bits: shadow_harvester_lib::difficulty_to_zero_bits(&unit.challenge.difficulty) as u32,
+                challenge: Some(Challenge {
+                    challenge_id: unit.challenge.challenge_id,
+                    difficulty: unit.challenge.difficulty,
+                    no_pre_mine: unit.challenge.no_pre_mine_key,
+                    no_pre_mine_hour: unit.challenge.no_pre_mine_hour_str,
+                    latest_submission: unit.challenge.latest_submission,
+                }),
+                start_nonce: unit.start,
+                nonce_count: unit.count,
+            }),
+            CoordinatorMessage::Welcome | CoordinatorMessage::Rejected { .. } => unreachable!("next_unit only hands out work"),
+        };
+        GetWorkResponse { work: Some(work) }
+    }
+
+    fn grpc_submit_solution(&self, request: SubmitSolutionRequest) -> SubmitSolutionResponse {
+        println!("✅ gRPC worker '{}' reports solution {:016x}.", request.worker_id, request.nonce);
+        let result = self.report(&request.worker_id, Protocol::Grpc, Report { job_id: request.job_id, nonce: Some(request.nonce), hashes: 0, elapsed: None });
+        if let Err(e) = &result {
+            self.worker_error(&request.worker_id, Protocol::Grpc, &e.to_string());
+        }
+        let accepted = result.is_ok();
+        SubmitSolutionResponse { accepted }
+    }
+
+    fn grpc_report_stats(&self, request: ReportStatsRequest) -> ReportStatsResponse {
+        // Without a solution, a report can't fail
+        let _ = self.report(&request.worker_id, Protocol::Grpc, Report {
+            job_id: request.job_id,
+            nonce: None,
+            hashes: request.hashes,
+            elapsed: Some(request.elapsed_ms).filter(|ms| *ms > 0).map(Duration::from_millis),
+        });
+        ReportStatsResponse {}
+    }
+
+    /// The response to a call of `method`, encoded, or a gRPC status code and message.
+    fn grpc_call(&self, method: &str, request: &[u8]) -> Result<Vec<u8>, (u32, String)> {
+        fn decode<T: Message + Default>(request: &[u8]) -> Result<T, (u32, String)> {
+            T::decode(request).map_err(|e| (STATUS_INVALID_ARGUMENT, format!("Malformed request: {}", e)))
+        }
+        Ok(match method {
+            "GetWork" => self.grpc_get_work(decode(request)?).encode_to_vec(),
+            "SubmitSolution" => self.grpc_submit_solution(decode(request)?).encode_to_vec(),
+            "ReportStats" => self.grpc_report_stats(decode(request)?).encode_to_vec(),
+            _ => return Err((STATUS_UNIMPLEMENTED, format!("Unknown method '{}'", method))),
+        })
+    }
+}
+
+// ===============================================
+// SERVER (gRPC over HTTP/2)
+// ===============================================
+
+const STATUS_OK: u32 = 0;
+const STATUS_INVALID_ARGUMENT: u32 = 3;
+const STATUS_RESOURCE_EXHAUSTED: u32 = 8;
+const STATUS_UNIMPLEMENTED: u32 = 12;
+const STATUS_INTERNAL: u32 = 13;
+const STATUS_UNAUTHENTICATED: u32 = 16;
+// The service's requests are a few dozen bytes; anything much bigger isn't one of them
+const MAX_FRAME_BYTES: usize = 64 * 1024;
+
+/// Serves the work distribution service on `bind` from a background thread. Fails if `bind` can't be listened on.
+pub(super) fn serve(bind: &str, coordinator: Arc<Coordinator>) -> Result<(), String> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()
+        .map_err(|e| format!("Could not start the gRPC runtime: {}", e))?;
+    let listener = runtime.block_on(tokio::net::TcpListener::bind(bind))
+        .map_err(|e| format!("Could not listen for gRPC workers on {}: {}", bind, e))?;
+    println!("📡 gRPC work service (shadowharvester.work.v1) listening on {}.", listener.local_addr().map_or(bind.to_string(), |a| a.to_string()));
+
+    std::thread::spawn(move || runtime.block_on(async move {
+        loop {
+            match listener.accept().await {
+                Ok((socket, _)) => { tokio::spawn(serve_connection(socket, coordinator.clone())); },
+                Err(e) => eprintln!("⚠️ gRPC worker connection failed: {}", e),
+            }
+        }
+    }));
+    Ok(())
+}
+
+async fn serve_connection(socket: tokio::net::TcpStream, coordinator: Arc<Coordinator>) {
+    let mut connection = match h2::server::handshake(socket).await {
+        Ok(connection) => connection,
+        Err(e) => return eprintln!("⚠️ gRPC handshake failed: {}", e),
+    };
+    while let Some(Ok((request, respond))) = connection.accept().await {
+        tokio::spawn(serve_call(request, respond, coordinator.clone()));
+    }
+}
+
+async fn serve_call(request: Request<h2::RecvStream>, mut respond: h2::server::SendResponse<Bytes>, coordinator: Arc<Coordinator>) {
+    let method = request.uri().path().strip_prefix(SERVICE_PATH).map(str::to_string);
+    let token = request.headers().get("authorization").and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    // Refused from the headers, before an unauthenticated client gets to send a body
+    let result = match method {
+        _ if !coordinator.authorized(token) => Err((STATUS_UNAUTHENTICATED, "Missing or wrong cluster token".to_string())),
+        None => Err((STATUS_UNIMPLEMENTED, "Unknown service".to_string())),
+        Some(method) => match read_body(request.into_body()).await {
+            Ok(frame) => match decode_frame(&frame) {
+                Ok(message) => {
+                    // The coordinator blocks (on its locks, the store and the API), which an async task must not
+                    let message = message.to_vec();
+                    tokio::task::spawn_blocking(move || coordinator.grpc_call(&method, &message)).await
+                        .unwrap_or_else(|e| Err((STATUS_INTERNAL, e.to_string())))
+                },
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        },
+    };
+
+    let response = Response::builder().status(200).header("content-type", "application/grpc");
+    let _ = match result {
+        Ok(message) => {
+            let data = encode_frame(&message);
+            let mut trailers = HeaderMap::new();
+            trailers.insert("grpc-status", HeaderValue::from(STATUS_OK));
+            respond.send_response(response.body(()).unwrap(), false)
+                .and_then(|mut stream| stream.send_data(data, false).and_then(|_| stream.send_trailers(trailers)))
+        },
+        // Trailers-only response
+        Err((status, message)) => {
+            let response = response.header("grpc-status", status)
+                .header("grpc-message", HeaderValue::from_str(&message).unwrap_or(HeaderValue::from_static("error")));
+            respond.send_response(response.body(()).unwrap(), true).map(|_| ())
+        },
+    };
+}
+
+/// The request body, up to MAX_FRAME_BYTES.
+async fn read_body(mut body: h2::RecvStream) -> Result<Vec<u8>, (u32, String)> {
+    let mut frame = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| (STATUS_INTERNAL, e.to_string()))?;
+        let _ = body.flow_control().release_capacity(chunk.len());
+        if frame.len() + chunk.len() > MAX_FRAME_BYTES {
+            return Err((STATUS_RESOURCE_EXHAUSTED, format!("Requests are limited to {} bytes", MAX_FRAME_BYTES)));
+        }
+        frame.extend_from_slice(&chunk);
+    }
+    Ok(frame)
+}
+
+/// Frames one gRPC message: compressed flag, big-endian length, then the protobuf encoding.
+fn encode_frame(message: &[u8]) -> Bytes {
+    let mut data = BytesMut::with_capacity(5 + message.len());
+    data.put_u8(0);
+    data.put_u32(message.len() as u32);
+    data.put_slice(message);
+    data.freeze()
+}
+
+/// The message of a request body holding exactly one uncompressed gRPC message.
+fn decode_frame(frame: &[u8]) -> Result<&[u8], (u32, String)> {
+    match frame.split_first_chunk::<5>() {
+        Some(([1, ..], _)) => Err((STATUS_UNIMPLEMENTED, "Compressed messages are not supported".to_string())),
+        Some((&[0, a, b, c, d], message)) if message.len() == u32::from_be_bytes([a, b, c, d]) as usize => Ok(message),
+        _ => Err((STATUS_INTERNAL, "Expected exactly one message".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_known_message() {
+        // Field 1 "w", field 2 varint 150
+        let message = GetWorkRequest { worker_id: "w".to_string(), nonces: 150 }.encode_to_vec();
+        assert_eq!(message, [0x0a, 0x01, b'w', 0x10, 0x96, 0x01]);
+        let frame = encode_frame(&message);
+        assert_eq!(&frame[..], &[0, 0, 0, 0, 6, 0x0a, 0x01, b'w', 0x10, 0x96, 0x01]);
+        assert_eq!(decode_frame(&frame), Ok(&message[..]));
+    }
+
+    #[test]
+    fn messages_round_trip_through_frames() {
+        let report = ReportStatsRequest { worker_id: "gpu-7".to_string(), job_id: 42, hashes: 1 << 40, elapsed_ms: 30_000 };
+        let frame = encode_frame(&report.encode_to_vec());
+        assert_eq!(ReportStatsRequest::decode(decode_frame(&frame).unwrap()).unwrap(), report);
+
+        let empty = encode_frame(&ReportStatsResponse {}.encode_to_vec());
+        assert_eq!(&empty[..], &[0, 0, 0, 0, 0]);
+        assert_eq!(decode_frame(&empty), Ok(&[][..]));
+    }
+
+    #[test]
+    fn rejects_compressed_and_malformed_frames() {
+        assert_eq!(decode_frame(&[1, 0, 0, 0, 1, 0]).unwrap_err().0, STATUS_UNIMPLEMENTED);
+        // Truncated header, short message, trailing second message
+        for frame in [&[0, 0, 0][..], &[0, 0, 0, 0, 4, 1, 2], &[0, 0, 0, 0, 1, 9, 0, 0, 0, 0, 0]] {
+            assert_eq!(decode_frame(frame).unwrap_err().0, STATUS_INTERNAL, "{:?}", frame);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+// src/cluster/stratum.rs
+
+// '--coordinator-stratum': the coordinator's work over a Stratum-style JSON-RPC line protocol, so mining
+// farm tooling can drive generic workers. Registration, CIP-8 signing and submission stay with the
+// coordinator; workers only see addresses, challenges and nonce ranges.
+//
+// Worker calls: mining.subscribe, mining.authorize [worker, password] (the cluster token, if any) and
+// mining.submit [worker, job_id, nonce as 16 hex digits]. The coordinator pushes mining.set_difficulty
+// [leading zero bits] and mining.notify [job_id, address, challenge_id, difficulty, no_pre_mine,
+// latest_submission, no_pre_mine_hour, start nonce as 16 hex digits, nonce count, clean_jobs] whenever it
+// switches wallets. The hash input is built as in the gRPC service (see proto/).
+
+use super::{Coordinator, CoordinatorMessage, MAX_LINE_BYTES, MAX_UNIT_NONCES, Protocol, Report, ReportError, send_line};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, ErrorKind, Read};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// How often a connection checks for a new job to notify while waiting for the worker's calls
+const NOTIFY_POLL: Duration = Duration::from_secs(1);
+
+// Stratum error codes
+const ERROR_OTHER: u32 = 20;
+const ERROR_JOB_NOT_FOUND: u32 = 21;
+const ERROR_LOW_DIFFICULTY: u32 = 23;
+const ERROR_UNAUTHORIZED: u32 = 24;
+
+#[derive(Debug, Deserialize)]
+struct StratumRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Vec<Value>,
+}
+
+#[derive(Debug, Default)]
+struct Session {
+    /// The worker's address, doubling as its subscription id.
+    peer: String,
+    subscribed: bool,
+    worker: Option<String>,
+    notified_job: Option<u64>,
+}
+
+/// Serves Stratum workers on `bind`, one thread per connection. Fails if `bind` can't be listened on.
+pub(super) fn serve(bind: &str, coordinator: Arc<Coordinator>) -> Result<(), String> {
+    let listener = TcpListener::bind(bind).map_err(|e| format!("Could not listen for Stratum workers on {}: {}", bind, e))?;
+    println!("📡 Stratum work service listening on {}.", listener.local_addr().map_or(bind.to_string(), |a| a.to_string()));
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let coordinator = coordinator.clone();
+                    thread::spawn(move || coordinator.serve_stratum(stream));
+                },
+                Err(e) => eprintln!("⚠️ Stratum worker connection failed: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+impl Coordinator {
+    fn serve_stratum(&self, mut stream: TcpStream) {
+        let peer = stream.peer_addr().map_or("unknown".to_string(), |a| a.to_string());
+        let Ok(read_half) = stream.try_clone() else { return };
+        // Reads time out so new jobs are pushed without waiting for the worker
+        if read_half.set_read_timeout(Some(NOTIFY_POLL)).is_err() {
+            return;
+        }
+        let mut reader = BufReader::new(read_half);
+        println!("🔗 Stratum worker {} connected ({} connected).", peer, self.connected(&peer, Protocol::Stratum));
+
+        let mut session = Session { peer: peer.clone(), ..Session::default() };
+        let mut line = Vec::new();
+        let outcome = loop {
+            // Never past MAX_LINE_BYTES, so a client sending no newline can't grow `line` without bound
+            let remaining = (MAX_LINE_BYTES - line.len()) as u64;
+            match (&mut reader).take(remaining).read_until(b'\n', &mut line) {
+                Ok(0) => break Ok(()),
+                Ok(_) if line.ends_with(b"\n") => {
+                    let call = std::mem::take(&mut line);
+                    self.seen(&peer, Protocol::Stratum);
+                    if let Err(e) = self.stratum_call(&mut stream, &mut session, &call) {
+                        break Err(e);
+                    }
+                },
+                Ok(_) if line.len() >= MAX_LINE_BYTES => break Err(format!("Request longer than {} bytes", MAX_LINE_BYTES)),
+                // A partial line stays in `line` until the rest arrives
+                Ok(_) => {},
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {},
+                Err(e) => break Err(e.to_string()),
+            }
+            if let Err(e) = self.stratum_notify(&mut stream, &mut session) {
+                break Err(e);
+            }
+        };
+        self.disconnected(&peer, Protocol::Stratum, outcome);
+    }
+
+    fn stratum_call(&self, stream: &mut TcpStream, session: &mut Session, line: &[u8]) -> Result<(), String> {
+        let request: StratumRequest = match serde_json::from_slice(line) {
+            Ok(request) => request,
+            Err(e) => {
+                let message = format!("Malformed request: {}", e);
+                self.worker_error(&session.peer, Protocol::Stratum, &message);
+                return send_line(stream, &json!({ "id": null, "result": null, "error": [ERROR_OTHER, message, null] }));
+            },
+        };
+        let result = match request.method.as_str() {
+            "mining.subscribe" => {
+                session.subscribed = true;
+                // Nonce ranges come with each job, so there is no extranonce to split
+                Ok(json!([[["mining.notify", session.peer]], "", 0]))
+            },
+            "mining.authorize" if !self.authorized(request.params.get(1).and_then(Value::as_str)) => {
+                eprintln!("⚠️ Stratum worker {} rejected: wrong password.", session.peer);
+                Err((ERROR_UNAUTHORIZED, "Wrong password".to_string()))
+            },
+            "mining.authorize" => {
+                let worker = request.params.first().and_then(Value::as_str).unwrap_or("anonymous").to_string();
+                println!("🔗 Stratum worker '{}' authorized.", worker);
+                self.set_worker_name(&session.peer, Protocol::Stratum, &worker);
+                session.worker = Some(worker);
+                Ok(json!(true))
+            },
+            "mining.submit" => match &session.worker {
+                None => Err((ERROR_UNAUTHORIZED, "Unauthorized worker".to_string())),
+                Some(worker) => self.stratum_submit(&session.peer, worker, &request.params),
+            },
+            method => Err((ERROR_OTHER, format!("Unknown method '{}'", method))),
+        };
+        let response = match result {
+            Ok(result) => json!({ "id": request.id, "result": result, "error": null }),
+            Err((code, message)) => {
+                self.worker_error(&session.peer, Protocol::Stratum, &message);
+                json!({ "id": request.id, "result": null, "error": [code, message, null] })
+            },
+        };
+        send_line(stream, &response)
+    }
+
+    fn stratum_submit(&self, peer: &str, worker: &str, params: &[Value]) -> Result<Value, (u32, String)> {
+        let hex_param = |index: usize| params.get(index).and_then(Value::as_str)
+            .and_then(|hex| u64::from_str_radix(hex.trim_start_matches("0x"), 16).ok());
+        let (Some(job_id), Some(nonce)) = (hex_param(1), hex_param(2)) else {
+            return Err((ERROR_OTHER, "Expected [worker, job_id, nonce] with hex job id and nonce".to_string()));
+        };
+        println!("✅ Stratum worker '{}' reports solution {:016x}.", worker, nonce);
+        self.report(peer, Protocol::Stratum, Report { job_id, nonce: Some(nonce), hashes: 0, elapsed: None })
+            .map_err(|e| match e {
+                ReportError::Invalid(_) => (ERROR_LOW_DIFFICULTY, e.to_string()),
+                ReportError::Stale(_) => (ERROR_JOB_NOT_FOUND, e.to_string()),
+            })?;
+        Ok(json!(true))
+    }
+
+    /// Pushes the current job to an authorized worker that hasn't been sent it yet.
+    fn stratum_notify(&self, stream: &mut TcpStream, session: &mut Session) -> Result<(), String> {
+        if !session.subscribed || session.worker.is_none() {
+            return Ok(());
+        }
+        let current = self.current_job_id();
+        if current.is_none() || current == session.notified_job {
+            return Ok(());
+        }
+        // Stratum workers can't ask for more nonces, so each job gets as many as a unit can hold
+        let CoordinatorMessage::Work(unit) = self.next_unit(&session.peer, Protocol::Stratum, MAX_UNIT_NONCES) else { return Ok(()) };
+        session.notified_job = Some(unit.job_id);
+        let challenge = unit.challenge;
+        send_line(stream, &json!({
+            "id": null,
+            "method": "mining.set_difficulty",
+            "params": [shadow_harvester_lib::difficulty_to_zero_bits(&challenge.difficulty)],
+        }))?;
+        send_line(stream, &json!({
+            "id": null,
+            "method": "mining.notify",
+            "params": [
+                format!("{:x}", unit.job_id), unit.address, challenge.challenge_id, challenge.difficulty,
+                challenge.no_pre_mine_key, challenge.latest_submission, challenge.no_pre_mine_hour_str,
+                format!("{:016x}", unit.start), unit.count, true,
+            ],
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::coordinator;
+    use serde_json::{Value, json};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    /// A worker connected to a Stratum session of `coordinator`, served on its own thread.
+    fn connect(coordinator: std::sync::Arc<super::Coordinator>) -> (TcpStream, BufReader<TcpStream>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let worker = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        thread::spawn(move || coordinator.serve_stratum(stream));
+        let reader = BufReader::new(worker.try_clone().unwrap());
+        (worker, reader)
+    }
+
+    fn call(worker: &mut TcpStream, reader: &mut BufReader<TcpStream>, request: &str) -> Value {
+        worker.write_all(format!("{}\n", request).as_bytes()).unwrap();
+        receive(reader)
+    }
+
+    fn receive(reader: &mut BufReader<TcpStream>) -> Value {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn subscribes_authorizes_and_notifies_the_job() {
+        let (mut worker, mut reader) = connect(coordinator(Some("secret"), Some((0x2a, "addr1test"))));
+        let subscribed = call(&mut worker, &mut reader, r#"{"id":1,"method":"mining.subscribe","params":[]}"#);
+        assert_eq!(subscribed["id"], 1);
+        assert_eq!(subscribed["result"][0][0][0], "mining.notify");
+        assert_eq!(subscribed["error"], Value::Null);
+
+        let rejected = call(&mut worker, &mut reader, r#"{"id":2,"method":"mining.authorize","params":["rig","wrong"]}"#);
+        assert_eq!(rejected["error"][0], 24);
+        let authorized = call(&mut worker, &mut reader, r#"{"id":3,"method":"mining.authorize","params":["rig","secret"]}"#);
+        assert_eq!(authorized, json!({ "id": 3, "result": true, "error": null }));
+
+        let difficulty = receive(&mut reader);
+        assert_eq!(difficulty["method"], "mining.set_difficulty");
+        assert_eq!(difficulty["params"], json!([shadow_harvester_lib::difficulty_to_zero_bits("000FFFFF")]));
+        let notify = receive(&mut reader);
+        assert_eq!(notify["method"], "mining.notify");
+        let params = notify["params"].as_array().unwrap();
+        assert_eq!(params[..8], [json!("2a"), json!("addr1test"), json!("**D01C05"), json!("000FFFFF"),
+            json!("e8a1958"), json!("2025-11-01T00:00:00Z"), json!("123456"), json!("0000000000001000")]);
+        assert_eq!(params[8], json!(super::MAX_UNIT_NONCES));
+        assert_eq!(params[9], json!(true));
+    }
+
+    #[test]
+    fn rejects_bad_calls_with_stratum_errors() {
+        let (mut worker, mut reader) = connect(coordinator(None, None));
+        let unauthorized = call(&mut worker, &mut reader, r#"{"id":1,"method":"mining.submit","params":["rig","1","00000000000000ff"]}"#);
+        assert_eq!(unauthorized["error"][0], 24);
+
+        call(&mut worker, &mut reader, r#"{"id":2,"method":"mining.authorize","params":["rig",""]}"#);
+        let malformed_nonce = call(&mut worker, &mut reader, r#"{"id":3,"method":"mining.submit","params":["rig","1","not hex"]}"#);
+        assert_eq!(malformed_nonce["error"][0], 20);
+        // No job 1 was ever mined, so its solution can't be queued
+        let unknown_job = call(&mut worker, &mut reader, r#"{"id":4,"method":"mining.submit","params":["rig","1","0x00000000000000ff"]}"#);
+        assert_eq!(unknown_job["error"][0], 21);
+
+        let unknown_method = call(&mut worker, &mut reader, r#"{"id":"a","method":"mining.extranonce.subscribe"}"#);
+        assert_eq!(unknown_method["id"], "a");
+        assert_eq!(unknown_method["error"][0], 20);
+        let malformed = call(&mut worker, &mut reader, "{not json");
+        assert_eq!(malformed["id"], Value::Null);
+        assert_eq!(malformed["error"][0], 20);
+    }
+
+    #[test]
+    fn drops_workers_sending_over_long_lines() {
+        let (mut worker, mut reader) = connect(coordinator(None, None));
+        // The coordinator may close before reading it all, resetting the connection
+        let _ = worker.write_all(&vec![b'x'; super::MAX_LINE_BYTES + 1]);
+        let mut rest = String::new();
+        assert!(reader.read_line(&mut rest).is_err() || rest.is_empty(), "answered {:?}", rest);
+    }
+}
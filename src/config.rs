@@ -0,0 +1,312 @@
+// src/config.rs
+
+use crate::api::ApiTimeouts;
+use crate::cli::Cli;
+use crate::notify::{NotifyEvent, Severity};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+// The config file looked up when '--profile' is given without '--config'
+pub const DEFAULT_CONFIG_FILE: &str = "shadow-harvester.toml";
+
+// ===============================================
+// CONFIG FILE STRUCTS
+// ===============================================
+
+/// The on-disk configuration file. Profiles live under `[profile.<name>]` tables.
+#[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub profile: HashMap<String, Profile>,
+    /// Notification sinks, one `[[notifier]]` table each. All of them receive events simultaneously.
+    #[serde(default)]
+    pub notifier: Vec<NotifierConfig>,
+    #[serde(default)]
+    pub timeouts: TimeoutsConfig,
+}
+
+/// A named set of defaults (e.g. `[profile.home]`, `[profile.datacenter]`) selected with `--profile`.
+/// Flags given on the command line always take precedence over the profile values.
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Profile {
+    pub api_url: Option<String>,
+    pub proxy: Option<String>,
+    pub threads: Option<ProfileThreads>,
+    pub oversubscription: Option<f64>,
+    pub mnemonic_file: Option<String>,
+    /// Account and first address index of the mnemonic-file wallet (default 0/0), for imported wallets
+    /// registered at a non-zero index.
+    pub mnemonic_account: Option<u32>,
+    pub mnemonic_starting_index: Option<u32>,
+    pub donate_to: Option<String>,
+    pub accept_tos: Option<bool>,
+    pub power_cap: Option<f64>,
+    pub peak_hours: Option<String>,
+    pub max_temp: Option<f64>,
+    pub max_hashrate: Option<f64>,
+}
+
+/// `threads = 8` or `threads = "auto"`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ProfileThreads {
+    Count(u32),
+    Keyword(String),
+}
+
+/// A notification sink. `events` limits it to the listed event kinds (empty means every event),
+/// `min-severity` drops less severe events and `cooldown-secs` rate-limits repeated warn/critical alerts.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct NotifierConfig {
+    #[serde(flatten)]
+    pub backend: NotifierBackend,
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub min_severity: Severity,
+    pub cooldown_secs: Option<u64>,
+}
+
+/// `[timeouts.challenge]`, `[timeouts.statistics]`, `[timeouts.registration]` and `[timeouts.submission]`:
+/// how long those API calls may take to connect (`connect-secs`) and then to answer (`read-secs`).
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TimeoutsConfig {
+    pub challenge: Option<EndpointTimeouts>,
+    pub statistics: Option<EndpointTimeouts>,
+    pub registration: Option<EndpointTimeouts>,
+    pub submission: Option<EndpointTimeouts>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct EndpointTimeouts {
+    pub connect_secs: Option<f64>,
+    pub read_secs: Option<f64>,
+}
+
+impl TimeoutsConfig {
+    /// Each endpoint's tables, for checking and applying them alike.
+    pub fn endpoints(&self) -> [(&'static str, Option<EndpointTimeouts>); 4] {
+        [("challenge", self.challenge), ("statistics", self.statistics), ("registration", self.registration), ("submission", self.submission)]
+    }
+}
+
+/// The notifier implementation, selected with `kind = "webhook" | "desktop" | "command"`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum NotifierBackend {
+    Webhook { url: String },
+    Desktop,
+    Command { command: String },
+}
+
+// ===============================================
+// LOADING AND MERGING
+// ===============================================
+
+pub fn load_config_file(path: &str) -> Result<ConfigFile, String> {
+    let config_toml = std::fs::read_to_string(path)
+        .map_err(|e| format!("Could not read config file {}: {}", path, e))?;
+
+    toml::from_str(&config_toml)
+        .map_err(|e| format!("Could not parse config file {}: {}", path, e))
+}
+
+/// Loads the profile selected with `--profile` (if any) and fills in every option not set on the command line.
+pub fn apply_profile(cli: &mut Cli) -> Result<(), String> {
+    let profile_name = match cli.profile.as_deref() {
+        Some(name) => name.to_string(),
+        None => return Ok(()),
+    };
+    let config_path = cli.config.clone().unwrap_or_else(|| DEFAULT_CONFIG_FILE.to_string());
+
+    let config = load_config_file(&config_path)?;
+    let profile = config.profile.get(&profile_name).ok_or_else(|| {
+        let mut available: Vec<&String> = config.profile.keys().collect();
+        available.sort();
+        format!("Profile '{}' not found in {}. Available profiles: {:?}", profile_name, config_path, available)
+    })?;
+
+    println!("⚙️ Using profile '{}' from {}", profile_name, config_path);
+
+    if cli.api_url.is_none() {
+        cli.api_url = profile.api_url.clone();
+    }
+    if cli.proxy.is_none() {
+        cli.proxy = profile.proxy.clone();
+    }
+    if cli.threads.is_none() {
+        cli.threads = profile.threads.as_ref().map(|threads| match threads {
+            ProfileThreads::Count(n) => n.to_string(),
+            ProfileThreads::Keyword(keyword) => keyword.clone(),
+        });
+    }
+    if cli.oversubscription.is_none() {
+        cli.oversubscription = profile.oversubscription;
+    }
+    if cli.no_donate {
+        if let Some(address) = &profile.donate_to {
+            println!("ℹ️ '--no-donate': ignoring donate-to {} from profile '{}'.", address, profile_name);
+        }
+    } else if cli.donate_to.is_none() {
+        cli.donate_to = profile.donate_to.clone();
+    }
+    if cli.power_cap.is_none() {
+        cli.power_cap = profile.power_cap;
+    }
+    if cli.peak_hours.is_none() {
+        cli.peak_hours = profile.peak_hours.clone();
+    }
+    if cli.max_temp.is_none() {
+        cli.max_temp = profile.max_temp;
+    }
+    if cli.max_hashrate.is_none() {
+        cli.max_hashrate = profile.max_hashrate;
+    }
+    if !cli.accept_tos {
+        cli.accept_tos = profile.accept_tos.unwrap_or(false);
+    }
+    // The wallets file only applies if no other key source was chosen on the command line
+    if cli.mnemonic.is_none() && cli.mnemonic_file.is_none() && cli.payment_key.is_none() && cli.external_signer.is_none() && !cli.ephemeral_key {
+        cli.mnemonic_file = profile.mnemonic_file.clone();
+        // The account and index belong to that wallet; a non-zero flag still wins
+        if cli.mnemonic_file.is_some() {
+            if cli.mnemonic_account == 0 {
+                cli.mnemonic_account = profile.mnemonic_account.unwrap_or(0);
+            }
+            if cli.mnemonic_starting_index == 0 {
+                cli.mnemonic_starting_index = profile.mnemonic_starting_index.unwrap_or(0);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// '--config', or the default config file if it exists.
+fn optional_config_path(cli: &Cli) -> Option<&str> {
+    match cli.config.as_deref() {
+        Some(path) => Some(path),
+        None if std::path::Path::new(DEFAULT_CONFIG_FILE).exists() => Some(DEFAULT_CONFIG_FILE),
+        None => None,
+    }
+}
+
+/// Loads the `[[notifier]]` sinks from '--config', or from the default config file if it exists.
+pub fn load_notifiers(cli: &Cli) -> Result<Vec<NotifierConfig>, String> {
+    let Some(config_path) = optional_config_path(cli) else { return Ok(Vec::new()) };
+    let config = load_config_file(config_path)?;
+
+    for notifier in &config.notifier {
+        if let Some(unknown) = notifier.events.iter().find(|e| !NotifyEvent::KINDS.contains(&e.as_str())) {
+            return Err(format!("Unknown notifier event '{}' in {}. Known events: {:?}", unknown, config_path, NotifyEvent::KINDS));
+        }
+    }
+    Ok(config.notifier)
+}
+
+/// The API call timeouts from the `[timeouts.*]` tables of '--config' or the default config file, defaults
+/// filling in whatever they leave out.
+pub fn load_api_timeouts(cli: &Cli) -> Result<ApiTimeouts, String> {
+    let mut timeouts = ApiTimeouts::default();
+    let Some(config_path) = optional_config_path(cli) else { return Ok(timeouts) };
+    let config = load_config_file(config_path)?;
+
+    for (name, configured) in config.timeouts.endpoints() {
+        let Some(configured) = configured else { continue };
+        let endpoint = match name {
+            "challenge" => &mut timeouts.challenge,
+            "statistics" => &mut timeouts.statistics,
+            "registration" => &mut timeouts.registration,
+            _ => &mut timeouts.submission,
+        };
+        for (key, secs, timeout) in [("connect-secs", configured.connect_secs, &mut endpoint.connect), ("read-secs", configured.read_secs, &mut endpoint.read)] {
+            match secs {
+                Some(secs) if !(secs > 0.0 && secs.is_finite()) => {
+                    return Err(format!("[timeouts.{}] {} in {} must be a positive number of seconds.", name, key, config_path));
+                },
+                Some(secs) => *timeout = Duration::from_secs_f64(secs),
+                None => {},
+            }
+        }
+    }
+    Ok(timeouts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    const CONFIG: &str = r#"
+[profile.home]
+api-url = "https://profile.example"
+threads = "auto"
+power-cap = 65.0
+peak-hours = "17-21"
+donate-to = "addr1donate"
+accept-tos = true
+mnemonic-file = "wallets.txt"
+mnemonic-account = 2
+mnemonic-starting-index = 10
+
+[profile.rack]
+threads = 32
+"#;
+
+    /// The CLI parsed from `args` after the profile of the config file was applied.
+    fn cli_with_profile(name: &str, args: &[&str]) -> Result<Cli, String> {
+        let path = std::env::temp_dir().join(format!("shadow-harvester-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, CONFIG).unwrap();
+        let path = path.to_str().unwrap().to_string();
+        let mut cli = Cli::try_parse_from(["shadow-harvester", "--config", &path].iter().chain(args)).unwrap();
+        let applied = apply_profile(&mut cli).map(|_| cli);
+        let _ = std::fs::remove_file(&path);
+        applied
+    }
+
+    #[test]
+    fn fills_in_unset_options_from_the_profile() {
+        let cli = cli_with_profile("fills", &["--profile", "home"]).unwrap();
+        assert_eq!(cli.api_url.as_deref(), Some("https://profile.example"));
+        assert_eq!(cli.threads.as_deref(), Some("auto"));
+        assert_eq!(cli.power_cap, Some(65.0));
+        assert_eq!(cli.peak_hours.as_deref(), Some("17-21"));
+        assert_eq!(cli.donate_to.as_deref(), Some("addr1donate"));
+        assert!(cli.accept_tos);
+        assert_eq!(cli.mnemonic_file.as_deref(), Some("wallets.txt"));
+        assert_eq!((cli.mnemonic_account, cli.mnemonic_starting_index), (2, 10));
+
+        let cli = cli_with_profile("count", &["--profile", "rack"]).unwrap();
+        assert_eq!(cli.threads.as_deref(), Some("32"));
+    }
+
+    #[test]
+    fn command_line_flags_win_over_the_profile() {
+        let cli = cli_with_profile("flags", &["--profile", "home", "--api-url", "https://flag.example", "--threads", "4",
+            "--no-donate", "--mnemonic-starting-index", "3"]).unwrap();
+        assert_eq!(cli.api_url.as_deref(), Some("https://flag.example"));
+        assert_eq!(cli.threads.as_deref(), Some("4"));
+        assert_eq!(cli.donate_to, None);
+        assert_eq!((cli.mnemonic_account, cli.mnemonic_starting_index), (2, 3));
+
+        // Another key source on the command line leaves the profile's wallets file out
+        let cli = cli_with_profile("keys", &["--profile", "home", "--ephemeral-key"]).unwrap();
+        assert_eq!(cli.mnemonic_file, None);
+        assert_eq!((cli.mnemonic_account, cli.mnemonic_starting_index), (0, 0));
+        let cli = cli_with_profile("signer", &["--profile", "home", "--external-signer", "sign.sh", "--address", "addr1signer"]).unwrap();
+        assert_eq!(cli.mnemonic_file, None);
+    }
+
+    #[test]
+    fn unknown_profiles_list_the_available_ones() {
+        let error = cli_with_profile("unknown", &["--profile", "office"]).unwrap_err();
+        assert!(error.contains("Profile 'office' not found") && error.contains(r#"["home", "rack"]"#), "{}", error);
+        assert!(cli_with_profile("none", &[]).unwrap().api_url.is_none());
+    }
+}
@@ -2,3 +2,18 @@
 
 // UserAgent String
 pub const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/100.0.0.0 Safari/537.36";
+
+// Timeout of the API calls without a [timeouts.*] table in the config file (T&C, donation)
+pub const API_TIMEOUT_SECS: u64 = 30;
+
+// Worker threads used when neither '--threads' nor the selected profile sets one
+pub const DEFAULT_THREADS: u32 = 24;
+
+// Wallets mined at the same time. Every mode currently mines one wallet after another.
+pub const CONCURRENT_WALLETS: u32 = 1;
+
+// Planned threads (threads x concurrent wallets) may exceed the logical cores by at most this factor before being clamped
+pub const DEFAULT_OVERSUBSCRIPTION_FACTOR: f64 = 1.0;
+
+// Seconds between two saves of the workers' nonce positions (with '--data-dir')
+pub const NONCE_CHECKPOINT_SECS: u64 = 30;
@@ -1,10 +1,20 @@
 // src/data_types.rs
 
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher, DefaultHasher};
-use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
+use shadow_harvester_lib::{NoncePartition, ScavengeControl};
+use std::path::{Path, PathBuf};
 use std::io::Write;
-use reqwest::blocking;
+use cryptoxide::hashing::blake2b::Blake2b;
 use serde::{Deserialize, Serialize};
+use rand_core::{OsRng, RngCore};
+use crate::notify::Notifications;
+use crate::proxies::WalletClients;
+use crate::throttle::ThrottleSettings;
+use crate::store::StateStore;
 
 // ===============================================
 // API RESPONSE STRUCTS (Moved from src/api.rs)
@@ -65,6 +75,37 @@ pub struct DonateResponse {
 }
 
 
+/// A challenge of GET /challenges, the published challenges one page at a time.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HistoricalChallenge {
+    pub challenge_id: String,
+    pub challenge_number: u16,
+    pub day: u8,
+    pub difficulty: String,
+    pub issued_at: String,
+    pub latest_submission: String,
+    // Solutions the API accepted for the challenge, from every wallet
+    #[serde(default)]
+    pub solutions: Option<u64>,
+}
+
+impl HistoricalChallenge {
+    /// "active" while solutions are still accepted, "expired" after.
+    pub fn status(&self) -> &'static str {
+        match chrono::DateTime::parse_from_rfc3339(&self.latest_submission) {
+            Ok(latest) if latest > chrono::Utc::now() => "active",
+            _ => "expired",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChallengeHistoryPage {
+    pub challenges: Vec<HistoricalChallenge>,
+    pub page: u32,
+    pub total_pages: u32,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ApiErrorResponse {
     pub message: String,
@@ -131,7 +172,9 @@ pub struct CliChallengeData {
 // Holds the common, validated state for the mining loops.
 #[derive(Debug)]
 pub struct MiningContext<'a> {
-    pub client: blocking::Client,
+    pub client: reqwest::Client,
+    /// Per-wallet clients ('--proxies-file'), for requests about one wallet.
+    pub wallet_clients: WalletClients,
     pub api_url: String,
     // FIX: Use the struct from its new location
     pub tc_response: TandCResponse,
@@ -139,16 +182,275 @@ pub struct MiningContext<'a> {
     pub threads: u32,
     pub cli_challenge: Option<&'a String>,
     pub data_dir: Option<&'a str>,
+    /// Mining state ('--data-dir'); None when no data dir is set.
+    pub store: Option<Arc<dyn StateStore>>,
+    pub notifications: Notifications,
+    pub stagger_secs: u64,
+    pub throttle: ThrottleSettings,
+    pub pending_queue: Option<PendingQueueIndex>,
+    pub control: Arc<ScavengeControl>,
+    pub nonce_partition: NoncePartition,
+    pub allow_stale_challenge: bool,
+    pub solutions_per_challenge: u32,
 }
 
 
 // Holds the data needed to submit a solution later.
+// Only the fields needed for submission are stored; the challenge itself is referenced by checksum.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PendingSolution {
     pub address: String,
     pub challenge_id: String,
     pub nonce: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub donation_address: Option<String>, // RE-ADDED this field
+    // Checksum of the saved challenge.json the nonce was mined against (missing in older queue files)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge_checksum: Option<String>,
+    // Sent with every submission attempt of this solution (missing in older queue files)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+}
+
+impl PendingSolution {
+    /// The key persisted when the solution was queued, or one derived from the solution for older queue files.
+    pub fn idempotency_key(&self) -> String {
+        self.idempotency_key.clone()
+            .unwrap_or_else(|| derived_idempotency_key(&["solution", &self.address, &self.challenge_id, &self.nonce]))
+    }
+}
+
+/// Random idempotency key (32 hex chars) for a new API write.
+pub fn new_idempotency_key() -> String {
+    let mut key = [0u8; 16];
+    OsRng.fill_bytes(&mut key);
+    hex::encode(key)
+}
+
+/// Stable idempotency key for a write that is fully identified by its parts, so retries reuse it across restarts.
+pub fn derived_idempotency_key(parts: &[&str]) -> String {
+    checksum_bytes(parts.join("/").as_bytes())
+}
+
+/// Short Blake2b-256 checksum (hex, 16 bytes) of a challenge as serialized into challenge.json.
+pub fn challenge_checksum(challenge: &ChallengeData) -> Option<String> {
+    let challenge_json = serde_json::to_string(challenge).ok()?;
+    Some(checksum_bytes(challenge_json.as_bytes()))
+}
+
+pub fn checksum_bytes(bytes: &[u8]) -> String {
+    let digest = Blake2b::<256>::new().update(bytes).finalize();
+    hex::encode(&digest[..16])
+}
+
+/// Reads the cached challenge.json of a challenge, verifying it against the checksum saved with it.
+/// Returns None if nothing is cached. A missing or mismatching checksum is an error unless `allow_stale`.
+pub fn load_cached_challenge(base_dir: &str, challenge_id: &str, allow_stale: bool) -> Result<Option<ChallengeData>, String> {
+    let challenge_path = receipts_root(base_dir).join(challenge_id).join(FILE_NAME_CHALLENGE);
+    let challenge_json = match std::fs::read(&challenge_path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Could not read {:?}: {}", challenge_path, e)),
+    };
+
+    let actual = checksum_bytes(&challenge_json);
+    let expected = std::fs::read_to_string(challenge_path.with_file_name(FILE_NAME_CHALLENGE_CHECKSUM))
+        .map(|c| c.trim().to_string())
+        .ok();
+    if expected.as_deref() != Some(actual.as_str()) {
+        let problem = match expected {
+            Some(expected) => format!("Cached challenge {:?} (checksum {}) does not match the server payload it was saved from ({})", challenge_path, actual, expected),
+            None => format!("Cached challenge {:?} has no {} to verify it against", challenge_path, FILE_NAME_CHALLENGE_CHECKSUM),
+        };
+        if !allow_stale {
+            return Err(format!("{}. Refusing to mine with it; pass '--allow-stale-challenge' to use it anyway.", problem));
+        }
+        eprintln!("⚠️ {}. Using it anyway ('--allow-stale-challenge').", problem);
+    }
+
+    serde_json::from_slice(&challenge_json)
+        .map(Some)
+        .map_err(|e| format!("Could not parse cached challenge {:?}: {}", challenge_path, e))
+}
+
+// Outcome of a single wallet's mining cycle, reported in the challenge summary
+#[derive(Debug, Serialize, Clone)]
+pub struct WalletOutcome {
+    pub address: String,
+    pub deriv_index: Option<u32>,
+    pub outcome: String,
+    pub hashes: u64,
+    pub elapsed_secs: f64,
+    pub night_allocation: Option<u32>,
+    pub night_delta: Option<i64>,
+}
+
+// A wallet's API statistics at one point of a challenge
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    pub crypto_receipts: u32,
+    pub night_allocation: u32,
+}
+
+impl From<&Statistics> for StatsSnapshot {
+    fn from(stats: &Statistics) -> Self {
+        Self { crypto_receipts: stats.crypto_receipts, night_allocation: stats.night_allocation }
+    }
+}
+
+// Statistics of one wallet at the start and end of a challenge, against the solutions queued for it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WalletStatsDiff {
+    pub address: String,
+    pub queued: usize,
+    pub start: Option<StatsSnapshot>,
+    pub end: Option<StatsSnapshot>,
+    pub credited_receipts: Option<i64>,
+    pub night_delta: Option<i64>,
+    /// Queued and credited differ, or either snapshot is missing: the wallet needs a follow-up.
+    pub mismatch: bool,
+}
+
+impl WalletStatsDiff {
+    pub fn new(address: &str, queued: usize, start: Option<StatsSnapshot>, end: Option<StatsSnapshot>) -> Self {
+        let credited_receipts = start.zip(end).map(|(start, end)| end.crypto_receipts as i64 - start.crypto_receipts as i64);
+        Self {
+            address: address.to_string(),
+            queued,
+            start,
+            end,
+            credited_receipts,
+            night_delta: start.zip(end).map(|(start, end)| end.night_allocation as i64 - start.night_allocation as i64),
+            mismatch: credited_receipts != Some(queued as i64),
+        }
+    }
+}
+
+// Per-challenge breakdown of every wallet mined, sent to the notifiers when the challenge ends
+#[derive(Debug, Serialize, Clone)]
+pub struct ChallengeSummary {
+    pub challenge_id: String,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub total_hashes: u64,
+    pub total_elapsed_secs: f64,
+    pub wallets: Vec<WalletOutcome>,
+    pub queued_solutions: usize,
+    pub credited_receipts: Option<i64>,
+    pub stats_diff: Vec<WalletStatsDiff>,
+    pub notes: Vec<String>,
+    // Statistics of each wallet before its first cycle of the challenge, in the order first mined
+    #[serde(skip)]
+    start_stats: Vec<(String, Option<StatsSnapshot>)>,
+}
+
+impl ChallengeSummary {
+    pub fn new(challenge_id: &str) -> Self {
+        Self {
+            challenge_id: challenge_id.to_string(),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            finished_at: None,
+            total_hashes: 0,
+            total_elapsed_secs: 0.0,
+            wallets: Vec::new(),
+            queued_solutions: 0,
+            credited_receipts: None,
+            stats_diff: Vec::new(),
+            notes: Vec::new(),
+            start_stats: Vec::new(),
+        }
+    }
+
+    /// True until the wallet's start snapshot has been taken for this challenge.
+    pub fn needs_start_snapshot(&self, address: &str) -> bool {
+        !self.start_stats.iter().any(|(a, _)| a == address)
+    }
+
+    /// Keeps the first snapshot seen for a wallet; `None` records that its statistics were unavailable.
+    pub fn snapshot_start(&mut self, address: &str, snapshot: Option<StatsSnapshot>) {
+        if self.needs_start_snapshot(address) {
+            self.start_stats.push((address.to_string(), snapshot));
+        }
+    }
+
+    pub fn record(&mut self, outcome: WalletOutcome) {
+        self.total_hashes += outcome.hashes;
+        self.total_elapsed_secs += outcome.elapsed_secs;
+        if outcome.outcome == format!("{:?}", MiningResult::FoundAndQueued) {
+            self.queued_solutions += 1;
+        }
+        self.wallets.push(outcome);
+    }
+
+    /// The wallets with a start-of-challenge snapshot.
+    pub fn snapshotted_addresses(&self) -> Vec<String> {
+        self.start_stats.iter().map(|(address, _)| address.clone()).collect()
+    }
+
+    /// Diffs every snapshotted wallet against its statistics now (`end_of`), once the challenge is over.
+    pub fn finish(&mut self, mut end_of: impl FnMut(&str) -> Option<StatsSnapshot>) {
+        let queued_outcome = format!("{:?}", MiningResult::FoundAndQueued);
+        self.stats_diff = self.start_stats.iter()
+            .map(|(address, start)| {
+                let queued = self.wallets.iter().filter(|w| &w.address == address && w.outcome == queued_outcome).count();
+                WalletStatsDiff::new(address, queued, *start, end_of(address))
+            })
+            .collect();
+        self.credited_receipts = self.stats_diff.iter().map(|diff| diff.credited_receipts).sum();
+        self.finished_at = Some(chrono::Utc::now().to_rfc3339());
+    }
+
+    pub fn mismatched_wallets(&self) -> usize {
+        self.stats_diff.iter().filter(|diff| diff.mismatch).count()
+    }
+}
+
+// Written next to a challenge's receipts when it ends
+pub const FILE_NAME_STATS_DIFF: &str = "stats_diff.json";
+
+pub fn stats_diff_path(base_dir: &str, challenge_id: &str) -> PathBuf {
+    receipts_root(base_dir).join(challenge_id).join(FILE_NAME_STATS_DIFF)
+}
+
+pub fn save_stats_diff(base_dir: &str, summary: &ChallengeSummary) -> Result<PathBuf, String> {
+    let path = stats_diff_path(base_dir, &summary.challenge_id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Could not create challenge directory {:?}: {}", parent, e))?;
+    }
+
+    let diff_json = serde_json::to_string_pretty(&summary.stats_diff)
+        .map_err(|e| format!("Could not serialize statistics diff: {}", e))?;
+    std::fs::write(&path, diff_json)
+        .map_err(|e| format!("Could not write statistics diff {:?}: {}", path, e))?;
+    Ok(path)
+}
+
+// Operator notes on a challenge ('challenges note'), one "<timestamp> <note>" per line next to its receipts
+pub const FILE_NAME_NOTES: &str = "notes.txt";
+
+pub fn append_challenge_note(base_dir: &str, challenge_id: &str, note: &str) -> Result<PathBuf, String> {
+    let note = note.trim();
+    if note.is_empty() || note.contains(['\n', '\r']) {
+        return Err("A challenge note must be one non-empty line.".to_string());
+    }
+    let dir = receipts_root(base_dir).join(challenge_id);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Could not create challenge directory {:?}: {}", dir, e))?;
+    let path = dir.join(FILE_NAME_NOTES);
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)
+        .map_err(|e| format!("Could not open {:?}: {}", path, e))?;
+    writeln!(file, "{} {}", chrono::Utc::now().format("%Y-%m-%d %H:%M UTC"), note)
+        .map_err(|e| format!("Could not append to {:?}: {}", path, e))?;
+    Ok(path)
+}
+
+pub fn load_challenge_notes(base_dir: &str, challenge_id: &str) -> Vec<String> {
+    std::fs::read_to_string(receipts_root(base_dir).join(challenge_id).join(FILE_NAME_NOTES)).unwrap_or_default()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect()
 }
 
 // Define a result type for the mining cycle
@@ -158,13 +460,183 @@ pub enum MiningResult {
     #[allow(dead_code)] // The submitter thread produces this result conceptually when processing a queue item, but the miner never constructs it.
     AlreadySolved, // The solution was successfully submitted by someone else
     MiningFailed,  // General mining or submission error (e.g., hash not found, transient API error)
+    SliceExpired,  // The address' time slice ran out before a solution was found ('--time-slice')
+    DeadlineReached, // The challenge's submission deadline was within '--deadline-margin'
+    ChallengeSuperseded, // A newer challenge was pushed while mining ('--challenge-push')
 }
 
 // --- DataDir Structures and Constants ---
 pub const FILE_NAME_CHALLENGE: &str = "challenge.json";
+// Checksum of challenge.json as written from the server payload, verified before the cached copy is mined
+pub const FILE_NAME_CHALLENGE_CHECKSUM: &str = "challenge.json.blake2b";
 pub const FILE_NAME_RECEIPT: &str = "receipt.json";
 pub const FILE_NAME_FOUND_SOLUTION: &str = "found.json"; // (Crash recovery file)
+pub const FILE_NAME_NONCE_CHECKPOINT: &str = "nonce_checkpoint.json"; // Worker positions of an unfinished search
+
+/// Where each worker's nonce search got to for one address and challenge, so a restart doesn't rehash it.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NonceCheckpointRecord {
+    pub address: String,
+    pub challenge_id: String,
+    pub threads: u32,
+    pub partition_worker_id: u32,
+    pub partition_worker_count: u32,
+    pub nonces: Vec<u64>,
+    pub updated_at: String,
+}
+
+pub const ROM_CACHE_DIR_NAME: &str = "rom_cache";
+
+/// Locations split out of '--data-dir' ('--receipts-dir', '--queue-dir'). Set once at startup;
+/// anything left unset stays under the data dir.
+#[derive(Debug, Clone, Default)]
+pub struct DirOverrides {
+    pub receipts_dir: Option<String>,
+    pub queue_dir: Option<String>,
+}
 
+static DIR_OVERRIDES: OnceLock<DirOverrides> = OnceLock::new();
+
+/// Returns false if the layout was already set.
+pub fn set_dir_overrides(overrides: DirOverrides) -> bool {
+    DIR_OVERRIDES.set(overrides).is_ok()
+}
+
+fn dir_overrides() -> &'static DirOverrides {
+    DIR_OVERRIDES.get_or_init(DirOverrides::default)
+}
+
+/// Root of the per-challenge tree (challenge.json, receipts, crash recovery files) and of backfilled receipts.
+pub fn receipts_root(base_dir: &str) -> PathBuf {
+    PathBuf::from(dir_overrides().receipts_dir.as_deref().unwrap_or(base_dir))
+}
+
+// Scratch files of atomic writes, one directory per challenge next to the challenge directories
+pub const TMP_DIR_NAME: &str = "tmp";
+// How long a challenge's tmp directory stays after its last write
+const TMP_DIR_GRACE: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// `<data-dir>/tmp/<challenge-id>` (below '--receipts-dir' when set, so the final rename stays on one filesystem).
+pub fn challenge_tmp_dir(base_dir: &str, challenge_id: &str) -> PathBuf {
+    receipts_root(base_dir).join(TMP_DIR_NAME).join(challenge_id)
+}
+
+/// Writes `path` through a scratch file in the challenge's tmp directory, synced and then renamed over
+/// `path`, so a crash leaves either the old file or the new one and the scratch file is cleaned up later.
+fn write_atomically(base_dir: &str, challenge_id: &str, path: &Path, contents: &[u8]) -> Result<(), String> {
+    static SCRATCH_COUNTER: AtomicU64 = AtomicU64::new(0);
+    let tmp_dir = challenge_tmp_dir(base_dir, challenge_id);
+    std::fs::create_dir_all(&tmp_dir)
+        .map_err(|e| format!("Could not create {:?}: {}", tmp_dir, e))?;
+    // Unique per process and write: wallets of one challenge write files with the same name
+    let tmp_path = tmp_dir.join(format!("{}.{}.{}",
+        path.file_name().and_then(|n| n.to_str()).unwrap_or_default(), std::process::id(), SCRATCH_COUNTER.fetch_add(1, Ordering::Relaxed)));
+
+    // The directory can be pruned between create_dir_all and here ('prune_challenge_tmp_dirs')
+    let written = std::fs::File::create(&tmp_path)
+        .or_else(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => std::fs::create_dir_all(&tmp_dir).and_then(|_| std::fs::File::create(&tmp_path)),
+            _ => Err(e),
+        })
+        .and_then(|mut file| file.write_all(contents).and_then(|_| file.sync_all()))
+        .and_then(|_| std::fs::rename(&tmp_path, path));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(format!("Could not write {:?}: {}", path, e));
+    }
+    Ok(())
+}
+
+/// Deletes the tmp directories of every challenge but `current_challenge_id` that weren't written to for
+/// TMP_DIR_GRACE: the submitter still writes the receipts of earlier challenges' queued solutions through
+/// theirs, while anything left in an idle one is debris of an interrupted write.
+pub fn prune_challenge_tmp_dirs(base_dir: &str, current_challenge_id: &str) {
+    let Ok(entries) = std::fs::read_dir(receipts_root(base_dir).join(TMP_DIR_NAME)) else { return };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()) {
+        let idle = std::fs::metadata(&path).and_then(|m| m.modified()).ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|idle| idle >= TMP_DIR_GRACE);
+        if idle && path.file_name().is_some_and(|name| name != current_challenge_id)
+            && let Err(e) = std::fs::remove_dir_all(&path)
+        {
+            eprintln!("⚠️ Could not delete temporary directory {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Directory holding the pending submission queue.
+pub fn queue_dir(base_dir: &str) -> PathBuf {
+    match dir_overrides().queue_dir.as_deref() {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(base_dir).join(QUEUE_DIR_NAME),
+    }
+}
+
+
+// Domain tag used as the HMAC key when deriving the opaque mnemonic directory name
+const MNEMONIC_DIR_ID_KEY: &[u8] = b"shadow-harvester/data-dir/mnemonic-id/v1";
+const BLAKE2B_BLOCK_SIZE: usize = 128;
+
+// HMAC (RFC 2104) over Blake2b-256
+fn hmac_blake2b256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block_key = [0u8; BLAKE2B_BLOCK_SIZE];
+    if key.len() > BLAKE2B_BLOCK_SIZE {
+        block_key[..32].copy_from_slice(&Blake2b::<256>::new().update(key).finalize());
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    let inner_pad: Vec<u8> = block_key.iter().map(|b| b ^ 0x36).collect();
+    let outer_pad: Vec<u8> = block_key.iter().map(|b| b ^ 0x5c).collect();
+
+    let inner = Blake2b::<256>::new().update(&inner_pad).update(message).finalize();
+    Blake2b::<256>::new().update(&outer_pad).update(&inner).finalize()
+}
+
+/// Opaque directory name for a mnemonic wallet. It is a keyed hash of the phrase,
+/// so the data-dir layout reveals nothing about the seed.
+pub fn mnemonic_dir_id(mnemonic: &str) -> String {
+    hex::encode(&hmac_blake2b256(MNEMONIC_DIR_ID_KEY, mnemonic.as_bytes())[..16])
+}
+
+// Directory name used by earlier versions (SipHash of the phrase). Only kept for the migration below.
+fn legacy_mnemonic_dir_id(mnemonic: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    mnemonic.hash(&mut hasher);
+    hasher.finish().to_string()
+}
+
+/// Renames every `<challenge>/mnemonic/<legacy id>` directory to the opaque `mnemonic_dir_id`.
+/// Queue files are named by address, so they need no update. Returns the number of directories moved.
+pub fn migrate_legacy_mnemonic_dirs(base_dir: &str, mnemonic: &str) -> Result<usize, String> {
+    let legacy_id = legacy_mnemonic_dir_id(mnemonic);
+    let new_id = mnemonic_dir_id(mnemonic);
+    let mut migrated = 0;
+
+    let entries = match std::fs::read_dir(receipts_root(base_dir)) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(0),
+    };
+
+    for challenge_path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let legacy_path = challenge_path.join("mnemonic").join(&legacy_id);
+        if !legacy_path.is_dir() {
+            continue;
+        }
+
+        let new_path = challenge_path.join("mnemonic").join(&new_id);
+        if new_path.exists() {
+            eprintln!("⚠️ Both legacy and migrated mnemonic directories exist in {:?}. Leaving {:?} untouched.", challenge_path, legacy_path);
+            continue;
+        }
+
+        std::fs::rename(&legacy_path, &new_path)
+            .map_err(|e| format!("Could not migrate {:?} to {:?}: {}", legacy_path, new_path, e))?;
+        migrated += 1;
+    }
+
+    Ok(migrated)
+}
 
 #[derive(Debug, Clone, Copy)]
 pub enum DataDir<'a> {
@@ -182,7 +654,7 @@ pub struct DataDirMnemonic<'a> {
 
 impl<'a> DataDir<'a> {
     pub fn challenge_dir(&'a self, base_dir: &str, challenge_id: &str) -> Result<PathBuf, String> {
-        let mut path = PathBuf::from(base_dir);
+        let mut path = receipts_root(base_dir);
         path.push(challenge_id);
         Ok(path)
     }
@@ -201,13 +673,7 @@ impl<'a> DataDir<'a> {
             },
             DataDir::Mnemonic(wallet) => {
                 path.push("mnemonic");
-
-                let mnemonic_hash = {
-                    let mut hasher = DefaultHasher::new();
-                    wallet.mnemonic.hash(&mut hasher);
-                    hasher.finish()
-                };
-                path.push(mnemonic_hash.to_string());
+                path.push(mnemonic_dir_id(wallet.mnemonic));
 
                 path.push(wallet.account.to_string());
 
@@ -228,47 +694,37 @@ impl<'a> DataDir<'a> {
         let challenge_json = serde_json::to_string(challenge)
             .map_err(|e| format!("Could not serialize challenge {}: {}", &challenge.challenge_id, e))?;
 
-        std::fs::write(&path, challenge_json)
-            .map_err(|e| format!("Could not write {}: {}", FILE_NAME_CHALLENGE, e))?;
+        write_atomically(base_dir, &challenge.challenge_id, &path, challenge_json.as_bytes())?;
 
-        Ok(())
+        path.set_file_name(FILE_NAME_CHALLENGE_CHECKSUM);
+        write_atomically(base_dir, &challenge.challenge_id, &path, checksum_bytes(challenge_json.as_bytes()).as_bytes())
     }
 
-    // Only saves the receipt, donation logic removed
+    // Only saves the receipt, donation logic removed. Earlier receipts of the wallet for the challenge are kept.
     pub fn save_receipt(&self, base_dir: &str, challenge_id: &str, receipt: &serde_json::Value) -> Result<(), String> {
-        let mut path = self.receipt_dir(base_dir, challenge_id)?;
-        path.push(FILE_NAME_RECEIPT);
-
-        let receipt_json = receipt.to_string();
-
-        let mut file = std::fs::File::create(&path)
-            .map_err(|e| format!("Could not create {}: {}", FILE_NAME_RECEIPT, e))?;
-
-        file.write_all(receipt_json.as_bytes())
-            .map_err(|e| format!("Could not write to {}: {}", FILE_NAME_RECEIPT, e))?;
-
-        file.sync_all()
-            .map_err(|e| format!("Could not sync {}: {}", FILE_NAME_RECEIPT, e))?;
+        let dir = self.receipt_dir(base_dir, challenge_id)?;
+        let path = (1..).map(|n| dir.join(receipt_file_name(n))).find(|path| !path.exists()).unwrap_or_default();
 
         // Donation file logic is intentionally removed here.
-
-        Ok(())
+        let receipt_json = receipt.to_string();
+        write_atomically(base_dir, challenge_id, &path, receipt_json.as_bytes())
     }
 
-    // Saves a PendingSolution to the queue directory
+    // Saves a PendingSolution to the queue directory (zstd-compressed JSON)
     pub fn save_pending_solution(&self, base_dir: &str, solution: &PendingSolution) -> Result<(), String> {
-        let mut path = PathBuf::from(base_dir);
-        path.push("pending_submissions"); // Dedicated directory for the queue
+        let mut path = queue_dir(base_dir); // Dedicated directory for the queue
         std::fs::create_dir_all(&path)
-            .map_err(|e| format!("Could not create pending_submissions directory: {}", e))?;
+            .map_err(|e| format!("Could not create queue directory {:?}: {}", path, e))?;
 
         // Use a unique file name based on challenge, address, and nonce
-        path.push(format!("{}_{}_{}.json", solution.address, solution.challenge_id, solution.nonce));
+        path.push(format!("{}_{}_{}{}", solution.address, solution.challenge_id, solution.nonce, QUEUE_FILE_EXTENSION));
 
-        let solution_json = serde_json::to_string(solution)
+        let solution_json = serde_json::to_vec(solution)
             .map_err(|e| format!("Could not serialize pending solution: {}", e))?;
+        let compressed = zstd::encode_all(solution_json.as_slice(), QUEUE_COMPRESSION_LEVEL)
+            .map_err(|e| format!("Could not compress pending solution: {}", e))?;
 
-        std::fs::write(&path, solution_json)
+        std::fs::write(&path, compressed)
             .map_err(|e| format!("Could not write pending solution file: {}", e))?;
 
         Ok(())
@@ -296,6 +752,30 @@ impl<'a> DataDir<'a> {
     }
 
     // Removes the temporary file
+    pub fn save_nonce_checkpoint(&self, base_dir: &str, checkpoint: &NonceCheckpointRecord) -> Result<(), String> {
+        let path = self.receipt_dir(base_dir, &checkpoint.challenge_id)?.join(FILE_NAME_NONCE_CHECKPOINT);
+        let checkpoint_json = serde_json::to_string(checkpoint)
+            .map_err(|e| format!("Could not serialize nonce checkpoint: {}", e))?;
+
+        // Written aside and renamed, so a crash mid-write leaves the previous checkpoint intact
+        write_atomically(base_dir, &checkpoint.challenge_id, &path, checkpoint_json.as_bytes())
+    }
+
+    pub fn load_nonce_checkpoint(&self, base_dir: &str, challenge_id: &str) -> Option<NonceCheckpointRecord> {
+        let path = self.receipt_dir(base_dir, challenge_id).ok()?.join(FILE_NAME_NONCE_CHECKPOINT);
+        let checkpoint_json = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&checkpoint_json).ok()
+    }
+
+    pub fn delete_nonce_checkpoint(&self, base_dir: &str, challenge_id: &str) -> Result<(), String> {
+        let path = self.receipt_dir(base_dir, challenge_id)?.join(FILE_NAME_NONCE_CHECKPOINT);
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .map_err(|e| format!("Failed to delete {}: {}", FILE_NAME_NONCE_CHECKPOINT, e))?;
+        }
+        Ok(())
+    }
+
     pub fn delete_found_solution(&self, base_dir: &str, challenge_id: &str) -> Result<(), String> {
         let mut path = self.receipt_dir(base_dir, challenge_id)?;
         path.push(FILE_NAME_FOUND_SOLUTION);
@@ -307,25 +787,278 @@ impl<'a> DataDir<'a> {
     }
 }
 
-// Checks if an address/challenge has a pending submission file in the queue dir
-pub fn is_solution_pending_in_queue(base_dir: &str, address: &str, challenge_id: &str) -> Result<bool, String> {
-    use std::path::PathBuf;
+// ===============================================
+// PENDING QUEUE INDEX
+// ===============================================
+
+pub const QUEUE_DIR_NAME: &str = "pending_submissions";
+pub const QUEUE_FILE_EXTENSION: &str = ".json.zst";
+// Older versions wrote plain JSON; these are still read and submitted
+const LEGACY_QUEUE_FILE_EXTENSION: &str = ".json";
+const QUEUE_COMPRESSION_LEVEL: i32 = 3;
+
+pub fn is_queue_file(file_name: &str) -> bool {
+    file_name.ends_with(QUEUE_FILE_EXTENSION) || file_name.ends_with(LEGACY_QUEUE_FILE_EXTENSION)
+}
+
+/// Reads a queue file, decompressing it unless it is a legacy plain JSON entry.
+pub fn read_pending_solution(path: &Path) -> Result<PendingSolution, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Failed to read pending solution file {:?}: {}", path, e))?;
 
-    let mut path = PathBuf::from(base_dir);
-    path.push("pending_submissions");
+    let is_compressed = path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.ends_with(QUEUE_FILE_EXTENSION));
+    let solution_json = if is_compressed {
+        zstd::decode_all(bytes.as_slice())
+            .map_err(|e| format!("Failed to decompress pending solution file {:?}: {}", path, e))?
+    } else {
+        bytes
+    };
+
+    serde_json::from_slice(&solution_json)
+        .map_err(|e| format!("Failed to parse pending solution JSON {:?}: {}", path, e))
+}
 
-    // Scan for any file that matches the address and challenge ID prefix
-    if let Ok(entries) = std::fs::read_dir(&path) {
-        for entry in entries.filter_map(|e| e.ok()) {
-            if let Some(filename) = entry.file_name().to_str() {
-                // Check if the filename starts with the required prefix and is a JSON file
-                // The filename format is: address_challenge_id_nonce.json
-                if filename.starts_with(&format!("{}_{}_", address, challenge_id)) && filename.ends_with(".json") {
-                    return Ok(true);
+/// A queue file, identified by its name: `address_challengeid_nonce.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueueEntry {
+    pub address: String,
+    pub challenge_id: String,
+    pub nonce: String,
+}
+
+impl QueueEntry {
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        // Addresses and nonces never contain '_', so split on the first and last separator
+        let stem = file_name.strip_suffix(QUEUE_FILE_EXTENSION)
+            .or_else(|| file_name.strip_suffix(LEGACY_QUEUE_FILE_EXTENSION))?;
+        let (address, rest) = stem.split_once('_')?;
+        let (challenge_id, nonce) = rest.rsplit_once('_')?;
+        Some(Self { address: address.to_string(), challenge_id: challenge_id.to_string(), nonce: nonce.to_string() })
+    }
+}
+
+/// Changes to the queue directory reported by the submitter thread.
+#[derive(Debug, Clone)]
+pub enum QueueEvent {
+    Queued(QueueEntry),
+    Removed(QueueEntry),
+}
+
+/// In-memory view of the pending submissions queue, so pending checks don't re-read the directory.
+/// Loaded once from disk and kept current with events from the submitter; the files stay the source of truth.
+#[derive(Debug)]
+pub struct PendingQueueIndex {
+    entries: HashMap<(String, String), HashSet<String>>,
+    events: Receiver<QueueEvent>,
+}
+
+impl PendingQueueIndex {
+    pub fn load(base_dir: &str, events: Receiver<QueueEvent>) -> Self {
+        let mut index = Self { entries: HashMap::new(), events };
+
+        let path = queue_dir(base_dir);
+        if let Ok(entries) = std::fs::read_dir(&path) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                if let Some(queue_entry) = entry.file_name().to_str().and_then(QueueEntry::from_file_name) {
+                    index.apply(QueueEvent::Queued(queue_entry));
                 }
             }
         }
+        index
+    }
+
+    fn apply(&mut self, event: QueueEvent) {
+        match event {
+            QueueEvent::Queued(e) => {
+                self.entries.entry((e.address, e.challenge_id)).or_default().insert(e.nonce);
+            },
+            QueueEvent::Removed(e) => {
+                let key = (e.address, e.challenge_id);
+                if let Some(nonces) = self.entries.get_mut(&key) {
+                    nonces.remove(&e.nonce);
+                    if nonces.is_empty() {
+                        self.entries.remove(&key);
+                    }
+                }
+            },
+        }
+    }
+
+    // Checks if an address/challenge has a pending submission in the queue
+    pub fn is_pending(&mut self, address: &str, challenge_id: &str) -> bool {
+        while let Ok(event) = self.events.try_recv() {
+            self.apply(event);
+        }
+        self.entries.contains_key(&(address.to_string(), challenge_id.to_string()))
+    }
+}
+
+
+pub const BACKFILL_DIR_NAME: &str = "backfill";
+
+/// Synthetic receipt written by `receipts backfill` for credits the API reports but no local challenge receipt covers.
+/// The statistics endpoint only exposes a per-address total, so these credits can't be attributed to a challenge.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BackfillReceipt {
+    pub address: String,
+    pub already_credited: bool,
+    pub source: String,
+    pub api_crypto_receipts: u32,
+    pub local_receipts: usize,
+    pub unattributed_receipts: usize,
+    pub night_allocation: u32,
+    pub backfilled_at: String,
+}
+
+pub fn backfill_receipt_path(base_dir: &str, address: &str) -> PathBuf {
+    receipts_root(base_dir).join(BACKFILL_DIR_NAME).join(format!("{}.json", address))
+}
+
+pub fn load_backfill_receipt(base_dir: &str, address: &str) -> Option<BackfillReceipt> {
+    let receipt_json = std::fs::read_to_string(backfill_receipt_path(base_dir, address)).ok()?;
+    serde_json::from_str(&receipt_json).ok()
+}
+
+pub fn save_backfill_receipt(base_dir: &str, receipt: &BackfillReceipt) -> Result<(), String> {
+    let path = backfill_receipt_path(base_dir, &receipt.address);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Could not create {} directory: {}", BACKFILL_DIR_NAME, e))?;
+    }
+
+    let receipt_json = serde_json::to_string_pretty(receipt)
+        .map_err(|e| format!("Could not serialize backfill receipt: {}", e))?;
+    std::fs::write(&path, receipt_json)
+        .map_err(|e| format!("Could not write backfill receipt {:?}: {}", path, e))
+}
+
+/// `receipt.json` for a wallet's first solution of a challenge, `receipt.<n>.json` for the n-th
+/// ('--solutions-per-challenge').
+pub fn receipt_file_name(n: usize) -> String {
+    match n {
+        0 | 1 => FILE_NAME_RECEIPT.to_string(),
+        n => format!("receipt.{}.json", n),
+    }
+}
+
+pub fn is_receipt_file_name(name: &str) -> bool {
+    name == FILE_NAME_RECEIPT
+        || name.strip_prefix("receipt.").and_then(|rest| rest.strip_suffix(".json")).is_some_and(|n| n.parse::<usize>().is_ok_and(|n| n > 1))
+}
+
+fn count_receipt_files(dir: &Path) -> usize {
+    std::fs::read_dir(dir).map_or(0, |entries| entries.filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_str().is_some_and(is_receipt_file_name))
+        .count())
+}
+
+/// Counts the receipts saved locally for a challenge, from every wallet and key mode.
+pub fn count_receipts_for_challenge(base_dir: &str, challenge_id: &str) -> usize {
+    fn count(dir: &Path) -> usize {
+        std::fs::read_dir(dir).map_or(0, |entries| entries.filter_map(|e| e.ok())
+            .map(|e| match e.file_type() {
+                Ok(kind) if kind.is_dir() => count(&e.path()),
+                _ => usize::from(e.file_name().to_str().is_some_and(is_receipt_file_name)),
+            })
+            .sum())
+    }
+    count(&receipts_root(base_dir).join(challenge_id))
+}
+
+// Counts the receipts saved locally for an address across challenges (Persistent or Ephemeral layout)
+pub fn count_challenge_receipts_for_address(base_dir: &str, address: &str) -> usize {
+    let entries = match std::fs::read_dir(receipts_root(base_dir)) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+
+    entries.filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|challenge_path| challenge_path.is_dir())
+        .map(|challenge_path| {
+            ["persistent", "ephemeral"].iter()
+                .map(|kind| count_receipt_files(&challenge_path.join(kind).join(address)))
+                .sum::<usize>()
+        })
+        .sum()
+}
+
+/// Whether a receipt of the address for the challenge is saved locally (Persistent or Ephemeral layout).
+pub fn has_challenge_receipt(base_dir: &str, address: &str, challenge_id: &str) -> bool {
+    let challenge_path = receipts_root(base_dir).join(challenge_id);
+    ["persistent", "ephemeral"].iter().any(|kind| count_receipt_files(&challenge_path.join(kind).join(address)) > 0)
+}
+
+// Counts every receipt held locally for an address, including credits recorded by 'receipts backfill'
+pub fn count_local_receipts_for_address(base_dir: &str, address: &str) -> usize {
+    let backfilled = load_backfill_receipt(base_dir, address).map_or(0, |r| r.unattributed_receipts);
+    count_challenge_receipts_for_address(base_dir, address) + backfilled
+}
+
+// --- Canary wallet ---
+pub const FILE_NAME_CANARY: &str = "canary.json";
+
+/// Written by '--canary' once a solution mined by this release was credited by the API.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CanaryRecord {
+    pub version: String,
+    pub address: String,
+    pub challenge_id: String,
+    pub verified_at: String,
+}
+
+pub fn load_canary_record(base_dir: &str) -> Option<CanaryRecord> {
+    let record_json = std::fs::read_to_string(PathBuf::from(base_dir).join(FILE_NAME_CANARY)).ok()?;
+    serde_json::from_str(&record_json).ok()
+}
+
+pub fn save_canary_record(base_dir: &str, record: &CanaryRecord) -> Result<(), String> {
+    let record_json = serde_json::to_string_pretty(record)
+        .map_err(|e| format!("Could not serialize canary record: {}", e))?;
+    std::fs::write(PathBuf::from(base_dir).join(FILE_NAME_CANARY), record_json)
+        .map_err(|e| format!("Could not write {}: {}", FILE_NAME_CANARY, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(address: &str, challenge_id: &str, nonce: &str) -> QueueEntry {
+        QueueEntry { address: address.to_string(), challenge_id: challenge_id.to_string(), nonce: nonce.to_string() }
+    }
+
+    #[test]
+    fn reads_queue_file_names() {
+        assert_eq!(QueueEntry::from_file_name("addr1qxy_**D01C05_00000000deadbeef.json.zst"), Some(entry("addr1qxy", "**D01C05", "00000000deadbeef")));
+        assert_eq!(QueueEntry::from_file_name("addr1qxy_**D01C05_00000000deadbeef.json"), Some(entry("addr1qxy", "**D01C05", "00000000deadbeef")));
+        // Only the address and the nonce are known to hold no '_'
+        assert_eq!(QueueEntry::from_file_name("addr1qxy_test_challenge_0f.json.zst"), Some(entry("addr1qxy", "test_challenge", "0f")));
+        for name in ["addr1qxy_**D01C05_0f.txt", "addr1qxy_0f.json.zst", "addr1qxy.json.zst", "nonce_checkpoint.json.tmp"] {
+            assert_eq!(QueueEntry::from_file_name(name), None, "'{}' isn't a queue file", name);
+        }
+    }
+
+    #[test]
+    fn queue_file_names_round_trip() {
+        let name = format!("{}_{}_{}{}", "addr1qz", "**D02C11", "0123456789abcdef", QUEUE_FILE_EXTENSION);
+        assert!(is_queue_file(&name));
+        assert_eq!(QueueEntry::from_file_name(&name), Some(entry("addr1qz", "**D02C11", "0123456789abcdef")));
+    }
+
+    #[test]
+    fn pending_index_follows_queue_events() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut index = PendingQueueIndex::load("/nonexistent-queue-dir-for-tests", receiver);
+        assert!(!index.is_pending("addr1a", "**D01C01"));
+
+        sender.send(QueueEvent::Queued(entry("addr1a", "**D01C01", "01"))).unwrap();
+        sender.send(QueueEvent::Queued(entry("addr1a", "**D01C01", "02"))).unwrap();
+        assert!(index.is_pending("addr1a", "**D01C01"));
+        assert!(!index.is_pending("addr1a", "**D01C02"));
+
+        sender.send(QueueEvent::Removed(entry("addr1a", "**D01C01", "01"))).unwrap();
+        assert!(index.is_pending("addr1a", "**D01C01"));
+        sender.send(QueueEvent::Removed(entry("addr1a", "**D01C01", "02"))).unwrap();
+        assert!(!index.is_pending("addr1a", "**D01C01"));
     }
-    // If the directory doesn't exist or no matching file is found
-    Ok(false)
 }
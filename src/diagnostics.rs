@@ -0,0 +1,276 @@
+// src/diagnostics.rs
+
+use crate::audit::AUDIT_LOG_FILE_NAME;
+use crate::cli::Cli;
+use crate::config::DEFAULT_CONFIG_FILE;
+use crate::data_types::{queue_dir, receipts_root};
+use crate::doctor::{cpu_features, huge_pages_summary};
+use crate::journal::SWITCH_JOURNAL_FILE_NAME;
+use crate::utils::CpuTopology;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Lines kept from the end of each log
+const LOG_TAIL_LINES: usize = 500;
+// Failed API writes copied from the audit log
+const API_ERROR_SAMPLES: usize = 20;
+// A data dir listing stops after this many entries
+const LISTING_MAX_ENTRIES: usize = 5000;
+// Config keys whose values are dropped entirely: wallet paths, donation targets, notifier endpoints and
+// proxy URLs, which can carry credentials
+const SECRET_CONFIG_KEYS: [&str; 5] = ["mnemonic_file", "donate_to", "url", "command", "proxy"];
+
+// ===============================================
+// REDACTION
+// ===============================================
+
+/// Replaces identifying values with stable placeholders (`<address-1>`, `<id-2>`), so one wallet reads
+/// the same across every file of the bundle without being revealed.
+#[derive(Default)]
+struct Redactor {
+    placeholders: HashMap<String, String>,
+    addresses: usize,
+    ids: usize,
+    home: Option<String>,
+}
+
+impl Redactor {
+    fn new() -> Self {
+        let home = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).ok().filter(|h| h.len() > 1);
+        Self { home, ..Self::default() }
+    }
+
+    fn placeholder(&mut self, token: &str) -> Option<String> {
+        let is_address = ["addr1", "addr_test1", "addr_vk", "stake1", "stake_test1"].iter().any(|p| token.starts_with(p)) && token.len() > 20;
+        // Keys, mnemonic directory ids, idempotency keys and checksums
+        let is_id = token.len() >= 32 && token.chars().all(|c| c.is_ascii_hexdigit());
+        if !is_address && !is_id {
+            return None;
+        }
+        if let Some(known) = self.placeholders.get(token) {
+            return Some(known.clone());
+        }
+        let placeholder = if is_address {
+            self.addresses += 1;
+            format!("<address-{}>", self.addresses)
+        } else {
+            self.ids += 1;
+            format!("<id-{}>", self.ids)
+        };
+        self.placeholders.insert(token.to_string(), placeholder.clone());
+        Some(placeholder)
+    }
+
+    fn redact(&mut self, text: &str) -> String {
+        let text = match &self.home {
+            Some(home) => text.replace(home.as_str(), "~"),
+            None => text.to_string(),
+        };
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+            out.push_str(&rest[..start]);
+            let token_len = rest[start..].find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len() - start);
+            let token = &rest[start..start + token_len];
+            match self.placeholder(token) {
+                Some(placeholder) => out.push_str(&placeholder),
+                None => out.push_str(token),
+            }
+            rest = &rest[start + token_len..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+// ===============================================
+// BUNDLE CONTENTS
+// ===============================================
+
+fn system_info(cli: &Cli) -> String {
+    let topology = CpuTopology::detect();
+    let features = cpu_features();
+    // Flag names only: values can hold mnemonics, keys and addresses
+    let flags: Vec<String> = std::env::args().skip(1)
+        .filter(|arg| arg.starts_with("--"))
+        .map(|arg| arg.split('=').next().unwrap_or_default().to_string())
+        .collect();
+    [
+        format!("Version:        {}", env!("CARGO_PKG_VERSION")),
+        format!("OS / Arch:      {} / {}", std::env::consts::OS, std::env::consts::ARCH),
+        format!("Cores:          {} logical / {} physical", topology.logical, topology.physical),
+        format!("CPU Features:   {}", if features.is_empty() { "none detected".to_string() } else { features.join(", ") }),
+        format!("NUMA Nodes:     {}", shadow_harvester_lib::numa::detect_nodes().len()),
+        format!("Huge Pages:     {}", huge_pages_summary().unwrap_or_else(|| "unknown".to_string())),
+        format!("API URL:        {}", cli.api_url.as_deref().unwrap_or("not set")),
+        format!("Profile:        {}", cli.profile.as_deref().unwrap_or("none")),
+        format!("Flags:          {}", flags.join(" ")),
+        format!("Collected at:   {}", chrono::Utc::now().to_rfc3339()),
+    ].join("\n") + "\n"
+}
+
+fn redact_config_value(value: &mut toml::Value) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                if SECRET_CONFIG_KEYS.contains(&key.replace('-', "_").as_str()) {
+                    *value = toml::Value::String("<redacted>".to_string());
+                } else {
+                    redact_config_value(value);
+                }
+            }
+        }
+        toml::Value::Array(values) => values.iter_mut().for_each(redact_config_value),
+        _ => {}
+    }
+}
+
+fn redacted_config(path: &str, redactor: &mut Redactor) -> String {
+    let config_toml = match std::fs::read_to_string(path) {
+        Ok(config_toml) => config_toml,
+        Err(e) => return format!("# Could not read {}: {}\n", redactor.redact(path), e),
+    };
+    match config_toml.parse::<toml::Value>() {
+        Ok(mut config) => {
+            redact_config_value(&mut config);
+            redactor.redact(&toml::to_string_pretty(&config).unwrap_or_default())
+        }
+        // Not copied: a file that doesn't parse can't be redacted key by key
+        Err(e) => format!("# {} does not parse as TOML: {}\n", redactor.redact(path), e.message()),
+    }
+}
+
+fn list_dir(root: &Path, dir: &Path, lines: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        if lines.len() >= LISTING_MAX_ENTRIES {
+            return;
+        }
+        let path = entry.path();
+        let relative = path.strip_prefix(root).unwrap_or(&path).display();
+        let Ok(metadata) = entry.metadata() else { continue };
+        if metadata.is_dir() {
+            lines.push(format!("{}/", relative));
+            list_dir(root, &path, lines);
+        } else {
+            let modified = metadata.modified().ok()
+                .map(|m| chrono::DateTime::<chrono::Utc>::from(m).format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default();
+            lines.push(format!("{}  {} bytes  {}", relative, metadata.len(), modified));
+        }
+    }
+}
+
+/// Every file and directory below the data dir (and the receipts and queue dirs when moved out of it).
+fn data_dir_listing(base_dir: &str, redactor: &mut Redactor) -> String {
+    let mut roots = vec![PathBuf::from(base_dir), receipts_root(base_dir), queue_dir(base_dir)];
+    roots.dedup();
+    let mut listing = String::new();
+    for root in roots {
+        if root != Path::new(base_dir) && root.starts_with(base_dir) {
+            continue;
+        }
+        let mut lines = Vec::new();
+        list_dir(&root, &root, &mut lines);
+        if lines.len() >= LISTING_MAX_ENTRIES {
+            lines.push(format!("... (stopped after {} entries)", LISTING_MAX_ENTRIES));
+        }
+        listing.push_str(&format!("# {}\n{}\n\n", root.display(), lines.join("\n")));
+    }
+    redactor.redact(&listing)
+}
+
+fn log_tail(path: &Path, redactor: &mut Redactor) -> Option<String> {
+    let log = std::fs::read_to_string(path).ok()?;
+    let lines: Vec<&str> = log.lines().collect();
+    let tail = &lines[lines.len().saturating_sub(LOG_TAIL_LINES)..];
+    Some(redactor.redact(&(tail.join("\n") + "\n")))
+}
+
+/// The latest failed registrations, donations and submissions recorded in the audit log.
+fn api_error_samples(base_dir: &str, redactor: &mut Redactor) -> String {
+    let log = std::fs::read_to_string(Path::new(base_dir).join(AUDIT_LOG_FILE_NAME)).unwrap_or_default();
+    let errors: Vec<&str> = log.lines()
+        .filter(|line| serde_json::from_str::<serde_json::Value>(line).is_ok_and(|entry| entry["outcome"] == "error"))
+        .collect();
+    let samples = &errors[errors.len().saturating_sub(API_ERROR_SAMPLES)..];
+    if samples.is_empty() {
+        return "No failed API writes recorded.\n".to_string();
+    }
+    redactor.redact(&(samples.join("\n") + "\n"))
+}
+
+// ===============================================
+// ARCHIVE (ustar, zstd-compressed)
+// ===============================================
+
+fn tar_header(name: &str, size: usize, mtime: i64) -> [u8; 512] {
+    let mut header = [0u8; 512];
+    let mut field = |offset: usize, value: &[u8]| header[offset..offset + value.len()].copy_from_slice(value);
+    field(0, &name.as_bytes()[..name.len().min(99)]);
+    field(100, b"0000644\0");
+    field(108, b"0000000\0");
+    field(116, b"0000000\0");
+    field(124, format!("{:011o}\0", size).as_bytes());
+    field(136, format!("{:011o}\0", mtime.max(0)).as_bytes());
+    field(148, b"        ");
+    field(156, b"0");
+    // Magic "ustar\0" and version "00"
+    field(257, b"ustar\x0000");
+    let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+    header
+}
+
+fn write_archive(path: &str, files: &[(String, String)]) -> Result<(), String> {
+    let file = std::fs::File::create(path).map_err(|e| format!("Could not create {}: {}", path, e))?;
+    let mut encoder = zstd::Encoder::new(file, 0).map_err(|e| format!("Could not start compression: {}", e))?;
+    let mtime = chrono::Utc::now().timestamp();
+    let written = files.iter()
+        .try_for_each(|(name, contents)| {
+            encoder.write_all(&tar_header(name, contents.len(), mtime))?;
+            encoder.write_all(contents.as_bytes())?;
+            encoder.write_all(&vec![0u8; contents.len().next_multiple_of(512) - contents.len()])
+        })
+        // Two zero blocks end the archive
+        .and_then(|_| encoder.write_all(&[0u8; 1024]))
+        .and_then(|_| encoder.finish().map(|_| ()));
+    written.map_err(|e| format!("Could not write {}: {}", path, e))
+}
+
+// ===============================================
+// DIAGNOSTICS BUNDLE COMMAND
+// ===============================================
+
+/// Writes a zstd-compressed tar of version info, the config, a data dir listing, log tails and recent API
+/// errors. Addresses, keys, mnemonic paths, notifier endpoints and the home directory are redacted.
+pub fn run_bundle(cli: &Cli, output: Option<&str>) -> Result<(), String> {
+    let output = output.map(str::to_string).unwrap_or_else(|| {
+        format!("shadow-harvester-diagnostics-{}.tar.zst", chrono::Utc::now().format("%Y%m%dT%H%M%SZ"))
+    });
+    let mut redactor = Redactor::new();
+    let mut files = vec![("system.txt".to_string(), system_info(cli))];
+
+    let config = cli.config.as_deref().or(Path::new(DEFAULT_CONFIG_FILE).exists().then_some(DEFAULT_CONFIG_FILE));
+    if let Some(config) = config {
+        files.push(("config.toml".to_string(), redacted_config(config, &mut redactor)));
+    }
+    if let Some(base_dir) = cli.data_dir.as_deref() {
+        files.push(("data_dir.txt".to_string(), data_dir_listing(base_dir, &mut redactor)));
+        for log in [AUDIT_LOG_FILE_NAME, SWITCH_JOURNAL_FILE_NAME] {
+            if let Some(tail) = log_tail(&Path::new(base_dir).join(log), &mut redactor) {
+                files.push((format!("logs/{}", log), tail));
+            }
+        }
+        files.push(("api_errors.txt".to_string(), api_error_samples(base_dir, &mut redactor)));
+    }
+
+    write_archive(&output, &files)?;
+    println!("📦 Diagnostics bundle written to {} ({} files: {}).",
+        output, files.len(), files.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(", "));
+    println!("🔒 Addresses, keys and ids are replaced by placeholders and secret config values dropped. Review the archive before sharing it.");
+    Ok(())
+}
@@ -0,0 +1,180 @@
+// src/doctor.rs
+
+use crate::api;
+use crate::cli::Cli;
+use crate::constants::DEFAULT_THREADS;
+use std::net::ToSocketAddrs;
+use std::path::PathBuf;
+
+// Memory needed for one ROM (1 GiB) plus headroom for the process itself
+const ROM_MEMORY_MB: u64 = 1024;
+const HEADROOM_MEMORY_MB: u64 = 512;
+
+// ===============================================
+// SYSTEM PROBES
+// ===============================================
+
+pub fn cpu_features() -> Vec<&'static str> {
+    #[allow(unused_mut)]
+    let mut features = Vec::new();
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if std::arch::is_x86_feature_detected!("sse4.2") { features.push("SSE4.2"); }
+        if std::arch::is_x86_feature_detected!("avx2") { features.push("AVX2"); }
+        if std::arch::is_x86_feature_detected!("avx512f") { features.push("AVX-512F"); }
+        if std::arch::is_x86_feature_detected!("bmi2") { features.push("BMI2"); }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") { features.push("NEON"); }
+        if std::arch::is_aarch64_feature_detected!("sha3") { features.push("SHA3"); }
+    }
+    features
+}
+
+/// Reserved huge pages and the transparent huge page mode, for '--huge-pages'.
+pub fn huge_pages_summary() -> Option<String> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let field = |name: &str| meminfo.lines()
+        .find(|line| line.starts_with(name))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|value| value.parse::<u64>().ok());
+    // The active mode is bracketed, e.g. "always [madvise] never"
+    let thp = std::fs::read_to_string("/sys/kernel/mm/transparent_hugepage/enabled").ok()
+        .and_then(|modes| Some(modes.split_once('[')?.1.split_once(']')?.0.to_string()))
+        .unwrap_or_else(|| "unknown".to_string());
+    Some(format!("{} of {} reserved free ({} kB each), transparent: {}",
+        field("HugePages_Free:")?, field("HugePages_Total:")?, field("Hugepagesize:")?, thp))
+}
+
+/// Reads a `kB` field from /proc/meminfo and returns it in MiB (Linux only).
+fn meminfo_mb(field: &str) -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo.lines()
+        .find(|line| line.starts_with(field))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb / 1024)
+}
+
+/// Returns the soft limit line for the given resource from /proc/self/limits (Linux only).
+fn ulimit(resource: &str) -> Option<String> {
+    let limits = std::fs::read_to_string("/proc/self/limits").ok()?;
+    limits.lines()
+        .find(|line| line.starts_with(resource))
+        .map(|line| line[resource.len()..].split_whitespace().next().unwrap_or("?").to_string())
+}
+
+fn check_dns(api_url: &str) -> Result<String, String> {
+    let url = reqwest::Url::parse(api_url).map_err(|e| format!("Invalid API URL: {}", e))?;
+    let host = url.host_str().ok_or("API URL has no host")?;
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addrs: Vec<String> = (host, port).to_socket_addrs()
+        .map_err(|e| format!("Could not resolve {}: {}", host, e))?
+        .map(|a| a.ip().to_string())
+        .collect();
+    Ok(format!("{} -> {}", host, addrs.join(", ")))
+}
+
+pub fn check_write_permissions(data_dir: &str) -> Result<(), String> {
+    std::fs::create_dir_all(data_dir)
+        .map_err(|e| format!("Could not create {}: {}", data_dir, e))?;
+
+    let probe_path = PathBuf::from(data_dir).join(".doctor_write_test");
+    std::fs::write(&probe_path, b"ok")
+        .map_err(|e| format!("Could not write to {}: {}", data_dir, e))?;
+    std::fs::remove_file(&probe_path)
+        .map_err(|e| format!("Could not delete test file in {}: {}", data_dir, e))?;
+    Ok(())
+}
+
+// ===============================================
+// DOCTOR COMMAND
+// ===============================================
+
+/// Prints a diagnostics report of the mining environment and suggested flags for this machine.
+pub fn run_doctor(cli: &Cli) {
+    println!("\n==============================================");
+    println!("🩺 Shadow Harvester: Environment Diagnostics");
+    println!("==============================================");
+
+    // --- CPU ---
+    let cores = std::thread::available_parallelism().map(|n| n.get() as u32).ok();
+    let features = cpu_features();
+    println!("** CPU **");
+    println!("  Architecture:     {}", std::env::consts::ARCH);
+    println!("  OS:               {}", std::env::consts::OS);
+    match cores {
+        Some(c) => println!("  Logical Cores:    {}", c),
+        None => println!("  Logical Cores:    unknown"),
+    }
+    println!("  Features:         {}", if features.is_empty() { "none detected".to_string() } else { features.join(", ") });
+    match shadow_harvester_lib::numa::detect_nodes().len() {
+        0 => println!("  NUMA Nodes:       unknown"),
+        1 => println!("  NUMA Nodes:       1"),
+        n => println!("  NUMA Nodes:       {} (try '--numa bind' or '--numa replicate')", n),
+    }
+
+    // --- Memory and limits ---
+    let total_mb = meminfo_mb("MemTotal:");
+    let available_mb = meminfo_mb("MemAvailable:");
+    println!("----------------------------------------------");
+    println!("** MEMORY / LIMITS **");
+    match (total_mb, available_mb) {
+        (Some(total), Some(available)) => println!("  RAM:              {} MiB total, {} MiB available", total, available),
+        _ => println!("  RAM:              unknown (no /proc/meminfo)"),
+    }
+    println!("  Max Open Files:   {}", ulimit("Max open files").unwrap_or_else(|| "unknown".to_string()));
+    println!("  Max Processes:    {}", ulimit("Max processes").unwrap_or_else(|| "unknown".to_string()));
+    println!("  Max Locked Mem:   {}", ulimit("Max locked memory").unwrap_or_else(|| "unknown".to_string()));
+    println!("  Huge Pages:       {}", huge_pages_summary().unwrap_or_else(|| "unknown".to_string()));
+
+    // --- Network ---
+    println!("----------------------------------------------");
+    println!("** NETWORK **");
+    println!("  TLS Backend:      native-tls (reqwest default-tls)");
+    match cli.api_url.as_deref() {
+        Some(api_url) => for endpoint in api::endpoints(api_url) {
+            match check_dns(&endpoint) {
+                Ok(resolved) => println!("  DNS:              ✅ {}", resolved),
+                Err(e) => println!("  DNS:              ❌ {}", e),
+            }
+        },
+        None => println!("  DNS:              skipped (no '--api-url' given)"),
+    }
+
+    // --- Storage ---
+    println!("----------------------------------------------");
+    println!("** STORAGE **");
+    match cli.data_dir.as_deref() {
+        Some(data_dir) => match check_write_permissions(data_dir) {
+            Ok(()) => println!("  Data Dir:         ✅ {} is writable", data_dir),
+            Err(e) => println!("  Data Dir:         ❌ {}", e),
+        },
+        None => println!("  Data Dir:         not set"),
+    }
+    for (label, dir) in [("Receipts Dir:", cli.receipts_dir.as_deref()), ("Queue Dir:", cli.queue_dir.as_deref())] {
+        if let Some(dir) = dir {
+            match check_write_permissions(dir) {
+                Ok(()) => println!("  {:<17} ✅ {} is writable", label, dir),
+                Err(e) => println!("  {:<17} ❌ {}", label, e),
+            }
+        }
+    }
+
+    // --- Suggestions ---
+    println!("----------------------------------------------");
+    println!("** SUGGESTIONS **");
+    let suggested_threads = cores.unwrap_or(DEFAULT_THREADS);
+    println!("  --threads {}      (one worker per logical core)", suggested_threads);
+    if let Some(available) = available_mb
+        && available < ROM_MEMORY_MB + HEADROOM_MEMORY_MB
+    {
+        println!("  ⚠️ Only {} MiB available: mining needs ~{} MiB for the ROM. Close other programs or expect swapping.", available, ROM_MEMORY_MB + HEADROOM_MEMORY_MB);
+    }
+    if !features.contains(&"AVX2") && !features.contains(&"NEON") {
+        println!("  ⚠️ No AVX2/NEON detected: expect a lower hash rate on this machine.");
+    }
+    println!("==============================================");
+}
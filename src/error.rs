@@ -0,0 +1,50 @@
+// src/error.rs
+
+// The errors of the API calls. The mining loops and the submitter decide whether to retry, hold or give up
+// on the variant rather than on the message. Code that reports errors as strings keeps using `?`.
+
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum ApiError {
+    /// No response from any endpoint through every retry: connection, TLS or timeout.
+    #[error("API unavailable: {0}")]
+    Network(String),
+    /// A status the call doesn't accept, with the API's error details or the raw body.
+    #[error("HTTP Error {status}: {message}")]
+    HttpStatus { status: u16, message: String },
+    /// A response that isn't what the call expects.
+    #[error("Could not parse the API response: {0}")]
+    Decode(String),
+    /// The address already submitted a solution to the challenge (409 Conflict).
+    #[error("Already solved: {0}")]
+    AlreadySolved(String),
+    /// The API asked to wait longer than a call holds for, e.g. while the submission window is closed.
+    #[error("Rate limited (Status {status}), retry in {}s: {message}", retry_after.as_secs())]
+    RateLimited { status: u16, retry_after: Duration, message: String },
+    /// No challenge is active: mining hasn't started or has ended.
+    #[error("{0}")]
+    NotActive(String),
+    /// The request can't be made as given, e.g. an invalid '--challenge'.
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl ApiError {
+    /// Whether the call is worth trying again later: the API was unreachable, failing (5xx) or rate
+    /// limiting, rather than rejecting the request.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ApiError::Network(_) | ApiError::RateLimited { .. } => true,
+            ApiError::HttpStatus { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl From<ApiError> for String {
+    fn from(error: ApiError) -> String {
+        error.to_string()
+    }
+}
@@ -0,0 +1,177 @@
+// src/health.rs
+
+// '--health-listen': a minimal HTTP listener for container supervisors (e.g. Kubernetes probes).
+// GET /healthz answers 200 while the process serves requests. GET /readyz answers 200 only while
+// the miner is hashing (or idle by design: paused, or waiting for the next challenge after a search),
+// the submission queue isn't backed up and the API answers. Otherwise it answers 503. Both return a
+// JSON body with the details.
+
+use crate::api;
+use crate::data_types::{is_queue_file, queue_dir};
+use reqwest::Client;
+use serde::Serialize;
+use shadow_harvester_lib::ScavengeControl;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How often the hash counter is sampled and the API probed
+const SAMPLE_SECS: u64 = 10;
+const API_PROBE_SECS: u64 = 60;
+// Not ready once a running search computed no hash for this long
+const STALLED_SECS: u64 = 3 * 60;
+// Not ready with more solutions than this waiting for submission
+const MAX_QUEUE_DEPTH: usize = 100;
+const REQUEST_TIMEOUT_SECS: u64 = 5;
+
+#[derive(Debug, Default)]
+struct Samples {
+    hashes: u64,
+    last_hash: Option<Instant>,
+    api_checked: Option<Instant>,
+    api_error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Check<T: Serialize> {
+    ok: bool,
+    #[serde(flatten)]
+    details: T,
+}
+
+#[derive(Debug, Serialize)]
+struct Mining {
+    hashes: u64,
+    last_hash_secs_ago: Option<u64>,
+    paused: bool,
+    /// No search runs: the miner searched before and now waits, e.g. for the next challenge after a solution.
+    idle: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Queue {
+    depth: Option<usize>,
+    max: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiReachability {
+    last_check_secs_ago: Option<u64>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Readiness {
+    ready: bool,
+    mining: Check<Mining>,
+    queue: Check<Queue>,
+    api: Check<ApiReachability>,
+}
+
+struct Health {
+    control: Arc<ScavengeControl>,
+    data_dir: Option<String>,
+    samples: Mutex<Samples>,
+    started: Instant,
+}
+
+/// Serves /healthz and /readyz on `bind`, and samples the miner and the API from background threads.
+/// Fails if `bind` can't be listened on.
+pub fn spawn_health_server(bind: &str, client: Client, api_url: String, data_dir: Option<String>, control: Arc<ScavengeControl>) -> Result<(), String> {
+    let listener = TcpListener::bind(bind).map_err(|e| format!("Could not listen for health checks on {}: {}", bind, e))?;
+    println!("🩺 Health endpoints (/healthz, /readyz) listening on {}.", listener.local_addr().map_or(bind.to_string(), |a| a.to_string()));
+    let health = Arc::new(Health { control, data_dir, samples: Mutex::new(Samples::default()), started: Instant::now() });
+
+    let sampler = health.clone();
+    thread::spawn(move || loop {
+        sampler.sample_hashes();
+        let due = sampler.samples.lock().unwrap_or_else(|e| e.into_inner()).api_checked
+            .is_none_or(|checked| checked.elapsed() >= Duration::from_secs(API_PROBE_SECS));
+        if due {
+            let error = api::block_on(api::fetch_challenge_status(&client, &api_url)).err().map(|e| e.to_string());
+            let mut samples = sampler.samples.lock().unwrap_or_else(|e| e.into_inner());
+            samples.api_checked = Some(Instant::now());
+            samples.api_error = error;
+        }
+        thread::sleep(Duration::from_secs(SAMPLE_SECS));
+    });
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let health = health.clone();
+                    thread::spawn(move || health.serve(stream));
+                },
+                Err(e) => eprintln!("⚠️ Health check connection failed: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+impl Health {
+    fn sample_hashes(&self) {
+        let hashes = self.control.hashes.load(Ordering::Relaxed);
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        if hashes != samples.hashes {
+            samples.hashes = hashes;
+            samples.last_hash = Some(Instant::now());
+        }
+    }
+
+    fn readiness(&self) -> Readiness {
+        self.sample_hashes();
+        let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        let last_hash = samples.last_hash.map(|at| at.elapsed());
+        let paused = self.control.allowed_threads() == 0;
+        let idle = self.control.is_idle();
+        let mining = Check {
+            ok: paused || idle || last_hash.is_some_and(|ago| ago < Duration::from_secs(STALLED_SECS)),
+            details: Mining { hashes: samples.hashes, last_hash_secs_ago: last_hash.map(|ago| ago.as_secs()), paused, idle },
+        };
+        let depth = self.data_dir.as_deref().map(queue_depth);
+        let queue = Check { ok: depth.unwrap_or(0) <= MAX_QUEUE_DEPTH, details: Queue { depth, max: MAX_QUEUE_DEPTH } };
+        let api = Check {
+            ok: samples.api_checked.is_some() && samples.api_error.is_none(),
+            details: ApiReachability { last_check_secs_ago: samples.api_checked.map(|at| at.elapsed().as_secs()), error: samples.api_error.clone() },
+        };
+        Readiness { ready: mining.ok && queue.ok && api.ok, mining, queue, api }
+    }
+
+    fn serve(&self, mut stream: TcpStream) {
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(REQUEST_TIMEOUT_SECS)));
+        let Ok(reader) = stream.try_clone() else { return };
+        let mut request_line = String::new();
+        if BufReader::new(reader).read_line(&mut request_line).is_err() {
+            return;
+        }
+        let mut parts = request_line.split_whitespace();
+        let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+        // Probes may add a query string
+        let path = path.split('?').next().unwrap_or_default();
+        let (status, body) = match (method, path) {
+            ("GET" | "HEAD", "/healthz") => ("200 OK", serde_json::json!({ "ok": true, "uptime_secs": self.started.elapsed().as_secs() }).to_string()),
+            ("GET" | "HEAD", "/readyz") => {
+                let readiness = self.readiness();
+                let status = if readiness.ready { "200 OK" } else { "503 Service Unavailable" };
+                (status, serde_json::to_string(&readiness).unwrap_or_default())
+            },
+            ("GET" | "HEAD", _) => ("404 Not Found", r#"{"error":"not found"}"#.to_string()),
+            _ => ("405 Method Not Allowed", r#"{"error":"method not allowed"}"#.to_string()),
+        };
+        let _ = write!(stream, "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, body.len(), if method == "HEAD" { "" } else { &body });
+    }
+}
+
+/// Solutions waiting in the submission queue.
+fn queue_depth(base_dir: &str) -> usize {
+    std::fs::read_dir(queue_dir(base_dir)).map_or(0, |entries| entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_str().is_some_and(is_queue_file))
+        .count())
+}
@@ -0,0 +1,256 @@
+// Huge-page backed memory for the ROM ('--huge-pages'). The hash reads the 1 GiB ROM at random offsets,
+// so with 4 KiB pages nearly every access misses the TLB; 2 MiB (or larger) pages cover it with a few
+// hundred entries.
+
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HugePageKind {
+    /// Pages reserved up front by the administrator (Linux hugetlbfs, Windows large pages).
+    Explicit,
+    /// Linux transparent huge pages, requested with madvise; the kernel backs what it can.
+    Transparent,
+}
+
+impl HugePageKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            HugePageKind::Explicit => "explicit huge pages",
+            HugePageKind::Transparent => "transparent huge pages",
+        }
+    }
+}
+
+/// Zeroed, page-aligned anonymous memory of `len` bytes held in huge pages. Freed on drop.
+pub struct HugePageBuffer {
+    ptr: NonNull<u8>,
+    len: usize,
+    // Windows frees by address alone
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    mapped_len: usize,
+    kind: HugePageKind,
+}
+
+// SAFETY: the buffer exclusively owns its mapping, like a Vec<u8>
+unsafe impl Send for HugePageBuffer {}
+unsafe impl Sync for HugePageBuffer {}
+
+impl HugePageBuffer {
+    pub fn kind(&self) -> HugePageKind {
+        self.kind
+    }
+}
+
+impl Deref for HugePageBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: ptr points to at least len initialised (zero-filled) bytes owned by self
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for HugePageBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY: as above, and &mut self guarantees exclusive access
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+// ===============================================
+// LINUX: hugetlbfs, then transparent huge pages
+// ===============================================
+
+#[cfg(target_os = "linux")]
+const DEFAULT_HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Size of the default huge page, from /proc/meminfo ("Hugepagesize: 2048 kB").
+#[cfg(target_os = "linux")]
+pub fn huge_page_size() -> usize {
+    std::fs::read_to_string("/proc/meminfo").ok()
+        .and_then(|meminfo| meminfo.lines()
+            .find_map(|line| line.strip_prefix("Hugepagesize:"))
+            .and_then(|value| value.trim().trim_end_matches("kB").trim().parse::<usize>().ok()))
+        .map_or(DEFAULT_HUGE_PAGE_SIZE, |kb| kb * 1024)
+}
+
+/// The transparent huge page mode selected in /sys/kernel/mm/transparent_hugepage/enabled ("always",
+/// "madvise" or "never"), or None on kernels without THP.
+#[cfg(target_os = "linux")]
+fn transparent_huge_page_mode() -> Option<String> {
+    let modes = std::fs::read_to_string("/sys/kernel/mm/transparent_hugepage/enabled").ok()?;
+    modes.split_whitespace()
+        .find_map(|mode| mode.strip_prefix('[').and_then(|mode| mode.strip_suffix(']')))
+        .map(str::to_string)
+}
+
+#[cfg(target_os = "linux")]
+impl HugePageBuffer {
+    /// Tries reserved huge pages first, then transparent huge pages. Fails if neither is available.
+    pub fn alloc(len: usize) -> io::Result<Self> {
+        let page = huge_page_size();
+        let mapped_len = len.next_multiple_of(page);
+
+        // SAFETY: anonymous mapping with no address hint; the result is checked against MAP_FAILED
+        let explicit = unsafe {
+            libc::mmap(std::ptr::null_mut(), mapped_len, libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_HUGETLB, -1, 0)
+        };
+        if explicit != libc::MAP_FAILED {
+            return Ok(Self { ptr: NonNull::new(explicit.cast()).unwrap(), len, mapped_len, kind: HugePageKind::Explicit });
+        }
+
+        // madvise succeeds even when THP is off, so the mode decides whether the kernel will back the range
+        match transparent_huge_page_mode().as_deref() {
+            Some("always" | "madvise") => {},
+            Some(mode) => return Err(io::Error::new(io::ErrorKind::Unsupported, format!("no reserved huge pages, and transparent huge pages are set to '{}'", mode))),
+            None => return Err(io::Error::new(io::ErrorKind::Unsupported, "no reserved huge pages, and the kernel has no transparent huge pages")),
+        }
+
+        // Transparent huge pages only back 2 MiB-aligned ranges: over-map by one page and trim
+        // SAFETY: as above
+        let base = unsafe {
+            libc::mmap(std::ptr::null_mut(), mapped_len + page, libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1, 0)
+        };
+        if base == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        let head = (base as usize).next_multiple_of(page) - base as usize;
+        let aligned = base.wrapping_byte_add(head);
+        // SAFETY: head and tail are the unused parts of the mapping just created
+        unsafe {
+            if head > 0 {
+                libc::munmap(base, head);
+            }
+            libc::munmap(aligned.wrapping_byte_add(mapped_len), page - head);
+        }
+
+        // SAFETY: aligned..aligned+mapped_len is mapped and owned here
+        if unsafe { libc::madvise(aligned, mapped_len, libc::MADV_HUGEPAGE) } != 0 {
+            let error = io::Error::last_os_error();
+            // SAFETY: unmaps the range mapped above, which nothing else references
+            unsafe { libc::munmap(aligned, mapped_len) };
+            return Err(io::Error::new(error.kind(), format!("no reserved huge pages, and transparent huge pages are unavailable: {}", error)));
+        }
+        Ok(Self { ptr: NonNull::new(aligned.cast()).unwrap(), len, mapped_len, kind: HugePageKind::Transparent })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for HugePageBuffer {
+    fn drop(&mut self) {
+        // SAFETY: ptr/mapped_len describe exactly the mapping created by alloc
+        unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.mapped_len) };
+    }
+}
+
+// ===============================================
+// WINDOWS: large pages (needs the "Lock pages in memory" right)
+// ===============================================
+
+#[cfg(windows)]
+mod windows {
+    use std::ffi::c_void;
+
+    pub const MEM_COMMIT: u32 = 0x1000;
+    pub const MEM_RESERVE: u32 = 0x2000;
+    pub const MEM_LARGE_PAGES: u32 = 0x2000_0000;
+    pub const MEM_RELEASE: u32 = 0x8000;
+    pub const PAGE_READWRITE: u32 = 0x04;
+    const TOKEN_ADJUST_PRIVILEGES: u32 = 0x0020;
+    const TOKEN_QUERY: u32 = 0x0008;
+    const SE_PRIVILEGE_ENABLED: u32 = 0x0002;
+
+    #[repr(C)]
+    struct TokenPrivileges {
+        count: u32,
+        luid_low: u32,
+        luid_high: i32,
+        attributes: u32,
+    }
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        pub fn GetLargePageMinimum() -> usize;
+        pub fn VirtualAlloc(address: *mut c_void, size: usize, allocation_type: u32, protect: u32) -> *mut c_void;
+        pub fn VirtualFree(address: *mut c_void, size: usize, free_type: u32) -> i32;
+        fn GetCurrentProcess() -> *mut c_void;
+        fn CloseHandle(handle: *mut c_void) -> i32;
+    }
+
+    #[link(name = "advapi32")]
+    unsafe extern "system" {
+        fn OpenProcessToken(process: *mut c_void, access: u32, token: *mut *mut c_void) -> i32;
+        fn LookupPrivilegeValueW(system: *const u16, name: *const u16, luid: *mut [u32; 2]) -> i32;
+        fn AdjustTokenPrivileges(token: *mut c_void, disable_all: i32, new_state: *const TokenPrivileges, length: u32, previous: *mut c_void, return_length: *mut u32) -> i32;
+    }
+
+    /// Enables SeLockMemoryPrivilege in the process token; large pages can't be allocated without it.
+    pub fn enable_lock_memory_privilege() -> bool {
+        let name: Vec<u16> = "SeLockMemoryPrivilege".encode_utf16().chain(Some(0)).collect();
+        // SAFETY: plain Win32 calls on this process's token, with buffers sized for them
+        unsafe {
+            let mut token = std::ptr::null_mut();
+            if OpenProcessToken(GetCurrentProcess(), TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY, &mut token) == 0 {
+                return false;
+            }
+            let mut luid = [0u32; 2];
+            let enabled = LookupPrivilegeValueW(std::ptr::null(), name.as_ptr(), &mut luid) != 0 && {
+                let privileges = TokenPrivileges { count: 1, luid_low: luid[0], luid_high: luid[1] as i32, attributes: SE_PRIVILEGE_ENABLED };
+                // Succeeds even when the right isn't held; ERROR_NOT_ALL_ASSIGNED then shows in the allocation
+                AdjustTokenPrivileges(token, 0, &privileges, 0, std::ptr::null_mut(), std::ptr::null_mut()) != 0
+            };
+            CloseHandle(token);
+            enabled
+        }
+    }
+}
+
+#[cfg(windows)]
+impl HugePageBuffer {
+    pub fn alloc(len: usize) -> io::Result<Self> {
+        use windows::*;
+
+        // SAFETY: no arguments
+        let page = unsafe { GetLargePageMinimum() };
+        if page == 0 {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "large pages are not supported on this system"));
+        }
+        enable_lock_memory_privilege();
+        let mapped_len = len.next_multiple_of(page);
+        // SAFETY: fresh allocation with no address hint; checked for null
+        let ptr = unsafe { VirtualAlloc(std::ptr::null_mut(), mapped_len, MEM_RESERVE | MEM_COMMIT | MEM_LARGE_PAGES, PAGE_READWRITE) };
+        match NonNull::new(ptr.cast()) {
+            Some(ptr) => Ok(Self { ptr, len, mapped_len, kind: HugePageKind::Explicit }),
+            None => Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                format!("large page allocation failed (grant the 'Lock pages in memory' right): {}", io::Error::last_os_error()))),
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for HugePageBuffer {
+    fn drop(&mut self) {
+        // SAFETY: releases the whole allocation made by alloc
+        unsafe { windows::VirtualFree(self.ptr.as_ptr().cast(), 0, windows::MEM_RELEASE) };
+    }
+}
+
+// ===============================================
+// OTHER PLATFORMS
+// ===============================================
+
+#[cfg(not(any(target_os = "linux", windows)))]
+impl HugePageBuffer {
+    pub fn alloc(_len: usize) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, format!("huge pages are not supported on {}", std::env::consts::OS)))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+impl Drop for HugePageBuffer {
+    fn drop(&mut self) {}
+}
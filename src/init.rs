@@ -0,0 +1,212 @@
+// src/init.rs
+
+use crate::cli::Cli;
+use crate::config::DEFAULT_CONFIG_FILE;
+use crate::constants::DEFAULT_THREADS;
+use crate::doctor::check_write_permissions;
+use bip39::Mnemonic;
+use pallas::ledger::addresses::Address;
+use rand_core::{OsRng, RngCore};
+use std::io::{self, BufRead, Write};
+
+const DEFAULT_PROFILE_NAME: &str = "solo";
+const DEFAULT_MNEMONIC_FILE: &str = "mnemonic.txt";
+// 256 bits of entropy -> 24 words
+const MNEMONIC_ENTROPY_BYTES: usize = 32;
+
+// ===============================================
+// PROMPT HELPERS
+// ===============================================
+
+/// Asks a question and returns the trimmed answer, or the default if the answer is empty.
+fn prompt(question: &str, default: Option<&str>) -> Result<String, String> {
+    match default {
+        Some(d) => print!("{} [{}]: ", question, d),
+        None => print!("{}: ", question),
+    }
+    io::stdout().flush().map_err(|e| format!("Could not write to stdout: {}", e))?;
+
+    let mut answer = String::new();
+    let read = io::stdin().lock().read_line(&mut answer)
+        .map_err(|e| format!("Could not read from stdin: {}", e))?;
+    if read == 0 {
+        return Err("Input closed before the wizard finished.".to_string());
+    }
+
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default.unwrap_or("").to_string() } else { answer.to_string() })
+}
+
+/// Re-asks until `validate` accepts the answer, printing its error each time.
+fn prompt_valid<T>(question: &str, default: Option<&str>, validate: impl Fn(&str) -> Result<T, String>) -> Result<T, String> {
+    loop {
+        let answer = prompt(question, default)?;
+        match validate(&answer) {
+            Ok(value) => return Ok(value),
+            Err(e) => println!("  ❌ {}", e),
+        }
+    }
+}
+
+pub fn prompt_yes_no(question: &str, default: bool) -> Result<bool, String> {
+    prompt_valid(question, Some(if default { "y" } else { "n" }), |answer| {
+        match answer.to_ascii_lowercase().as_str() {
+            "y" | "yes" => Ok(true),
+            "n" | "no" => Ok(false),
+            _ => Err("Please answer 'y' or 'n'.".to_string()),
+        }
+    })
+}
+
+// ===============================================
+// ANSWER VALIDATION
+// ===============================================
+
+fn validate_api_url(answer: &str) -> Result<String, String> {
+    let url = reqwest::Url::parse(answer).map_err(|e| format!("Invalid URL: {}", e))?;
+    if url.scheme() != "https" && url.scheme() != "http" {
+        return Err("The API URL must start with https://".to_string());
+    }
+    Ok(answer.trim_end_matches('/').to_string())
+}
+
+fn validate_mnemonic(answer: &str) -> Result<String, String> {
+    let mnemonic = Mnemonic::parse(answer).map_err(|e| format!("Invalid mnemonic: {}", e))?;
+    if mnemonic.word_count() != 24 {
+        return Err(format!("Expected 24 words, got {}.", mnemonic.word_count()));
+    }
+    Ok(mnemonic.to_string())
+}
+
+fn validate_donation_address(answer: &str) -> Result<Option<String>, String> {
+    if answer.is_empty() {
+        return Ok(None);
+    }
+    Address::from_bech32(answer).map_err(|_| format!("'{}' is not a valid bech32 Cardano address.", answer))?;
+    Ok(Some(answer.to_string()))
+}
+
+fn validate_threads(answer: &str) -> Result<u32, String> {
+    match answer.parse::<u32>() {
+        Ok(t) if t > 0 => Ok(t),
+        _ => Err("Enter a whole number of threads greater than 0.".to_string()),
+    }
+}
+
+fn generate_mnemonic() -> Result<String, String> {
+    let mut entropy = [0u8; MNEMONIC_ENTROPY_BYTES];
+    OsRng.fill_bytes(&mut entropy);
+    Mnemonic::from_entropy(&entropy)
+        .map(|m| m.to_string())
+        .map_err(|e| format!("Could not generate mnemonic: {}", e))
+}
+
+// ===============================================
+// FILE OUTPUT
+// ===============================================
+
+fn write_mnemonic_file(path: &str, mnemonic: &str) -> Result<(), String> {
+    // The phrase controls the wallets: create the file readable by the owner only, so it's never
+    // written with wider permissions, and tighten a file that already existed before writing to it
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)
+        .map_err(|e| format!("Could not write mnemonic file {}: {}", path, e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Could not restrict permissions of {}: {}", path, e))?;
+    }
+    writeln!(file, "{}", mnemonic).map_err(|e| format!("Could not write mnemonic file {}: {}", path, e))
+}
+
+/// Adds (or replaces) `[profile.<name>]` in the config file, keeping any other profiles and sinks.
+fn write_profile(config_path: &str, profile_name: &str, profile: toml::Table) -> Result<(), String> {
+    let mut config: toml::Table = match std::fs::read_to_string(config_path) {
+        Ok(existing) => toml::from_str(&existing)
+            .map_err(|e| format!("Could not parse existing config file {}: {}", config_path, e))?,
+        Err(_) => toml::Table::new(),
+    };
+
+    let profiles = config.entry("profile")
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        .as_table_mut()
+        .ok_or_else(|| format!("'profile' in {} is not a table", config_path))?;
+    profiles.insert(profile_name.to_string(), toml::Value::Table(profile));
+
+    let config_toml = toml::to_string_pretty(&config)
+        .map_err(|e| format!("Could not serialize config file: {}", e))?;
+    std::fs::write(config_path, config_toml)
+        .map_err(|e| format!("Could not write config file {}: {}", config_path, e))
+}
+
+// ===============================================
+// INIT COMMAND
+// ===============================================
+
+/// Interactive quickstart: asks the essential questions, writes the config profile and mnemonic file,
+/// and prints the command to start mining.
+pub fn run_init(cli: &Cli) -> Result<(), String> {
+    println!("\n==============================================");
+    println!("🧭 Shadow Harvester: Quickstart");
+    println!("==============================================");
+    println!("Press Enter to accept the [default] answer.\n");
+
+    let config_path = cli.config.clone().unwrap_or_else(|| DEFAULT_CONFIG_FILE.to_string());
+
+    // --- 1. API ---
+    let api_url = prompt_valid("Scavenger Mine API URL", cli.api_url.as_deref(), validate_api_url)?;
+
+    // --- 2. Wallets ---
+    let mnemonic = if prompt_yes_no("Do you already have a 24-word mnemonic to mine with?", false)? {
+        prompt_valid("Enter your 24-word mnemonic", None, validate_mnemonic)?
+    } else {
+        let mnemonic = generate_mnemonic()?;
+        println!("\n🔑 New mnemonic (write it down and keep it offline, it controls your rewards):\n\n   {}\n", mnemonic);
+        mnemonic
+    };
+    let mnemonic_file = prompt("File to store the mnemonic in", Some(DEFAULT_MNEMONIC_FILE))?;
+
+    // --- 3. Donation ---
+    let donate_to = prompt_valid("Address to donate all rewards to (leave empty to keep them)", None, validate_donation_address)?;
+
+    // --- 4. Threads ---
+    let cores = std::thread::available_parallelism().map(|n| n.get() as u32).unwrap_or(DEFAULT_THREADS);
+    let threads = prompt_valid("Number of mining threads", Some(&cores.to_string()), validate_threads)?;
+
+    // --- 5. Data dir ---
+    let data_dir = prompt_valid("Directory for receipts and the submission queue", cli.data_dir.as_deref(), |answer| {
+        check_write_permissions(answer).map(|_| answer.to_string())
+    })?;
+
+    let profile_name = prompt("Profile name", Some(DEFAULT_PROFILE_NAME))?;
+
+    // --- Write files ---
+    if std::path::Path::new(&mnemonic_file).exists()
+        && !prompt_yes_no(&format!("{} already exists. Overwrite it?", mnemonic_file), false)? {
+        return Err("Aborted: the mnemonic file was left untouched.".to_string());
+    }
+    write_mnemonic_file(&mnemonic_file, &mnemonic)?;
+
+    let mut profile = toml::Table::new();
+    profile.insert("api-url".to_string(), toml::Value::String(api_url));
+    profile.insert("threads".to_string(), toml::Value::Integer(threads as i64));
+    profile.insert("mnemonic-file".to_string(), toml::Value::String(mnemonic_file.clone()));
+    if let Some(address) = donate_to {
+        profile.insert("donate-to".to_string(), toml::Value::String(address));
+    }
+    write_profile(&config_path, &profile_name, profile)?;
+
+    println!("\n✅ Wrote the mnemonic to {} and profile '{}' to {}.", mnemonic_file, profile_name, config_path);
+    println!("\nReview the Terms and Conditions, then start mining with:\n");
+    let config_arg = if config_path == DEFAULT_CONFIG_FILE { String::new() } else { format!(" --config {}", config_path) };
+    println!("   shadow-harvester{} --profile {} --data-dir {} --accept-tos\n", config_arg, profile_name, data_dir);
+    println!("(Run it once without '--accept-tos' to display the terms.)");
+    Ok(())
+}
@@ -0,0 +1,161 @@
+// src/journal.rs
+
+use crate::data_types::{
+    ChallengeData, DataDir, PendingSolution, FILE_NAME_CHALLENGE, FILE_NAME_CHALLENGE_CHECKSUM, FILE_NAME_FOUND_SOLUTION,
+    load_cached_challenge, prune_challenge_tmp_dirs, receipts_root,
+};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Append-only JSON lines in '--data-dir': one entry before and one after each challenge switch
+pub const SWITCH_JOURNAL_FILE_NAME: &str = "challenge_switch.log";
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchPhase {
+    Started,
+    Completed,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SwitchEntry {
+    pub timestamp: String,
+    pub phase: SwitchPhase,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_challenge_id: Option<String>,
+    pub new_challenge_id: String,
+    /// Addresses holding a found but unqueued solution (found.json) for the old challenge.
+    #[serde(default)]
+    pub in_flight: Vec<String>,
+}
+
+fn journal_path(base_dir: &str) -> PathBuf {
+    PathBuf::from(base_dir).join(SWITCH_JOURNAL_FILE_NAME)
+}
+
+/// Last entry of the journal. Unparseable lines (e.g. one torn by a crash) are skipped.
+pub fn last_entry(base_dir: &str) -> Option<SwitchEntry> {
+    let journal = std::fs::read_to_string(journal_path(base_dir)).ok()?;
+    journal.lines().rev().find_map(|line| serde_json::from_str(line).ok())
+}
+
+fn append(base_dir: &str, entry: &SwitchEntry) -> Result<(), String> {
+    let path = journal_path(base_dir);
+    let line = serde_json::to_string(entry)
+        .map_err(|e| format!("Could not serialize challenge switch entry: {}", e))?;
+    // A line torn by a crash is terminated first, so it can't swallow this entry
+    let torn = std::fs::read(&path).is_ok_and(|journal| journal.last().is_some_and(|&b| b != b'\n'));
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)
+        .map_err(|e| format!("Could not open {:?}: {}", path, e))?;
+    writeln!(file, "{}{}", if torn { "\n" } else { "" }, line)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Could not append to {:?}: {}", path, e))
+}
+
+/// Every found.json below a challenge directory, whatever the wallet kind.
+fn found_solution_files(dir: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            found_solution_files(&path, found);
+        } else if entry.file_name() == FILE_NAME_FOUND_SOLUTION {
+            found.push(path);
+        }
+    }
+}
+
+fn found_solutions(base_dir: &str, challenge_id: &str) -> Vec<(PathBuf, PendingSolution)> {
+    let mut paths = Vec::new();
+    found_solution_files(&receipts_root(base_dir).join(challenge_id), &mut paths);
+    paths.sort();
+    paths.into_iter()
+        .filter_map(|path| {
+            let solution = serde_json::from_str(&std::fs::read_to_string(&path).ok()?).ok()?;
+            Some((path, solution))
+        })
+        .collect()
+}
+
+/// Queues the found-but-unqueued solutions of a challenge the miner has moved away from.
+fn queue_found_solutions(base_dir: &str, challenge_id: &str) -> Result<usize, String> {
+    let solutions = found_solutions(base_dir, challenge_id);
+    for (path, solution) in &solutions {
+        DataDir::Ephemeral(&solution.address).save_pending_solution(base_dir, solution)?;
+        if let Err(e) = std::fs::remove_file(path) {
+            eprintln!("⚠️ Queued {} for challenge {} but could not delete {:?}: {}", solution.address, challenge_id, path, e);
+        }
+    }
+    Ok(solutions.len())
+}
+
+/// Saves the challenge for this cycle. When it differs from the last challenge journaled, the switch is
+/// bracketed by journal entries and the old challenge's unqueued solutions are queued in between.
+pub fn save_challenge(base_dir: &str, data_dir: &DataDir, challenge: &ChallengeData) -> Result<(), String> {
+    let previous = last_entry(base_dir);
+    if previous.as_ref().is_some_and(|p| p.phase == SwitchPhase::Completed && p.new_challenge_id == challenge.challenge_id) {
+        return data_dir.save_challenge(base_dir, challenge);
+    }
+
+    let old_challenge_id = previous.map(|p| p.new_challenge_id).filter(|id| *id != challenge.challenge_id);
+    let in_flight = old_challenge_id.as_deref()
+        .map(|old| found_solutions(base_dir, old).into_iter().map(|(_, s)| s.address).collect())
+        .unwrap_or_default();
+    let mut entry = SwitchEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        phase: SwitchPhase::Started,
+        old_challenge_id,
+        new_challenge_id: challenge.challenge_id.clone(),
+        in_flight,
+    };
+    append(base_dir, &entry)?;
+
+    if let Some(old) = entry.old_challenge_id.as_deref() {
+        let queued = queue_found_solutions(base_dir, old)?;
+        if queued > 0 {
+            println!("📥 Queued {} unsubmitted solution(s) left on challenge {}.", queued, old);
+        }
+    }
+    data_dir.save_challenge(base_dir, challenge)?;
+    // The old challenge's searches are over; their scratch files go with it
+    prune_challenge_tmp_dirs(base_dir, &challenge.challenge_id);
+
+    entry.timestamp = chrono::Utc::now().to_rfc3339();
+    entry.phase = SwitchPhase::Completed;
+    append(base_dir, &entry)
+}
+
+/// Finishes a switch the previous run died in: the old challenge's solutions are queued and a cached
+/// challenge.json of the new one that doesn't match its checksum is dropped, to be fetched again.
+pub fn repair_interrupted_switch(base_dir: &str) -> Result<(), String> {
+    let Some(mut entry) = last_entry(base_dir).filter(|e| e.phase == SwitchPhase::Started) else { return Ok(()) };
+    println!("🩹 Previous run stopped while switching to challenge {}{}. Repairing the data dir...",
+        entry.new_challenge_id,
+        entry.old_challenge_id.as_deref().map(|old| format!(" from {}", old)).unwrap_or_default());
+
+    if let Some(old) = entry.old_challenge_id.as_deref() {
+        let queued = queue_found_solutions(base_dir, old)?;
+        if queued > 0 {
+            println!("  Queued {} unsubmitted solution(s) left on challenge {}.", queued, old);
+        }
+    }
+
+    if load_cached_challenge(base_dir, &entry.new_challenge_id, false).is_err() {
+        let challenge_dir = receipts_root(base_dir).join(&entry.new_challenge_id);
+        for file_name in [FILE_NAME_CHALLENGE, FILE_NAME_CHALLENGE_CHECKSUM] {
+            let path = challenge_dir.join(file_name);
+            if path.exists() {
+                std::fs::remove_file(&path).map_err(|e| format!("Could not remove half-written {:?}: {}", path, e))?;
+            }
+        }
+        println!("  Half-written {} of challenge {} removed.", FILE_NAME_CHALLENGE, entry.new_challenge_id);
+    }
+    prune_challenge_tmp_dirs(base_dir, &entry.new_challenge_id);
+
+    entry.timestamp = chrono::Utc::now().to_rfc3339();
+    entry.phase = SwitchPhase::Completed;
+    append(base_dir, &entry)?;
+    println!("✅ Challenge switch to {} completed.", entry.new_challenge_id);
+    Ok(())
+}
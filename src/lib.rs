@@ -1,454 +1,547 @@
 pub mod rom;
 pub mod cardano;
+pub mod numa;
+pub mod hugepages;
+pub mod hashcore;
 pub use rom::{RomGenerationType, Rom, RomDigest};
+pub use hashcore::{hash, hash_structure_good};
 
-use cryptoxide::{
-    hashing::blake2b::{self, Blake2b},
-    kdf::argon2,
-};
+use cryptoxide::hashing::blake2b::Blake2b;
+use hashcore::{NonceRange, Preimage, Searcher, Target};
 
 // ** Consolidated Imports required for scavenge function **
-use std::sync::mpsc::{Sender, channel};
-use std::{sync::Arc, thread, time::SystemTime};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, Sender, channel};
+use std::{sync::Arc, thread, time::{Duration, Instant, SystemTime}};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::path::{Path, PathBuf};
 use indicatif::{ProgressBar, ProgressStyle};
 // ************************************
 
 
-// 1 byte operator
-// 3 bytes operands (src1, src2, dst)
-// 28 bytes data
-const INSTR_SIZE: usize = 20;
-const NB_REGS: usize = 1 << REGS_BITS;
-const REGS_BITS: usize = 5;
-const REGS_INDEX_MASK: u8 = NB_REGS as u8 - 1;
-
-type Register = u64;
-
-const REGISTER_SIZE: usize = std::mem::size_of::<Register>();
-
-struct VM {
-    program: Program,
-    regs: [Register; NB_REGS],
-    ip: u32,
-    prog_digest: blake2b::Context<512>,
-    mem_digest: blake2b::Context<512>,
-    prog_seed: [u8; 64],
-    memory_counter: u32,
-    loop_counter: u32,
-}
-
-#[derive(Clone, Copy)]
-enum Instr {
-    Op3(Op3),
-    Op2(Op2),
-}
-
-#[derive(Clone, Copy)]
-enum Op3 {
-    Add,
-    Mul,
-    MulH,
-    Xor,
-    Div,
-    Mod,
-    And,
-    Hash(u8),
-}
-
-#[derive(Clone, Copy)]
-enum Op2 {
-    ISqrt,
-    Neg,
-    BitRev,
-    RotL,
-    RotR,
-}
-
-// special encoding
-
-impl From<u8> for Instr {
-    fn from(value: u8) -> Self {
-        match value {
-            0..40 => Instr::Op3(Op3::Add),                   // 40
-            40..80 => Instr::Op3(Op3::Mul),                  // 40
-            80..96 => Instr::Op3(Op3::MulH),                 // 16
-            96..112 => Instr::Op3(Op3::Div),                 // 16
-            112..128 => Instr::Op3(Op3::Mod),                // 16
-            128..138 => Instr::Op2(Op2::ISqrt),              // 10
-            138..148 => Instr::Op2(Op2::BitRev),             // 10
-            148..188 => Instr::Op3(Op3::Xor),                // 40
-            188..204 => Instr::Op2(Op2::RotL),               // 16
-            204..220 => Instr::Op2(Op2::RotR),               // 16
-            220..240 => Instr::Op2(Op2::Neg),                // 20
-            240..248 => Instr::Op3(Op3::And),                // 8
-            248..=255 => Instr::Op3(Op3::Hash(value - 248)), // 8
+// --------------------------------------------------------------------------
+// SCAVENGE LOGIC
+// --------------------------------------------------------------------------
+
+pub struct Thread {}
+
+const MB: usize = 1024 * 1024;
+const GB: usize = 1024 * MB;
+
+// '--low-memory': keep only the part of the ROM the hash reads
+static LOW_MEMORY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_low_memory(enabled: bool) {
+    LOW_MEMORY.store(enabled, Ordering::Relaxed);
+}
+
+// '--huge-pages': hold the ROM in huge pages to cut TLB misses
+static HUGE_PAGES: AtomicBool = AtomicBool::new(false);
+
+pub fn set_huge_pages(enabled: bool) {
+    HUGE_PAGES.store(enabled, Ordering::Relaxed);
+}
+
+// '--nice': run by every hashing worker thread as it starts, e.g. to lower its own priority
+static WORKER_THREAD_SETUP: OnceLock<fn()> = OnceLock::new();
+
+/// Registers `setup` to run at the start of every hashing worker thread. Only the first call counts.
+pub fn set_worker_thread_setup(setup: fn()) {
+    let _ = WORKER_THREAD_SETUP.set(setup);
+}
+
+/// With '--huge-pages', moves a ROM into huge pages, keeping it as it is if none are available.
+fn into_huge_pages(rom: Rom) -> Rom {
+    if !HUGE_PAGES.load(Ordering::Relaxed) || rom.huge_pages().is_some() {
+        return rom;
+    }
+    match rom.to_huge_pages() {
+        Ok(huge) => {
+            println!("ROM held in {}.", huge.huge_pages().map_or("", |kind| kind.name()));
+            huge
+        },
+        Err(e) => {
+            eprintln!("⚠️ '--huge-pages': {}. Using regular pages.", e);
+            rom
+        },
+    }
+}
+
+/// Builds the 1 GiB ROM of a challenge from its no_pre_mine key.
+pub fn challenge_rom(no_pre_mine_key: &str) -> Rom {
+    let gen_type = RomGenerationType::TwoStep {
+        pre_size: 16 * MB,
+        mixing_numbers: 4,
+    };
+    if LOW_MEMORY.load(Ordering::Relaxed) {
+        return Rom::new_low_memory(no_pre_mine_key.as_bytes(), gen_type, GB);
+    }
+    if HUGE_PAGES.load(Ordering::Relaxed) {
+        match Rom::new_huge_pages(no_pre_mine_key.as_bytes(), gen_type, GB) {
+            Ok(rom) => {
+                println!("ROM held in {}.", rom.huge_pages().map_or("", |kind| kind.name()));
+                return rom;
+            },
+            Err(e) => eprintln!("⚠️ '--huge-pages': {}. Using regular pages.", e),
         }
     }
+    Rom::new(no_pre_mine_key.as_bytes(), gen_type, GB)
+}
+
+// ROM of the challenge being mined, kept between cycles so every wallet mining it reuses one allocation
+static SHARED_ROM: Mutex<Option<(String, Arc<Rom>)>> = Mutex::new(None);
+
+/// Returns the ROM of a challenge, only building it if the last one requested was for another key.
+/// Callers asking for the same key at the same time wait for a single build and share it.
+pub fn shared_challenge_rom(no_pre_mine_key: &str) -> Arc<Rom> {
+    let mut shared = SHARED_ROM.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((key, rom)) = shared.as_ref()
+        && key == no_pre_mine_key
+    {
+        println!("Reusing loaded ROM with key: {}", no_pre_mine_key);
+        return rom.clone();
+    }
+
+    // Release the previous challenge's ROM before allocating the next one
+    *shared = None;
+    let rom = match take_prepared_rom(no_pre_mine_key) {
+        Some(rom) => {
+            println!("Using the ROM prepared ahead for key: {}", no_pre_mine_key);
+            rom
+        },
+        None => Arc::new(cached_challenge_rom(no_pre_mine_key)),
+    };
+    *shared = Some((no_pre_mine_key.to_string(), rom.clone()));
+    rom
 }
 
-#[derive(Clone, Copy)]
-enum Operand {
-    Reg,
-    Memory,
-    Literal,
-    Special1,
-    Special2,
+/// The ROM of a challenge the miner hasn't switched to yet, built in the background.
+enum PreparedRom {
+    Building(String),
+    Ready(String, Arc<Rom>),
 }
 
-impl From<u8> for Operand {
-    fn from(value: u8) -> Self {
-        assert!(value <= 0x0f);
-        match value {
-            0..5 => Self::Reg,
-            5..9 => Self::Memory,
-            9..13 => Self::Literal,
-            13..14 => Self::Special1,
-            14.. => Self::Special2,
+// Only locked to read or change the state, never during the build; PREPARED_ROM_DONE is signalled when a build ends
+static PREPARED_ROM: Mutex<Option<PreparedRom>> = Mutex::new(None);
+static PREPARED_ROM_DONE: Condvar = Condvar::new();
+
+/// Takes the prepared ROM of `no_pre_mine_key`, waiting for it if it is being built. Anything prepared
+/// for another key is dropped (a build in progress is discarded when it ends), and None returned.
+fn take_prepared_rom(no_pre_mine_key: &str) -> Option<Arc<Rom>> {
+    let mut prepared = PREPARED_ROM.lock().unwrap_or_else(|e| e.into_inner());
+    loop {
+        match prepared.take() {
+            Some(PreparedRom::Ready(key, rom)) if key == no_pre_mine_key => return Some(rom),
+            Some(PreparedRom::Building(key)) if key == no_pre_mine_key => {
+                *prepared = Some(PreparedRom::Building(key));
+                prepared = PREPARED_ROM_DONE.wait(prepared).unwrap_or_else(|e| e.into_inner());
+            },
+            _ => return None,
         }
     }
 }
 
-impl VM {
-    /// Create a new VM which is specific to the ROM by using the RomDigest,
-    /// but mainly dependent on the salt which is an arbitrary byte content
-    pub fn new(rom_digest: &RomDigest, nb_instrs: u32, salt: &[u8]) -> Self {
-        const DIGEST_INIT_SIZE: usize = 64;
-        const REGS_CONTENT_SIZE: usize = REGISTER_SIZE * NB_REGS;
+/// Whether `shared_challenge_rom` holds the ROM of `no_pre_mine_key` or is loading one. It holds its lock
+/// for the whole build, so a locked ROM counts as loading.
+fn shared_rom_loading_or_loaded(no_pre_mine_key: &str) -> bool {
+    match SHARED_ROM.try_lock() {
+        Ok(shared) => shared.as_ref().is_some_and(|(key, _)| key == no_pre_mine_key),
+        Err(std::sync::TryLockError::Poisoned(e)) => e.into_inner().as_ref().is_some_and(|(key, _)| key == no_pre_mine_key),
+        Err(std::sync::TryLockError::WouldBlock) => true,
+    }
+}
 
-        let mut init_buffer = [0; REGS_CONTENT_SIZE + 3 * DIGEST_INIT_SIZE];
+/// Builds the ROM of an upcoming challenge on a background thread, so `shared_challenge_rom` finds it ready
+/// at the switch. Does nothing if that ROM is already loaded, prepared or being built, or while the miner
+/// is loading a ROM or another one is being prepared; the caller asks again later.
+pub fn prepare_challenge_rom(no_pre_mine_key: &str) {
+    if shared_rom_loading_or_loaded(no_pre_mine_key) {
+        return;
+    }
+    let mut prepared = PREPARED_ROM.lock().unwrap_or_else(|e| e.into_inner());
+    match prepared.as_ref() {
+        Some(PreparedRom::Building(_)) => return,
+        Some(PreparedRom::Ready(key, _)) if key == no_pre_mine_key => return,
+        // A ROM prepared for another key is released before allocating this one
+        _ => *prepared = Some(PreparedRom::Building(no_pre_mine_key.to_string())),
+    }
+    drop(prepared);
+
+    let key = no_pre_mine_key.to_string();
+    thread::spawn(move || {
+        println!("🔮 Preparing the ROM for key {} in the background...", key);
+        let rom = Arc::new(cached_challenge_rom(&key));
+        let mut prepared = PREPARED_ROM.lock().unwrap_or_else(|e| e.into_inner());
+        // Otherwise the miner moved to another key meanwhile, and the ROM is dropped
+        if matches!(prepared.as_ref(), Some(PreparedRom::Building(building)) if *building == key) {
+            *prepared = Some(PreparedRom::Ready(key, rom));
+        }
+        PREPARED_ROM_DONE.notify_all();
+    });
+}
 
-        let mut init_buffer_input = rom_digest.0.to_vec();
-        init_buffer_input.extend_from_slice(salt);
-        argon2::hprime(&mut init_buffer, &init_buffer_input);
+// Per-node copies of the shared ROM for '--numa replicate', kept as long as the challenge doesn't change
+static NODE_ROMS: Mutex<Option<(String, Vec<Arc<Rom>>)>> = Mutex::new(None);
 
-        let (init_buffer_regs, init_buffer_digests) = init_buffer.split_at(REGS_CONTENT_SIZE);
+/// One copy of `rom` per NUMA node, each made by a thread pinned to that node so its pages are local.
+fn node_roms(no_pre_mine_key: &str, rom: &Arc<Rom>, nodes: &[numa::NumaNode]) -> Vec<Arc<Rom>> {
+    let mut cached = NODE_ROMS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((key, roms)) = cached.as_ref()
+        && key == no_pre_mine_key
+    {
+        return roms.clone();
+    }
 
-        let mut regs = [0; NB_REGS];
-        for (reg, reg_bytes) in regs.iter_mut().zip(init_buffer_regs.chunks(REGISTER_SIZE)) {
-            *reg = u64::from_le_bytes(*<&[u8; 8]>::try_from(reg_bytes).unwrap());
-        }
+    *cached = None;
+    println!("Replicating ROM on {} NUMA nodes...", nodes.len());
+    let roms: Vec<Arc<Rom>> = thread::scope(|s| {
+        let copies: Vec<_> = nodes.iter()
+            .map(|node| s.spawn(move || {
+                numa::pin_current_thread(&node.cpus);
+                Arc::new(rom.replicate())
+            }))
+            .collect();
+        copies.into_iter().map(|copy| copy.join().expect("ROM replication thread panicked")).collect()
+    });
+    *cached = Some((no_pre_mine_key.to_string(), roms.clone()));
+    roms
+}
 
-        let mut digests = init_buffer_digests.chunks(DIGEST_INIT_SIZE);
-        let prog_digest = Blake2b::<512>::new().update(digests.next().unwrap());
-        let mem_digest = Blake2b::<512>::new().update(digests.next().unwrap());
-        let prog_seed = *<&[u8; 64]>::try_from(digests.next().unwrap()).unwrap();
+// Directory keeping generated ROMs between runs ('--rom-cache'); unset means ROMs only live in memory
+static ROM_CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+// Each cache file takes 1 GiB: keep the current challenge's and the previous one's
+const MAX_CACHED_ROMS: usize = 2;
 
-        assert_eq!(digests.next(), None);
+/// Enables the on-disk ROM cache. Returns false if it was already set.
+pub fn set_rom_cache_dir(dir: PathBuf) -> bool {
+    ROM_CACHE_DIR.set(dir).is_ok()
+}
 
-        let program = Program::new(nb_instrs);
+fn rom_cache_path(dir: &Path, no_pre_mine_key: &str) -> PathBuf {
+    let key_hash = Blake2b::<256>::new().update(no_pre_mine_key.as_bytes()).finalize();
+    dir.join(format!("{}.rom", hex::encode(&key_hash[..16])))
+}
 
-        Self {
-            program,
-            regs,
-            prog_digest,
-            mem_digest,
-            prog_seed,
-            ip: 0,
-            loop_counter: 0,
-            memory_counter: 0,
+/// Maps the cached ROM of a challenge if there is one, otherwise builds it and, with the cache
+/// enabled, writes it out and maps it back so its pages are backed by the file.
+fn cached_challenge_rom(no_pre_mine_key: &str) -> Rom {
+    let Some(dir) = ROM_CACHE_DIR.get() else {
+        println!("Generating ROM with key: {}", no_pre_mine_key);
+        return challenge_rom(no_pre_mine_key);
+    };
+    let path = rom_cache_path(dir, no_pre_mine_key);
+
+    if path.exists() {
+        match Rom::open_mapped(&path, GB) {
+            Ok(rom) => {
+                println!("Mapped cached ROM {:?} for key: {}", path, no_pre_mine_key);
+                // Marks the file as recently used for pruning
+                let _ = std::fs::File::options().append(true).open(&path).and_then(|f| f.set_modified(SystemTime::now()));
+                return into_huge_pages(rom);
+            },
+            Err(e) => eprintln!("⚠️ Rebuilding unusable ROM cache {:?}: {}", path, e),
         }
     }
 
-    pub fn step(&mut self, rom: &Rom) {
-        execute_one_instruction(self, rom);
-        self.ip = self.ip.wrapping_add(1);
+    println!("Generating ROM with key: {}", no_pre_mine_key);
+    let rom = challenge_rom(no_pre_mine_key);
+    let saved = std::fs::create_dir_all(dir).and_then(|_| rom.save(&path));
+    if let Err(e) = saved {
+        eprintln!("⚠️ Could not write ROM cache {:?}: {}", path, e);
+        return rom;
+    }
+    prune_rom_cache(dir);
+    // A huge-page ROM stays in memory: mapping the file back would put it in regular pages
+    if rom.huge_pages().is_some() {
+        return rom;
     }
+    match Rom::open_mapped(&path, GB) {
+        Ok(mapped) => mapped,
+        Err(_) => rom,
+    }
+}
 
-    fn sum_regs(&self) -> u64 {
-        self.regs.iter().fold(0, |acc, r| acc.wrapping_add(*r))
+/// Deletes the least recently used cache files beyond `MAX_CACHED_ROMS`.
+fn prune_rom_cache(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    let mut cached: Vec<(SystemTime, PathBuf)> = entries.filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rom"))
+        .filter_map(|path| Some((path.metadata().ok()?.modified().ok()?, path)))
+        .collect();
+    cached.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+
+    for (_, path) in cached.into_iter().skip(MAX_CACHED_ROMS) {
+        if let Err(e) = std::fs::remove_file(&path) {
+            eprintln!("⚠️ Could not delete old ROM cache {:?}: {}", path, e);
+        }
     }
+}
 
-    pub fn post_instructions(&mut self) {
-        let sum_regs = self.sum_regs();
+// Structure to hold dynamic challenge parameters from the API
+#[derive(Clone)]
+pub struct ChallengeParams {
+    pub rom_key: String, // no_pre_mine hex string (used for ROM init)
+    pub difficulty_mask: String, // difficulty hex string (used for submission check)
+    pub address: String, // Registered Cardano address
+    pub challenge_id: String,
+    pub latest_submission: String,
+    pub no_pre_mine_hour: String,
+    pub required_zero_bits: usize, // Derived from difficulty_mask
+    pub rom: Arc<Rom>,
+}
 
-        let prog_value = self
-            .prog_digest
-            .clone()
-            .update(&sum_regs.to_le_bytes())
-            .finalize();
-        let mem_value = self
-            .mem_digest
-            .clone()
-            .update(&sum_regs.to_le_bytes())
-            .finalize();
+/// Shared knobs read by a running `scavenge`, letting the caller throttle the
+/// workers without restarting the mining cycle.
+#[derive(Debug)]
+pub struct ScavengeControl {
+    /// Number of workers allowed to hash. Workers with a higher index idle until raised again.
+    pub active_threads: AtomicU32,
+    /// Upper bound on the active workers chosen by the user at runtime (e.g. via signals).
+    pub target_threads: AtomicU32,
+    /// Extra text appended to the progress bar message (e.g. estimated power draw).
+    pub status: Mutex<String>,
+    /// Hashes computed by the workers across every cycle, sampled to measure throughput.
+    pub hashes: AtomicU64,
+    /// Total hash rate (hashes/s) the allowed workers share; 0 means unlimited.
+    pub max_hash_rate: AtomicU64,
+    /// When set, every worker idles (e.g. while the user is active), whatever the thread counts.
+    paused: AtomicBool,
+    /// Like `paused`, but set and cleared by the user at runtime (signal or keypress), independently of it.
+    held: AtomicBool,
+    /// When set, `scavenge` gives up on the current address once this instant has passed.
+    deadline: Mutex<Option<Instant>>,
+    /// Handles of the running workers, so idle ones can be woken as soon as they are allowed to hash again.
+    workers: Mutex<Vec<thread::Thread>>,
+    /// Hashes computed by each worker during the last `scavenge` call.
+    thread_hashes: Mutex<Vec<u64>>,
+    /// Whether `scavenge` redraws a live progress line; otherwise it logs plain status lines.
+    live: AtomicBool,
+    /// `scavenge` gives up this long before the challenge's submission deadline; unset, it doesn't watch it.
+    deadline_margin: Mutex<Option<Duration>>,
+    /// The newest published challenge, when pushed to the miner; a search on another stops once it arrives.
+    latest_challenge: Mutex<Option<String>>,
+    /// Searches running now (`scavenge` calls and coordinator cycles); none between challenges or wallets.
+    searches: AtomicU32,
+    /// Set once the first search starts.
+    searched: AtomicBool,
+}
 
-        let mixing_value = Blake2b::<512>::new()
-            .update(&prog_value)
-            .update(&mem_value)
-            .update(&self.loop_counter.to_le_bytes())
-            .finalize();
-        let mut mixing_out = vec![0; NB_REGS * REGISTER_SIZE * 32];
-        argon2::hprime(&mut mixing_out, &mixing_value);
+/// Counts a search as running until dropped.
+pub struct SearchGuard<'a>(&'a ScavengeControl);
 
-        for mem_chunks in mixing_out.chunks(NB_REGS * REGISTER_SIZE) {
-            for (reg, reg_chunk) in self.regs.iter_mut().zip(mem_chunks.chunks(8)) {
-                *reg ^= u64::from_le_bytes(*<&[u8; 8]>::try_from(reg_chunk).unwrap())
-            }
+impl Drop for SearchGuard<'_> {
+    fn drop(&mut self) {
+        self.0.searches.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl ScavengeControl {
+    pub fn new(nb_threads: u32) -> Self {
+        Self {
+            active_threads: AtomicU32::new(nb_threads),
+            target_threads: AtomicU32::new(nb_threads),
+            status: Mutex::new(String::new()),
+            hashes: AtomicU64::new(0),
+            max_hash_rate: AtomicU64::new(0),
+            paused: AtomicBool::new(false),
+            held: AtomicBool::new(false),
+            deadline: Mutex::new(None),
+            workers: Mutex::new(Vec::new()),
+            thread_hashes: Mutex::new(Vec::new()),
+            live: AtomicBool::new(true),
+            deadline_margin: Mutex::new(None),
+            latest_challenge: Mutex::new(None),
+            searches: AtomicU32::new(0),
+            searched: AtomicBool::new(false),
         }
+    }
+
+    /// Marks a search as running while the returned guard lives.
+    pub fn start_search(&self) -> SearchGuard<'_> {
+        self.searches.fetch_add(1, Ordering::Relaxed);
+        self.searched.store(true, Ordering::Relaxed);
+        SearchGuard(self)
+    }
 
-        self.prog_seed = prog_value;
-        self.loop_counter = self.loop_counter.wrapping_add(1)
+    /// True between searches once one has run, e.g. while waiting for the next challenge after a solution.
+    pub fn is_idle(&self) -> bool {
+        self.searched.load(Ordering::Relaxed) && self.searches.load(Ordering::Relaxed) == 0
     }
 
-    pub fn execute(&mut self, rom: &Rom, instr: u32) {
-        self.program.shuffle(&self.prog_seed);
-        for _ in 0..instr {
-            self.step(rom)
+    pub fn set_deadline_margin(&self, margin: Option<Duration>) {
+        if let Ok(mut deadline_margin) = self.deadline_margin.lock() {
+            *deadline_margin = margin;
         }
-        self.post_instructions()
-    }
-
-    pub fn finalize(self) -> [u8; 64] {
-        let prog_digest = self.prog_digest.finalize();
-        let mem_digest = self.mem_digest.finalize();
-        let mut context = Blake2b::<512>::new()
-            .update(&prog_digest)
-            .update(&mem_digest)
-            .update(&self.memory_counter.to_le_bytes());
-        for r in self.regs {
-            context.update_mut(&r.to_le_bytes());
+    }
+
+    pub fn deadline_margin(&self) -> Option<Duration> {
+        self.deadline_margin.lock().ok().and_then(|m| *m)
+    }
+
+    /// Records the newest published challenge, so a search on an older one stops.
+    pub fn set_latest_challenge(&self, challenge_id: &str) {
+        if let Ok(mut latest) = self.latest_challenge.lock() {
+            *latest = Some(challenge_id.to_string());
         }
-        context.finalize()
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn debug(&self) -> String {
-        let mut out = String::new();
-        for (i, r) in self.regs.iter().enumerate() {
-            out.push_str(&format!("[{i:02x}] {r:016x} "));
-            if (i % 4) == 3 {
-                out.push('\n');
-            }
+    /// True once a challenge newer than `challenge_id` was published.
+    pub fn challenge_superseded(&self, challenge_id: &str) -> bool {
+        self.latest_challenge.lock().ok().is_some_and(|latest| latest.as_deref().is_some_and(|id| id != challenge_id))
+    }
+
+    pub fn set_live(&self, live: bool) {
+        self.live.store(live, Ordering::Relaxed);
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.live.load(Ordering::Relaxed)
+    }
+
+    /// Number of workers currently allowed to hash.
+    pub fn allowed_threads(&self) -> u32 {
+        if self.paused.load(Ordering::Relaxed) || self.is_held() {
+            return 0;
         }
-        out.push_str(&format!("ip {:08x}\n", self.ip,));
-        out
+        self.active_threads.load(Ordering::Relaxed).min(self.target_threads.load(Ordering::Relaxed))
     }
-}
 
-struct Program {
-    instructions: Vec<u8>,
-}
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+        self.wake_workers();
+    }
 
-impl Program {
-    pub fn new(nb_instrs: u32) -> Self {
-        let size = nb_instrs as usize * INSTR_SIZE;
-        let instructions = vec![0; size];
-        Self { instructions }
+    pub fn is_held(&self) -> bool {
+        self.held.load(Ordering::Relaxed)
     }
 
-    pub fn at(&self, i: u32) -> &[u8; INSTR_SIZE] {
-        let start = (i as usize).wrapping_mul(INSTR_SIZE) % self.instructions.len();
-        <&[u8; INSTR_SIZE]>::try_from(&self.instructions[start..start + INSTR_SIZE]).unwrap()
+    /// Holds (or releases) every worker on the user's request. Returns false if it was already in that state.
+    pub fn set_held(&self, held: bool) -> bool {
+        let changed = self.held.swap(held, Ordering::Relaxed) != held;
+        self.wake_workers();
+        changed
     }
 
-    pub fn shuffle(&mut self, seed: &[u8; 64]) {
-        argon2::hprime(&mut self.instructions, seed)
+    pub fn set_active_threads(&self, nb_threads: u32) {
+        self.active_threads.store(nb_threads, Ordering::Relaxed);
+        self.wake_workers();
     }
-}
 
-#[derive(Clone)]
-pub struct Instruction {
-    opcode: Instr,
-    op1: Operand,
-    op2: Operand,
-    r1: u8,
-    r2: u8,
-    r3: u8,
-    lit1: u64,
-    lit2: u64,
-}
-
-#[inline]
-fn decode_instruction(instruction: &[u8; INSTR_SIZE]) -> Instruction {
-    let opcode = Instr::from(instruction[0]);
-    let op1 = Operand::from(instruction[1] >> 4);
-    let op2 = Operand::from(instruction[1] & 0x0f);
-
-    let rs = ((instruction[2] as u16) << 8) | (instruction[3] as u16);
-    let r1 = ((rs >> (2 * REGS_BITS)) as u8) & REGS_INDEX_MASK;
-    let r2 = ((rs >> REGS_BITS) as u8) & REGS_INDEX_MASK;
-    let r3 = (rs as u8) & REGS_INDEX_MASK;
-
-    let lit1 = u64::from_le_bytes(*<&[u8; 8]>::try_from(&instruction[4..12]).unwrap());
-    let lit2 = u64::from_le_bytes(*<&[u8; 8]>::try_from(&instruction[12..20]).unwrap());
-
-    Instruction {
-        opcode,
-        op1,
-        op2,
-        r1,
-        r2,
-        r3,
-        lit1,
-        lit2,
-    }
-}
-
-fn execute_one_instruction(vm: &mut VM, rom: &Rom) {
-    let prog_chunk = *vm.program.at(vm.ip);
-
-    macro_rules! mem_access64 {
-        ($vm:ident, $rom:ident, $addr:ident) => {{
-            let mem = rom.at($addr as u32);
-            $vm.mem_digest.update_mut(mem);
-            $vm.memory_counter = $vm.memory_counter.wrapping_add(1);
-
-            // divide memory access into 8 chunks of 8 bytes
-            let idx = (($vm.memory_counter % (64 / 8)) as usize) * 8;
-            u64::from_le_bytes(*<&[u8; 8]>::try_from(&mem[idx..idx + 8]).unwrap())
-        }};
-    }
-
-    macro_rules! special1_value64 {
-        ($vm:ident) => {{
-            let r = $vm.prog_digest.clone().finalize();
-            u64::from_le_bytes(*<&[u8; 8]>::try_from(&r[0..8]).unwrap())
-        }};
-    }
-
-    macro_rules! special2_value64 {
-        ($vm:ident) => {{
-            let r = $vm.mem_digest.clone().finalize();
-            u64::from_le_bytes(*<&[u8; 8]>::try_from(&r[0..8]).unwrap())
-        }};
-    }
-
-    let Instruction {
-        opcode,
-        op1,
-        op2,
-        r1,
-        r2,
-        r3,
-        lit1,
-        lit2,
-    } = decode_instruction(&prog_chunk);
-
-    match opcode {
-        Instr::Op3(operator) => {
-            let src1 = match op1 {
-                Operand::Reg => vm.regs[r1 as usize],
-                Operand::Memory => mem_access64!(vm, rom, lit1),
-                Operand::Literal => lit1,
-                Operand::Special1 => special1_value64!(vm),
-                Operand::Special2 => special2_value64!(vm),
-            };
-            let src2 = match op2 {
-                Operand::Reg => vm.regs[r2 as usize],
-                Operand::Memory => mem_access64!(vm, rom, lit2),
-                Operand::Literal => lit2,
-                Operand::Special1 => special1_value64!(vm),
-                Operand::Special2 => special2_value64!(vm),
-            };
+    pub fn set_target_threads(&self, nb_threads: u32) {
+        self.target_threads.store(nb_threads, Ordering::Relaxed);
+        self.wake_workers();
+    }
 
-            let result = match operator {
-                Op3::Add => src1.wrapping_add(src2),
-                Op3::Mul => src1.wrapping_mul(src2),
-                Op3::MulH => ((src1 as u128 * src2 as u128) >> 64) as u64,
-                Op3::Xor => src1 ^ src2,
-                Op3::Div => {
-                    if src2 == 0 {
-                        special1_value64!(vm)
-                    } else {
-                        src1 / src2
-                    }
-                }
-                Op3::Mod => {
-                    if src2 == 0 {
-                        special1_value64!(vm)
-                    } else {
-                        src1 / src2
-                    }
-                }
-                Op3::And => src1 & src2,
-                Op3::Hash(v) => {
-                    assert!(v < 8);
-                    let out = Blake2b::<512>::new()
-                        .update(&src1.to_le_bytes())
-                        .update(&src2.to_le_bytes())
-                        .finalize();
-                    if let Some(chunk) = out.chunks(8).nth(v as usize) {
-                        u64::from_le_bytes(*<&[u8; 8]>::try_from(chunk).unwrap())
-                    } else {
-                        panic!("chunk doesn't exist")
-                    }
-                }
-            };
+    pub fn set_max_hash_rate(&self, hashes_per_sec: u64) {
+        self.max_hash_rate.store(hashes_per_sec, Ordering::Relaxed);
+    }
 
-            vm.regs[r3 as usize] = result;
+    /// Limits the next `scavenge` calls to `slice` from now (no limit for `None`).
+    pub fn start_time_slice(&self, slice: Option<Duration>) {
+        if let Ok(mut deadline) = self.deadline.lock() {
+            *deadline = slice.map(|s| Instant::now() + s);
         }
-        Instr::Op2(operator) => {
-            let src1 = match op1 {
-                Operand::Reg => vm.regs[r1 as usize],
-                Operand::Memory => mem_access64!(vm, rom, lit1),
-                Operand::Literal => lit1,
-                Operand::Special1 => special1_value64!(vm),
-                Operand::Special2 => special2_value64!(vm),
-            };
+    }
 
-            let result = match operator {
-                Op2::Neg => !src1,
-                Op2::RotL => src1.rotate_left(r1 as u32),
-                Op2::RotR => src1.rotate_right(r1 as u32),
-                Op2::ISqrt => src1.isqrt(),
-                Op2::BitRev => src1.reverse_bits(),
-            };
-            vm.regs[r3 as usize] = result;
+    /// True once the current time slice is over.
+    pub fn time_slice_expired(&self) -> bool {
+        self.deadline.lock().ok().and_then(|d| *d).is_some_and(|d| Instant::now() >= d)
+    }
+
+    /// Hashes computed by each worker (indexed by thread) during the last mining cycle.
+    pub fn thread_hashes(&self) -> Vec<u64> {
+        self.thread_hashes.lock().map(|h| h.clone()).unwrap_or_default()
+    }
+
+    /// Unparks every idle worker so it re-checks whether it may hash.
+    pub fn wake_workers(&self) {
+        if let Ok(workers) = self.workers.lock() {
+            for worker in workers.iter() {
+                worker.unpark();
+            }
         }
     }
-    vm.prog_digest.update_mut(&prog_chunk);
 }
 
-pub fn hash(salt: &[u8], rom: &Rom, nb_loops: u32, nb_instrs: u32) -> [u8; 64] {
-    assert!(nb_loops >= 2);
-    assert!(nb_instrs >= 256);
-    let mut vm = VM::new(&rom.digest, nb_instrs, salt);
-    for _ in 0..nb_loops {
-        vm.execute(rom, nb_instrs);
+/// Splits the 64-bit nonce space into `worker_count` disjoint ranges so several machines
+/// mining the same address and challenge never hash the same nonce. `seed` offsets the start
+/// within the range, for independent miners that don't coordinate worker ids.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoncePartition {
+    pub worker_id: u32,
+    pub worker_count: u32,
+    pub seed: u64,
+}
+
+impl Default for NoncePartition {
+    fn default() -> Self {
+        Self { worker_id: 0, worker_count: 1, seed: 0 }
     }
-    vm.finalize()
 }
 
-pub fn hash_structure_good(hash: &[u8], zero_bits: usize) -> bool {
-    let full_bytes = zero_bits / 8; // Number of full zero bytes
-    let remaining_bits = zero_bits % 8; // Bits to check in the next byte
+impl NoncePartition {
+    pub fn new(worker_id: u32, worker_count: u32) -> std::result::Result<Self, String> {
+        if worker_count == 0 || worker_id >= worker_count {
+            return Err(format!("Invalid nonce partition: worker id {} must be lower than the worker count {}", worker_id, worker_count));
+        }
+        Ok(Self { worker_id, worker_count, seed: 0 })
+    }
 
-    // Check full zero bytes
-    if hash.len() < full_bytes || hash[..full_bytes].iter().any(|&b| b != 0) {
-        return false;
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
     }
 
-    if remaining_bits == 0 {
-        return true;
+    /// This worker's range, end exclusive, with both ends aligned to the progress reporting chunk.
+    fn range(&self) -> (u128, u128) {
+        let bound = |id: u32| (((id as u128) << 64) / self.worker_count as u128) & !0xff;
+        (bound(self.worker_id), bound(self.worker_id + 1))
     }
-    if hash.len() > full_bytes {
-        // Mask for the most significant bits
-        let mask = 0xFF << (8 - remaining_bits);
-        hash[full_bytes] & mask == 0
-    } else {
-        false
+
+    /// First nonce to hash: the seed's offset into this worker's range, aligned to the progress reporting chunk.
+    /// The seed is mixed first, so small fixed seeds don't all land in the range's first chunk.
+    pub fn base(&self) -> u64 {
+        let (start, end) = self.range();
+        ((start + mix_seed(self.seed) as u128 % (end - start)) as u64) & !0xff
+    }
+
+    fn contains(&self, nonce: u64) -> bool {
+        let (start, end) = self.range();
+        (start..end).contains(&(nonce as u128))
     }
 }
 
+/// The MurmurHash3 finalizer: spreads every seed bit over the whole word, and keeps seed 0 at the start.
+fn mix_seed(seed: u64) -> u64 {
+    let mut z = seed;
+    z = (z ^ (z >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+    z = (z ^ (z >> 33)).wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    z ^ (z >> 33)
+}
 
-// --------------------------------------------------------------------------
-// SCAVENGE LOGIC
-// --------------------------------------------------------------------------
+/// Receives the next nonce of every worker.
+pub type CheckpointSaver<'a> = Box<dyn FnMut(&[u64]) + 'a>;
 
-pub struct Thread {}
+/// Lets `scavenge` resume from saved worker positions and hands it a callback to save them again.
+pub struct NonceCheckpoint<'a> {
+    /// Next nonce of each worker from an earlier run. Ignored unless it fits the thread count and partition.
+    pub resume: Vec<u64>,
+    /// How often `save` receives the positions; they are also saved whenever a search stops.
+    pub interval: Duration,
+    pub save: CheckpointSaver<'a>,
+}
 
-// Structure to hold dynamic challenge parameters from the API
-#[derive(Clone)]
-pub struct ChallengeParams {
-    pub rom_key: String, // no_pre_mine hex string (used for ROM init)
-    pub difficulty_mask: String, // difficulty hex string (used for submission check)
-    pub address: String, // Registered Cardano address
-    pub challenge_id: String,
-    pub latest_submission: String,
-    pub no_pre_mine_hour: String,
-    pub required_zero_bits: usize, // Derived from difficulty_mask
-    pub rom: Arc<Rom>,
+impl NonceCheckpoint<'_> {
+    /// The saved positions, if they lie in this worker's range on one lattice of nonces (`start + thread_id + k * nb_threads`).
+    /// The start itself may differ from this run's, e.g. with a random '--nonce-seed'.
+    fn resume_nonces(&self, nb_threads: u64, partition: &NoncePartition) -> Option<&[u64]> {
+        let start = self.resume.first().copied().unwrap_or_default();
+        let fits = self.resume.len() as u64 == nb_threads
+            && self.resume.iter().all(|&nonce| partition.contains(nonce))
+            && self.resume.iter().enumerate()
+                .all(|(thread_id, nonce)| nonce.wrapping_sub(start.wrapping_add(thread_id as u64)) % nb_threads == 0);
+        fits.then_some(self.resume.as_slice())
+    }
 }
 
 #[derive(Clone)]
@@ -480,7 +573,7 @@ pub fn build_preimage(
 }
 
 // Utility function to convert difficulty mask (e.g., "000FFFFF") to number of required zero bits
-fn difficulty_to_zero_bits(difficulty_hex: &str) -> usize {
+pub fn difficulty_to_zero_bits(difficulty_hex: &str) -> usize {
     let difficulty_bytes = hex::decode(difficulty_hex).unwrap();
     let mut zero_bits = 0;
     for &byte in difficulty_bytes.iter() {
@@ -494,45 +587,155 @@ fn difficulty_to_zero_bits(difficulty_hex: &str) -> usize {
     zero_bits
 }
 
+/// Hashes needed on average for one solution: a hash meets `zero_bits` leading zero bits with
+/// probability 2^-zero_bits.
+pub fn expected_hashes(zero_bits: usize) -> f64 {
+    2f64.powi(zero_bits as i32)
+}
+
+/// Probability of finding at least one solution within `seconds` at `hash_rate` hashes/s. The search is
+/// memoryless, so time already spent on the wallet doesn't change it.
+pub fn solution_probability(zero_bits: usize, hash_rate: f64, seconds: f64) -> f64 {
+    if hash_rate <= 0.0 || seconds <= 0.0 {
+        return 0.0;
+    }
+    // 1 - (1 - p)^n, kept accurate for tiny p
+    let miss_per_hash = (-1.0 / expected_hashes(zero_bits)).ln_1p();
+    -(hash_rate * seconds * miss_per_hash).exp_m1()
+}
+
+fn short_duration(seconds: f64) -> String {
+    let s = seconds.max(0.0).round() as u64;
+    match s {
+        s if s >= 86400 => format!("{}d{:02}h", s / 86400, (s % 86400) / 3600),
+        s if s >= 3600 => format!("{}h{:02}m", s / 3600, (s % 3600) / 60),
+        s if s >= 60 => format!("{}m{:02}s", s / 60, s % 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// Expected time to a solution and the chance of one before the deadline, for the progress bar.
+fn solution_outlook(zero_bits: usize, hash_rate: f64, deadline_secs: Option<f64>) -> String {
+    if hash_rate <= 0.0 {
+        return String::new();
+    }
+    let eta = format!(" | ETA ~{}", short_duration(expected_hashes(zero_bits) / hash_rate));
+    match deadline_secs {
+        Some(left) if left > 0.0 => format!("{}, {:.0}% by deadline", eta, 100.0 * solution_probability(zero_bits, hash_rate, left)),
+        Some(_) => format!("{}, deadline passed", eta),
+        None => eta,
+    }
+}
+
+/// Keeps one worker at or below its share of `ScavengeControl::max_hash_rate` by sleeping between hashes.
+struct HashPacer {
+    window_start: Instant,
+    window_hashes: u64,
+    share: f64,
+}
+
+impl HashPacer {
+    // Shorter pauses are not worth a syscall; the debt carries over to the next hash
+    const MIN_PAUSE: Duration = Duration::from_millis(1);
+
+    fn new() -> Self {
+        Self { window_start: Instant::now(), window_hashes: 0, share: 0.0 }
+    }
+
+    /// Starts a new pacing window, e.g. after the worker was parked, so idle time isn't spent as a burst.
+    fn reset(&mut self) {
+        self.window_start = Instant::now();
+        self.window_hashes = 0;
+    }
+
+    fn pace(&mut self, control: &ScavengeControl) {
+        let max_hash_rate = control.max_hash_rate.load(Ordering::Relaxed);
+        if max_hash_rate == 0 {
+            return;
+        }
+        let share = max_hash_rate as f64 / control.allowed_threads().max(1) as f64;
+        if share != self.share {
+            self.share = share;
+            self.reset();
+        }
+
+        self.window_hashes += 1;
+        let due = Duration::from_secs_f64(self.window_hashes as f64 / share);
+        let elapsed = self.window_start.elapsed();
+        if due > elapsed + Self::MIN_PAUSE {
+            thread::sleep(due - elapsed);
+        }
+    }
+}
+
 // The worker thread function
-fn spin(params: ChallengeParams, sender: Sender<Result>, stop_signal: Arc<AtomicBool>, start_nonce: u64, step_size: u64) {
+#[allow(clippy::too_many_arguments)]
+fn spin(params: ChallengeParams, sender: Sender<Result>, stop_signal: Arc<AtomicBool>, control: Arc<ScavengeControl>, thread_index: u32, start_nonce: u64, step_size: u64, position: &AtomicU64, thread_hashes: &AtomicU64) {
     let mut nonce_value = start_nonce;
+    let mut hashes_since_check: usize = 0;
     const CHUNKS_SIZE: usize = 0xff;
-    const NB_LOOPS: u32 = 8;
-    const NB_INSTRS: u32 = 256;
+    // Parked workers are woken by ScavengeControl; the timeout only bounds how late they see the stop signal
+    const THROTTLE_POLL: Duration = Duration::from_millis(250);
 
-    let my_address = &params.address;
+    let mut searcher = Searcher::new(preimage(&params));
+    let target = Target { zero_bits: params.required_zero_bits };
+    let mut pacer = HashPacer::new();
 
     while !stop_signal.load(Ordering::Relaxed) {
-        let preimage_string = build_preimage(
-            nonce_value,
-            my_address,
-            &params.challenge_id,
-            &params.difficulty_mask,
-            &params.rom_key,
-            &params.latest_submission,
-            &params.no_pre_mine_hour,
-        );
-        let preimage_bytes = preimage_string.as_bytes();
-        let h = hash(preimage_bytes, &params.rom, NB_LOOPS, NB_INSTRS);
-
-        if hash_structure_good(&h, params.required_zero_bits) {
-            if sender.send(Result::Found(nonce_value)).is_ok() {
+        // Hash one nonce at a time while paced, so the pacer can spread them out
+        let paced = control.max_hash_rate.load(Ordering::Relaxed) > 0;
+        let range = NonceRange { start: nonce_value, step: step_size, count: if paced { 1 } else { CHUNKS_SIZE as u64 } };
+        if let Some(solution) = searcher.search(&params.rom, target, range, &stop_signal) {
+            // A search resumed from here continues after the solution instead of finding it again
+            position.store(solution.nonce.wrapping_add(step_size), Ordering::Relaxed);
+            if sender.send(Result::Found(solution.nonce)).is_ok() {
                 // Sent the found nonce
             }
             return;
         }
-
-        if nonce_value & (CHUNKS_SIZE as u64) == 0 && sender.send(Result::Progress(CHUNKS_SIZE)).is_err() {
-             return;
+        if stop_signal.load(Ordering::Relaxed) {
+            return;
+        }
+        nonce_value = nonce_value.wrapping_add(step_size.wrapping_mul(range.count));
+        hashes_since_check += range.count as usize;
+        if paced {
+            pacer.pace(&control);
         }
 
-        // Increment nonce by the thread step size
-        nonce_value = nonce_value.wrapping_add(step_size);
+        // Idle while this worker is throttled away by the caller
+        if hashes_since_check >= CHUNKS_SIZE {
+            control.hashes.fetch_add(hashes_since_check as u64, Ordering::Relaxed);
+            thread_hashes.fetch_add(hashes_since_check as u64, Ordering::Relaxed);
+            // Every nonce of this worker below this one has been hashed
+            position.store(nonce_value, Ordering::Relaxed);
+            if sender.send(Result::Progress(hashes_since_check)).is_err() {
+                return;
+            }
+            hashes_since_check = 0;
+            if thread_index >= control.allowed_threads() {
+                while thread_index >= control.allowed_threads() && !stop_signal.load(Ordering::Relaxed) {
+                    thread::park_timeout(THROTTLE_POLL);
+                }
+                pacer.reset();
+            }
+        }
     }
 }
 
+/// The preimage of a challenge's nonces for `params.address`.
+fn preimage(params: &ChallengeParams) -> Preimage {
+    Preimage::new(
+        &params.address,
+        &params.challenge_id,
+        &params.difficulty_mask,
+        &params.rom_key,
+        &params.latest_submission,
+        &params.no_pre_mine_hour,
+    )
+}
+
 // The main orchestration function
+#[allow(clippy::too_many_arguments)]
 pub fn scavenge(
     my_registered_address: String,
     challenge_id: String,
@@ -541,28 +744,48 @@ pub fn scavenge(
     latest_submission: String,
     no_pre_mine_hour: String,
     nb_threads: u32,
+    control: Arc<ScavengeControl>,
+    partition: NoncePartition,
+    mut checkpoint: Option<NonceCheckpoint>,
 ) -> (Option<String>, u64, f64) { // <-- FIX: Explicitly define the return type
-    const MB: usize = 1024 * 1024;
-    const GB: usize = 1024 * MB;
-
     let required_zero_bits = difficulty_to_zero_bits(&difficulty);
+    // Seconds left until the submission deadline when the search starts
+    let deadline_secs = chrono::DateTime::parse_from_rfc3339(&latest_submission).ok()
+        .map(|deadline| (deadline.timestamp_millis() - chrono::Utc::now().timestamp_millis()) as f64 / 1000.0);
+
+    // Seconds after the search starts at which it gives up ('--deadline-margin')
+    let submission_cutoff = deadline_secs.zip(control.deadline_margin()).map(|(deadline, margin)| deadline - margin.as_secs_f64());
 
     // We rely on the caller to print required_zero_bits
+    const SLICE_POLL: Duration = Duration::from_secs(1);
+    // Without a live progress line, how often a status line is logged instead
+    const STATUS_LOG_INTERVAL: Duration = Duration::from_secs(60);
 
     let nb_threads_u64 = nb_threads as u64;
     let step_size = nb_threads_u64;
 
-    let (found_nonce, final_hashes_checked, elapsed_time) = thread::scope(|s| {
-        println!("Generating ROM with key: {}", no_pre_mine_key);
+    // Set start_nonce = partition base + thread_id, unless a checkpoint says where each worker got to
+    let resume = checkpoint.as_ref().and_then(|c| c.resume_nonces(nb_threads_u64, &partition));
+    if resume.is_some() {
+        println!("⏩ Resuming the nonce search from the last checkpoint.");
+    }
+    let positions: Vec<AtomicU64> = (0..nb_threads_u64)
+        .map(|thread_id| AtomicU64::new(match resume {
+            Some(nonces) => nonces[thread_id as usize],
+            None => partition.base().wrapping_add(thread_id),
+        }))
+        .collect();
+    let worker_hashes: Vec<AtomicU64> = (0..nb_threads_u64).map(|_| AtomicU64::new(0)).collect();
+    let _search = control.start_search();
+    let save_positions = |checkpoint: &mut Option<NonceCheckpoint>| {
+        if let Some(checkpoint) = checkpoint.as_mut() {
+            let nonces: Vec<u64> = positions.iter().map(|p| p.load(Ordering::Relaxed)).collect();
+            (checkpoint.save)(&nonces);
+        }
+    };
 
-        let rom = Rom::new(
-            no_pre_mine_key.as_bytes(),
-            RomGenerationType::TwoStep {
-                pre_size: 16 * MB,
-                mixing_numbers: 4,
-            },
-            GB,
-        );
+    let (found_nonce, final_hashes_checked, elapsed_time) = thread::scope(|s| {
+        let rom = shared_challenge_rom(&no_pre_mine_key);
         println!("{}", rom.digest);
 
         let (sender, receiver) = channel();
@@ -576,20 +799,49 @@ pub fn scavenge(
             latest_submission: latest_submission.clone(),
             no_pre_mine_hour: no_pre_mine_hour.clone(),
             required_zero_bits,
-            rom: Arc::new(rom),
+            rom,
+        };
+
+        let placement = numa::active_placement();
+        let replicas = match placement {
+            Some(p) if p.mode == numa::NumaMode::Replicate => node_roms(&no_pre_mine_key, &common_params.rom, &p.nodes),
+            _ => Vec::new(),
         };
 
+        let control_handles = control.clone();
+        if let Ok(mut workers) = control_handles.workers.lock() {
+            workers.clear();
+        }
         for thread_id in 0..nb_threads_u64 {
-            let params = common_params.clone();
+            let mut params = common_params.clone();
             let sender = sender.clone();
             let stop_signal = stop_signal.clone();
+            let control = control.clone();
+            let position = &positions[thread_id as usize];
+            let thread_hashes = &worker_hashes[thread_id as usize];
+            let start_nonce = position.load(Ordering::Relaxed);
+
+            // '--numa': keep the worker on one node, hashing against that node's ROM copy if there is one
+            let node = placement.map(|p| {
+                let index = numa::node_for_worker(thread_id as usize, nb_threads as usize, p.nodes.len());
+                if let Some(replica) = replicas.get(index) {
+                    params.rom = replica.clone();
+                }
+                &p.nodes[index]
+            });
 
-            // Set start_nonce = thread_id
-            let start_nonce = thread_id;
-
-            s.spawn(move || {
-                spin(params, sender, stop_signal, start_nonce, step_size)
+            let worker = s.spawn(move || {
+                if let Some(setup) = WORKER_THREAD_SETUP.get() {
+                    setup();
+                }
+                if let Some(node) = node {
+                    numa::pin_current_thread(&node.cpus);
+                }
+                spin(params, sender, stop_signal, control, thread_id as u32, start_nonce, step_size, position, thread_hashes)
             });
+            if let Ok(mut workers) = control_handles.workers.lock() {
+                workers.push(worker.thread().clone());
+            }
         }
 
         // Drop the extra sender handle in the main thread to ensure the receiver loop terminates
@@ -597,20 +849,52 @@ pub fn scavenge(
 
         let start_loop = SystemTime::now();
         let mut pos = 0;
-        let pb = ProgressBar::new(u64::MAX);
+        let pb = if control.is_live() { ProgressBar::new(u64::MAX) } else { ProgressBar::hidden() };
         pb.set_style(
             ProgressStyle::with_template(
-                "{spinner:.green} {pos}/{len} [{elapsed_precise}] {bar:40.cyan/blue} {msg}",
+                "{spinner:.green} [{elapsed_precise}] {human_pos} hashes | {msg}",
             )
-            .unwrap()
-            .progress_chars("#>-"),
+            .unwrap(),
         );
+        // '--no-live', or output that isn't a terminal (log capture): the line isn't drawn, so log it now and then
+        let log_status = pb.is_hidden();
+        let mut last_status_log = Instant::now();
 
         let mut found = Vec::new();
         let mut should_stop_after_found = false;
 
-        // Use a loop that waits for channel messages until all senders are dropped
-        while let Ok(r) = receiver.recv() {
+        // Wait for channel messages until all senders are dropped, waking up regularly to check the time slice
+        let mut last_checkpoint = Instant::now();
+        let superseded_at_start = control.challenge_superseded(&challenge_id);
+        loop {
+            let received = receiver.recv_timeout(SLICE_POLL);
+            if checkpoint.as_ref().is_some_and(|c| last_checkpoint.elapsed() >= c.interval) {
+                save_positions(&mut checkpoint);
+                last_checkpoint = Instant::now();
+            }
+            if !should_stop_after_found && control.time_slice_expired() {
+                println!("\n⏱️ Time slice over, pausing this address.");
+                stop_signal.store(true, Ordering::Relaxed);
+                should_stop_after_found = true;
+            }
+            // Only a push during the search stops it, so an API still serving the old challenge can't stop every search
+            if !should_stop_after_found && !superseded_at_start && control.challenge_superseded(&challenge_id) {
+                println!("\n🔔 A new challenge was published, stopping the search on {}.", challenge_id);
+                stop_signal.store(true, Ordering::Relaxed);
+                should_stop_after_found = true;
+            }
+            // A solution found this close to the deadline couldn't be submitted in time
+            let elapsed = start_loop.elapsed().unwrap().as_secs_f64();
+            if !should_stop_after_found && submission_cutoff.is_some_and(|cutoff| elapsed >= cutoff) {
+                println!("\n⏰ Submission deadline in {}, giving up on this challenge.", short_duration(deadline_secs.unwrap_or_default() - elapsed));
+                stop_signal.store(true, Ordering::Relaxed);
+                should_stop_after_found = true;
+            }
+            let r = match received {
+                Ok(r) => r,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
             match r {
                 Result::Progress(sz) => {
                     if should_stop_after_found {
@@ -623,11 +907,29 @@ pub fn scavenge(
                     let elapsed = start_loop.elapsed().unwrap().as_secs_f64();
                     let current_speed = (pos as f64) / elapsed;
 
-                    pb.set_message(format!(
-                        "Speed: {:.2} hash/s found: {}",
+                    let status = control.status.lock().map(|s| s.clone()).unwrap_or_default();
+                    // Slowest and fastest worker, so a stuck or throttled core stands out from the total
+                    let spread = if nb_threads > 1 {
+                        let rates = worker_hashes.iter().map(|h| h.load(Ordering::Relaxed) as f64 / elapsed);
+                        let (min, max) = rates.fold((f64::MAX, 0.0f64), |(min, max), rate| (min.min(rate), max.max(rate)));
+                        format!(" (per thread {:.2}-{:.2})", min, max)
+                    } else {
+                        String::new()
+                    };
+
+                    let message = format!(
+                        "Speed: {:.2} hash/s{} found: {}{}{}",
                         current_speed,
-                        found.len()
-                    ));
+                        spread,
+                        found.len(),
+                        solution_outlook(required_zero_bits, current_speed, deadline_secs.map(|d| d - elapsed)),
+                        if status.is_empty() { String::new() } else { format!(" | {}", status) }
+                    );
+                    if log_status && last_status_log.elapsed() >= STATUS_LOG_INTERVAL {
+                        println!("⛏️ [{}] {} hashes | {}", short_duration(elapsed), pos, message);
+                        last_status_log = Instant::now();
+                    }
+                    pb.set_message(message);
                 }
                 Result::Found(nonce) => {
                     let nonce_hex = format!("{:016x}", nonce);
@@ -643,15 +945,25 @@ pub fn scavenge(
         }
 
         // Final message after the mining stops (channel disconnects)
+        if let Ok(mut thread_hashes) = control.thread_hashes.lock() {
+            *thread_hashes = worker_hashes.iter().map(|h| h.load(Ordering::Relaxed)).collect();
+        }
+        save_positions(&mut checkpoint);
         let final_nonce_hex = found.pop().map(|nonce| format!("{:016x}", nonce));
         let final_elapsed = start_loop.elapsed().unwrap().as_secs_f64();
         let final_hashes = pos;
 
+        let msg = match final_nonce_hex {
+            Some(_) => format!("Scavenging complete. Found 1 solution. Total hashes checked: {}", pos),
+            None => "Scavenging stopped (No solution found).".to_string(),
+        };
+        if log_status {
+            println!("{}", msg);
+        }
         if final_nonce_hex.is_some() {
-            let msg = format!("Scavenging complete. Found 1 solution. Total hashes checked: {}", pos);
             pb.finish_with_message(msg);
         } else {
-             pb.abandon_with_message("Scavenging stopped (No solution found).");
+            pb.abandon_with_message(msg);
         }
 
         // Return the found nonce (if any) from the thread scope
@@ -660,3 +972,31 @@ pub fn scavenge(
 
     (found_nonce, final_hashes_checked, elapsed_time)
 }
+
+/// Hashes nonces of a challenge on `nb_threads` threads for `duration` without stopping on a solution,
+/// and returns the number of hashes computed. Used by the benchmark command to compare thread counts.
+pub fn benchmark_hashes(params: &ChallengeParams, nb_threads: u32, duration: Duration) -> u64 {
+    let stop_signal = AtomicBool::new(false);
+    let total_hashes = AtomicU64::new(0);
+
+    thread::scope(|s| {
+        for thread_id in 0..nb_threads as u64 {
+            let (stop_signal, total_hashes) = (&stop_signal, &total_hashes);
+            s.spawn(move || {
+                let mut searcher = Searcher::new(preimage(params));
+                let mut nonce_value = thread_id;
+                let mut hashes = 0;
+                while !stop_signal.load(Ordering::Relaxed) {
+                    std::hint::black_box(searcher.hash(&params.rom, nonce_value));
+                    hashes += 1;
+                    nonce_value = nonce_value.wrapping_add(nb_threads as u64);
+                }
+                total_hashes.fetch_add(hashes, Ordering::Relaxed);
+            });
+        }
+        thread::sleep(duration);
+        stop_signal.store(true, Ordering::Relaxed);
+    });
+
+    total_hashes.into_inner()
+}
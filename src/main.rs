@@ -1,46 +1,107 @@
 // src/main.rs - Final Minimal Version
 
 use clap::Parser;
+use std::sync::mpsc;
 use std::thread; // ADDED
 
 // Declare modules
 mod api;
+mod api_check;
+mod api_trace;
+mod audit;
 mod backoff;
+mod benchmark;
 mod cli;
+mod config;
 mod constants;
 mod cardano;
+mod checklist;
+mod cluster;
 mod data_types;
+mod diagnostics;
+mod doctor;
+mod error;
+mod health;
+mod init;
+mod journal;
+mod mock_api;
 mod utils; // The helpers module
 mod mining;
+mod notify;
+mod proxies;
+mod push;
+mod receipts;
+mod sandbox;
+mod signer;
+mod standby;
+mod stats;
+mod store;
 mod submitter;
+mod throttle;
+mod validate;
+mod watch;
+mod webhook;
 
 use mining::{run_persistent_key_mining, run_mnemonic_sequential_mining, run_ephemeral_key_mining};
 use utils::{setup_app, print_mining_setup}; // Importing refactored helpers
 use cli::Cli;
-use api::get_active_challenge_data;
+use signer::Signer;
 
 
 /// Runs the main application logic based on CLI flags.
-fn run_app(cli: Cli) -> Result<(), String> {
-    let context = match setup_app(&cli) {
+fn run_app(mut cli: Cli) -> Result<(), String> {
+    config::apply_profile(&mut cli)?;
+
+    if let Some(scenario) = cli.mock_api.clone() {
+        cli.api_url = Some(mock_api::spawn_mock_api(&scenario)?);
+    }
+
+    // Sandbox before any thread is started, so every thread inherits it
+    if cli.harden {
+        sandbox::harden(&cli)?;
+    }
+
+    let mut context = match setup_app(&cli) {
         Ok(c) => c,
         // Exit the app if a command like 'Challenges' was run successfully
         Err(e) if e == "COMMAND EXECUTED" => return Ok(()),
         Err(e) => return Err(e),
     };
 
+    // Read-only ticker: standby, heartbeat and the submitter are all skipped
+    if cli.watch {
+        return watch::run_watch(&context.client, &context.api_url, cli.address.as_deref());
+    }
+
+    // --- Warm standby: stay idle while the primary is alive ---
+    if let Some(source) = cli.standby_of.as_deref() {
+        standby::wait_while_primary_alive(&context.client, source, cli.standby_timeout);
+    }
+    if let Some(path) = cli.heartbeat.clone() {
+        standby::spawn_heartbeat_writer(path)?;
+    }
+
     // --- Start Background Submitter Thread ---
-    // Clone client, API URL, and data_dir for the background thread
+    // Clone the clients, API URL, and data_dir for the background thread
     let _submitter_handle = if let Some(base_dir) = context.data_dir {
-        let client_clone = context.client.clone();
+        let clients_clone = context.wallet_clients.clone();
         let api_url_clone = context.api_url.clone();
         let data_dir_clone = base_dir.to_string();
+        let check_before_submit = cli.check_before_submit;
+        let notifications = context.notifications.clone();
+
+        // The miner keeps an in-memory index of the queue; the submitter reports changes to it
+        let (queue_events_tx, queue_events_rx) = mpsc::channel();
+        context.pending_queue = Some(data_types::PendingQueueIndex::load(base_dir, queue_events_rx));
 
         println!("📦 Starting background submitter thread...");
         let handle = thread::spawn(move || {
-            match submitter::run_submitter_thread(client_clone, api_url_clone, data_dir_clone) {
+            match submitter::run_submitter_thread(clients_clone, api_url_clone, data_dir_clone, check_before_submit, queue_events_tx, notifications.clone()) {
                 Ok(_) => {},
-                Err(e) => eprintln!("FATAL SUBMITTER ERROR: {}", e),
+                Err(e) => {
+                    eprintln!("FATAL SUBMITTER ERROR: {}", e);
+                    notifications.send(notify::NotifyEvent::SubmitterStopped { error: e });
+                },
             }
         });
         Some(handle)
@@ -61,9 +122,9 @@ fn run_app(cli: Cli) -> Result<(), String> {
     };
 
     // 1. Default mode: display info and exit
-    if cli.payment_key.is_none() && !cli.ephemeral_key && mnemonic.is_none() && cli.challenge.is_none() {
+    if cli.payment_key.is_none() && cli.external_signer.is_none() && !cli.ephemeral_key && mnemonic.is_none() && cli.challenge.is_none() {
         // Fetch challenge for info display
-        match get_active_challenge_data(&context.client, &context.api_url) {
+        match api::block_on(api::get_active_challenge_data(&context.client, &context.api_url)) {
             Ok(challenge_params) => {
                  print_mining_setup(
                     &context.api_url,
@@ -71,6 +132,7 @@ fn run_app(cli: Cli) -> Result<(), String> {
                     context.threads,
                     &challenge_params
                 );
+                utils::print_donation_policy(cli.donate_to.as_ref());
             },
             Err(e) => eprintln!("Could not fetch active challenge for info display: {}", e),
         };
@@ -78,10 +140,22 @@ fn run_app(cli: Cli) -> Result<(), String> {
         return Ok(())
     }
 
+    // A fixed challenge never switches, so there is no next ROM to prepare
+    if let Some(stream_url) = cli.challenge_push.clone() {
+        push::spawn_challenge_push(stream_url, context.client.clone(), context.api_url.clone(), context.control.clone(), !cli.no_rom_prefetch, cli.proxy.as_deref())?;
+    } else if cli.challenge.is_none() && !cli.no_rom_prefetch {
+        utils::spawn_rom_prefetcher(context.client.clone(), context.api_url.clone());
+    }
+
     // 2. Determine Operation Mode and Start Mining
     let result = if let Some(skey_hex) = cli.payment_key.as_ref() {
         // Mode A: Persistent Key Mining
-        run_persistent_key_mining(context, skey_hex)
+        run_persistent_key_mining(context, Signer::Local(cardano::generate_cardano_key_pair_from_skey(skey_hex)))
+    }
+    else if let Some(command) = cli.external_signer.clone() {
+        // Mode A (external): the key stays with the signing tool
+        let address = cli.address.clone().ok_or("'--external-signer' requires '--address', the address the signer signs for.")?;
+        run_persistent_key_mining(context, Signer::External { address, command })
     }
     else if let Some(mnemonic_phrase) = mnemonic {
         // Mode B: Mnemonic Sequential Mining
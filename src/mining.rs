@@ -1,11 +1,18 @@
 // src/mining.rs
 
 use crate::api;
-use crate::data_types::{DataDir, DataDirMnemonic, MiningContext, MiningResult, ChallengeData, PendingSolution, FILE_NAME_FOUND_SOLUTION, is_solution_pending_in_queue, FILE_NAME_RECEIPT};
+use crate::error::ApiError;
+use crate::audit;
+use crate::data_types::{DataDir, DataDirMnemonic, MiningContext, MiningResult, ChallengeData, ChallengeSummary, StatsSnapshot, WalletOutcome, CanaryRecord, FILE_NAME_FOUND_SOLUTION, migrate_legacy_mnemonic_dirs, derived_idempotency_key, load_canary_record, load_challenge_notes, save_canary_record, save_stats_diff};
 use crate::cli::Cli;
+use crate::store::StateStore;
+use crate::backoff::Backoff;
+use crate::submitter;
 use crate::cardano;
-use crate::utils::{self, next_wallet_deriv_index_for_challenge, print_mining_setup, print_statistics, receipt_exists_for_index, run_single_mining_cycle};
-use std::{fs, path::PathBuf}; // Added fs, path::PathBuf
+use crate::notify::NotifyEvent;
+use crate::signer::Signer;
+use crate::utils::{self, Stagger, count_mnemonic_receipts_for_challenge, format_duration, next_wallet_deriv_index_for_challenge, print_donation_policy, print_mining_setup, print_statistics, run_single_mining_cycle};
+use std::collections::VecDeque;
 
 // ===============================================
 // SOLUTION RECOVERY FUNCTION
@@ -13,28 +20,18 @@ use std::{fs, path::PathBuf}; // Added fs, path::PathBuf
 
 /// Checks the local storage for any solution that was found but not yet queued
 /// and queues it if found.
-fn check_for_unsubmitted_solutions(base_dir: &str, challenge_id: &str, mining_address: &str, data_dir_variant: &DataDir) -> Result<(), String> {
-    // Determine the base path for the specific wallet/challenge
-    let mut path = data_dir_variant.receipt_dir(base_dir, challenge_id)?;
-    path.push(FILE_NAME_FOUND_SOLUTION);
-
-    if path.exists() {
-        println!("\n⚠️ Recovery file detected at {:?}. Recovering solution...", path);
-
-        let solution_json = fs::read_to_string(&path)
-            .map_err(|e| format!("Failed to read recovery file {:?}: {}", path, e))?;
-
-        let pending_solution: PendingSolution = serde_json::from_str(&solution_json)
-            .map_err(|e| format!("Failed to parse recovery solution JSON {:?}: {}", path, e))?;
+fn check_for_unsubmitted_solutions(store: &dyn StateStore, challenge_id: &str, mining_address: &str, wallet: &DataDir) -> Result<(), String> {
+    if let Some(pending_solution) = store.load_found_solution(wallet, challenge_id)? {
+        println!("\n⚠️ Recovery file detected for {} / challenge {}. Recovering solution...", mining_address, challenge_id);
 
         // 1. Save to the main submission queue
-        if let Err(e) = data_dir_variant.save_pending_solution(base_dir, &pending_solution) {
+        if let Err(e) = store.save_pending_solution(&pending_solution) {
             return Err(format!("FATAL RECOVERY ERROR: Could not queue recovered solution: {}", e));
         }
 
         // 2. Delete the recovery file
-        if let Err(e) = fs::remove_file(&path) {
-            eprintln!("WARNING: Successfully queued recovered solution but FAILED TO DELETE RECOVERY FILE {:?}: {}", path, e);
+        if let Err(e) = store.delete_found_solution(wallet, challenge_id) {
+            eprintln!("WARNING: Successfully queued recovered solution but FAILED TO DELETE RECOVERY FILE {}: {}", FILE_NAME_FOUND_SOLUTION, e);
         } else {
             println!("✅ Successfully recovered and queued solution for address {} / challenge {}.", mining_address, challenge_id);
         }
@@ -42,88 +39,176 @@ fn check_for_unsubmitted_solutions(base_dir: &str, challenge_id: &str, mining_ad
     Ok(())
 }
 
+// ===============================================
+// CHALLENGE SUMMARY REPORTING
+// ===============================================
+
+/// Finishes the current summary and publishes it to the notifiers once a new challenge is seen,
+/// then makes sure a summary exists for the given challenge.
+fn roll_challenge_summary(context: &MiningContext, summary: &mut Option<ChallengeSummary>, challenge_id: &str) {
+    if summary.as_ref().is_some_and(|s| s.challenge_id != challenge_id)
+        && let Some(mut finished) = summary.take()
+    {
+        // All of a pool's wallets at once, rather than one request after another
+        let mut end_stats = api::block_on(api::fetch_statistics_many(&context.wallet_clients, &context.api_url, &finished.snapshotted_addresses()));
+        finished.finish(|address| end_stats.remove(address).and_then(Result::ok).map(|stats| StatsSnapshot::from(&stats)));
+        finished.notes = context.data_dir.map(|base_dir| load_challenge_notes(base_dir, &finished.challenge_id)).unwrap_or_default();
+        print_stats_diff(context, &finished);
+        context.notifications.send(NotifyEvent::ChallengeSummary(finished));
+    }
+    if summary.is_none() {
+        *summary = Some(ChallengeSummary::new(challenge_id));
+    }
+}
+
+/// Takes a wallet's start-of-challenge statistics before its first cycle of the challenge.
+fn snapshot_wallet_start(context: &MiningContext, summary: &mut Option<ChallengeSummary>, address: &str) {
+    if let Some(summary) = summary.as_mut().filter(|s| s.needs_start_snapshot(address)) {
+        let snapshot = api::block_on(api::fetch_statistics(context.wallet_clients.for_wallet(address), &context.api_url, address)).ok().map(|stats| StatsSnapshot::from(&stats));
+        summary.snapshot_start(address, snapshot);
+    }
+}
+
+/// Reports what the API credited for the finished challenge and keeps the diff next to its receipts.
+fn print_stats_diff(context: &MiningContext, summary: &ChallengeSummary) {
+    println!("\n📊 Challenge {}: {} solution(s) queued, {} receipt(s) credited by the API.",
+        summary.challenge_id,
+        summary.queued_solutions,
+        summary.credited_receipts.map_or("unknown".to_string(), |credited| credited.to_string()));
+
+    let saved = context.data_dir.map(|base_dir| save_stats_diff(base_dir, summary));
+    if let Some(Err(e)) = &saved {
+        eprintln!("⚠️ {}", e);
+    }
+    for diff in summary.stats_diff.iter().filter(|diff| diff.mismatch) {
+        eprintln!("⚠️ {}: {} queued, {} credited.", diff.address, diff.queued,
+            diff.credited_receipts.map_or("unknown".to_string(), |credited| credited.to_string()));
+    }
+    if let Some(Ok(path)) = saved
+        && summary.mismatched_wallets() > 0
+    {
+        println!("{} wallet(s) need a follow-up, recorded in {:?}.", summary.mismatched_wallets(), path);
+    }
+    for note in &summary.notes {
+        println!("📝 {}", note);
+    }
+}
+
 // ===============================================
 // MINING MODE FUNCTIONS (Core Logic Only)
 // ===============================================
 
-/// MODE A: Persistent Key Continuous Mining
+// Registration tries at startup before persistent-key mining falls back to queue-only mode (~2.5 minutes of backoff)
+const INITIAL_REGISTRATION_ATTEMPTS: u32 = 6;
+
+/// MODE A: Persistent Key Continuous Mining (key from '--payment-key', or held by '--external-signer')
 #[allow(unused_assignments)] // Suppress warnings for final_hashes/final_elapsed assignments
-pub fn run_persistent_key_mining(context: MiningContext, skey_hex: &String) -> Result<(), String> {
-    let key_pair = cardano::generate_cardano_key_pair_from_skey(skey_hex);
-    let mining_address = key_pair.2.to_bech32().unwrap();
+pub fn run_persistent_key_mining(context: MiningContext, signer: Signer) -> Result<(), String> {
+    let mining_address = signer.address();
     let mut final_hashes: u64 = 0;
     let mut final_elapsed: f64 = 0.0;
     let reg_message = context.tc_response.message.clone();
     let data_dir = DataDir::Persistent(&mining_address);
 
     println!("\n[REGISTRATION] Attempting initial registration for address: {}", mining_address);
-    let reg_signature = cardano::cip8_sign(&key_pair, &reg_message);
-    if let Err(e) = api::register_address(
-        &context.client, &context.api_url, &mining_address, &context.tc_response.message, &reg_signature.0, &hex::encode(key_pair.1.as_ref()),
-    ) {
-        eprintln!("Address registration failed: {}. Cannot start mining.", e);
-        return Err("Address registration failed.".to_string());
+    let (reg_signature, reg_pubkey) = signer.sign(&reg_message)?;
+    let mut registered = register_with_retry(&context, &mining_address, &reg_signature, &reg_pubkey, INITIAL_REGISTRATION_ATTEMPTS);
+    if !registered {
+        if context.store.is_none() {
+            return Err("Address registration failed, and without '--data-dir' there is no queue to mine into.".to_string());
+        }
+        eprintln!("⚠️ Registration of {} still fails after {} attempts. Mining in queue-only mode: solutions stay queued until a registration retry at the start of a cycle succeeds.", mining_address, INITIAL_REGISTRATION_ATTEMPTS);
+        submitter::hold_address(&mining_address);
     }
 
     println!("\n==============================================");
-    println!("⛏️  Shadow Harvester: PERSISTENT KEY MINING Mode ({})", if context.cli_challenge.is_some() { "FIXED CHALLENGE" } else { "DYNAMIC POLLING" });
+    println!("⛏️  Shadow Harvester: {} Mode ({})",
+        if matches!(signer, Signer::External { .. }) { "EXTERNAL SIGNER MINING" } else { "PERSISTENT KEY MINING" },
+        if context.cli_challenge.is_some() { "FIXED CHALLENGE" } else { "DYNAMIC POLLING" });
     println!("==============================================");
-    if context.donate_to_option.is_some() { println!("Donation Target: {}", context.donate_to_option.unwrap()); }
+    print_donation_policy(context.donate_to_option);
 
     let mut current_challenge_id = String::new();
     let mut last_active_challenge_data: Option<ChallengeData> = None;
+    let mut challenge_summary: Option<ChallengeSummary> = None;
+    let mut final_outcome = String::new();
+    let mut stagger = Stagger::new(context.stagger_secs);
+    // Every '--api-url' endpoint failed by the time an error gets here
+    let mut backoff_challenge = Backoff::new(5, 300, 2.0);
     loop {
         let challenge_params = match utils::get_challenge_params(&context.client, &context.api_url, context.cli_challenge, &mut current_challenge_id) {
             Ok(Some(params)) => {
+                backoff_challenge.reset();
                 last_active_challenge_data = Some(params.clone());
                 params
             },
             Ok(None) => continue,
             Err(e) => {
                 // If a challenge ID is set AND we detect a network failure, continue mining.
-                if !current_challenge_id.is_empty() && e.contains("API request failed") {
+                if !current_challenge_id.is_empty() && e.is_transient() {
                     eprintln!("⚠️ Challenge API poll failed (Network Error): {}. Continuing mining with previous challenge parameters (ID: {})...", e, current_challenge_id);
-                    last_active_challenge_data.as_ref().cloned().ok_or_else(|| {
-                        format!("FATAL LOGIC ERROR: Challenge ID {} is set but no previous challenge data was stored.", current_challenge_id)
-                    })?
+                    match utils::resume_challenge(&context, &current_challenge_id, last_active_challenge_data.as_ref()) {
+                        Ok(params) => params,
+                        Err(e) => {
+                            eprintln!("⚠️ Could not resume challenge {}: {}. Retrying with exponential backoff...", current_challenge_id, e);
+                            backoff_challenge.sleep();
+                            continue;
+                        }
+                    }
                 } else {
-                    eprintln!("⚠️ Critical API Error during challenge check: {}. Retrying in 1 minute...", e);
-                    std::thread::sleep(std::time::Duration::from_secs(60));
+                    eprintln!("⚠️ Critical API Error during challenge check: {}. Retrying with exponential backoff...", e);
+                    backoff_challenge.sleep();
                     continue;
                 }
             }
         };
 
+        roll_challenge_summary(&context, &mut challenge_summary, &challenge_params.challenge_id);
+        stagger.wait(&challenge_params.challenge_id, &mining_address);
+
+        if !registered && register_with_retry(&context, &mining_address, &reg_signature, &reg_pubkey, 1) {
+            println!("▶️ {} is registered. Leaving queue-only mode and submitting its queued solutions.", mining_address);
+            submitter::release_address(&mining_address);
+            registered = true;
+        }
+
         // Check for unsubmitted solutions from previous run
-        if let Some(base_dir) = context.data_dir {
-            check_for_unsubmitted_solutions(base_dir, &challenge_params.challenge_id, &mining_address, &data_dir)?;
+        if let Some(store) = context.store.as_deref() {
+            check_for_unsubmitted_solutions(store, &challenge_params.challenge_id, &mining_address, &data_dir)?;
         }
 
-        if let Some(base_dir) = context.data_dir { data_dir.save_challenge(base_dir, &challenge_params)?; }
+        if let Some(store) = context.store.as_deref() { store.save_challenge(&data_dir, &challenge_params)?; }
         print_mining_setup(&context.api_url, Some(mining_address.as_str()), context.threads, &challenge_params);
+        snapshot_wallet_start(&context, &mut challenge_summary, &mining_address);
 
+        let mut solutions: u32 = 0;
         loop {
             // UPDATED CALL: Removed client and api_url
             let (result, total_hashes, elapsed_secs) = run_single_mining_cycle(
-                mining_address.clone(), context.threads, context.donate_to_option, &challenge_params, context.data_dir,
+                mining_address.clone(), context.threads, context.donate_to_option, &challenge_params, context.store.as_deref(), &context.throttle, &context.control, context.nonce_partition,
+                solutions + 1 < context.solutions_per_challenge,
             );
             final_hashes = total_hashes; final_elapsed = elapsed_secs;
+            final_outcome = format!("{:?}", result);
 
             match result {
                 MiningResult::FoundAndQueued => {
-                    if let Some(ref destination_address) = context.donate_to_option {
+                    solutions += 1;
+                    if let Some(destination_address) = context.donate_to_option.filter(|_| solutions == 1) {
                         let donation_message = format!("Assign accumulated Scavenger rights to: {}", destination_address);
-                        let donation_signature = cardano::cip8_sign(&key_pair, &donation_message);
-
                         // Intentionally perform donation attempt synchronously here.
-                        match api::donate_to(
-                            &context.client, &context.api_url, &mining_address, destination_address, &donation_signature.0,
-                        ) {
+                        match signer.sign(&donation_message).and_then(|(donation_signature, _)| donate(
+                            &context, &mining_address, destination_address, &donation_signature,
+                        ).map_err(String::from)) {
                             Ok(id) => println!("🚀 Donation initiated successfully. ID: {}", id),
                             Err(e) => eprintln!("⚠️ Donation failed (synchronous attempt): {}", e),
                         }
                     }
 
+                    if solutions < context.solutions_per_challenge {
+                        println!("\n✅ Solution {} of {} queued. Searching on for the next one.", solutions, context.solutions_per_challenge);
+                        continue;
+                    }
                     println!("\n✅ Solution queued. Checking for new challenge/expiration.");
                     break; // Break the inner loop to re-poll the challenge API.
                 },
@@ -132,10 +217,19 @@ pub fn run_persistent_key_mining(context: MiningContext, skey_hex: &String) -> R
                     // Solution saved by submitter/already exists, so check for a new challenge.
                     break;
                 }
-                MiningResult::MiningFailed => {
+                MiningResult::DeadlineReached => {
+                    if context.cli_challenge.is_some() {
+                        println!("🏁 Fixed challenge {} is closing for submissions. Stopping.", challenge_params.challenge_id);
+                        return Ok(());
+                    }
+                    println!("⏭️ Skipping the rest of challenge {}. Waiting for the next challenge...", challenge_params.challenge_id);
+                    break;
+                }
+                MiningResult::ChallengeSuperseded => break,
+                MiningResult::MiningFailed | MiningResult::SliceExpired => {
                     eprintln!("\n⚠️ Mining cycle failed. Checking if challenge is still valid before retrying...");
                     if context.cli_challenge.is_none() {
-                        match api::get_active_challenge_data(&context.client,&context.api_url) {
+                        match api::block_on(api::get_active_challenge_data(&context.client,&context.api_url)) {
                             Ok(active_params) if active_params.challenge_id == current_challenge_id => {
                                 eprintln!("Challenge is still valid. Retrying mining cycle in 1 minute...");
                                 std::thread::sleep(std::time::Duration::from_secs(60));
@@ -152,14 +246,202 @@ pub fn run_persistent_key_mining(context: MiningContext, skey_hex: &String) -> R
                 }
             }
         }
-        let stats_result = api::fetch_statistics(&context.client, &context.api_url, &mining_address);
-        print_statistics(stats_result, final_hashes, final_elapsed);
+        let stats_result = api::block_on(api::fetch_statistics(context.wallet_clients.for_wallet(&mining_address), &context.api_url, &mining_address));
+        if let Some(summary) = challenge_summary.as_mut() {
+            summary.record(WalletOutcome {
+                address: mining_address.clone(),
+                deriv_index: None,
+                outcome: final_outcome.clone(),
+                hashes: final_hashes,
+                elapsed_secs: final_elapsed,
+                night_allocation: stats_result.as_ref().ok().map(|s| s.night_allocation),
+                night_delta: None,
+            });
+        }
+        print_statistics(stats_result, final_hashes, final_elapsed, &context.control.thread_hashes());
+    }
+}
+
+
+// ===============================================
+// API WRITES
+// ===============================================
+
+/// POST /register with an idempotency key derived from the address, recorded in the audit log.
+fn register(context: &MiningContext, address: &str, signature: &str, pubkey: &str) -> Result<(), ApiError> {
+    let idempotency_key = derived_idempotency_key(&["register", address]);
+    let result = api::block_on(api::register_address(context.wallet_clients.for_wallet(address), &context.api_url, address, &context.tc_response.message, signature, pubkey, &idempotency_key));
+    audit::record(context.data_dir, "register", address, &idempotency_key, &result);
+    result
+}
+
+/// Registers an address, retrying with backoff up to `attempts` times. An address whose statistics the
+/// API already serves counts as registered, whatever the registration call returned.
+fn register_with_retry(context: &MiningContext, address: &str, signature: &str, pubkey: &str, attempts: u32) -> bool {
+    let mut backoff = Backoff::new(5, 300, 2.0);
+    for attempt in 1..=attempts {
+        let error = match register(context, address, signature, pubkey) {
+            Ok(()) => return true,
+            Err(e) => e,
+        };
+        if api::block_on(api::fetch_statistics(context.wallet_clients.for_wallet(address), &context.api_url, address)).is_ok() {
+            println!("✅ Address {} is already registered.", address);
+            return true;
+        }
+        eprintln!("⚠️ Registration attempt {}/{} failed: {}", attempt, attempts, error);
+        if attempt < attempts {
+            backoff.sleep();
+        }
     }
+    false
 }
 
+/// POST /donate_to with an idempotency key derived from both addresses, recorded in the audit log.
+fn donate(context: &MiningContext, address: &str, destination_address: &str, signature: &str) -> Result<String, ApiError> {
+    let idempotency_key = derived_idempotency_key(&["donate_to", address, destination_address]);
+    let result = api::block_on(api::donate_to(context.wallet_clients.for_wallet(address), &context.api_url, address, destination_address, signature, &idempotency_key));
+    audit::record(context.data_dir, "donate_to", address, &idempotency_key, &result);
+    result
+}
+
+// ===============================================
+// CANARY WALLET
+// ===============================================
+
+const CANARY_POLL_SECS: u64 = 30;
+// Covers the submitter's retries; a release that signs incorrectly never gets credited
+const CANARY_CREDIT_TIMEOUT_SECS: u64 = 30 * 60;
+
+/// '--canary': the first run of a new release mines and submits with one designated wallet and waits for
+/// the API to credit it before the other wallets are mined, so a release deriving addresses or signing
+/// payloads incorrectly stops after a single wallet. Later runs of a verified release skip the check.
+fn run_canary(cli: &Cli, context: &MiningContext, mnemonic_phrase: &str, canary_index: u32) -> Result<(), String> {
+    let base_dir = context.data_dir.ok_or("'--canary' requires '--data-dir'.")?;
+    let version = env!("CARGO_PKG_VERSION");
+    if let Some(record) = load_canary_record(base_dir).filter(|r| r.version == version) {
+        println!("🐤 Release {} already verified by canary {} on challenge {} ({}).", version, record.address, record.challenge_id, record.verified_at);
+        return Ok(());
+    }
+
+    let key_pair = cardano::derive_key_pair_from_mnemonic(mnemonic_phrase, cli.mnemonic_account, canary_index);
+    let canary_address = key_pair.2.to_bech32().unwrap();
+    let wallet_config = DataDirMnemonic { mnemonic: mnemonic_phrase, account: cli.mnemonic_account, deriv_index: canary_index };
+    println!("\n🐤 CANARY: verifying release {} with wallet index {} ({}) before mining with the other wallets.", version, canary_index, canary_address);
+
+    // --- Baseline receipt count, which the credit wait compares against ---
+    // A guessed baseline could mistake an earlier receipt for the canary's credit, so it has to be fetched
+    let started = std::time::Instant::now();
+    let mut registered = false;
+    let receipts_before = loop {
+        match api::block_on(api::fetch_statistics(context.wallet_clients.for_wallet(&canary_address), &context.api_url, &canary_address)) {
+            Ok(stats) => break stats.crypto_receipts,
+            Err(e) if e.is_transient() => eprintln!("⚠️ Canary statistics check failed: {}", e),
+            // The API serves no statistics for an unregistered address
+            Err(_) if !registered => {
+                let reg_signature = cardano::cip8_sign(&key_pair, &context.tc_response.message);
+                register(context, &canary_address, &reg_signature.0, &hex::encode(key_pair.1.as_ref()))
+                    .map_err(|e| format!("Canary registration failed: {}. Not mining with the other wallets.", e))?;
+                registered = true;
+                continue;
+            },
+            Err(e) => return Err(format!("Could not fetch the statistics of canary {}: {}. Not mining with the other wallets.", canary_address, e)),
+        }
+        if started.elapsed().as_secs() >= CANARY_CREDIT_TIMEOUT_SECS {
+            return Err(format!("Could not fetch the statistics of canary {} within {} minutes. Not mining with the other wallets.", canary_address, CANARY_CREDIT_TIMEOUT_SECS / 60));
+        }
+        std::thread::sleep(std::time::Duration::from_secs(CANARY_POLL_SECS));
+    };
+
+    let mut current_challenge_id = String::new();
+    let challenge_params = 'challenge: loop {
+        let challenge_params = loop {
+            match utils::get_challenge_params(&context.client, &context.api_url, context.cli_challenge, &mut current_challenge_id) {
+                Ok(Some(params)) => break params,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("⚠️ Canary could not fetch the active challenge: {}. Retrying in 1 minute...", e);
+                    std::thread::sleep(std::time::Duration::from_secs(60));
+                }
+            }
+        };
+        let store = context.store.as_deref().ok_or("'--canary' requires '--data-dir'.")?;
+        if store.receipt_exists(&DataDir::Mnemonic(wallet_config), &challenge_params.challenge_id)? {
+            return Err(format!("Canary wallet index {} already solved challenge {} with a previous release. Pick an unused '--canary' index.", canary_index, challenge_params.challenge_id));
+        }
+        store.save_challenge(&DataDir::Mnemonic(wallet_config), &challenge_params)?;
+
+        // --- Mine and queue one solution ---
+        print_mining_setup(&context.api_url, Some(canary_address.as_str()), context.threads, &challenge_params);
+        loop {
+            let (result, _, _) = run_single_mining_cycle(
+                canary_address.clone(), context.threads, context.donate_to_option, &challenge_params, context.store.as_deref(), &context.throttle, &context.control, context.nonce_partition, false,
+            );
+            match result {
+                MiningResult::FoundAndQueued => break 'challenge challenge_params,
+                MiningResult::AlreadySolved => return Err(format!("Canary wallet {} already solved challenge {}. Pick an unused '--canary' index.", canary_address, challenge_params.challenge_id)),
+                MiningResult::DeadlineReached if context.cli_challenge.is_some() => {
+                    return Err(format!("Canary could not solve fixed challenge {} before its submission deadline.", challenge_params.challenge_id));
+                }
+                MiningResult::DeadlineReached | MiningResult::ChallengeSuperseded => {
+                    println!("🐤 Canary will verify the release on the next challenge.");
+                    continue 'challenge;
+                }
+                MiningResult::MiningFailed | MiningResult::SliceExpired => {
+                    eprintln!("⚠️ Canary mining cycle failed. Retrying in 1 minute...");
+                    std::thread::sleep(std::time::Duration::from_secs(60));
+                }
+            }
+        }
+    };
+
+    // --- Wait for the submitter's submission to be credited ---
+    println!("🐤 Canary solution queued. Waiting for the API to credit {}...", canary_address);
+    let started = std::time::Instant::now();
+    loop {
+        std::thread::sleep(std::time::Duration::from_secs(CANARY_POLL_SECS));
+        match api::block_on(api::fetch_statistics(context.wallet_clients.for_wallet(&canary_address), &context.api_url, &canary_address)) {
+            Ok(stats) if stats.crypto_receipts > receipts_before => break,
+            Ok(_) => {},
+            Err(e) => eprintln!("⚠️ Canary statistics check failed: {}", e),
+        }
+        if started.elapsed().as_secs() >= CANARY_CREDIT_TIMEOUT_SECS {
+            context.notifications.send(NotifyEvent::SubmissionFailed {
+                address: canary_address.clone(),
+                challenge_id: challenge_params.challenge_id.clone(),
+                error: "canary solution not credited".to_string(),
+            });
+            return Err(format!(
+                "Canary solution for {} was not credited within {} minutes. Refusing to mine with the other wallets on release {}.",
+                canary_address, CANARY_CREDIT_TIMEOUT_SECS / 60, version
+            ));
+        }
+    }
+
+    if let Some(destination_address) = context.donate_to_option {
+        let donation_message = format!("Assign accumulated Scavenger rights to: {}", destination_address);
+        let donation_signature = cardano::cip8_sign(&key_pair, &donation_message);
+        match donate(context, &canary_address, destination_address, &donation_signature.0) {
+            Ok(id) => println!("🚀 Donation initiated successfully. ID: {}", id),
+            Err(e) => eprintln!("⚠️ Donation failed (synchronous attempt): {}", e),
+        }
+    }
+
+    save_canary_record(base_dir, &CanaryRecord {
+        version: version.to_string(),
+        address: canary_address.clone(),
+        challenge_id: challenge_params.challenge_id.clone(),
+        verified_at: chrono::Utc::now().to_rfc3339(),
+    })?;
+    println!("✅ CANARY: solution credited to {}. Release {} verified, unlocking the other wallets.", canary_address, version);
+    Ok(())
+}
+
+
+// How often to look for a new challenge once '--stop-pool-after' is reached
+const POOL_CAP_POLL_SECS: u64 = 60;
 
 /// MODE B: Mnemonic Sequential Mining
-pub fn run_mnemonic_sequential_mining(cli: &Cli, context: MiningContext, mnemonic_phrase: String) -> Result<(), String> {
+pub fn run_mnemonic_sequential_mining(cli: &Cli, mut context: MiningContext, mnemonic_phrase: String) -> Result<(), String> {
     let reg_message = context.tc_response.message.clone();
     let mut wallet_deriv_index: u32 = 0;
     let mut first_run = true;
@@ -169,11 +451,39 @@ pub fn run_mnemonic_sequential_mining(cli: &Cli, context: MiningContext, mnemoni
     let mut last_seen_challenge_id = String::new();
     let mut current_challenge_id = String::new();
     let mut last_active_challenge_data: Option<ChallengeData> = None;
+    let mut challenge_summary: Option<ChallengeSummary> = None;
+    let mut stagger = Stagger::new(context.stagger_secs);
+    // '--time-slice': wallets whose slice ran out, waiting for their next turn
+    let time_slice = cli.time_slice;
+    let mut rotation: VecDeque<u32> = VecDeque::new();
+    // '--stop-pool-after': wallets holding a solution for the current challenge
+    let mut pool_solutions: usize = 0;
+    let mut pool_cap_announced = false;
+    // '--deadline-margin': the current challenge is closing, remaining wallets are skipped
+    let mut deadline_announced = false;
+    // Solutions queued so far by the index at wallet_deriv_index ('--solutions-per-challenge')
+    let mut index_solutions: u32 = 0;
 
     println!("\n==============================================");
     println!("⛏️  Shadow Harvester: MNEMONIC SEQUENTIAL MINING Mode ({})", if context.cli_challenge.is_some() { "FIXED CHALLENGE" } else { "DYNAMIC POLLING" });
     println!("==============================================");
-    if context.donate_to_option.is_some() { println!("Donation Target: {}", context.donate_to_option.unwrap()); }
+    print_donation_policy(context.donate_to_option);
+
+    // Move directories named with the legacy mnemonic hash to the opaque identifier
+    if let Some(base_dir) = context.data_dir {
+        let migrated = migrate_legacy_mnemonic_dirs(base_dir, &mnemonic_phrase)?;
+        if migrated > 0 {
+            println!("🔒 Migrated {} mnemonic data directories to opaque identifiers.", migrated);
+        }
+    }
+
+    if let Some(canary_index) = cli.canary {
+        run_canary(cli, &context, &mnemonic_phrase, canary_index)?;
+    }
+    let keys = cardano::KeyCache::new(&mnemonic_phrase, cli.mnemonic_account, cli.prederive_keys);
+    if let Some(slice) = cli.time_slice {
+        println!("⏱️ Time slicing: rotating {} wallets, {} each.", cli.time_slice_wallets, format_duration(slice.as_secs_f64()));
+    }
 
     loop {
         // --- 1. Challenge Discovery and Initial Index Reset ---
@@ -193,6 +503,14 @@ pub fn run_mnemonic_sequential_mining(cli: &Cli, context: MiningContext, mnemoni
 
                     // FIX: Take the maximum of the index derived from receipts and the CLI starting index.
                     wallet_deriv_index = next_index_from_receipts.max(cli.mnemonic_starting_index);
+                    rotation.clear();
+                    index_solutions = 0;
+                    pool_solutions = match &cli.data_dir {
+                        Some(base_dir) => count_mnemonic_receipts_for_challenge(base_dir, &params.challenge_id, &DataDirMnemonic { mnemonic: &mnemonic_phrase, account: cli.mnemonic_account, deriv_index: 0 })?,
+                        None => 0,
+                    };
+                    pool_cap_announced = false;
+                    deadline_announced = false;
                 }
                 last_seen_challenge_id = params.challenge_id.clone();
                 params
@@ -200,12 +518,17 @@ pub fn run_mnemonic_sequential_mining(cli: &Cli, context: MiningContext, mnemoni
             Ok(None) => { backoff_challenge.reset(); continue; },
             Err(e) => {
                 // If a challenge ID is set AND we detect a network failure, continue mining.
-                if !current_challenge_id.is_empty() && e.contains("API request failed") {
+                if !current_challenge_id.is_empty() && e.is_transient() {
                     eprintln!("⚠️ Challenge API poll failed (Network Error): {}. Continuing mining with previous challenge parameters (ID: {})...", e, current_challenge_id);
                     backoff_challenge.reset();
-                    last_active_challenge_data.as_ref().cloned().ok_or_else(|| {
-                        format!("FATAL LOGIC ERROR: Challenge ID {} is set but no previous challenge data was stored.", current_challenge_id)
-                    })?
+                    match utils::resume_challenge(&context, &current_challenge_id, last_active_challenge_data.as_ref()) {
+                        Ok(params) => params,
+                        Err(e) => {
+                            eprintln!("⚠️ Could not resume challenge {}: {}. Retrying with exponential backoff...", current_challenge_id, e);
+                            backoff_challenge.sleep();
+                            continue;
+                        }
+                    }
                 } else {
                     eprintln!("⚠️ Critical API Error during challenge polling: {}. Retrying with exponential backoff...", e);
                     backoff_challenge.sleep();
@@ -214,10 +537,39 @@ pub fn run_mnemonic_sequential_mining(cli: &Cli, context: MiningContext, mnemoni
             }
         };
         first_run = false;
+        roll_challenge_summary(&context, &mut challenge_summary, &challenge_params.challenge_id);
+
+        if let Some(cap) = cli.stop_pool_after.filter(|&cap| pool_solutions >= cap as usize) {
+            if context.cli_challenge.is_some() {
+                println!("\n🏁 {} wallet(s) hold a solution for fixed challenge {} ('--stop-pool-after {}'). Stopping.", pool_solutions, challenge_params.challenge_id, cap);
+                return Ok(());
+            }
+            if !pool_cap_announced {
+                println!("\n🏁 {} wallet(s) hold a solution for challenge {} ('--stop-pool-after {}'). Waiting for the next challenge...", pool_solutions, challenge_params.challenge_id, cap);
+                pool_cap_announced = true;
+            }
+            std::thread::sleep(std::time::Duration::from_secs(POOL_CAP_POLL_SECS));
+            continue;
+        }
+
+        if utils::submission_deadline_near(&challenge_params, &context.control) {
+            if context.cli_challenge.is_some() {
+                println!("\n🏁 Fixed challenge {} is closing for submissions ('--deadline-margin'). Stopping.", challenge_params.challenge_id);
+                return Ok(());
+            }
+            if !deadline_announced {
+                println!("\n⏰ Challenge {} is closing for submissions ('--deadline-margin'). Skipping index {} onwards{}. Waiting for the next challenge...",
+                    challenge_params.challenge_id, wallet_deriv_index,
+                    if rotation.is_empty() { String::new() } else { format!(" and {} wallet(s) in rotation", rotation.len()) });
+                deadline_announced = true;
+            }
+            std::thread::sleep(std::time::Duration::from_secs(POOL_CAP_POLL_SECS));
+            continue;
+        }
 
         // Save challenge details
         let temp_data_dir = DataDir::Mnemonic(DataDirMnemonic { mnemonic: &mnemonic_phrase, account: cli.mnemonic_account, deriv_index: 0 });
-        if let Some(base_dir) = context.data_dir { temp_data_dir.save_challenge(base_dir, &challenge_params)?; }
+        if let Some(store) = context.store.as_deref() { store.save_challenge(&temp_data_dir, &challenge_params)?; }
 
         // --- 2. Continuous Index Skip Check ---
         // This loop ensures we skip indices with existing receipts, even if the index hasn't changed.
@@ -225,44 +577,57 @@ pub fn run_mnemonic_sequential_mining(cli: &Cli, context: MiningContext, mnemoni
             let wallet_config = DataDirMnemonic { mnemonic: &mnemonic_phrase, account: cli.mnemonic_account, deriv_index: wallet_deriv_index };
             let data_dir = DataDir::Mnemonic(wallet_config); // Full DataDir for recovery check
 
+            // An index searching on for more solutions has its own receipts and queued solutions
+            if index_solutions > 0 {
+                break 'skip_check;
+            }
+
             // Get the temporary mining address for this index (needed for queue file lookup/recovery)
-            let mining_address_temp = cardano::derive_key_pair_from_mnemonic(&mnemonic_phrase, cli.mnemonic_account, wallet_deriv_index).2.to_bech32().unwrap();
+            let mining_address_temp = keys.key_pair(wallet_deriv_index).2.to_bech32().unwrap();
 
             // Check for unsubmitted solutions (recovery file or pending queue)
-            if let Some(base_dir) = context.data_dir {
-                if wallet_deriv_index >= cli.mnemonic_starting_index {
-                    // 1. Check for crash recovery file (found.json)
-                    check_for_unsubmitted_solutions(base_dir, &challenge_params.challenge_id, &mining_address_temp, &data_dir)?;
-
-                    // 2. Check if a solution for this address/challenge is already in the pending queue
-                    if is_solution_pending_in_queue(base_dir, &mining_address_temp, &challenge_params.challenge_id)? {
-                        println!("\nℹ️ Index {} has a pending submission in the queue. Skipping and checking next index.", wallet_deriv_index);
-                        wallet_deriv_index = wallet_deriv_index.wrapping_add(1);
-                        continue 'skip_check;
-                    }
+            if let Some(store) = context.store.as_deref()
+                && wallet_deriv_index >= cli.mnemonic_starting_index
+            {
+                // 1. Check for crash recovery file (found.json)
+                check_for_unsubmitted_solutions(store, &challenge_params.challenge_id, &mining_address_temp, &data_dir)?;
+
+                // 2. Check if a solution for this address/challenge is already in the pending queue
+                if context.pending_queue.as_mut().is_some_and(|queue| queue.is_pending(&mining_address_temp, &challenge_params.challenge_id)) {
+                    println!("\nℹ️ Index {} has a pending submission in the queue. Skipping and checking next index.", wallet_deriv_index);
+                    wallet_deriv_index = wallet_deriv_index.wrapping_add(1);
+                    continue 'skip_check;
                 }
             }
 
             // --- Final Receipt Check (Multi-Path Resumption) ---
-            if let Some(base_dir) = context.data_dir {
+            if let Some(store) = context.store.as_deref() {
                 // 1. Check Correct Mnemonic Path (where it should be)
-                if receipt_exists_for_index(base_dir, &challenge_params.challenge_id, &wallet_config)? {
+                if store.receipt_exists(&data_dir, &challenge_params.challenge_id)? {
                     println!("\nℹ️ Index {} already has a local receipt (Mnemonic path). Skipping.", wallet_deriv_index);
                     wallet_deriv_index = wallet_deriv_index.wrapping_add(1);
                     continue 'skip_check;
                 }
 
                 // 2. Check INCORRECT Persistent Path (where submitter currently writes receipts due to heuristic)
-                let mut persistent_path = data_dir.challenge_dir(base_dir, &challenge_params.challenge_id)?;
-                persistent_path.push("persistent");
-                persistent_path.push(&mining_address_temp); // The address derived for this index
-                persistent_path.push(FILE_NAME_RECEIPT);
-
-                if persistent_path.exists() {
+                if store.receipt_exists(&DataDir::Persistent(&mining_address_temp), &challenge_params.challenge_id)? {
                     println!("\n⚠️ Index {} found receipt in Persistent path (Submitter heuristic failure). Skipping.", wallet_deriv_index);
                     wallet_deriv_index = wallet_deriv_index.wrapping_add(1);
                     continue 'skip_check;
                 }
+
+                // 3. Another instance sharing the state ('--state') mines this index. If the claim can't be made
+                // (e.g. Redis is down), the index is mined anyway: the API rejects a second solution.
+                let claimed = wallet_deriv_index < cli.mnemonic_starting_index || store.claim(&mining_address_temp, &challenge_params)
+                    .unwrap_or_else(|e| {
+                        eprintln!("⚠️ Could not claim Index {} in the shared state: {}. Mining it anyway.", wallet_deriv_index, e);
+                        true
+                    });
+                if !claimed {
+                    println!("\nℹ️ Index {} is being mined by another instance. Skipping.", wallet_deriv_index);
+                    wallet_deriv_index = wallet_deriv_index.wrapping_add(1);
+                    continue 'skip_check;
+                }
             }
 
             // If none of the above conditions met, we break and mine.
@@ -270,62 +635,125 @@ pub fn run_mnemonic_sequential_mining(cli: &Cli, context: MiningContext, mnemoni
         }
 
         // --- 3. Key Generation, Registration, and Mining ---
-        let key_pair = cardano::derive_key_pair_from_mnemonic(&mnemonic_phrase, cli.mnemonic_account, wallet_deriv_index);
+        // With a full rotation, the wallet waiting the longest gets the next slice instead of a new index
+        let rotated_index = match cli.time_slice_wallets {
+            n if time_slice.is_some() && rotation.len() >= n as usize => rotation.pop_front(),
+            _ => None,
+        };
+        let mined_index = rotated_index.unwrap_or(wallet_deriv_index);
+        // Rotating wallets and the next new index are mined again; anything below them isn't
+        keys.forget_below(rotation.iter().copied().chain([mined_index, wallet_deriv_index]).min().unwrap());
+        let key_pair = keys.key_pair(mined_index);
         let mining_address = key_pair.2.to_bech32().unwrap();
 
-        println!("\n[CYCLE START] Deriving Address Index {}: {}", wallet_deriv_index, mining_address);
-        if match max_registered_index { Some(idx) => wallet_deriv_index > idx, None => true } {
-            let stats_result = api::fetch_statistics(&context.client, &context.api_url, &mining_address);
+        stagger.wait(&challenge_params.challenge_id, &mining_address);
+        println!("\n[CYCLE START] Deriving Address Index {}: {}", mined_index, mining_address);
+        let mut night_before: Option<u32> = None;
+        if match max_registered_index { Some(idx) => mined_index > idx, None => true } {
+            let stats_result = api::block_on(api::fetch_statistics(context.wallet_clients.for_wallet(&mining_address), &context.api_url, &mining_address));
             match stats_result {
-                Ok(stats) => { println!("  Crypto Receipts (Solutions): {}", stats.crypto_receipts); println!("  Night Allocation: {}", stats.night_allocation); }
+                Ok(stats) => { night_before = Some(stats.night_allocation); println!("  Crypto Receipts (Solutions): {}", utils::format_thousands(stats.crypto_receipts as u64)); println!("  Night Allocation: {}", utils::format_thousands(stats.night_allocation as u64)); }
                 Err(_) => {
                     let reg_signature = cardano::cip8_sign(&key_pair, &reg_message);
-                    if let Err(e) = api::register_address(&context.client, &context.api_url, &mining_address, &reg_message, &reg_signature.0, &hex::encode(key_pair.1.as_ref())) {
+                    if let Err(e) = register(&context, &mining_address, &reg_signature.0, &hex::encode(key_pair.1.as_ref())) {
                         eprintln!("Registration failed: {}. Retrying with exponential backoff...", e); backoff_reg.sleep(); continue;
                     }
                 }
             }
-            max_registered_index = Some(wallet_deriv_index); backoff_reg.reset();
+            max_registered_index = Some(mined_index); backoff_reg.reset();
         }
 
         print_mining_setup(&context.api_url, Some(mining_address.as_str()), context.threads, &challenge_params);
+        snapshot_wallet_start(&context, &mut challenge_summary, &mining_address);
 
+        context.control.start_time_slice(time_slice);
         // UPDATED CALL: Removed client and api_url
         let (result, total_hashes, elapsed_secs) = run_single_mining_cycle(
-            mining_address.clone(), context.threads, context.donate_to_option, &challenge_params, context.data_dir,
+            mining_address.clone(), context.threads, context.donate_to_option, &challenge_params, context.store.as_deref(), &context.throttle, &context.control, context.nonce_partition,
+            rotated_index.is_none() && index_solutions + 1 < context.solutions_per_challenge,
         );
 
+        context.control.start_time_slice(None);
+        let outcome = format!("{:?}", result);
+
         // --- 4. Post-Mining Index Advancement ---
         match result {
             MiningResult::FoundAndQueued => {
-                if let Some(ref destination_address) = context.donate_to_option {
+                if rotated_index.is_none() {
+                    index_solutions += 1;
+                }
+                // The pool cap counts wallets, so only a wallet's first solution adds to it
+                let first_solution = rotated_index.is_some() || index_solutions == 1;
+                if first_solution {
+                    pool_solutions += 1;
+                }
+                if let Some(destination_address) = context.donate_to_option.filter(|_| first_solution) {
                     // key_pair is available locally in this loop scope
                     let donation_message = format!("Assign accumulated Scavenger rights to: {}", destination_address);
                     let donation_signature = cardano::cip8_sign(&key_pair, &donation_message);
 
                     // Attempt donation synchronously. Ignore result here to keep the main flow clean.
-                    match api::donate_to(
-                        &context.client, &context.api_url, &mining_address, destination_address, &donation_signature.0,
+                    match donate(
+                        &context, &mining_address, destination_address, &donation_signature.0,
                     ) {
                         Ok(id) => println!("🚀 Donation initiated successfully. ID: {}", id),
                         Err(e) => eprintln!("⚠️ Donation failed (synchronous attempt): {}", e),
                     }
                 }
 
-                wallet_deriv_index = wallet_deriv_index.wrapping_add(1);
-                println!("\n✅ Solution queued. Incrementing index to {}.", wallet_deriv_index);
+                if rotated_index.is_none() && index_solutions < context.solutions_per_challenge {
+                    println!("\n✅ Solution {} of {} queued. Index {} searches on.", index_solutions, context.solutions_per_challenge, mined_index);
+                } else {
+                    if rotated_index.is_none() {
+                        wallet_deriv_index = wallet_deriv_index.wrapping_add(1);
+                        index_solutions = 0;
+                    }
+                    println!("\n✅ Solution queued. Next new index is {}.", wallet_deriv_index);
+                }
             },
             MiningResult::AlreadySolved => {
                 // This scenario means the submitter/API reported it was already solved
-                wallet_deriv_index = wallet_deriv_index.wrapping_add(1);
-                println!("\n✅ Challenge already solved. Incrementing index to {}.", wallet_deriv_index);
+                if rotated_index.is_none() {
+                    wallet_deriv_index = wallet_deriv_index.wrapping_add(1);
+                    index_solutions = 0;
+                }
+                println!("\n✅ Challenge already solved. Next new index is {}.", wallet_deriv_index);
             }
             MiningResult::MiningFailed => {
-                eprintln!("\n⚠️ Mining cycle failed. Retrying with the SAME index {}.", wallet_deriv_index);
+                eprintln!("\n⚠️ Mining cycle failed. Retrying with the SAME index {}.", mined_index);
+                if rotated_index.is_some() {
+                    rotation.push_front(mined_index);
+                }
+            }
+            // The deadline check at the top of the loop skips the remaining wallets; a superseded wallet mines the new challenge
+            MiningResult::DeadlineReached | MiningResult::ChallengeSuperseded => {
+                if rotated_index.is_some() {
+                    rotation.push_front(mined_index);
+                }
             }
+            MiningResult::SliceExpired => {
+                if rotated_index.is_none() {
+                    wallet_deriv_index = wallet_deriv_index.wrapping_add(1);
+                    index_solutions = 0;
+                }
+                rotation.push_back(mined_index);
+                println!("\n⏱️ Index {} used its time slice. {} wallet(s) in rotation.", mined_index, rotation.len());
+            }
+        }
+        let stats_result = api::block_on(api::fetch_statistics(context.wallet_clients.for_wallet(&mining_address), &context.api_url, &mining_address));
+        if let Some(summary) = challenge_summary.as_mut() {
+            let night_after = stats_result.as_ref().ok().map(|s| s.night_allocation);
+            summary.record(WalletOutcome {
+                address: mining_address.clone(),
+                deriv_index: Some(mined_index),
+                outcome,
+                hashes: total_hashes,
+                elapsed_secs,
+                night_allocation: night_after,
+                night_delta: night_before.zip(night_after).map(|(before, after)| after as i64 - before as i64),
+            });
         }
-        let stats_result = api::fetch_statistics(&context.client, &context.api_url, &mining_address);
-        print_statistics(stats_result, total_hashes, elapsed_secs);
+        print_statistics(stats_result, total_hashes, elapsed_secs, &context.control.thread_hashes());
     }
 }
 
@@ -335,56 +763,86 @@ pub fn run_ephemeral_key_mining(context: MiningContext) -> Result<(), String> {
     println!("\n==============================================");
     println!("⛏️  Shadow Harvester: EPHEMERAL KEY MINING Mode ({})", if context.cli_challenge.is_some() { "FIXED CHALLENGE" } else { "DYNAMIC POLLING" });
     println!("==============================================");
-    if context.donate_to_option.is_some() { println!("Donation Target: {}", context.donate_to_option.unwrap()); }
+    print_donation_policy(context.donate_to_option);
 
     let mut final_hashes: u64 = 0;
     let mut final_elapsed: f64 = 0.0;
     let mut current_challenge_id = String::new();
     let mut last_active_challenge_data: Option<ChallengeData> = None;
+    let mut challenge_summary: Option<ChallengeSummary> = None;
+    let mut stagger = Stagger::new(context.stagger_secs);
+    // Every '--api-url' endpoint failed by the time an error gets here
+    let mut backoff_challenge = Backoff::new(5, 300, 2.0);
+    let mut backoff_reg = Backoff::new(5, 300, 2.0);
 
     loop {
         let challenge_params: ChallengeData = match utils::get_challenge_params(&context.client, &context.api_url, context.cli_challenge, &mut current_challenge_id) {
             Ok(Some(p)) => {
+                backoff_challenge.reset();
                 last_active_challenge_data = Some(p.clone());
                 p
             },
             Ok(None) => continue,
             Err(e) => {
                 // If a challenge ID is set AND we detect a network failure, continue mining.
-                if !current_challenge_id.is_empty() && e.contains("API request failed") {
+                if !current_challenge_id.is_empty() && e.is_transient() {
                     eprintln!("⚠️ Challenge API poll failed (Network Error): {}. Continuing mining with previous challenge parameters (ID: {})...", e, current_challenge_id);
-                    last_active_challenge_data.as_ref().cloned().ok_or_else(|| {
-                        format!("FATAL LOGIC ERROR: Challenge ID {} is set but no previous challenge data was stored.", current_challenge_id)
-                    })?
+                    match utils::resume_challenge(&context, &current_challenge_id, last_active_challenge_data.as_ref()) {
+                        Ok(params) => params,
+                        Err(e) => {
+                            eprintln!("⚠️ Could not resume challenge {}: {}. Retrying with exponential backoff...", current_challenge_id, e);
+                            backoff_challenge.sleep();
+                            continue;
+                        }
+                    }
                 } else {
-                    eprintln!("⚠️ Could not fetch active challenge (Ephemeral Key Mode): {}. Retrying in 5 minutes...", e);
-                    std::thread::sleep(std::time::Duration::from_secs(5 * 60));
+                    eprintln!("⚠️ Could not fetch active challenge (Ephemeral Key Mode): {}. Retrying with exponential backoff...", e);
+                    backoff_challenge.sleep();
                     continue;
                 }
             }
         };
 
+        roll_challenge_summary(&context, &mut challenge_summary, &challenge_params.challenge_id);
+
         let key_pair = cardano::generate_cardano_key_and_address();
         let generated_mining_address = key_pair.2.to_bech32().unwrap();
+        stagger.wait(&challenge_params.challenge_id, &generated_mining_address);
         let data_dir = DataDir::Ephemeral(&generated_mining_address);
 
-        if let Some(base_dir) = context.data_dir { data_dir.save_challenge(base_dir, &challenge_params)?; }
+        if let Some(store) = context.store.as_deref() { store.save_challenge(&data_dir, &challenge_params)?; }
         println!("\n[CYCLE START] Generated Address: {}", generated_mining_address);
 
         let reg_message = context.tc_response.message.clone();
         let reg_signature = cardano::cip8_sign(&key_pair, &reg_message);
 
-        if let Err(e) = api::register_address(&context.client, &context.api_url, &generated_mining_address, &context.tc_response.message, &reg_signature.0, &hex::encode(key_pair.1.as_ref())) {
-            eprintln!("Registration failed: {}. Retrying in 5 minutes...", e); std::thread::sleep(std::time::Duration::from_secs(5 * 60)); continue;
+        if let Err(e) = register(&context, &generated_mining_address, &reg_signature.0, &hex::encode(key_pair.1.as_ref())) {
+            eprintln!("Registration failed: {}. Retrying with exponential backoff...", e); backoff_reg.sleep(); continue;
         }
+        backoff_reg.reset();
 
         print_mining_setup(&context.api_url, Some(&generated_mining_address.to_string()), context.threads, &challenge_params);
+        snapshot_wallet_start(&context, &mut challenge_summary, &generated_mining_address);
 
-        // UPDATED CALL: Removed client and api_url
-        let (result, total_hashes, elapsed_secs) = run_single_mining_cycle(
-                generated_mining_address.to_string(), context.threads, context.donate_to_option, &challenge_params, context.data_dir,
-            );
-        final_hashes = total_hashes; final_elapsed = elapsed_secs;
+        // The generated address searches on until it has '--solutions-per-challenge' solutions
+        let mut solutions: u32 = 0;
+        let result = loop {
+            // UPDATED CALL: Removed client and api_url
+            let (result, total_hashes, elapsed_secs) = run_single_mining_cycle(
+                    generated_mining_address.to_string(), context.threads, context.donate_to_option, &challenge_params, context.store.as_deref(), &context.throttle, &context.control, context.nonce_partition,
+                    solutions + 1 < context.solutions_per_challenge,
+                );
+            final_hashes = total_hashes; final_elapsed = elapsed_secs;
+            if let MiningResult::FoundAndQueued = result {
+                solutions += 1;
+                if solutions < context.solutions_per_challenge {
+                    println!("\n✅ Solution {} of {} queued. Searching on for the next one.", solutions, context.solutions_per_challenge);
+                    continue;
+                }
+            }
+            break result;
+        };
+        let outcome = format!("{:?}", result);
 
         match result {
             MiningResult::FoundAndQueued => {
@@ -394,8 +852,8 @@ pub fn run_ephemeral_key_mining(context: MiningContext) -> Result<(), String> {
                     let donation_signature = cardano::cip8_sign(&key_pair, &donation_message);
 
                     // Attempt donation synchronously. Ignore result here to keep the main thread fast.
-                    match api::donate_to(
-                        &context.client, &context.api_url, &generated_mining_address, destination_address, &donation_signature.0,
+                    match donate(
+                        &context, &generated_mining_address, destination_address, &donation_signature.0,
                     ) {
                         Ok(id) => println!("🚀 Donation initiated successfully. ID: {}", id),
                         Err(e) => eprintln!("⚠️ Donation failed (synchronous attempt): {}", e),
@@ -404,11 +862,30 @@ pub fn run_ephemeral_key_mining(context: MiningContext) -> Result<(), String> {
                 eprintln!("Solution queued. Starting next cycle immediately...");
             }
             MiningResult::AlreadySolved => { eprintln!("Solution was already accepted by the network. Starting next cycle immediately..."); }
-            MiningResult::MiningFailed => { eprintln!("Mining cycle failed. Retrying next cycle in 1 minute..."); std::thread::sleep(std::time::Duration::from_secs(60)); }
+            MiningResult::DeadlineReached => {
+                if context.cli_challenge.is_some() {
+                    println!("🏁 Fixed challenge {} is closing for submissions. Stopping.", challenge_params.challenge_id);
+                    return Ok(());
+                }
+                eprintln!("Challenge is closing for submissions. Waiting for the next challenge...");
+            }
+            MiningResult::ChallengeSuperseded => { eprintln!("Starting the next cycle on the new challenge..."); }
+            MiningResult::MiningFailed | MiningResult::SliceExpired => { eprintln!("Mining cycle failed. Retrying next cycle in 1 minute..."); std::thread::sleep(std::time::Duration::from_secs(60)); }
         }
 
-        let stats_result = api::fetch_statistics(&context.client, &context.api_url, &generated_mining_address);
-        print_statistics(stats_result, final_hashes, final_elapsed);
+        let stats_result = api::block_on(api::fetch_statistics(context.wallet_clients.for_wallet(&generated_mining_address), &context.api_url, &generated_mining_address));
+        if let Some(summary) = challenge_summary.as_mut() {
+            summary.record(WalletOutcome {
+                address: generated_mining_address.clone(),
+                deriv_index: None,
+                outcome,
+                hashes: final_hashes,
+                elapsed_secs: final_elapsed,
+                night_allocation: stats_result.as_ref().ok().map(|s| s.night_allocation),
+                night_delta: None,
+            });
+        }
+        print_statistics(stats_result, final_hashes, final_elapsed, &context.control.thread_hashes());
         println!("\n[CYCLE END] Starting next mining cycle immediately...");
     }
 }
@@ -0,0 +1,283 @@
+// src/mock_api.rs
+
+// '--mock-api <scenario.json>': serves a canned Scavenger Mine API from 127.0.0.1, so the mining loops and
+// the submitter can run end to end in CI or during development without the real API. The scenario lists
+// the challenges to publish one after another, from startup, and optionally the responses of the other
+// routes. Each route's responses are served in turn, the last one repeating; routes left out answer
+// like a healthy API. Every key is optional:
+//
+//   {
+//     "challenges": [{ "difficulty": "FFFFFFFF", "duration_secs": 600 }],
+//     "responses": {
+//       "register": [{ "status": 500 }, { "status": 201 }],
+//       "solution": [{ "status": 429, "retry_after": 5 }, { "status": 200 }]
+//     }
+//   }
+//
+// Challenges take `challenge_id`, `difficulty`, `no_pre_mine`, `no_pre_mine_hour` and `duration_secs`.
+// Routes are `tandc`, `register`, `statistics`, `solution` and `donate_to`; a response has a `status`, an
+// optional JSON `body` (a success without one gets the healthy API's) and an optional `retry_after` in
+// seconds. Default statistics count the solutions the mock accepted from the address. GET /challenges lists
+// the challenges published so far, paginated, with the solutions the mock accepted for each, and GET
+// /work_to_star_rate a rate for each day so far.
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT_SECS: u64 = 5;
+const DEFAULT_DIFFICULTY: &str = "FFFFFFFF";
+const DEFAULT_NO_PRE_MINE: &str = "e8a195800b1b3f0b2a5d8c3b1f6d4e9a7c2b5d8e1f4a7c0b3d6e9f2a5c8b1e4d";
+const DEFAULT_DURATION_SECS: u64 = 10 * 60;
+const MOCK_STAR_RATE: u64 = 2_500_000;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    #[serde(default)]
+    challenges: Vec<MockChallenge>,
+    #[serde(default)]
+    responses: HashMap<String, Vec<MockResponse>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MockChallenge {
+    challenge_id: Option<String>,
+    difficulty: Option<String>,
+    no_pre_mine: Option<String>,
+    no_pre_mine_hour: Option<String>,
+    duration_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MockResponse {
+    status: u16,
+    body: Option<Value>,
+    retry_after: Option<u64>,
+}
+
+const ROUTES: [&str; 5] = ["tandc", "register", "statistics", "solution", "donate_to"];
+
+struct MockApi {
+    scenario: Scenario,
+    started: DateTime<Utc>,
+    // How many requests each route has answered
+    served: Mutex<HashMap<&'static str, usize>>,
+    // Solutions accepted per address
+    accepted: Mutex<HashMap<String, u32>>,
+    // Solutions accepted per challenge
+    solved: Mutex<HashMap<String, u64>>,
+}
+
+/// Serves the scenario at `path` on a free local port and returns the API URL to use.
+pub fn spawn_mock_api(path: &str) -> Result<String, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Could not read mock API scenario {}: {}", path, e))?;
+    let mut scenario: Scenario = serde_json::from_str(&text).map_err(|e| format!("Invalid mock API scenario {}: {}", path, e))?;
+    if let Some(route) = scenario.responses.keys().find(|route| !ROUTES.contains(&route.as_str())) {
+        return Err(format!("Invalid mock API scenario {}: unknown route '{}' (expected one of {}).", path, route, ROUTES.join(", ")));
+    }
+    if scenario.challenges.is_empty() {
+        scenario.challenges.push(MockChallenge::default());
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| format!("Could not start the mock API: {}", e))?;
+    let api_url = format!("http://{}", listener.local_addr().map_err(|e| format!("Could not start the mock API: {}", e))?);
+    println!("🧪 Mock API serving {} ({} challenge(s)) on {}.", path, scenario.challenges.len(), api_url);
+
+    let mock = Arc::new(MockApi { scenario, started: Utc::now(), served: Mutex::new(HashMap::new()), accepted: Mutex::new(HashMap::new()), solved: Mutex::new(HashMap::new()) });
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let mock = mock.clone();
+                    thread::spawn(move || mock.serve(stream));
+                },
+                Err(e) => eprintln!("⚠️ Mock API connection failed: {}", e),
+            }
+        }
+    });
+    Ok(api_url)
+}
+
+impl MockApi {
+    /// GET /challenge as the real API answers it at this moment of the scenario.
+    fn challenge_status(&self) -> Value {
+        let mut start = self.started;
+        let last_day = self.scenario.challenges.len().div_ceil(24);
+        let period_ends: DateTime<Utc> = self.started + self.scenario.challenges.iter()
+            .map(|challenge| chrono::Duration::seconds(challenge.duration_secs.unwrap_or(DEFAULT_DURATION_SECS) as i64))
+            .sum::<chrono::Duration>();
+        for (index, challenge) in self.scenario.challenges.iter().enumerate() {
+            let end = start + chrono::Duration::seconds(challenge.duration_secs.unwrap_or(DEFAULT_DURATION_SECS) as i64);
+            if Utc::now() < end {
+                let (number, day) = (index + 1, index / 24 + 1);
+                return json!({
+                    "code": "active",
+                    "challenge": {
+                        "challenge_id": challenge.challenge_id.clone().unwrap_or_else(|| format!("**D{:02}C{:02}", day, index % 24 + 1)),
+                        "difficulty": challenge.difficulty.as_deref().unwrap_or(DEFAULT_DIFFICULTY),
+                        "no_pre_mine": challenge.no_pre_mine.as_deref().unwrap_or(DEFAULT_NO_PRE_MINE),
+                        "no_pre_mine_hour": challenge.no_pre_mine_hour.as_deref().unwrap_or("1"),
+                        "latest_submission": end.to_rfc3339(),
+                        "challenge_number": number,
+                        "day": day,
+                        "issued_at": start.to_rfc3339(),
+                    },
+                    "mining_period_ends": period_ends.to_rfc3339(),
+                    "max_day": last_day,
+                    "total_challenges": self.scenario.challenges.len(),
+                    "current_day": day,
+                    "next_challenge_starts_at": end.to_rfc3339(),
+                });
+            }
+            start = end;
+        }
+        json!({ "code": "after", "mining_period_ends": period_ends.to_rfc3339(), "max_day": last_day, "current_day": last_day })
+    }
+
+    /// GET /challenges: a page of the challenges published so far, oldest first.
+    fn challenge_history(&self, query: &str) -> Value {
+        let param = |name: &str| query.split('&').find_map(|pair| pair.strip_prefix(name)?.strip_prefix('=')?.parse::<usize>().ok());
+        let (page, limit) = (param("page").unwrap_or(1).max(1), param("limit").unwrap_or(100).max(1));
+        let solved = self.solved.lock().unwrap_or_else(|e| e.into_inner());
+        let mut published = Vec::new();
+        let mut start = self.started;
+        for (index, challenge) in self.scenario.challenges.iter().enumerate() {
+            if start > Utc::now() {
+                break;
+            }
+            let end = start + chrono::Duration::seconds(challenge.duration_secs.unwrap_or(DEFAULT_DURATION_SECS) as i64);
+            let challenge_id = challenge.challenge_id.clone().unwrap_or_else(|| format!("**D{:02}C{:02}", index / 24 + 1, index % 24 + 1));
+            published.push(json!({
+                "challenge_id": challenge_id,
+                "challenge_number": index + 1,
+                "day": index / 24 + 1,
+                "difficulty": challenge.difficulty.as_deref().unwrap_or(DEFAULT_DIFFICULTY),
+                "issued_at": start.to_rfc3339(),
+                "latest_submission": end.to_rfc3339(),
+                "solutions": solved.get(&challenge_id).copied().unwrap_or(0),
+            }));
+            start = end;
+        }
+        let total_pages = published.len().div_ceil(limit).max(1);
+        let challenges: Vec<Value> = published.into_iter().skip((page - 1) * limit).take(limit).collect();
+        json!({ "challenges": challenges, "page": page, "total_pages": total_pages })
+    }
+
+    /// The scenario's next response for `route`, or what a healthy API answers.
+    fn respond(&self, route: &'static str, segments: &[&str]) -> MockResponse {
+        let served = {
+            let mut served = self.served.lock().unwrap_or_else(|e| e.into_inner());
+            let count = served.entry(route).or_insert(0);
+            *count += 1;
+            *count - 1
+        };
+        let mut response = match self.scenario.responses.get(route).filter(|responses| !responses.is_empty()) {
+            Some(responses) => responses[served.min(responses.len() - 1)].clone(),
+            None => MockResponse { status: if route == "register" { 201 } else { 200 }, body: None, retry_after: None },
+        };
+        if (200..300).contains(&response.status) {
+            if route == "solution" {
+                self.accept(segments);
+            }
+            if response.body.is_none() {
+                response.body = Some(self.healthy_body(route, segments, served));
+            }
+        }
+        response
+    }
+
+    /// What the real API answers a successful call on `route` with.
+    fn healthy_body(&self, route: &str, segments: &[&str], served: usize) -> Value {
+        match route {
+            "tandc" => json!({ "version": "1-0", "content": "Mock API terms. Nothing mined here counts.", "message": "I agree to abide by the terms and conditions as described in version 1-0 of the Midnight scavenger mining process" }),
+            "register" => json!({ "registrationReceipt": { "mock": true, "address": segments.first() } }),
+            "statistics" => {
+                let receipts = segments.first().map_or(0, |address| self.accepted.lock().unwrap_or_else(|e| e.into_inner()).get(*address).copied().unwrap_or(0));
+                json!({
+                    "global": { "wallets": 1, "challenges": self.scenario.challenges.len(), "total_challenges": self.scenario.challenges.len(), "total_crypto_receipts": receipts, "recent_crypto_receipts": receipts },
+                    "local": { "crypto_receipts": receipts, "night_allocation": receipts * 1000 },
+                })
+            },
+            "solution" => json!({ "crypto_receipt": { "mock": true, "address": segments.first(), "challenge_id": segments.get(1), "nonce": segments.get(2) } }),
+            _ => json!({ "status": "success", "donation_id": format!("mock-{}", served + 1) }),
+        }
+    }
+
+    fn accept(&self, segments: &[&str]) {
+        if let Some(address) = segments.first() {
+            *self.accepted.lock().unwrap_or_else(|e| e.into_inner()).entry(address.to_string()).or_insert(0) += 1;
+        }
+        if let Some(challenge_id) = segments.get(1) {
+            *self.solved.lock().unwrap_or_else(|e| e.into_inner()).entry(challenge_id.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    fn serve(&self, mut stream: TcpStream) {
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(REQUEST_TIMEOUT_SECS)));
+        let Ok(reader) = stream.try_clone() else { return };
+        let mut reader = BufReader::new(reader);
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).is_err() {
+            return;
+        }
+        // Drain the headers and body so the client sees the whole request read
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).is_err() || header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':')
+                && name.trim().eq_ignore_ascii_case("content-length")
+            {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+        let _ = reader.by_ref().take(content_length).read_to_end(&mut Vec::new());
+
+        let mut parts = request_line.split_whitespace();
+        let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        let route = match (method, segments[0]) {
+            ("GET", "challenge") | ("GET", "challenges") | ("GET", "work_to_star_rate") => None,
+            ("GET", "TandC") => Some("tandc"),
+            ("GET", "statistics") => Some("statistics"),
+            ("POST", "register") => Some("register"),
+            ("POST", "solution") => Some("solution"),
+            ("POST", "donate_to") => Some("donate_to"),
+            _ => {
+                let _ = write!(stream, "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: 21\r\nConnection: close\r\n\r\n{{\"message\":\"no mock\"}}");
+                return;
+            },
+        };
+        let response = match route {
+            None if segments[0] == "challenges" => MockResponse { status: 200, body: Some(self.challenge_history(query)), retry_after: None },
+            None if segments[0] == "work_to_star_rate" => {
+                let days = (Utc::now() - self.started).num_days() as usize + 1;
+                MockResponse { status: 200, body: Some(json!(vec![MOCK_STAR_RATE; days])), retry_after: None }
+            },
+            None => MockResponse { status: 200, body: Some(self.challenge_status()), retry_after: None },
+            Some(route) => self.respond(route, &segments[1..]),
+        };
+        // Signatures make full paths unreadable
+        println!("🧪 Mock API: {} /{} -> {}", method, segments.iter().take(2).copied().collect::<Vec<_>>().join("/"), response.status);
+
+        let body = response.body.map(|body| body.to_string()).unwrap_or_else(|| match response.status {
+            200..=299 => "{}".to_string(),
+            status => json!({ "message": format!("Mock API status {}", status), "statusCode": status }).to_string(),
+        });
+        let retry_after = response.retry_after.map(|secs| format!("Retry-After: {}\r\n", secs)).unwrap_or_default();
+        let _ = write!(stream, "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+            response.status, body.len(), retry_after, body);
+    }
+}
@@ -0,0 +1,323 @@
+// src/notify.rs
+
+use crate::api;
+use crate::config::{NotifierBackend, NotifierConfig};
+use crate::data_types::ChallengeSummary;
+use crate::webhook;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const NOTIFICATION_TITLE: &str = "Shadow Harvester";
+// Repeats of a warn/critical event within this window are suppressed per sink (e.g. a flapping API)
+pub const DEFAULT_ALERT_COOLDOWN_SECS: u64 = 30 * 60;
+
+// ===============================================
+// EVENTS
+// ===============================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warn,
+    Critical,
+}
+
+/// Events published by the miner and the submitter, fanned out to every subscribed sink.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum NotifyEvent {
+    ChallengeSummary(ChallengeSummary),
+    SolutionSubmitted { address: String, challenge_id: String },
+    SubmissionFailed { address: String, challenge_id: String, error: String },
+    SubmitterStopped { error: String },
+}
+
+impl NotifyEvent {
+    /// Event names accepted in a sink's `events` filter.
+    pub const KINDS: [&'static str; 4] = ["challenge_summary", "solution_submitted", "submission_failed", "submitter_stopped"];
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NotifyEvent::ChallengeSummary(_) => "challenge_summary",
+            NotifyEvent::SolutionSubmitted { .. } => "solution_submitted",
+            NotifyEvent::SubmissionFailed { .. } => "submission_failed",
+            NotifyEvent::SubmitterStopped { .. } => "submitter_stopped",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            NotifyEvent::ChallengeSummary(_) | NotifyEvent::SolutionSubmitted { .. } => Severity::Info,
+            NotifyEvent::SubmissionFailed { .. } => Severity::Warn,
+            NotifyEvent::SubmitterStopped { .. } => Severity::Critical,
+        }
+    }
+
+    /// One-line human readable description, used by sinks that don't take JSON.
+    pub fn message(&self) -> String {
+        match self {
+            NotifyEvent::ChallengeSummary(summary) => format!("Challenge {} finished: {} wallet(s), {} hashes, {} queued, {} credited, {} to follow up{}", summary.challenge_id, summary.wallets.len(), summary.total_hashes, summary.queued_solutions, summary.credited_receipts.map_or("unknown".to_string(), |credited| credited.to_string()), summary.mismatched_wallets(),
+                summary.notes.iter().map(|note| format!("\n📝 {}", note)).collect::<String>()),
+            NotifyEvent::SolutionSubmitted { address, challenge_id } => format!("Solution for {} submitted (challenge {})", address, challenge_id),
+            NotifyEvent::SubmissionFailed { address, challenge_id, error } => format!("Submission for {} failed (challenge {}): {}", address, challenge_id, error),
+            NotifyEvent::SubmitterStopped { error } => format!("Background submitter stopped: {}", error),
+        }
+    }
+}
+
+/// An event as delivered to a sink, with its severity and how many repeats were suppressed before it.
+#[derive(Debug, Serialize)]
+pub struct Alert<'a> {
+    #[serde(flatten)]
+    pub event: &'a NotifyEvent,
+    pub severity: Severity,
+    pub suppressed: u32,
+}
+
+impl Alert<'_> {
+    pub fn message(&self) -> String {
+        if self.suppressed > 0 {
+            format!("{} (+{} similar suppressed)", self.event.message(), self.suppressed)
+        } else {
+            self.event.message()
+        }
+    }
+}
+
+// ===============================================
+// NOTIFIER TRAIT AND SINKS
+// ===============================================
+
+/// A notification backend. Implementations are called from the dispatcher thread, one event at a time.
+pub trait Notifier: Send {
+    fn name(&self) -> String;
+    fn notify(&mut self, alert: &Alert) -> Result<(), String>;
+}
+
+/// POSTs every event as JSON to a URL.
+pub struct WebhookNotifier {
+    client: Client,
+    url: String,
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> String {
+        format!("webhook {}", self.url)
+    }
+
+    fn notify(&mut self, alert: &Alert) -> Result<(), String> {
+        api::block_on(webhook::post_json(&self.client, &self.url, alert))
+    }
+}
+
+/// Shows a desktop notification (notify-send on Linux, osascript on macOS).
+pub struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    fn name(&self) -> String {
+        "desktop".to_string()
+    }
+
+    fn notify(&mut self, alert: &Alert) -> Result<(), String> {
+        let message = alert.message();
+        let mut command = match std::env::consts::OS {
+            "linux" => {
+                let urgency = match alert.severity {
+                    Severity::Info => "low",
+                    Severity::Warn => "normal",
+                    Severity::Critical => "critical",
+                };
+                let mut c = Command::new("notify-send");
+                c.arg("-u").arg(urgency).arg(NOTIFICATION_TITLE).arg(&message);
+                c
+            },
+            "macos" => {
+                let mut c = Command::new("osascript");
+                c.arg("-e").arg(format!("display notification {:?} with title {:?}", message, NOTIFICATION_TITLE));
+                c
+            },
+            os => return Err(format!("Desktop notifications are not supported on {}", os)),
+        };
+
+        let status = command.status().map_err(|e| format!("Could not run desktop notifier: {}", e))?;
+        if !status.success() {
+            return Err(format!("Desktop notifier exited with {}", status));
+        }
+        Ok(())
+    }
+}
+
+/// Runs a shell command per event, with the event JSON on stdin and its kind/message in the environment.
+pub struct CommandNotifier {
+    command: String,
+}
+
+impl Notifier for CommandNotifier {
+    fn name(&self) -> String {
+        format!("command '{}'", self.command)
+    }
+
+    fn notify(&mut self, alert: &Alert) -> Result<(), String> {
+        let event_json = serde_json::to_string(alert)
+            .map_err(|e| format!("Could not serialize event: {}", e))?;
+
+        let mut command = if cfg!(windows) {
+            let mut c = Command::new("cmd");
+            c.arg("/C").arg(&self.command);
+            c
+        } else {
+            let mut c = Command::new("sh");
+            c.arg("-c").arg(&self.command);
+            c
+        };
+
+        let mut child = command
+            .env("SHADOW_HARVESTER_EVENT", alert.event.kind())
+            .env("SHADOW_HARVESTER_SEVERITY", format!("{:?}", alert.severity).to_lowercase())
+            .env("SHADOW_HARVESTER_MESSAGE", alert.message())
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Could not start command: {}", e))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // The command may not read stdin at all, so a broken pipe is not an error
+            let _ = stdin.write_all(event_json.as_bytes());
+        }
+
+        let status = child.wait().map_err(|e| format!("Could not wait for command: {}", e))?;
+        if !status.success() {
+            return Err(format!("Command exited with {}", status));
+        }
+        Ok(())
+    }
+}
+
+// ===============================================
+// DISPATCHER
+// ===============================================
+
+/// A notifier plus its filters: subscribed event kinds (empty means all), minimum severity,
+/// and the cooldown during which repeats of the same warn/critical event are suppressed.
+pub struct Sink {
+    notifier: Box<dyn Notifier>,
+    events: Vec<String>,
+    min_severity: Severity,
+    cooldown: Duration,
+    last_sent: HashMap<&'static str, Instant>,
+    suppressed: HashMap<&'static str, u32>,
+}
+
+impl Sink {
+    pub fn new(notifier: Box<dyn Notifier>, events: Vec<String>, min_severity: Severity, cooldown_secs: u64) -> Self {
+        Self {
+            notifier,
+            events,
+            min_severity,
+            cooldown: Duration::from_secs(cooldown_secs),
+            last_sent: HashMap::new(),
+            suppressed: HashMap::new(),
+        }
+    }
+
+    fn accepts(&self, event: &NotifyEvent) -> bool {
+        event.severity() >= self.min_severity
+            && (self.events.is_empty() || self.events.iter().any(|e| e == event.kind()))
+    }
+
+    /// Returns the number of suppressed repeats to report with this event, or None to suppress it.
+    fn take_cooldown(&mut self, event: &NotifyEvent) -> Option<u32> {
+        // Info events (summaries, successful submissions) are distinct every time
+        if event.severity() < Severity::Warn {
+            return Some(0);
+        }
+
+        let kind = event.kind();
+        if self.last_sent.get(kind).is_some_and(|sent| sent.elapsed() < self.cooldown) {
+            *self.suppressed.entry(kind).or_insert(0) += 1;
+            return None;
+        }
+        self.last_sent.insert(kind, Instant::now());
+        Some(self.suppressed.remove(kind).unwrap_or(0))
+    }
+
+    fn dispatch(&mut self, event: &NotifyEvent) {
+        if !self.accepts(event) {
+            return;
+        }
+        let suppressed = match self.take_cooldown(event) {
+            Some(n) => n,
+            None => {
+                println!("🔕 {} notification to {} suppressed (cooldown).", event.kind(), self.notifier.name());
+                return;
+            }
+        };
+
+        let alert = Alert { event, severity: event.severity(), suppressed };
+        match self.notifier.notify(&alert) {
+            Ok(()) => println!("📨 {} notification sent to {}.", event.kind(), self.notifier.name()),
+            Err(e) => eprintln!("⚠️ Could not send {} notification to {}: {}", event.kind(), self.notifier.name(), e),
+        }
+    }
+}
+
+/// Builds the sinks configured in the config file, plus '--webhook-url' (challenge summaries only).
+pub fn build_sinks(client: &Client, webhook_url: Option<&String>, configs: &[NotifierConfig]) -> Vec<Sink> {
+    let mut sinks = Vec::new();
+
+    if let Some(url) = webhook_url {
+        let notifier = WebhookNotifier { client: client.clone(), url: url.clone() };
+        sinks.push(Sink::new(Box::new(notifier), vec!["challenge_summary".to_string()], Severity::Info, DEFAULT_ALERT_COOLDOWN_SECS));
+    }
+
+    for config in configs {
+        let notifier: Box<dyn Notifier> = match &config.backend {
+            NotifierBackend::Webhook { url } => Box::new(WebhookNotifier { client: client.clone(), url: url.clone() }),
+            NotifierBackend::Desktop => Box::new(DesktopNotifier),
+            NotifierBackend::Command { command } => Box::new(CommandNotifier { command: command.clone() }),
+        };
+        let cooldown_secs = config.cooldown_secs.unwrap_or(DEFAULT_ALERT_COOLDOWN_SECS);
+        sinks.push(Sink::new(notifier, config.events.clone(), config.min_severity, cooldown_secs));
+    }
+    sinks
+}
+
+fn run_dispatcher(mut sinks: Vec<Sink>, receiver: Receiver<NotifyEvent>) {
+    // Runs until every Notifications handle has been dropped
+    for event in receiver {
+        for sink in sinks.iter_mut() {
+            sink.dispatch(&event);
+        }
+    }
+}
+
+/// Cloneable handle used to publish events. Sending is a no-op when no sinks are configured.
+#[derive(Debug, Clone, Default)]
+pub struct Notifications {
+    sender: Option<Sender<NotifyEvent>>,
+}
+
+impl Notifications {
+    /// Starts the dispatcher thread feeding the given sinks.
+    pub fn start(sinks: Vec<Sink>) -> Self {
+        if sinks.is_empty() {
+            return Self::default();
+        }
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || run_dispatcher(sinks, receiver));
+        Self { sender: Some(sender) }
+    }
+
+    pub fn send(&self, event: NotifyEvent) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(event);
+        }
+    }
+}
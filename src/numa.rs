@@ -0,0 +1,103 @@
+// NUMA placement of the worker threads and ROM replicas ('--numa'). Nodes are read from Linux sysfs;
+// elsewhere no node is found and placement stays off.
+
+use std::sync::OnceLock;
+
+const SYSFS_NODES: &str = "/sys/devices/system/node";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumaMode {
+    Off,
+    /// Pin each worker to the CPUs of one node; the ROM stays where it was generated.
+    Bind,
+    /// Pin each worker to a node and give every node its own local copy of the ROM.
+    Replicate,
+}
+
+impl NumaMode {
+    pub fn parse(mode_str: &str) -> Result<Self, String> {
+        match mode_str.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(NumaMode::Off),
+            "bind" => Ok(NumaMode::Bind),
+            "replicate" => Ok(NumaMode::Replicate),
+            _ => Err(format!("Invalid '--numa {}'. Use 'off', 'bind' or 'replicate'.", mode_str)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumaNode {
+    pub id: usize,
+    pub cpus: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NumaPlacement {
+    pub mode: NumaMode,
+    pub nodes: Vec<NumaNode>,
+}
+
+static PLACEMENT: OnceLock<NumaPlacement> = OnceLock::new();
+
+/// Parses a sysfs CPU list such as `0-3,8-11`.
+pub fn parse_cpu_list(list: &str) -> Vec<usize> {
+    list.trim()
+        .split(',')
+        .filter(|range| !range.is_empty())
+        .flat_map(|range| match range.split_once('-') {
+            Some((first, last)) => match (first.parse::<usize>(), last.parse::<usize>()) {
+                (Ok(first), Ok(last)) => (first..=last).collect(),
+                _ => Vec::new(),
+            },
+            None => range.parse::<usize>().into_iter().collect(),
+        })
+        .collect()
+}
+
+/// NUMA nodes with at least one online CPU, ordered by id. Empty if the topology is unknown.
+pub fn detect_nodes() -> Vec<NumaNode> {
+    let Ok(entries) = std::fs::read_dir(SYSFS_NODES) else { return Vec::new() };
+    let mut nodes: Vec<NumaNode> = entries.filter_map(|e| e.ok())
+        .filter_map(|entry| {
+            let id = entry.file_name().to_str()?.strip_prefix("node")?.parse::<usize>().ok()?;
+            let cpus = parse_cpu_list(&std::fs::read_to_string(entry.path().join("cpulist")).ok()?);
+            (!cpus.is_empty()).then_some(NumaNode { id, cpus })
+        })
+        .collect();
+    nodes.sort_by_key(|node| node.id);
+    nodes
+}
+
+/// Sets the placement used by `scavenge`. Returns false if it was already set.
+pub fn set_placement(placement: NumaPlacement) -> bool {
+    PLACEMENT.set(placement).is_ok()
+}
+
+/// The placement to apply, if any: a mode other than `Off` on a machine with several nodes.
+pub fn active_placement() -> Option<&'static NumaPlacement> {
+    PLACEMENT.get().filter(|p| p.mode != NumaMode::Off && p.nodes.len() > 1)
+}
+
+/// Node index (into `NumaPlacement::nodes`) for a worker: workers are split into contiguous, even blocks.
+pub fn node_for_worker(worker: usize, nb_workers: usize, nb_nodes: usize) -> usize {
+    (worker * nb_nodes / nb_workers.max(1)).min(nb_nodes.saturating_sub(1))
+}
+
+/// Restricts the calling thread to `cpus`. Returns false where thread affinity isn't supported.
+#[cfg(target_os = "linux")]
+pub fn pin_current_thread(cpus: &[usize]) -> bool {
+    // SAFETY: cpu_set_t is a plain bit set, fully initialised by CPU_ZERO before use
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for &cpu in cpus {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_current_thread(_cpus: &[usize]) -> bool {
+    false
+}
@@ -0,0 +1,74 @@
+// src/proxies.rs
+
+// '--proxies-file': outbound proxies per wallet, so the registration, statistics, donation and submission
+// requests of different wallets leave from different IPs. Each line holds a proxy URL, shared out among
+// the wallets by address, or '<address> <proxy URL>' to pin a wallet to a proxy. Blank lines and '#'
+// comments are skipped. Requests that aren't about one wallet (challenge polling, webhooks, ...) keep the
+// '--proxy' or default route, as do unpinned wallets when the file lists no shared proxies.
+
+use crate::utils::create_api_client;
+use reqwest::Client;
+use std::collections::HashMap;
+
+/// The API client to use for each wallet.
+#[derive(Debug, Clone)]
+pub struct WalletClients {
+    default: Client,
+    shared: Vec<Client>,
+    pinned: HashMap<String, Client>,
+}
+
+impl WalletClients {
+    /// Every wallet uses `client`.
+    pub fn single(client: Client) -> Self {
+        Self { default: client, shared: Vec::new(), pinned: HashMap::new() }
+    }
+
+    /// Reads a '--proxies-file'. Wallets without a proxy from it use `default`.
+    pub fn load(path: &str, default: Client) -> Result<Self, String> {
+        let proxies = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read proxies file {}: {}", path, e))?;
+        let mut clients = Self::single(default);
+        for (index, line) in proxies.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let client = |proxy: &str| create_api_client(Some(proxy)).map_err(|e| format!("{} line {}: {}", path, index + 1, e));
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [] => {},
+                [proxy] => clients.shared.push(client(proxy)?),
+                [address, proxy] => { clients.pinned.insert(address.to_string(), client(proxy)?); },
+                _ => return Err(format!("{} line {}: expected '<proxy URL>' or '<address> <proxy URL>'.", path, index + 1)),
+            }
+        }
+        if clients.shared.is_empty() && clients.pinned.is_empty() {
+            return Err(format!("Proxies file {} contains no proxies.", path));
+        }
+        println!("🌐 {} shared and {} pinned wallet proxies from {}.", clients.shared.len(), clients.pinned.len(), path);
+        Ok(clients)
+    }
+
+    /// The client for requests about `address`. A wallet keeps its shared proxy across restarts.
+    pub fn for_wallet(&self, address: &str) -> &Client {
+        if let Some(client) = self.pinned.get(address) {
+            return client;
+        }
+        if self.shared.is_empty() {
+            return &self.default;
+        }
+        &self.shared[(fnv1a(address.as_bytes()) % self.shared.len() as u64) as usize]
+    }
+}
+
+/// The proxy URLs of a '--proxies-file', shared and pinned.
+pub fn read_proxy_urls(path: &str) -> Result<Vec<String>, String> {
+    let proxies = std::fs::read_to_string(path)
+        .map_err(|e| format!("Could not read proxies file {}: {}", path, e))?;
+    Ok(proxies.lines()
+        .filter_map(|line| line.split('#').next().unwrap_or_default().split_whitespace().last())
+        .map(str::to_string)
+        .collect())
+}
+
+// Stable across builds and platforms, unlike std's hasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
@@ -0,0 +1,120 @@
+// src/push.rs
+
+// '--challenge-push': follows challenge changes on a Server-Sent Events stream instead of polling for them.
+// Each event's data is the GET /challenge JSON. A newly published challenge stops the search on the old one
+// within a second and, unless '--no-rom-prefetch', gets its ROM built right away. While the stream is down,
+// the challenge is polled every 30 seconds and the stream retried.
+
+use crate::api;
+use crate::constants::USER_AGENT;
+use crate::data_types::ChallengeResponse;
+use crate::utils;
+use reqwest::Client;
+use shadow_harvester_lib::ScavengeControl;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const POLL_SECS: u64 = 30;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// The stream counts as dropped after this long without an event or keep-alive comment
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+struct Watcher {
+    control: Arc<ScavengeControl>,
+    prefetch_rom: bool,
+    latest: Option<String>,
+}
+
+impl Watcher {
+    fn observe(&mut self, status: &ChallengeResponse) {
+        let Some(challenge) = status.challenge.as_ref().filter(|_| status.code == "active") else { return };
+        if self.latest.as_deref() == Some(challenge.challenge_id.as_str()) {
+            return;
+        }
+        if self.latest.is_some() {
+            println!("\n🔔 Challenge {} was published.", challenge.challenge_id);
+        }
+        if self.prefetch_rom {
+            shadow_harvester_lib::prepare_challenge_rom(&challenge.no_pre_mine_key);
+        }
+        self.control.set_latest_challenge(&challenge.challenge_id);
+        self.latest = Some(challenge.challenge_id.clone());
+    }
+}
+
+/// Follows `stream_url` from a background thread, polling `api_url` whenever the stream is down.
+pub fn spawn_challenge_push(stream_url: String, client: Client, api_url: String, control: Arc<ScavengeControl>, prefetch_rom: bool, proxy: Option<&str>) -> Result<(), String> {
+    // The API client's request timeout would cut the stream
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(CONNECT_TIMEOUT);
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(utils::parse_proxy(proxy)?);
+    }
+    for certificate in api::tls().ca_certificates {
+        builder = builder.add_root_certificate(certificate);
+    }
+    let stream_client = builder.build()
+        .map_err(|e| format!("Could not create the challenge stream client: {}", e))?;
+
+    thread::spawn(move || {
+        let mut watcher = Watcher { control, prefetch_rom, latest: None };
+        let mut polling = false;
+        loop {
+            match api::block_on(follow(&stream_client, &stream_url, &mut watcher, &mut polling)) {
+                Err(e) if !polling => {
+                    eprintln!("⚠️ Challenge stream {} is down ({}). Polling every {}s until it reconnects.", stream_url, e, POLL_SECS);
+                    polling = true;
+                },
+                _ => {},
+            }
+            if let Ok(status) = api::block_on(api::fetch_challenge_status(&client, &api_url)) {
+                watcher.observe(&status);
+            }
+            thread::sleep(Duration::from_secs(POLL_SECS));
+        }
+    });
+    Ok(())
+}
+
+/// Feeds the stream's events to `watcher` until it drops.
+async fn follow(client: &Client, url: &str, watcher: &mut Watcher, polling: &mut bool) -> Result<(), String> {
+    let mut response = client.get(url).header("Accept", "text/event-stream").send().await
+        .map_err(|e| format!("Network/Client Error: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    println!("🔔 Following challenge changes from {}.", url);
+    *polling = false;
+
+    let mut line = Vec::new();
+    let mut data = String::new();
+    loop {
+        let chunk = tokio::time::timeout(IDLE_TIMEOUT, response.chunk()).await
+            .map_err(|_| format!("silent for {}s", IDLE_TIMEOUT.as_secs()))?
+            .map_err(|e| e.to_string())?
+            .ok_or("closed by the server")?;
+        for &byte in chunk.iter() {
+            if byte != b'\n' {
+                line.push(byte);
+                continue;
+            }
+            let text = String::from_utf8_lossy(&line).trim_end_matches('\r').to_string();
+            line.clear();
+            // A blank line ends an event; other fields (event, id, retry) and ':' keep-alives are ignored
+            if text.is_empty() && !data.is_empty() {
+                match serde_json::from_str::<ChallengeResponse>(&data) {
+                    Ok(status) => watcher.observe(&status),
+                    Err(e) => eprintln!("⚠️ Ignoring a challenge event that isn't challenge JSON: {}", e),
+                }
+                data.clear();
+            } else if let Some(value) = text.strip_prefix("data:") {
+                if !data.is_empty() {
+                    data.push('\n');
+                }
+                data.push_str(value.strip_prefix(' ').unwrap_or(value));
+            }
+        }
+    }
+}
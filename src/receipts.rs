@@ -0,0 +1,149 @@
+// src/receipts.rs
+
+use crate::api;
+use crate::error::ApiError;
+use crate::data_types::{BackfillReceipt, count_challenge_receipts_for_address, save_backfill_receipt};
+use crate::proxies::WalletClients;
+use std::collections::HashMap;
+
+// ===============================================
+// WALLETS FILE
+// ===============================================
+
+/// The address of a wallets file line and the note after it ('<address>  # <note>'). `None` for blank
+/// and comment lines.
+pub fn parse_wallets_line(line: &str) -> Option<(&str, Option<&str>)> {
+    let (address, note) = match line.split_once('#') {
+        Some((address, note)) => (address.trim(), Some(note.trim()).filter(|note| !note.is_empty())),
+        None => (line.trim(), None),
+    };
+    (!address.is_empty()).then_some((address, note))
+}
+
+/// Reads one bech32 address per line, skipping blank lines and '#' comments.
+pub fn read_wallets_file(path: &str) -> Result<Vec<String>, String> {
+    let wallets = std::fs::read_to_string(path)
+        .map_err(|e| format!("Could not read wallets file {}: {}", path, e))?;
+
+    let addresses: Vec<String> = wallets.lines()
+        .filter_map(parse_wallets_line)
+        .map(|(address, _)| address.to_string())
+        .collect();
+
+    if addresses.is_empty() {
+        return Err(format!("Wallets file {} contains no addresses.", path));
+    }
+    Ok(addresses)
+}
+
+/// Notes of the wallets file by address.
+pub fn read_wallet_notes(path: &str) -> HashMap<String, String> {
+    std::fs::read_to_string(path).unwrap_or_default().lines()
+        .filter_map(parse_wallets_line)
+        .filter_map(|(address, note)| Some((address.to_string(), note?.to_string())))
+        .collect()
+}
+
+/// 'wallets note': replaces the note of a wallet, given by address or by its position among the addresses
+/// (from 0). An empty note removes it. Other lines are kept as they are.
+pub fn run_wallet_note(wallets_file: &str, wallet: &str, note: &str) -> Result<(), String> {
+    let note = note.trim();
+    if note.contains(['\n', '\r']) {
+        return Err("A wallet note must fit on one line.".to_string());
+    }
+    let wallets = std::fs::read_to_string(wallets_file)
+        .map_err(|e| format!("Could not read wallets file {}: {}", wallets_file, e))?;
+
+    let mut position = 0;
+    let mut noted = None;
+    let mut lines: Vec<String> = Vec::new();
+    for line in wallets.lines() {
+        let Some((address, _)) = parse_wallets_line(line) else {
+            lines.push(line.to_string());
+            continue;
+        };
+        if noted.is_none() && (address == wallet || wallet.parse() == Ok(position)) {
+            noted = Some(address.to_string());
+            lines.push(if note.is_empty() { address.to_string() } else { format!("{}  # {}", address, note) });
+        } else {
+            lines.push(line.to_string());
+        }
+        position += 1;
+    }
+    let address = noted.ok_or_else(|| format!("Wallet {} is not in {} ({} address(es)).", wallet, wallets_file, position))?;
+
+    // Written aside and renamed, so an interrupted write can't truncate the wallets file
+    let tmp_path = format!("{}.tmp", wallets_file);
+    std::fs::write(&tmp_path, lines.join("\n") + "\n")
+        .and_then(|_| std::fs::rename(&tmp_path, wallets_file))
+        .map_err(|e| format!("Could not write wallets file {}: {}", wallets_file, e))?;
+    match note {
+        "" => println!("📝 Note of {} removed.", address),
+        note => println!("📝 {}: {}", address, note),
+    }
+    Ok(())
+}
+
+// ===============================================
+// RECEIPTS BACKFILL COMMAND
+// ===============================================
+
+/// Queries the API statistics for every address and records the credited receipts that have no local
+/// challenge receipt, so duplicate checks and reports match the API after mining without '--data-dir'.
+pub fn run_backfill(clients: &WalletClients, api_url: &str, data_dir: &str, wallets_file: &str) -> Result<(), String> {
+    let addresses = read_wallets_file(wallets_file)?;
+    let notes = read_wallet_notes(wallets_file);
+
+    println!("\n==============================================");
+    println!("🧾 Backfilling receipts for {} address(es) into {}", addresses.len(), data_dir);
+    println!("==============================================");
+
+    let mut backfilled_total = 0;
+    let mut failed = 0;
+
+    let mut all_stats = api::block_on(api::fetch_statistics_many(clients, api_url, &addresses));
+    for address in &addresses {
+        let stats = match all_stats.remove(address).unwrap_or_else(|| Err(ApiError::Network("request failed".to_string()))) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("❌ {}: could not fetch statistics: {}", address, e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        let local_receipts = count_challenge_receipts_for_address(data_dir, address);
+        let unattributed_receipts = (stats.crypto_receipts as usize).saturating_sub(local_receipts);
+
+        let receipt = BackfillReceipt {
+            address: address.clone(),
+            already_credited: true,
+            source: "statistics".to_string(),
+            api_crypto_receipts: stats.crypto_receipts,
+            local_receipts,
+            unattributed_receipts,
+            night_allocation: stats.night_allocation,
+            backfilled_at: chrono::Utc::now().to_rfc3339(),
+        };
+
+        match save_backfill_receipt(data_dir, &receipt) {
+            Ok(()) => {
+                println!("✅ {}{}: {} credited on the API, {} local, {} backfilled", address,
+                    notes.get(address).map(|note| format!(" ({})", note)).unwrap_or_default(),
+                    stats.crypto_receipts, local_receipts, unattributed_receipts);
+                backfilled_total += unattributed_receipts;
+            },
+            Err(e) => {
+                eprintln!("❌ {}: {}", address, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!("----------------------------------------------");
+    println!("Backfilled {} receipt(s) across {} address(es).", backfilled_total, addresses.len() - failed);
+    if failed > 0 {
+        return Err(format!("{} address(es) could not be backfilled.", failed));
+    }
+    Ok(())
+}
@@ -3,7 +3,9 @@ use cryptoxide::{
     kdf::argon2,
 };
 
-use std::{fmt, convert::TryInto};
+use crate::hugepages::{HugePageBuffer, HugePageKind};
+use memmap2::{Mmap, MmapOptions};
+use std::{fmt, convert::TryInto, fs::File, io::{self, Read, Write}, ops::Deref, path::Path};
 
 // function to help debug bytestrings
 pub fn print_hex(name: &str, data: &[u8]) {
@@ -30,7 +32,55 @@ impl fmt::Display for RomDigest {
 /// The **R**ead **O**only **M**emory used to generate the proram.
 pub struct Rom {
     pub digest: RomDigest,
-    data: Vec<u8>,
+    data: RomData,
+}
+
+/// ROM bytes, either generated in memory or mapped from a cache file written by `Rom::save`.
+enum RomData {
+    Owned(Vec<u8>),
+    Mapped(Mmap),
+    /// '--huge-pages': generated into (or copied to) huge-page backed memory.
+    Huge(HugePageBuffer),
+    /// Low-memory ROM: only the leading bytes `Rom::at` can reach are kept, `len` is the full size.
+    Prefix { bytes: Vec<u8>, len: usize },
+}
+
+impl RomData {
+    /// Size of the whole ROM, including the bytes a `Prefix` doesn't keep.
+    fn rom_len(&self) -> usize {
+        match self {
+            RomData::Prefix { len, .. } => *len,
+            data => data.deref().len(),
+        }
+    }
+}
+
+/// The resident bytes.
+impl Deref for RomData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            RomData::Owned(data) => data,
+            RomData::Mapped(map) => map,
+            RomData::Huge(buffer) => buffer,
+            RomData::Prefix { bytes, .. } => bytes,
+        }
+    }
+}
+
+/// Number of leading ROM bytes `Rom::at` reads from: it starts an access at any byte offset
+/// below the chunk count, never beyond.
+fn reachable_len(size: usize) -> usize {
+    (size / DATASET_ACCESS_SIZE + DATASET_ACCESS_SIZE).next_multiple_of(DATASET_ACCESS_SIZE).min(size)
+}
+
+// Cache file layout: magic, digest, Blake2b-512 checksum of the ROM bytes, then the ROM bytes
+const CACHE_MAGIC: &[u8; 8] = b"SHROM\0\0\x02";
+const CACHE_HEADER_SIZE: usize = CACHE_MAGIC.len() + 64 + 64;
+
+fn data_checksum(data: &[u8]) -> [u8; 64] {
+    blake2b::Context::<512>::new().update(data).finalize()
 }
 
 /// The generation type of the **ROM**.
@@ -65,20 +115,9 @@ pub fn xorbuf(out: &mut [u8], input: &[u8]) {
     assert_eq!(out.len(), input.len());
     assert_eq!(out.len(), 64);
 
-    // Convert slice pointers to u64 pointers
-    let input_ptr = input.as_ptr() as *const u64;
-    let out_ptr = out.as_mut_ptr() as *mut u64;
-
-    unsafe {
-        // FIX: Use read_unaligned and write_unaligned to prevent memory alignment panics
-        for i in 0..8 {
-            let input_word = std::ptr::read_unaligned(input_ptr.offset(i));
-            let mut out_word = std::ptr::read_unaligned(out_ptr.offset(i));
-
-            out_word ^= input_word;
-
-            std::ptr::write_unaligned(out_ptr.offset(i), out_word);
-        }
+    for (o, i) in out.chunks_exact_mut(8).zip(input.chunks_exact(8)) {
+        let word = u64::from_le_bytes(*<&[u8; 8]>::try_from(&*o).unwrap()) ^ u64::from_le_bytes(*<&[u8; 8]>::try_from(i).unwrap());
+        o.copy_from_slice(&word.to_le_bytes());
     }
 }
 
@@ -101,19 +140,121 @@ impl Rom {
             .update(key)
             .finalize();
 
-        let digest = random_gen(gen_type, seed, &mut data);
-        Self { digest, data }
+        let digest = random_gen(gen_type, seed, size, &mut data);
+        Self { digest, data: RomData::Owned(data) }
+    }
+
+    /// Same ROM as `new`, but only the bytes the hash can read are kept in memory (16 MiB of a
+    /// 1 GiB ROM); the rest is generated once for the digest and thrown away.
+    pub fn new_low_memory(key: &[u8], gen_type: RomGenerationType, size: usize) -> Self {
+        let size_bytes = (size as u32).to_le_bytes();
+
+        let seed = blake2b::Context::<256>::new()
+            .update(&size_bytes)
+            .update(key)
+            .finalize();
+
+        let mut bytes = vec![0; reachable_len(size)];
+        let digest = random_gen(gen_type, seed, size, &mut bytes);
+        Self { digest, data: RomData::Prefix { bytes, len: size } }
+    }
+
+    /// Same ROM as `new`, generated straight into huge pages.
+    pub fn new_huge_pages(key: &[u8], gen_type: RomGenerationType, size: usize) -> io::Result<Self> {
+        let mut buffer = HugePageBuffer::alloc(size)?;
+        let size_bytes = (size as u32).to_le_bytes();
+
+        let seed = blake2b::Context::<256>::new()
+            .update(&size_bytes)
+            .update(key)
+            .finalize();
+
+        let digest = random_gen(gen_type, seed, size, &mut buffer);
+        Ok(Self { digest, data: RomData::Huge(buffer) })
+    }
+
+    /// Copy of the ROM in huge pages, e.g. of a ROM mapped from the cache. A low-memory ROM isn't copied.
+    pub fn to_huge_pages(&self) -> io::Result<Self> {
+        if let RomData::Prefix { .. } = self.data {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "a low-memory ROM only holds part of the ROM"));
+        }
+        let mut buffer = HugePageBuffer::alloc(self.data.len())?;
+        buffer.copy_from_slice(&self.data);
+        Ok(Self { digest: RomDigest(self.digest.0), data: RomData::Huge(buffer) })
+    }
+
+    /// The kind of huge pages holding the ROM, if any.
+    pub fn huge_pages(&self) -> Option<HugePageKind> {
+        match &self.data {
+            RomData::Huge(buffer) => Some(buffer.kind()),
+            _ => None,
+        }
+    }
+
+    /// Deep copy held in memory allocated (and first touched) by the calling thread, which places it
+    /// on that thread's NUMA node. A mapped ROM is copied into memory, a huge-page ROM into huge pages
+    /// when they are still available.
+    pub fn replicate(&self) -> Self {
+        if let Some(copy) = self.huge_pages().and_then(|_| self.to_huge_pages().ok()) {
+            return copy;
+        }
+        let data = match &self.data {
+            RomData::Prefix { bytes, len } => RomData::Prefix { bytes: bytes.clone(), len: *len },
+            data => RomData::Owned(data.to_vec()),
+        };
+        Self { digest: RomDigest(self.digest.0), data }
+    }
+
+    /// Writes the ROM to a cache file that `open_mapped` can map back. The file is written under a
+    /// temporary name and renamed, so a crash never leaves a truncated cache behind.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let RomData::Prefix { .. } = self.data {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "a low-memory ROM only holds part of the ROM"));
+        }
+        let tmp_path = path.with_extension("tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(CACHE_MAGIC)?;
+        file.write_all(&self.digest.0)?;
+        file.write_all(&data_checksum(&self.data))?;
+        file.write_all(&self.data)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    }
+
+    /// Maps a cache file written by `save` instead of regenerating the ROM, after checking it against
+    /// the stored checksum. The pages can be shared with (and evicted to) the page cache.
+    pub fn open_mapped(path: &Path, size: usize) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        if file.metadata()?.len() != (CACHE_HEADER_SIZE + size) as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "ROM cache file has the wrong size"));
+        }
+
+        let mut header = [0u8; CACHE_HEADER_SIZE];
+        file.read_exact(&mut header)?;
+        if &header[..CACHE_MAGIC.len()] != CACHE_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a ROM cache file"));
+        }
+        let digest = RomDigest(header[CACHE_MAGIC.len()..CACHE_MAGIC.len() + 64].try_into().unwrap());
+        // SAFETY: the cache files are only written through a rename, never modified in place
+        let data = unsafe { MmapOptions::new().offset(CACHE_HEADER_SIZE as u64).len(size).map(&file)? };
+
+        // Catches bit rot and partial copies; hashing the file is still far cheaper than regenerating it
+        if data_checksum(&data)[..] != header[CACHE_MAGIC.len() + 64..] {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "ROM cache checksum mismatch"));
+        }
+        Ok(Self { digest, data: RomData::Mapped(data) })
     }
 
     pub(crate) fn at(&self, i: u32) -> &[u8; DATASET_ACCESS_SIZE] {
-        let start = i as usize % (self.data.len() / DATASET_ACCESS_SIZE);
+        let start = i as usize % (self.data.rom_len() / DATASET_ACCESS_SIZE);
         <&[u8; DATASET_ACCESS_SIZE]>::try_from(&self.data[start..start + DATASET_ACCESS_SIZE])
             .unwrap()
     }
 }
 
 
-fn random_gen(gen_type: RomGenerationType, seed: [u8; 32], output: &mut [u8]) -> RomDigest {
+/// Generates a ROM of `size` bytes, keeping the first `output.len()` of them in `output`.
+fn random_gen(gen_type: RomGenerationType, seed: [u8; 32], size: usize, output: &mut [u8]) -> RomDigest {
     if let RomGenerationType::TwoStep { pre_size, mixing_numbers } = gen_type {
 
         assert!(pre_size.is_power_of_two());
@@ -135,7 +276,7 @@ fn random_gen(gen_type: RomGenerationType, seed: [u8; 32], output: &mut [u8]) ->
             offsets_diff.extend(digest_to_u16s(&command.as_slice().try_into().unwrap()));
         }
 
-        let nb_chunks_bytes = output.len() / DATASET_ACCESS_SIZE;
+        let nb_chunks_bytes = size / DATASET_ACCESS_SIZE;
         let mut offsets_bytes = vec![0; nb_chunks_bytes];
 
         let offset_bytes_input = blake2b::Context::<512>::new()
@@ -150,7 +291,13 @@ fn random_gen(gen_type: RomGenerationType, seed: [u8; 32], output: &mut [u8]) ->
         let mut digest = blake2b::Context::<512>::new();
         let nb_source_chunks = (pre_size / DATASET_ACCESS_SIZE) as u32;
 
-        for (i, chunk) in output.chunks_mut(DATASET_ACCESS_SIZE).enumerate() {
+        // Chunks past the kept part are only generated for the digest
+        let mut scratch = [0u8; DATASET_ACCESS_SIZE];
+        for i in 0..nb_chunks_bytes {
+            let chunk = match output.get_mut(i * DATASET_ACCESS_SIZE..(i + 1) * DATASET_ACCESS_SIZE) {
+                Some(chunk) => chunk,
+                None => &mut scratch[..],
+            };
 
             let start_idx = offsets[i % offsets.len()] as u32 % nb_source_chunks;
             let idx0 = (i as u32) % nb_source_chunks;
@@ -171,8 +318,16 @@ fn random_gen(gen_type: RomGenerationType, seed: [u8; 32], output: &mut [u8]) ->
         RomDigest(digest.finalize().as_slice().try_into().unwrap())
 
     } else {
-        argon2::hprime(output, &seed);
-        RomDigest(blake2b::Context::<512>::new().update(output).finalize().as_slice().try_into().unwrap())
+        if output.len() == size {
+            argon2::hprime(output, &seed);
+            return RomDigest(blake2b::Context::<512>::new().update(output).finalize().as_slice().try_into().unwrap());
+        }
+        // hprime can't produce a prefix on its own, so a low-memory ROM is generated in full here
+        let mut data = vec![0; size];
+        argon2::hprime(&mut data, &seed);
+        let kept = output.len();
+        output.copy_from_slice(&data[..kept]);
+        RomDigest(blake2b::Context::<512>::new().update(&data).finalize().as_slice().try_into().unwrap())
     }
 }
 
@@ -309,7 +464,7 @@ pub fn build_rom_from_state(mut state: RomMixingState, size: usize) -> Rom {
 
     Rom {
         digest: final_digest,
-        data: rom_data_vec,
+        data: RomData::Owned(rom_data_vec),
     }
 }
 
@@ -333,7 +488,7 @@ mod tests {
             SIZE,
         );
 
-        for byte in rom.data {
+        for &byte in rom.data.iter() {
             let index = byte as usize;
             distribution[index] += 1;
         }
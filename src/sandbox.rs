@@ -0,0 +1,450 @@
+// src/sandbox.rs
+
+use crate::cli::Cli;
+#[cfg(target_os = "linux")]
+use crate::config::DEFAULT_CONFIG_FILE;
+#[cfg(target_os = "linux")]
+use std::path::{Path, PathBuf};
+
+// Read by TLS (certificates), DNS (resolv.conf, hosts, NSS), and the thermal/NUMA probes.
+// /run holds the target of /etc/resolv.conf on systemd machines.
+#[cfg(target_os = "linux")]
+const SYSTEM_READ_ONLY_PATHS: [&str; 8] = ["/etc", "/usr", "/lib", "/lib64", "/proc", "/sys", "/run", "/dev"];
+
+/// Paths the hardened process may still open: the data directories and files it writes, and the files
+/// it reads after startup.
+#[cfg(target_os = "linux")]
+struct SandboxPaths {
+    read_write: Vec<PathBuf>,
+    read_only: Vec<PathBuf>,
+}
+
+#[cfg(target_os = "linux")]
+fn sandbox_paths(cli: &Cli) -> SandboxPaths {
+    let mut read_write: Vec<PathBuf> = [&cli.data_dir, &cli.receipts_dir, &cli.queue_dir, &cli.rom_cache_dir]
+        .into_iter()
+        .flatten()
+        .map(PathBuf::from)
+        .collect();
+    // The heartbeat is replaced through a temporary file next to it, the API trace rotated next to it
+    for file in [cli.heartbeat.as_deref(), cli.api_trace.as_deref()].into_iter().flatten() {
+        read_write.push(match Path::new(file).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        });
+    }
+
+    let mut read_only = vec![PathBuf::from(cli.config.as_deref().unwrap_or(DEFAULT_CONFIG_FILE))];
+    read_only.extend(cli.mnemonic_file.iter().map(PathBuf::from));
+    read_only.extend([&cli.api_ca_file, &cli.coordinator_tls_cert, &cli.coordinator_tls_key, &cli.worker_tls_ca, &cli.cluster_token_file]
+        .into_iter().flatten().map(PathBuf::from));
+    read_only.extend(cli.standby_of.iter().filter(|source| !source.contains("://")).map(PathBuf::from));
+
+    SandboxPaths { read_write, read_only }
+}
+
+// Environment proxies reqwest uses without '--proxy'
+#[cfg(target_os = "linux")]
+const PROXY_ENV_VARS: [&str; 6] = ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"];
+// reqwest's port for a SOCKS proxy URL without one
+#[cfg(target_os = "linux")]
+const SOCKS_DEFAULT_PORT: u16 = 1080;
+
+/// TCP ports the process connects to: those of the API endpoints, the '--worker's coordinator, the '--state'
+/// store and every proxy, plus the webhooks', the challenge stream's and the standby source's if they are URLs.
+#[cfg(target_os = "linux")]
+fn connect_ports(cli: &Cli) -> Result<Vec<u16>, String> {
+    let webhooks: Vec<String> = crate::config::load_notifiers(cli)?.into_iter()
+        .filter_map(|notifier| match notifier.backend {
+            crate::config::NotifierBackend::Webhook { url } => Some(url),
+            _ => None,
+        })
+        .collect();
+    let mut ports: Vec<u16> = cli.api_url.as_deref().map(crate::api::endpoints).unwrap_or_default()
+        .into_iter()
+        .chain([cli.webhook_url.clone(), cli.standby_of.clone(), cli.challenge_push.clone()].into_iter().flatten())
+        .chain(webhooks)
+        .chain(proxy_urls(cli)?)
+        .filter_map(|url| reqwest::Url::parse(&url).ok())
+        .filter_map(|url| match url.scheme() {
+            "socks5" | "socks5h" => Some(url.port().unwrap_or(SOCKS_DEFAULT_PORT)),
+            _ => url.port_or_known_default(),
+        })
+        .collect();
+    // '--state redis://' or s3:// (HTTPS, or the AWS_ENDPOINT_URL's port)
+    match cli.state.as_deref().and_then(|url| reqwest::Url::parse(url).ok()) {
+        Some(url) if url.scheme() == "redis" => ports.push(url.port().unwrap_or(crate::store::REDIS_DEFAULT_PORT)),
+        Some(url) if url.scheme() == "s3" => ports.extend(std::env::var("AWS_ENDPOINT_URL").ok()
+            .map_or(Some(443), |endpoint| reqwest::Url::parse(&endpoint).ok().and_then(|url| url.port_or_known_default()))),
+        _ => {},
+    }
+    match &cli.worker {
+        Some(Some(coordinator)) => ports.push(address_port(coordinator, "--worker")?),
+        // The coordinator's port is only known once discovered
+        Some(None) => return Err("'--harden' needs the '--worker's coordinator address: its port can't be allowed before '--auto-discover' finds it.".to_string()),
+        None => {},
+    }
+    ports.sort_unstable();
+    ports.dedup();
+    Ok(ports)
+}
+
+/// '--proxy', the '--proxies-file' ones, or the environment's.
+#[cfg(target_os = "linux")]
+fn proxy_urls(cli: &Cli) -> Result<Vec<String>, String> {
+    let mut urls = match &cli.proxy {
+        Some(proxy) => vec![proxy.clone()],
+        None => PROXY_ENV_VARS.iter().filter_map(|name| std::env::var(name).ok()).collect(),
+    };
+    if let Some(path) = cli.proxies_file.as_deref() {
+        urls.extend(crate::proxies::read_proxy_urls(path)?);
+    }
+    Ok(urls)
+}
+
+/// TCP ports the process listens on: '--health-listen' and the coordinator's.
+#[cfg(target_os = "linux")]
+fn bind_ports(cli: &Cli) -> Result<Vec<u16>, String> {
+    let listeners = [
+        (&cli.health_listen, "--health-listen"),
+        (&cli.coordinator, "--coordinator"),
+        (&cli.coordinator_grpc, "--coordinator-grpc"),
+        (&cli.coordinator_stratum, "--coordinator-stratum"),
+    ];
+    listeners.into_iter()
+        .filter_map(|(address, flag)| address.as_deref().map(|address| address_port(address, flag)))
+        .collect()
+}
+
+/// Refuses the notifiers that run a program: the seccomp filter blocks execve, so every alert would fail.
+/// (The thermal probe only runs programs on macOS, where '--harden' is refused anyway.)
+#[cfg(target_os = "linux")]
+fn refuse_spawning_notifiers(cli: &Cli) -> Result<(), String> {
+    for notifier in crate::config::load_notifiers(cli)? {
+        let kind = match notifier.backend {
+            crate::config::NotifierBackend::Desktop => "desktop",
+            crate::config::NotifierBackend::Command { .. } => "command",
+            crate::config::NotifierBackend::Webhook { .. } => continue,
+        };
+        return Err(format!("'--harden' blocks running programs, so the {} notifier could never deliver an alert. Remove it from the config, or use a webhook notifier.", kind));
+    }
+    Ok(())
+}
+
+/// The port of a host:port address.
+#[cfg(target_os = "linux")]
+fn address_port(address: &str, flag: &str) -> Result<u16, String> {
+    address.rsplit_once(':').and_then(|(_, port)| port.parse().ok())
+        .ok_or_else(|| format!("Invalid '{}' address '{}': expected host:port.", flag, address))
+}
+
+// ===============================================
+// '--harden'
+// ===============================================
+
+/// Locks the process down before it spawns any thread: refuses root unless '--allow-root', drops
+/// capabilities and core dumps, confines files to the data dir (Landlock) and TCP to the ports it connects to
+/// and listens on (Landlock ABI 4), and blocks syscalls the miner never makes (seccomp). Every thread started
+/// later inherits it.
+#[cfg(target_os = "linux")]
+pub fn harden(cli: &Cli) -> Result<(), String> {
+    // SAFETY: geteuid has no preconditions
+    if unsafe { libc::geteuid() } == 0 && !cli.allow_root {
+        return Err("Refusing to run as root with '--harden'. Run as an unprivileged user, or pass '--allow-root'.".to_string());
+    }
+    refuse_spawning_notifiers(cli)?;
+
+    let paths = sandbox_paths(cli);
+    let (connect, bind) = (connect_ports(cli)?, bind_ports(cli)?);
+    for dir in &paths.read_write {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Could not create {:?} before hardening: {}", dir, e))?;
+    }
+
+    linux::drop_privileges()?;
+    match linux::restrict_filesystem_and_network(&paths, &connect, &bind)? {
+        Some(abi) => println!("🔒 Landlock (ABI {}): files limited to {} writable and {} read-only path(s).", abi, paths.read_write.len(), SYSTEM_READ_ONLY_PATHS.len() + paths.read_only.len()),
+        None => eprintln!("⚠️ Landlock is not available on this kernel. Files and network are NOT confined."),
+    }
+    if linux::install_seccomp_filter()? {
+        println!("🔒 Seccomp filter installed: process execution, debugging and kernel administration syscalls are blocked.");
+    } else {
+        eprintln!("⚠️ No seccomp filter for {}. Syscalls are NOT filtered.", std::env::consts::ARCH);
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn harden(_cli: &Cli) -> Result<(), String> {
+    Err(format!("'--harden' is only supported on Linux, not {}.", std::env::consts::OS))
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{SandboxPaths, SYSTEM_READ_ONLY_PATHS};
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    fn last_os_error(what: &str) -> String {
+        format!("{} failed: {}", what, std::io::Error::last_os_error())
+    }
+
+    // ===============================================
+    // PRIVILEGES
+    // ===============================================
+
+    const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+    #[repr(C)]
+    struct CapHeader {
+        version: u32,
+        pid: libc::c_int,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    struct CapData {
+        effective: u32,
+        permitted: u32,
+        inheritable: u32,
+    }
+
+    /// No capabilities, no privilege gain through exec, and no core dumps or ptrace of the key material.
+    pub fn drop_privileges() -> Result<(), String> {
+        // SAFETY: prctl with integer arguments only
+        unsafe {
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                return Err(last_os_error("PR_SET_NO_NEW_PRIVS"));
+            }
+            if libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) != 0 {
+                return Err(last_os_error("PR_SET_DUMPABLE"));
+            }
+        }
+
+        let header = CapHeader { version: LINUX_CAPABILITY_VERSION_3, pid: 0 };
+        let data = [CapData::default(); 2];
+        // SAFETY: version 3 takes one header and two data structs, both alive for the call
+        if unsafe { libc::syscall(libc::SYS_capset, &header, data.as_ptr()) } != 0 {
+            return Err(last_os_error("capset"));
+        }
+        Ok(())
+    }
+
+    // ===============================================
+    // LANDLOCK
+    // ===============================================
+
+    const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1 << 0;
+    const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+    const LANDLOCK_RULE_NET_PORT: libc::c_int = 2;
+
+    const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+    const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+    const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+    const ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+    const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+    const ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+    const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+    const ACCESS_FS_REFER: u64 = 1 << 13; // ABI 2
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14; // ABI 3
+    const ACCESS_NET_BIND_TCP: u64 = 1 << 0; // ABI 4
+    const ACCESS_NET_CONNECT_TCP: u64 = 1 << 1; // ABI 4
+    // Rights that apply to a file (as opposed to a directory) rule
+    const ACCESS_FS_FILE: u64 = ACCESS_FS_EXECUTE | ACCESS_FS_WRITE_FILE | ACCESS_FS_READ_FILE | ACCESS_FS_TRUNCATE;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+        handled_access_net: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    #[repr(C)]
+    struct NetPortAttr {
+        allowed_access: u64,
+        port: u64,
+    }
+
+    fn landlock_abi() -> Option<u32> {
+        // SAFETY: the version query takes no attribute
+        let abi = unsafe { libc::syscall(libc::SYS_landlock_create_ruleset, std::ptr::null::<RulesetAttr>(), 0usize, LANDLOCK_CREATE_RULESET_VERSION) };
+        (abi > 0).then_some(abi as u32)
+    }
+
+    fn add_path_rule(ruleset_fd: libc::c_int, path: &Path, access: u64) -> Result<(), String> {
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| format!("Invalid path {:?}", path))?;
+        // SAFETY: c_path is a valid NUL-terminated string
+        let fd = unsafe { libc::open(c_path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(last_os_error(&format!("Opening {:?} for the sandbox", path)));
+        }
+        let access = if path.is_dir() { access } else { access & ACCESS_FS_FILE };
+        let rule = PathBeneathAttr { allowed_access: access, parent_fd: fd };
+        // SAFETY: rule is a valid path_beneath attribute and fd stays open for the call
+        let added = unsafe { libc::syscall(libc::SYS_landlock_add_rule, ruleset_fd, LANDLOCK_RULE_PATH_BENEATH, &rule, 0u32) };
+        // SAFETY: fd was opened above
+        unsafe { libc::close(fd) };
+        if added != 0 {
+            return Err(last_os_error(&format!("Adding sandbox rule for {:?}", path)));
+        }
+        Ok(())
+    }
+
+    /// Applies a Landlock ruleset to the calling thread and its future threads. Returns the ABI used,
+    /// or `None` if the kernel has no Landlock.
+    pub fn restrict_filesystem_and_network(paths: &SandboxPaths, connect: &[u16], bind: &[u16]) -> Result<Option<u32>, String> {
+        let Some(abi) = landlock_abi() else { return Ok(None) };
+
+        let read_only = ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR;
+        let mut read_write = read_only | ACCESS_FS_WRITE_FILE | ACCESS_FS_REMOVE_DIR | ACCESS_FS_REMOVE_FILE | ACCESS_FS_MAKE_DIR | ACCESS_FS_MAKE_REG;
+        let mut handled_fs = (1u64 << 13) - 1; // Every right of ABI 1
+        if abi >= 2 {
+            handled_fs |= ACCESS_FS_REFER;
+            read_write |= ACCESS_FS_REFER;
+        }
+        if abi >= 3 {
+            handled_fs |= ACCESS_FS_TRUNCATE;
+            read_write |= ACCESS_FS_TRUNCATE;
+        }
+        let attr = RulesetAttr {
+            handled_access_fs: handled_fs,
+            handled_access_net: if abi >= 4 { ACCESS_NET_BIND_TCP | ACCESS_NET_CONNECT_TCP } else { 0 },
+        };
+        // Kernels before ABI 4 reject the larger attribute
+        let attr_size = if abi >= 4 { std::mem::size_of::<RulesetAttr>() } else { std::mem::size_of::<u64>() };
+
+        // SAFETY: attr is valid for attr_size bytes
+        let ruleset_fd = unsafe { libc::syscall(libc::SYS_landlock_create_ruleset, &attr, attr_size, 0u32) } as libc::c_int;
+        if ruleset_fd < 0 {
+            return Err(last_os_error("landlock_create_ruleset"));
+        }
+
+        let rules = (|| {
+            for path in SYSTEM_READ_ONLY_PATHS.iter().map(Path::new).chain(paths.read_only.iter().map(|p| p.as_path())) {
+                if path.exists() {
+                    add_path_rule(ruleset_fd, path, read_only)?;
+                }
+            }
+            for path in &paths.read_write {
+                add_path_rule(ruleset_fd, path, read_write)?;
+            }
+            if abi >= 4 {
+                let rules = connect.iter().map(|&port| (port, ACCESS_NET_CONNECT_TCP))
+                    .chain(bind.iter().map(|&port| (port, ACCESS_NET_BIND_TCP)));
+                for (port, access) in rules {
+                    let rule = NetPortAttr { allowed_access: access, port: port as u64 };
+                    // SAFETY: rule is a valid net_port attribute
+                    if unsafe { libc::syscall(libc::SYS_landlock_add_rule, ruleset_fd, LANDLOCK_RULE_NET_PORT, &rule, 0u32) } != 0 {
+                        return Err(last_os_error(&format!("Adding sandbox rule for port {}", port)));
+                    }
+                }
+            }
+            // SAFETY: ruleset_fd is a Landlock ruleset; no_new_privs was set by drop_privileges
+            if unsafe { libc::syscall(libc::SYS_landlock_restrict_self, ruleset_fd, 0u32) } != 0 {
+                return Err(last_os_error("landlock_restrict_self"));
+            }
+            Ok(())
+        })();
+        // SAFETY: ruleset_fd was created above
+        unsafe { libc::close(ruleset_fd) };
+        rules.map(|_| Some(abi))
+    }
+
+    // ===============================================
+    // SECCOMP
+    // ===============================================
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xC000_003E;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xC000_00B7;
+    // x32 syscalls share the x86_64 arch tag but have this bit set
+    #[cfg(target_arch = "x86_64")]
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    // Offsets into struct seccomp_data
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const SECCOMP_DATA_NR: u32 = 0;
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const SECCOMP_DATA_ARCH: u32 = 4;
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const SECCOMP_DATA_ARG0: u32 = 16;
+
+    /// Syscalls answered with EPERM: running programs, inspecting other processes, changing identity
+    /// and administering the kernel.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const DENIED_SYSCALLS: [libc::c_long; 33] = [
+        libc::SYS_execve, libc::SYS_execveat,
+        libc::SYS_ptrace, libc::SYS_process_vm_readv, libc::SYS_process_vm_writev,
+        libc::SYS_setuid, libc::SYS_setgid, libc::SYS_setreuid, libc::SYS_setregid,
+        libc::SYS_setresuid, libc::SYS_setresgid, libc::SYS_setgroups,
+        libc::SYS_unshare, libc::SYS_setns, libc::SYS_personality,
+        libc::SYS_mount, libc::SYS_umount2, libc::SYS_pivot_root, libc::SYS_chroot,
+        libc::SYS_init_module, libc::SYS_finit_module, libc::SYS_delete_module,
+        libc::SYS_kexec_load, libc::SYS_kexec_file_load, libc::SYS_reboot,
+        libc::SYS_swapon, libc::SYS_swapoff, libc::SYS_acct,
+        libc::SYS_bpf, libc::SYS_perf_event_open, libc::SYS_userfaultfd,
+        libc::SYS_keyctl, libc::SYS_open_by_handle_at,
+    ];
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn stmt(code: u32, k: u32) -> libc::sock_filter {
+        libc::sock_filter { code: code as u16, jt: 0, jf: 0, k }
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter { code: code as u16, jt, jf, k }
+    }
+
+    /// Installs the seccomp filter. Returns false on architectures it has no syscall table for.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub fn install_seccomp_filter() -> Result<bool, String> {
+        use libc::{BPF_ABS, BPF_JEQ, BPF_JMP, BPF_K, BPF_LD, BPF_RET, BPF_W};
+        let deny = |errno: i32| stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_ERRNO | errno as u32);
+        let allow = stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_ALLOW);
+
+        let mut filter = vec![
+            stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_ARCH),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, AUDIT_ARCH, 1, 0),
+            stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+            stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_NR),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        filter.extend([jump(BPF_JMP | libc::BPF_JGE | BPF_K, X32_SYSCALL_BIT, 0, 1), deny(libc::EPERM)]);
+        for nr in DENIED_SYSCALLS {
+            filter.extend([jump(BPF_JMP | BPF_JEQ | BPF_K, nr as u32, 0, 1), deny(libc::EPERM)]);
+        }
+        // Sockets: IP for the API, Unix and netlink for name resolution; no raw or packet sockets
+        filter.extend([
+            jump(BPF_JMP | BPF_JEQ | BPF_K, libc::SYS_socket as u32, 0, 6),
+            stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_ARG0),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, libc::AF_UNIX as u32, 4, 0),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, libc::AF_INET as u32, 3, 0),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, libc::AF_INET6 as u32, 2, 0),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, libc::AF_NETLINK as u32, 1, 0),
+            deny(libc::EAFNOSUPPORT),
+            allow,
+        ]);
+
+        let program = libc::sock_fprog { len: filter.len() as u16, filter: filter.as_mut_ptr() };
+        // SAFETY: program points to `filter`, which outlives the call; the kernel copies it
+        if unsafe { libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &program as *const libc::sock_fprog) } != 0 {
+            return Err(last_os_error("Installing the seccomp filter"));
+        }
+        Ok(true)
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    pub fn install_seccomp_filter() -> Result<bool, String> {
+        Ok(false)
+    }
+}
@@ -0,0 +1,85 @@
+// src/signer.rs
+
+use crate::cardano::{self, KeyPairAndAddress};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Produces the CIP-8 signatures for registration and donation, either with a key held in this
+/// process or by handing the message to an external tool so the key never enters it.
+pub enum Signer {
+    Local(KeyPairAndAddress),
+    /// `--external-signer`: a shell command (e.g. cardano-signer, a CIP-30 bridge) signing for `address`.
+    External { address: String, command: String },
+}
+
+impl Signer {
+    pub fn address(&self) -> String {
+        match self {
+            Signer::Local(key_pair) => key_pair.2.to_bech32().unwrap(),
+            Signer::External { address, .. } => address.clone(),
+        }
+    }
+
+    /// Returns the COSE_Sign1 signature (hex) and the raw public key (hex) for `message`.
+    pub fn sign(&self, message: &str) -> Result<(String, String), String> {
+        match self {
+            Signer::Local(key_pair) => Ok(cardano::cip8_sign(key_pair, message)),
+            Signer::External { address, command } => run_external_signer(command, address, message)
+                .map_err(|e| format!("External signer '{}' failed: {}", command, e)),
+        }
+    }
+}
+
+/// Runs the signer with the request as JSON on stdin (and in SHADOW_HARVESTER_SIGN_* variables for tools
+/// taking arguments). It must print `{"signature": ..., "key": ...}` (cardano-signer '--json') or
+/// `<signature> <key>`; the key may be a raw public key or a COSE_Key.
+fn run_external_signer(command_str: &str, address: &str, message: &str) -> Result<(String, String), String> {
+    let request_json = serde_json::json!({ "address": address, "message": message }).to_string();
+
+    let mut command = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(command_str);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(command_str);
+        c
+    };
+
+    let mut child = command
+        .env("SHADOW_HARVESTER_SIGN_ADDRESS", address)
+        .env("SHADOW_HARVESTER_SIGN_MESSAGE", message)
+        .env("SHADOW_HARVESTER_SIGN_MESSAGE_HEX", hex::encode(message.as_bytes()))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not start command: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The command may take the message from the environment instead, so a broken pipe is not an error
+        let _ = stdin.write_all(request_json.as_bytes());
+    }
+
+    let output = child.wait_with_output().map_err(|e| format!("Could not wait for command: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Command exited with {}", output.status));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let (signature, key) = match serde_json::from_str::<serde_json::Value>(stdout.trim()) {
+        Ok(response) => (
+            response["signature"].as_str().ok_or("Response has no 'signature' field")?.to_string(),
+            response["key"].as_str().ok_or("Response has no 'key' field")?.to_string(),
+        ),
+        Err(_) => {
+            let mut parts = stdout.split_whitespace();
+            match (parts.next(), parts.next()) {
+                (Some(signature), Some(key)) => (signature.to_string(), key.to_string()),
+                _ => return Err("Expected a JSON object or '<signature> <key>' on stdout".to_string()),
+            }
+        }
+    };
+
+    hex::decode(&signature).map_err(|e| format!("Signature is not valid hex: {}", e))?;
+    Ok((signature.to_lowercase(), cardano::cose_key_public_key(&key)?))
+}
@@ -0,0 +1,121 @@
+// src/standby.rs
+
+use crate::api;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// The primary refreshes its heartbeat this often; the standby checks it at the same pace
+const HEARTBEAT_INTERVAL_SECS: u64 = 30;
+
+/// Written by '--heartbeat' and read by '--standby-of'.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Heartbeat {
+    pub updated_at: String,
+    pub version: String,
+    pub pid: u32,
+}
+
+// ===============================================
+// PRIMARY: HEARTBEAT WRITER
+// ===============================================
+
+fn write_heartbeat(path: &Path) -> Result<(), String> {
+    let heartbeat = Heartbeat {
+        updated_at: Utc::now().to_rfc3339(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        pid: std::process::id(),
+    };
+    let heartbeat_json = serde_json::to_string_pretty(&heartbeat)
+        .map_err(|e| format!("Could not serialize heartbeat: {}", e))?;
+
+    // Written aside and renamed, so a standby polling the file never reads half of it
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, heartbeat_json)
+        .map_err(|e| format!("Could not write heartbeat {:?}: {}", tmp_path, e))?;
+    std::fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Could not replace heartbeat {:?}: {}", path, e))
+}
+
+/// Refreshes the heartbeat file for the lifetime of the process. The first write happens before
+/// returning, so a path that can't be written is reported at startup.
+pub fn spawn_heartbeat_writer(path: String) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    write_heartbeat(&path)?;
+    println!("💓 Writing heartbeat to {:?} every {}s.", path, HEARTBEAT_INTERVAL_SECS);
+
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
+        if let Err(e) = write_heartbeat(&path) {
+            eprintln!("⚠️ {}", e);
+        }
+    });
+    Ok(())
+}
+
+// ===============================================
+// STANDBY: WAIT FOR THE PRIMARY TO GO SILENT
+// ===============================================
+
+fn age_of(updated_at: &str) -> Result<Duration, String> {
+    let updated_at = DateTime::parse_from_rfc3339(updated_at)
+        .map_err(|e| format!("Invalid heartbeat timestamp '{}': {}", updated_at, e))?;
+    Ok((Utc::now() - updated_at.with_timezone(&Utc)).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Time since the primary's last heartbeat. A URL has to serve the heartbeat JSON (e.g. the file
+/// behind a web server); a file without a parseable timestamp falls back to its modification time.
+fn heartbeat_age(client: &Client, source: &str) -> Result<Duration, String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let heartbeat: Heartbeat = api::block_on(async {
+            let response = client.get(source).send().await
+                .map_err(|e| format!("Network/Client Error: {}", e))?;
+            if !response.status().is_success() {
+                return Err(format!("Heartbeat URL answered with status {}", response.status()));
+            }
+            response.json().await.map_err(|e| format!("Heartbeat URL did not return a heartbeat: {}", e))
+        })?;
+        return age_of(&heartbeat.updated_at);
+    }
+
+    let heartbeat_json = std::fs::read_to_string(source)
+        .map_err(|e| format!("Could not read heartbeat {}: {}", source, e))?;
+    match serde_json::from_str::<Heartbeat>(&heartbeat_json) {
+        Ok(heartbeat) => age_of(&heartbeat.updated_at),
+        Err(_) => std::fs::metadata(source)
+            .and_then(|m| m.modified())
+            .map(|modified| SystemTime::now().duration_since(modified).unwrap_or(Duration::ZERO))
+            .map_err(|e| format!("Could not read heartbeat {}: {}", source, e)),
+    }
+}
+
+/// Blocks while the primary's heartbeat is fresh. Returns once it is older than `timeout`, or has
+/// been unreadable for that long, at which point this machine takes over.
+pub fn wait_while_primary_alive(client: &Client, source: &str, timeout: Duration) {
+    println!("\n==============================================");
+    println!("🛌 Shadow Harvester: WARM STANDBY of {}", source);
+    println!("==============================================");
+    println!("Not mining or registering while the primary's heartbeat is younger than {}s.", timeout.as_secs());
+
+    let mut last_seen_alive = SystemTime::now();
+    loop {
+        match heartbeat_age(client, source) {
+            Ok(age) if age <= timeout => last_seen_alive = SystemTime::now() - age,
+            Ok(age) => {
+                println!("\n🚨 Primary heartbeat is {}s old. Taking over.", age.as_secs());
+                return;
+            },
+            Err(e) => eprintln!("⚠️ Could not check the primary's heartbeat: {}", e),
+        }
+
+        let silent_for = SystemTime::now().duration_since(last_seen_alive).unwrap_or(Duration::ZERO);
+        if silent_for > timeout {
+            println!("\n🚨 No heartbeat from the primary for {}s. Taking over.", silent_for.as_secs());
+            return;
+        }
+        thread::sleep(Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
+    }
+}
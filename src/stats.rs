@@ -0,0 +1,83 @@
+// src/stats.rs
+
+// 'stats network': the network-wide totals of GET /statistics, the STAR each solution earns per mining day
+// (GET /work_to_star_rate), and how the given addresses rank by solutions with their share of the
+// network's. The API publishes no leaderboard, so addresses are ranked among themselves.
+
+use crate::api;
+use crate::proxies::WalletClients;
+use crate::receipts::{read_wallet_notes, read_wallets_file};
+use crate::utils::format_thousands;
+
+/// Prints the network statistics, ranking `addresses` and those of `wallets_file`.
+pub fn run_network_stats(clients: &WalletClients, api_url: &str, addresses: Vec<String>, wallets_file: Option<&str>) -> Result<(), String> {
+    let mut addresses = addresses;
+    let notes = wallets_file.map(read_wallet_notes).unwrap_or_default();
+    if let Some(path) = wallets_file {
+        for address in read_wallets_file(path)? {
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+    }
+    if addresses.is_empty() {
+        return Err("'stats network' needs '--address' or '--wallets-file': the API serves the network totals with an address's statistics.".to_string());
+    }
+
+    let mut all_stats = api::block_on(api::fetch_statistics_many(clients, api_url, &addresses));
+    let star_rates = api::block_on(api::fetch_work_to_star_rate(clients.for_wallet(&addresses[0]), api_url));
+    let mut ranked = Vec::new();
+    for address in &addresses {
+        match all_stats.remove(address) {
+            Some(Ok(stats)) => ranked.push(stats),
+            Some(Err(e)) => eprintln!("❌ {}: could not fetch statistics: {}", address, e),
+            None => eprintln!("❌ {}: could not fetch statistics", address),
+        }
+    }
+    let Some(network) = ranked.first() else {
+        return Err("Could not fetch the statistics of any address.".to_string());
+    };
+
+    println!("\n==============================================");
+    println!("🌐 Network Statistics");
+    println!("==============================================");
+    println!("  Wallets:            {}", format_thousands(network.wallets as u64));
+    println!("  Challenges:         {} / {}", network.challenges, network.total_challenges);
+    println!("  Solutions (total):  {}", format_thousands(network.total_crypto_receipts as u64));
+    println!("  Solutions (recent): {}", format_thousands(network.recent_crypto_receipts as u64));
+    let total_receipts = network.total_crypto_receipts;
+
+    println!("----------------------------------------------");
+    println!("** STAR PER SOLUTION BY DAY **");
+    match star_rates {
+        Ok(rates) if rates.is_empty() => println!("  No rates published yet."),
+        Ok(rates) => for (day, rate) in rates.iter().enumerate() {
+            println!("  Day {:<3} {:>16}", day + 1, format_thousands(rate.round() as u64));
+        },
+        Err(e) => println!("  ❌ Could not fetch the rates: {}", e),
+    }
+
+    println!("----------------------------------------------");
+    println!("** MY ADDRESSES **");
+    ranked.sort_by_key(|stats| std::cmp::Reverse(stats.crypto_receipts));
+    println!("  {:>4}  {:<20} {:>9} {:>8} {:>12}", "RANK", "ADDRESS", "SOLUTIONS", "SHARE", "NIGHT");
+    for (rank, stats) in ranked.iter().enumerate() {
+        let share = if total_receipts == 0 { 0.0 } else { 100.0 * stats.crypto_receipts as f64 / total_receipts as f64 };
+        let label = notes.get(&stats.local_address).cloned().unwrap_or_else(|| abbreviate(&stats.local_address));
+        println!("  {:>4}  {:<20} {:>9} {:>7.3}% {:>12}", rank + 1, label, format_thousands(stats.crypto_receipts as u64), share, format_thousands(stats.night_allocation as u64));
+    }
+    let mine: u64 = ranked.iter().map(|stats| stats.crypto_receipts as u64).sum();
+    if ranked.len() > 1 {
+        println!("  Total: {} solution(s), {:.3}% of the network's.", format_thousands(mine), if total_receipts == 0 { 0.0 } else { 100.0 * mine as f64 / total_receipts as f64 });
+    }
+    println!("==============================================");
+    Ok(())
+}
+
+// "addr1qxy...k3z9"
+fn abbreviate(address: &str) -> String {
+    match (address.get(..8), address.get(address.len().saturating_sub(8)..)) {
+        (Some(start), Some(end)) if address.len() > 20 => format!("{}...{}", start, end),
+        _ => address.to_string(),
+    }
+}
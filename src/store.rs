@@ -0,0 +1,393 @@
+// src/store.rs
+
+use crate::data_types::{ChallengeData, DataDir, NonceCheckpointRecord, PendingSolution, FILE_NAME_FOUND_SOLUTION, FILE_NAME_RECEIPT};
+use crate::journal;
+use chrono::{DateTime, Utc};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::Duration;
+
+mod s3;
+pub use s3::S3Store;
+
+/// Where the mining loops keep their state: challenges, receipt lookups, each wallet's crash-recovery
+/// and nonce checkpoint files, and additions to the submission queue. A wallet is identified by its `DataDir` (kind plus
+/// address or mnemonic index). Another backend, e.g. networked or object storage for fleets with
+/// ephemeral disks, is one more implementation; the mining code doesn't change.
+pub trait StateStore: std::fmt::Debug + Send + Sync {
+    fn save_challenge(&self, wallet: &DataDir, challenge: &ChallengeData) -> Result<(), String>;
+
+    fn receipt_exists(&self, wallet: &DataDir, challenge_id: &str) -> Result<bool, String>;
+
+    /// Adds a solution to the submission queue.
+    fn save_pending_solution(&self, solution: &PendingSolution) -> Result<(), String>;
+
+    /// Crash recovery: a solution found but not queued yet.
+    fn save_found_solution(&self, wallet: &DataDir, solution: &PendingSolution) -> Result<(), String>;
+    fn load_found_solution(&self, wallet: &DataDir, challenge_id: &str) -> Result<Option<PendingSolution>, String>;
+    fn delete_found_solution(&self, wallet: &DataDir, challenge_id: &str) -> Result<(), String>;
+
+    fn save_nonce_checkpoint(&self, wallet: &DataDir, checkpoint: &NonceCheckpointRecord) -> Result<(), String>;
+    fn load_nonce_checkpoint(&self, wallet: &DataDir, challenge_id: &str) -> Option<NonceCheckpointRecord>;
+    fn delete_nonce_checkpoint(&self, wallet: &DataDir, challenge_id: &str) -> Result<(), String>;
+
+    /// Reserves `address` for `challenge` so other instances sharing the state skip it. False if another
+    /// instance holds it. Always true for stores that aren't shared.
+    fn claim(&self, _address: &str, _challenge: &ChallengeData) -> Result<bool, String> {
+        Ok(true)
+    }
+}
+
+// ===============================================
+// FILESYSTEM ('--data-dir')
+// ===============================================
+
+/// The '--data-dir' layout ('--receipts-dir' and '--queue-dir' included). Challenge switches are journaled.
+#[derive(Debug, Clone)]
+pub struct FsStore {
+    base_dir: String,
+}
+
+impl FsStore {
+    pub fn new(base_dir: &str) -> Self {
+        Self { base_dir: base_dir.to_string() }
+    }
+}
+
+impl StateStore for FsStore {
+    fn save_challenge(&self, wallet: &DataDir, challenge: &ChallengeData) -> Result<(), String> {
+        journal::save_challenge(&self.base_dir, wallet, challenge)
+    }
+
+    fn receipt_exists(&self, wallet: &DataDir, challenge_id: &str) -> Result<bool, String> {
+        Ok(wallet.receipt_dir(&self.base_dir, challenge_id)?.join(FILE_NAME_RECEIPT).exists())
+    }
+
+    fn save_pending_solution(&self, solution: &PendingSolution) -> Result<(), String> {
+        DataDir::Ephemeral(&solution.address).save_pending_solution(&self.base_dir, solution)
+    }
+
+    fn save_found_solution(&self, wallet: &DataDir, solution: &PendingSolution) -> Result<(), String> {
+        wallet.save_found_solution(&self.base_dir, &solution.challenge_id, solution)
+    }
+
+    fn load_found_solution(&self, wallet: &DataDir, challenge_id: &str) -> Result<Option<PendingSolution>, String> {
+        let path = wallet.receipt_dir(&self.base_dir, challenge_id)?.join(FILE_NAME_FOUND_SOLUTION);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let solution_json = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read recovery file {:?}: {}", path, e))?;
+        serde_json::from_str(&solution_json)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse recovery solution JSON {:?}: {}", path, e))
+    }
+
+    fn delete_found_solution(&self, wallet: &DataDir, challenge_id: &str) -> Result<(), String> {
+        wallet.delete_found_solution(&self.base_dir, challenge_id)
+    }
+
+    fn save_nonce_checkpoint(&self, wallet: &DataDir, checkpoint: &NonceCheckpointRecord) -> Result<(), String> {
+        wallet.save_nonce_checkpoint(&self.base_dir, checkpoint)
+    }
+
+    fn load_nonce_checkpoint(&self, wallet: &DataDir, challenge_id: &str) -> Option<NonceCheckpointRecord> {
+        wallet.load_nonce_checkpoint(&self.base_dir, challenge_id)
+    }
+
+    fn delete_nonce_checkpoint(&self, wallet: &DataDir, challenge_id: &str) -> Result<(), String> {
+        wallet.delete_nonce_checkpoint(&self.base_dir, challenge_id)
+    }
+}
+
+// ===============================================
+// REDIS ('--state')
+// ===============================================
+
+pub const REDIS_DEFAULT_PORT: u16 = 6379;
+const REDIS_KEY_PREFIX: &str = "shadow-harvester:";
+const REDIS_TIMEOUT: Duration = Duration::from_secs(5);
+// Queued solutions stay visible to the fleet for longer than a challenge is open
+const REDIS_SOLUTION_TTL_SECS: u64 = 7 * 24 * 3600;
+const REDIS_MAX_CLAIM_SECS: i64 = 24 * 3600;
+
+/// '--state redis://[[user]:password@]host[:port][/db]': the data dir as with `FsStore`, plus which
+/// address/challenge pairs have a queued solution or are being mined, shared through Redis by the
+/// instances of a fleet. Redis errors are logged and answered from the data dir alone.
+#[derive(Debug)]
+pub struct RedisStore {
+    local: FsStore,
+    url: reqwest::Url,
+    /// Stable across restarts, so a restarted instance takes its own claims back.
+    instance: String,
+    connection: Mutex<Option<BufReader<TcpStream>>>,
+}
+
+impl RedisStore {
+    /// Fails if `url` isn't a redis:// URL or Redis can't be reached.
+    pub fn connect(url: &str, base_dir: &str) -> Result<Self, String> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid '--state' URL '{}': {}", url, e))?;
+        if parsed.scheme() != "redis" {
+            return Err(format!("Unsupported '--state' URL '{}': expected redis://host[:port][/db] or s3://bucket[/prefix].", url));
+        }
+        let base_dir_path = std::path::absolute(base_dir).unwrap_or_else(|_| base_dir.into());
+        let store = Self {
+            local: FsStore::new(base_dir),
+            url: parsed,
+            instance: format!("{}:{}", crate::utils::hostname(), base_dir_path.display()),
+            connection: Mutex::new(None),
+        };
+        store.command(&["PING"])?;
+        println!("🗄️ Sharing mining state through Redis at {} as '{}'.",
+            store.url.host_str().unwrap_or("localhost"), store.instance);
+        Ok(store)
+    }
+
+    fn solution_key(address: &str, challenge_id: &str) -> String {
+        format!("{}solution:{}:{}", REDIS_KEY_PREFIX, challenge_id, address)
+    }
+
+    /// Runs `args` as one command, reconnecting once if the connection was lost.
+    fn command(&self, args: &[&str]) -> Result<RedisReply, String> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let mut result = Err(String::new());
+        for _ in 0..2 {
+            if connection.is_none() {
+                *connection = Some(self.open()?);
+            }
+            result = redis_call(connection.as_mut().unwrap(), args);
+            match &result {
+                Err(_) => *connection = None,
+                Ok(_) => break,
+            }
+        }
+        match result? {
+            RedisReply::Error(e) => Err(format!("Redis {} failed: {}", args[0], e)),
+            reply => Ok(reply),
+        }
+    }
+
+    fn open(&self) -> Result<BufReader<TcpStream>, String> {
+        let host = self.url.host_str().unwrap_or("localhost");
+        let port = self.url.port().unwrap_or(REDIS_DEFAULT_PORT);
+        let address = (host, port).to_socket_addrs().ok().and_then(|mut a| a.next())
+            .ok_or_else(|| format!("Could not resolve Redis host '{}'.", host))?;
+        let stream = TcpStream::connect_timeout(&address, REDIS_TIMEOUT)
+            .map_err(|e| format!("Could not connect to Redis at {}:{}: {}", host, port, e))?;
+        stream.set_read_timeout(Some(REDIS_TIMEOUT)).and_then(|_| stream.set_write_timeout(Some(REDIS_TIMEOUT)))
+            .map_err(|e| format!("Could not configure the Redis connection: {}", e))?;
+        let mut connection = BufReader::new(stream);
+
+        let password = self.url.password().map(percent_decode);
+        let user = Some(percent_decode(self.url.username())).filter(|u| !u.is_empty());
+        let auth = match (&user, &password) {
+            (Some(user), Some(password)) => vec!["AUTH", user, password],
+            (None, Some(password)) => vec!["AUTH", password],
+            _ => vec![],
+        };
+        let db = self.url.path().trim_start_matches('/');
+        for args in [auth, if db.is_empty() { vec![] } else { vec!["SELECT", db] }] {
+            if args.is_empty() {
+                continue;
+            }
+            if let RedisReply::Error(e) = redis_call(&mut connection, &args)? {
+                return Err(format!("Redis {} failed: {}", args[0], e));
+            }
+        }
+        Ok(connection)
+    }
+
+    /// Whether another instance queued a solution for `address` and `challenge_id`.
+    fn solution_shared(&self, address: &str, challenge_id: &str) -> bool {
+        match self.command(&["EXISTS", &Self::solution_key(address, challenge_id)]) {
+            Ok(reply) => reply == RedisReply::Integer(1),
+            Err(e) => {
+                eprintln!("⚠️ {}", e);
+                false
+            },
+        }
+    }
+}
+
+impl StateStore for RedisStore {
+    fn save_challenge(&self, wallet: &DataDir, challenge: &ChallengeData) -> Result<(), String> {
+        self.local.save_challenge(wallet, challenge)
+    }
+
+    fn receipt_exists(&self, wallet: &DataDir, challenge_id: &str) -> Result<bool, String> {
+        if self.local.receipt_exists(wallet, challenge_id)? {
+            return Ok(true);
+        }
+        // Mnemonic wallets are also looked up by address (`DataDir::Persistent`)
+        Ok(match wallet {
+            DataDir::Persistent(address) | DataDir::Ephemeral(address) => self.solution_shared(address, challenge_id),
+            DataDir::Mnemonic(_) => false,
+        })
+    }
+
+    fn save_pending_solution(&self, solution: &PendingSolution) -> Result<(), String> {
+        self.local.save_pending_solution(solution)?;
+        let key = Self::solution_key(&solution.address, &solution.challenge_id);
+        if let Err(e) = self.command(&["SET", &key, &self.instance, "EX", &REDIS_SOLUTION_TTL_SECS.to_string()]) {
+            eprintln!("⚠️ Solution queued locally but not shared with the fleet: {}", e);
+        }
+        Ok(())
+    }
+
+    fn save_found_solution(&self, wallet: &DataDir, solution: &PendingSolution) -> Result<(), String> {
+        self.local.save_found_solution(wallet, solution)
+    }
+
+    fn load_found_solution(&self, wallet: &DataDir, challenge_id: &str) -> Result<Option<PendingSolution>, String> {
+        self.local.load_found_solution(wallet, challenge_id)
+    }
+
+    fn delete_found_solution(&self, wallet: &DataDir, challenge_id: &str) -> Result<(), String> {
+        self.local.delete_found_solution(wallet, challenge_id)
+    }
+
+    fn save_nonce_checkpoint(&self, wallet: &DataDir, checkpoint: &NonceCheckpointRecord) -> Result<(), String> {
+        self.local.save_nonce_checkpoint(wallet, checkpoint)
+    }
+
+    fn load_nonce_checkpoint(&self, wallet: &DataDir, challenge_id: &str) -> Option<NonceCheckpointRecord> {
+        self.local.load_nonce_checkpoint(wallet, challenge_id)
+    }
+
+    fn delete_nonce_checkpoint(&self, wallet: &DataDir, challenge_id: &str) -> Result<(), String> {
+        self.local.delete_nonce_checkpoint(wallet, challenge_id)
+    }
+
+    /// Claims `address` for `challenge` until its submission deadline. False if another instance holds the claim.
+    fn claim(&self, address: &str, challenge: &ChallengeData) -> Result<bool, String> {
+        let key = format!("{}claim:{}:{}", REDIS_KEY_PREFIX, challenge.challenge_id, address);
+        let ttl = DateTime::parse_from_rfc3339(&challenge.latest_submission)
+            .map(|deadline| (deadline.with_timezone(&Utc) - Utc::now()).num_seconds())
+            .unwrap_or(REDIS_MAX_CLAIM_SECS)
+            .clamp(60, REDIS_MAX_CLAIM_SECS);
+        if self.command(&["SET", &key, &self.instance, "NX", "EX", &ttl.to_string()])? != RedisReply::Nil {
+            return Ok(true);
+        }
+        Ok(self.command(&["GET", &key])? == RedisReply::Bulk(self.instance.clone()))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum RedisReply {
+    Status(String),
+    Error(String),
+    Integer(i64),
+    Bulk(String),
+    Nil,
+}
+
+/// Sends one RESP command and reads its reply (arrays aren't used).
+fn redis_call<S: Read + Write>(connection: &mut BufReader<S>, args: &[&str]) -> Result<RedisReply, String> {
+    let mut request = format!("*{}\r\n", args.len());
+    for arg in args {
+        request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    let io_error = |e: std::io::Error| format!("Redis connection failed: {}", e);
+    connection.get_mut().write_all(request.as_bytes()).map_err(io_error)?;
+
+    let mut line = String::new();
+    connection.read_line(&mut line).map_err(io_error)?;
+    let line = line.trim_end_matches(['\r', '\n']);
+    let (kind, value) = line.split_at_checked(1).ok_or("Redis closed the connection.")?;
+    match kind {
+        "+" => Ok(RedisReply::Status(value.to_string())),
+        "-" => Ok(RedisReply::Error(value.to_string())),
+        ":" => value.parse().map(RedisReply::Integer).map_err(|_| format!("Malformed Redis reply '{}'.", line)),
+        "$" if value == "-1" => Ok(RedisReply::Nil),
+        "$" => {
+            let len: usize = value.parse().map_err(|_| format!("Malformed Redis reply '{}'.", line))?;
+            let mut data = vec![0u8; len + 2];
+            connection.read_exact(&mut data).map_err(io_error)?;
+            data.truncate(len);
+            Ok(RedisReply::Bulk(String::from_utf8_lossy(&data).into_owned()))
+        },
+        _ => Err(format!("Unexpected Redis reply '{}'.", line)),
+    }
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => { decoded.push(byte); i += 3; },
+            (byte, _) => { decoded.push(byte); i += 1; },
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Replays `replies` to whatever is written to it.
+    struct Replay {
+        replies: Cursor<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl Read for Replay {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.replies.read(buf)
+        }
+    }
+
+    impl Write for Replay {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn replay(replies: &[u8]) -> BufReader<Replay> {
+        BufReader::new(Replay { replies: Cursor::new(replies.to_vec()), written: Vec::new() })
+    }
+
+    #[test]
+    fn sends_commands_as_bulk_string_arrays() {
+        let mut connection = replay(b"+OK\r\n");
+        assert_eq!(redis_call(&mut connection, &["SET", "key", "a b", "NX"]), Ok(RedisReply::Status("OK".to_string())));
+        assert_eq!(connection.get_ref().written, b"*4\r\n$3\r\nSET\r\n$3\r\nkey\r\n$3\r\na b\r\n$2\r\nNX\r\n");
+    }
+
+    #[test]
+    fn parses_each_reply_kind() {
+        let mut connection = replay(b"+PONG\r\n-ERR wrong type\r\n:42\r\n:-1\r\n$5\r\nhello\r\n$0\r\n\r\n$-1\r\n$8\r\nline\r\nx!\r\n");
+        let mut next = || redis_call(&mut connection, &["GET", "k"]);
+        assert_eq!(next(), Ok(RedisReply::Status("PONG".to_string())));
+        assert_eq!(next(), Ok(RedisReply::Error("ERR wrong type".to_string())));
+        assert_eq!(next(), Ok(RedisReply::Integer(42)));
+        assert_eq!(next(), Ok(RedisReply::Integer(-1)));
+        assert_eq!(next(), Ok(RedisReply::Bulk("hello".to_string())));
+        assert_eq!(next(), Ok(RedisReply::Bulk(String::new())));
+        assert_eq!(next(), Ok(RedisReply::Nil));
+        // A bulk string is read by length, so it may hold CRLF
+        assert_eq!(next(), Ok(RedisReply::Bulk("line\r\nx!".to_string())));
+    }
+
+    #[test]
+    fn rejects_malformed_and_missing_replies() {
+        for reply in [&b":forty\r\n"[..], b"$x\r\n", b"*1\r\n", b"", b"$10\r\nshort\r\n"] {
+            assert!(redis_call(&mut replay(reply), &["PING"]).is_err(), "{:?}", String::from_utf8_lossy(reply));
+        }
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(percent_decode("p%40ss%3Aword"), "p@ss:word");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+}
@@ -1,20 +1,74 @@
 // src/submitter.rs
 
-use crate::data_types::{PendingSolution, DataDir};
+use crate::data_types::{ChallengeData, PendingSolution, DataDir, QueueEntry, QueueEvent, FILE_NAME_CHALLENGE, checksum_bytes, count_local_receipts_for_address, has_challenge_receipt, is_queue_file, queue_dir, read_pending_solution, receipts_root};
 use crate::api;
+use crate::error::ApiError;
+use crate::audit;
+use crate::notify::{Notifications, NotifyEvent};
 use crate::backoff::Backoff;
-use reqwest::blocking::Client;
+use crate::proxies::WalletClients;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::sync::Mutex;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
 use std::{fs, thread};
 
 // CONSTANTS for the submitter loop
 const SUBMISSION_INTERVAL_SECS: u64 = 5;
-const QUEUE_BASE_DIR: &str = "pending_submissions";
+// Spacing between submissions once a closed submission window reopens, so the backlog doesn't arrive at once
+const BURST_SPACING_MILLIS: u64 = 250;
 
-pub fn run_submitter_thread(client: Client, api_url: String, data_dir_base: String) -> Result<(), String> {
+/// Outcome of one attempt at a queued file that the queue loop has to act on.
+enum Attempt {
+    Done,
+    // The API is not taking submissions yet: hold the whole queue for this long
+    WindowClosed(Duration),
+}
+
+/// Submission window as seen from the API's responses.
+#[derive(PartialEq)]
+enum Window {
+    Open,
+    Closed { reopens_at: Instant },
+    // Open again, with the held queue still being worked through
+    Reopened,
+}
+
+// Addresses whose queued solutions stay in the queue, e.g. while their registration keeps failing
+static HELD_ADDRESSES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Keeps the queued solutions of an address out of submission until `release_address`. Submitting
+/// them unregistered would be rejected and delete them from the queue.
+pub fn hold_address(address: &str) {
+    if let Ok(mut held) = HELD_ADDRESSES.lock()
+        && !held.iter().any(|a| a == address)
+    {
+        held.push(address.to_string());
+    }
+}
+
+pub fn release_address(address: &str) {
+    if let Ok(mut held) = HELD_ADDRESSES.lock() {
+        held.retain(|a| a != address);
+    }
+}
+
+fn is_held(path: &Path) -> bool {
+    queue_entry_for(path).is_some_and(|entry| HELD_ADDRESSES.lock().is_ok_and(|held| held.contains(&entry.address)))
+}
+
+/// Reports queue changes to the miner's in-memory index. Sending fails once the miner is gone, which is fine to ignore.
+fn notify(queue_events: &Sender<QueueEvent>, event: QueueEvent) {
+    let _ = queue_events.send(event);
+}
+
+pub fn run_submitter_thread(clients: WalletClients, api_url: String, data_dir_base: String, check_before_submit: bool, queue_events: Sender<QueueEvent>, notifications: Notifications) -> Result<(), String> {
     println!("📦 Starting background submission queue monitor.");
-    let queue_path = PathBuf::from(&data_dir_base).join(QUEUE_BASE_DIR);
+    let queue_path = queue_dir(&data_dir_base);
+    // Queue files already reported to the index
+    let mut known_entries: HashSet<QueueEntry> = HashSet::new();
 
     if !queue_path.exists() {
         if let Err(e) = fs::create_dir_all(&queue_path) {
@@ -22,19 +76,67 @@ pub fn run_submitter_thread(client: Client, api_url: String, data_dir_base: Stri
         }
     }
 
+    let mut window = Window::Open;
+
     loop {
+        if let Window::Closed { reopens_at } = window {
+            thread::sleep(reopens_at.saturating_duration_since(Instant::now()));
+        }
+
         // --- 1. Scan for pending solution files ---
         let mut processed_submission = false;
         match fs::read_dir(&queue_path) {
             Ok(entries) => {
-                for entry in entries.filter_map(|e| e.ok()) {
-                    let path = entry.path();
-                    if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
-                        // Attempt to process the file, break on success to immediately check the next one
-                        if process_pending_solution(&client, &api_url, &path, &data_dir_base).is_ok() {
+                let mut files: Vec<PathBuf> = entries.filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|path| path.is_file() && path.file_name().and_then(|n| n.to_str()).is_some_and(is_queue_file))
+                    .collect();
+                // Solutions whose challenge closes first go first
+                sort_by_deadline(&mut files, &data_dir_base);
+
+                // Announce files queued by the miner (or a previous run) since the last scan
+                for path in &files {
+                    if let Some(queue_entry) = queue_entry_for(path)
+                        && known_entries.insert(queue_entry.clone())
+                    {
+                        notify(&queue_events, QueueEvent::Queued(queue_entry));
+                    }
+                }
+
+                for path in files.iter().filter(|path| !is_held(path)) {
+                    // Attempt to process the file, break on success to immediately check the next one
+                    let result = process_pending_solution(&clients, &api_url, path, &data_dir_base, check_before_submit, &notifications);
+
+                    // Any outcome that deleted the file takes it out of the index
+                    if !path.exists()
+                        && let Some(queue_entry) = queue_entry_for(path)
+                    {
+                        known_entries.remove(&queue_entry);
+                        notify(&queue_events, QueueEvent::Removed(queue_entry));
+                    }
+
+                    match result {
+                        Ok(Attempt::Done) => {
+                            if let Window::Closed { .. } = window {
+                                println!("▶️ Submission window is open. Submitting held solutions by deadline.");
+                                window = Window::Reopened;
+                            }
+                            if window == Window::Reopened {
+                                // Work through the held queue in a paced burst
+                                thread::sleep(Duration::from_millis(BURST_SPACING_MILLIS));
+                            }
                             processed_submission = true;
                             break;
-                        }
+                        },
+                        Ok(Attempt::WindowClosed(wait)) => {
+                            if !matches!(window, Window::Closed { .. }) {
+                                println!("⏸️ Submission window is closed. Holding {} queued solution(s), next try in {}s.", files.len(), wait.as_secs());
+                            }
+                            window = Window::Closed { reopens_at: Instant::now() + wait };
+                            processed_submission = true;
+                            break;
+                        },
+                        Err(_) => {},
                     }
                 }
             },
@@ -42,58 +144,154 @@ pub fn run_submitter_thread(client: Client, api_url: String, data_dir_base: Stri
         }
 
         // --- 2. Sleep based on activity ---
+        if !processed_submission && window == Window::Reopened {
+            window = Window::Open;
+        }
         if !processed_submission {
             thread::sleep(Duration::from_secs(SUBMISSION_INTERVAL_SECS));
         }
     }
 }
 
-fn process_pending_solution(client: &Client, api_url: &str, file_path: &Path, data_dir_base: &str) -> Result<(), String> {
-    // --- 1. Load the pending solution ---
-    let solution_json = fs::read_to_string(file_path)
-        .map_err(|e| format!("Failed to read pending solution file {:?}: {}", file_path, e))?;
+/// Warns if the saved challenge.json no longer matches the checksum the solution was queued with.
+fn warn_on_challenge_mismatch(solution: &PendingSolution, data_dir_base: &str) {
+    let expected = match solution.challenge_checksum.as_deref() {
+        Some(c) => c,
+        None => return,
+    };
+    let challenge_path = receipts_root(data_dir_base).join(&solution.challenge_id).join(FILE_NAME_CHALLENGE);
+    if let Ok(challenge_json) = fs::read(&challenge_path) {
+        let actual = checksum_bytes(&challenge_json);
+        if actual != expected {
+            eprintln!("⚠️ Saved challenge {:?} (checksum {}) differs from the one this solution was mined against ({}). Submitting anyway...", challenge_path, actual, expected);
+        }
+    }
+}
+
+/// Orders queue files by the submission deadline of their challenge (from the saved challenge.json),
+/// files of unknown challenges last.
+fn sort_by_deadline(files: &mut [PathBuf], data_dir_base: &str) {
+    let mut deadlines: HashMap<String, Option<DateTime<Utc>>> = HashMap::new();
+    let mut deadline_of = |path: &PathBuf| {
+        let challenge_id = queue_entry_for(path)?.challenge_id;
+        *deadlines.entry(challenge_id.clone()).or_insert_with(|| {
+            let challenge_json = fs::read(receipts_root(data_dir_base).join(&challenge_id).join(FILE_NAME_CHALLENGE)).ok()?;
+            let challenge: ChallengeData = serde_json::from_slice(&challenge_json).ok()?;
+            DateTime::parse_from_rfc3339(&challenge.latest_submission).ok().map(|d| d.with_timezone(&Utc))
+        })
+    };
+    files.sort_by_cached_key(|path| {
+        let deadline = deadline_of(path);
+        (deadline.is_none(), deadline, path.clone())
+    });
+}
+
+fn queue_entry_for(path: &Path) -> Option<QueueEntry> {
+    path.file_name().and_then(|name| name.to_str()).and_then(QueueEntry::from_file_name)
+}
 
-    let solution: PendingSolution = serde_json::from_str(&solution_json)
-        .map_err(|e| format!("Failed to parse pending solution JSON {:?}: {}", file_path, e))?;
+// Determine the correct DataDir variant for saving the receipt
+// Heuristic: differentiate Ephemeral from Persistent/Mnemonic based on address string.
+fn receipt_data_dir(address: &str) -> DataDir<'_> {
+    if address.starts_with("addr_vk") {
+        DataDir::Ephemeral(address)
+    } else {
+        // Use Persistent as a default for address-based pathing (covers both Persistent and Mnemonic key modes' final address structure)
+        DataDir::Persistent(address)
+    }
+}
+
+/// Returns the API receipt count if it credits the address with more receipts than we hold locally,
+/// meaning a previous submission was accepted but its receipt was lost (e.g. after a reinstall). The
+/// statistics don't say for which challenges, so this alone never marks a solution as a duplicate.
+fn uncounted_api_receipts(clients: &WalletClients, api_url: &str, data_dir_base: &str, address: &str) -> Result<Option<u32>, String> {
+    let stats = api::block_on(api::fetch_statistics(clients.for_wallet(address), api_url, address))?;
+    let local_receipts = count_local_receipts_for_address(data_dir_base, address);
+
+    if stats.crypto_receipts as usize > local_receipts {
+        Ok(Some(stats.crypto_receipts))
+    } else {
+        Ok(None)
+    }
+}
+
+fn process_pending_solution(clients: &WalletClients, api_url: &str, file_path: &Path, data_dir_base: &str, check_before_submit: bool, notifications: &Notifications) -> Result<Attempt, String> {
+    // --- 1. Load the pending solution ---
+    let solution: PendingSolution = read_pending_solution(file_path)?;
 
     println!("\n📦 Attempting to submit queued solution for Challenge ID {} (Nonce: {})...", solution.challenge_id, solution.nonce);
+    warn_on_challenge_mismatch(&solution, data_dir_base);
+
+    // --- 2. Optional duplicate check ---
+    // The API credits one solution per address and challenge, so only a receipt for this challenge proves a duplicate.
+    // A duplicate the local receipts don't show is answered with 409 Conflict below, and removed then.
+    if check_before_submit {
+        if has_challenge_receipt(data_dir_base, &solution.address, &solution.challenge_id) {
+            println!("✅ Address {} already has a receipt for Challenge {}. Removing the duplicate solution from the queue.", solution.address, solution.challenge_id);
+            if let Err(e) = fs::remove_file(file_path) {
+                eprintln!("⚠️ WARNING: FAILED TO DELETE PENDING FILE {:?} for already credited solution: {}.", file_path, e);
+            }
+            return Ok(Attempt::Done);
+        }
+        match uncounted_api_receipts(clients, api_url, data_dir_base, &solution.address) {
+            Ok(Some(api_receipts)) => println!("ℹ️ Address {} has {} credited receipt(s) on the API, more than saved locally ('receipts backfill' records them). Submitting anyway: the API rejects a duplicate.", solution.address, api_receipts),
+            Ok(None) => {},
+            Err(e) => eprintln!("⚠️ Duplicate check against API state failed: {}. Submitting anyway...", e),
+        }
+    }
 
-    // --- 2. Submission Retry Loop (with Backoff) ---
+    // --- 3. Submission Retry Loop (with Backoff) ---
     let mut backoff = Backoff::new(5, 300, 2.0); // min 5s, max 300s, 2.0 factor
     let mut final_receipt: Option<serde_json::Value> = None;
     let mut submission_success = false;
     let mut non_recoverable_error = false;
 
-    // Retry indefinitely on network errors, but break on API validation errors
+    // Retry indefinitely on network errors, but break on API validation errors.
+    // Every attempt carries the same idempotency key, so a retry after a timeout can't be applied twice.
+    let idempotency_key = solution.idempotency_key();
     loop {
-        match api::submit_solution(
-            client, api_url, &solution.address, &solution.challenge_id, &solution.nonce,
-        ) {
+        let submission = api::block_on(api::submit_solution(
+            clients.for_wallet(&solution.address), api_url, &solution.address, &solution.challenge_id, &solution.nonce, &idempotency_key,
+        ));
+        audit::record(Some(data_dir_base), "solution", &solution.address, &idempotency_key, &submission);
+        match submission {
             Ok(receipt) => {
                 final_receipt = Some(receipt);
                 submission_success = true;
                 break;
             },
-            Err(e) if e.contains("Network/Client Error") => {
-                eprintln!("⚠️ Solution submission failed (Network Error): {}. Retrying...", e);
+            Err(ApiError::RateLimited { retry_after, .. }) => {
+                // Not a failure of this solution: leave it queued for when the window opens
+                return Ok(Attempt::WindowClosed(retry_after));
+            },
+            Err(ApiError::AlreadySolved(message)) => {
+                // An earlier attempt was accepted but its answer lost, e.g. to a timeout
+                println!("✅ Challenge {} is already solved by {} ({}). Removing it from the queue.", solution.challenge_id, solution.address, message);
+                let receipt = serde_json::json!({ "already_credited": true, "source": "solution", "message": message });
+                if let Err(e) = receipt_data_dir(&solution.address).save_receipt(data_dir_base, &solution.challenge_id, &receipt) {
+                    eprintln!("⚠️ WARNING: Could not save local receipt for already solved challenge: {}", e);
+                }
+                if let Err(e) = fs::remove_file(file_path) {
+                    eprintln!("⚠️ WARNING: FAILED TO DELETE PENDING FILE {:?} for already solved challenge: {}.", file_path, e);
+                }
+                return Ok(Attempt::Done);
+            },
+            Err(e) if e.is_transient() => {
+                // The API was unreachable or failing (5xx) through every retry of the call
+                eprintln!("⚠️ Solution submission failed (API unavailable): {}. Retrying with backoff...", e);
                 backoff.sleep();
                 continue;
             },
             Err(e) => {
-                // Determine if this is a recoverable 5xx error or a non-recoverable 4xx validation error.
-                // We check for 50x or 51x (to be safe) status codes, which indicate server-side failure.
-                let is_5xx_server_error = e.contains("(Status 50") || e.contains("(Status 51");
-
-                if is_5xx_server_error {
-                    eprintln!("⚠️ Solution submission failed (Server Error, possibly transient): {}. Retrying with backoff...", e);
-                    backoff.sleep();
-                    continue;
-                } else {
-                    // Treat 4xx errors (API Validation, Already Solved, Challenge Expired, etc.) as non-recoverable.
-                    eprintln!("❌ Non-recoverable API Submission Error. Deleting from queue. Details: {}", e);
-                    non_recoverable_error = true;
-                    break;
-                }
+                // Treat other 4xx errors (API Validation, Challenge Expired, etc.) as non-recoverable.
+                eprintln!("❌ Non-recoverable API Submission Error. Deleting from queue. Details: {}", e);
+                notifications.send(NotifyEvent::SubmissionFailed {
+                    address: solution.address.clone(),
+                    challenge_id: solution.challenge_id.clone(),
+                    error: e.to_string(),
+                });
+                non_recoverable_error = true;
+                break;
             }
         }
     }
@@ -101,18 +299,14 @@ fn process_pending_solution(client: &Client, api_url: &str, file_path: &Path, da
     if submission_success {
         // Submission Success Confirmation
         println!("🚀 Successfully submitted solution for Index {} (Challenge: {})", solution.address, solution.challenge_id);
+        notifications.send(NotifyEvent::SolutionSubmitted {
+            address: solution.address.clone(),
+            challenge_id: solution.challenge_id.clone(),
+        });
 
-        // --- 3. Save Receipt and Clean Up ---
+        // --- 4. Save Receipt and Clean Up ---
         let receipt = final_receipt.unwrap();
-
-        // Determine the correct DataDir variant for saving the receipt
-        // Heuristic: differentiate Ephemeral from Persistent/Mnemonic based on address string.
-        let data_dir_instance = if solution.address.starts_with("addr_vk") {
-            DataDir::Ephemeral(&solution.address)
-        } else {
-            // Use Persistent as a default for address-based pathing (covers both Persistent and Mnemonic key modes' final address structure)
-            DataDir::Persistent(&solution.address)
-        };
+        let data_dir_instance = receipt_data_dir(&solution.address);
 
         // Call simplified save_receipt function (no donation ID)
         if let Err(e) = data_dir_instance.save_receipt(data_dir_base, &solution.challenge_id, &receipt) {
@@ -125,7 +319,7 @@ fn process_pending_solution(client: &Client, api_url: &str, file_path: &Path, da
             eprintln!("⚠️ WARNING: Successfully submitted solution but FAILED TO DELETE PENDING FILE {:?}: {}. This file may be resubmitted.", file_path, e);
         }
 
-        Ok(())
+        Ok(Attempt::Done)
     } else if non_recoverable_error {
         // Non-recoverable error, clean up the file
          if let Err(e) = fs::remove_file(file_path) {
@@ -0,0 +1,729 @@
+// src/throttle.rs
+
+use chrono::{Local, Timelike};
+use shadow_harvester_lib::ScavengeControl;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// Linux RAPL counter for the CPU package (cumulative microjoules)
+const RAPL_ENERGY_PATH: &str = "/sys/class/powercap/intel-rapl:0/energy_uj";
+const RAPL_RANGE_PATH: &str = "/sys/class/powercap/intel-rapl:0/max_energy_range_uj";
+
+// Linux hwmon sensors; the CPU drivers are preferred over other chips (GPU, NVMe, ...)
+const HWMON_PATH: &str = "/sys/class/hwmon";
+const CPU_HWMON_NAMES: &[&str] = &["coretemp", "k10temp", "zenpower", "cpu_thermal", "soc_thermal", "cpu-thermal"];
+// macOS has no sysfs: the SMC is read through one of these tools, which print e.g. "61.2°C"
+const MACOS_TEMP_COMMANDS: &[&str] = &["osx-cpu-temp", "smctemp -c"];
+
+const GOVERNOR_INTERVAL: Duration = Duration::from_secs(5);
+const GOVERNOR_POLL: Duration = Duration::from_millis(250);
+// Only add threads back once the estimate is comfortably below the cap
+const POWER_CAP_HYSTERESIS: f64 = 0.9;
+// Resume full speed only once the CPU cooled this far below '--max-temp'
+const THERMAL_HYSTERESIS_CELSIUS: f64 = 5.0;
+
+// '--threads auto': each candidate count runs for the warmup, then is measured over the sample ticks
+const AUTO_TUNE_WARMUP: Duration = Duration::from_secs(3);
+const AUTO_TUNE_TICK: Duration = Duration::from_secs(1);
+const AUTO_TUNE_SAMPLE_TICKS: u32 = 15;
+// Rates within this fraction of each other are measurement noise; the smaller thread count wins
+const AUTO_TUNE_TOLERANCE: f64 = 0.02;
+
+// '--pause-when-active': how often the user's idle time is checked
+const ACTIVITY_POLL: Duration = Duration::from_secs(2);
+// Linux: /proc/interrupts lines of the PS/2 keyboard and touchpad controller
+const INPUT_INTERRUPT_NAME: &str = "i8042";
+
+// ===============================================
+// SETTINGS
+// ===============================================
+
+/// Local-time hour window, e.g. `17-21`. Wraps around midnight when start > end (e.g. `22-6`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HourWindow {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl HourWindow {
+    pub fn parse(window_str: &str) -> Result<Self, String> {
+        let (start, end) = window_str.split_once('-')
+            .ok_or_else(|| format!("Invalid hour window '{}'. Expected format: START-END (e.g. 17-21)", window_str))?;
+        let start: u32 = start.trim().parse().map_err(|_| format!("Invalid start hour in '{}'", window_str))?;
+        let end: u32 = end.trim().parse().map_err(|_| format!("Invalid end hour in '{}'", window_str))?;
+        if start > 23 || end > 24 {
+            return Err(format!("Hours in '{}' must be between 0 and 24", window_str));
+        }
+        Ok(Self { start, end })
+    }
+
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start <= self.end {
+            hour >= self.start && hour < self.end
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+
+    pub fn is_now(&self) -> bool {
+        self.contains(Local::now().hour())
+    }
+}
+
+/// '--threads': a fixed worker count, or `auto` to tune it while mining.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThreadCount {
+    Auto,
+    Fixed(u32),
+}
+
+impl ThreadCount {
+    pub fn parse(threads_str: &str) -> Result<Self, String> {
+        if threads_str.trim().eq_ignore_ascii_case("auto") {
+            return Ok(ThreadCount::Auto);
+        }
+        match threads_str.trim().parse::<u32>() {
+            Ok(n) if n > 0 => Ok(ThreadCount::Fixed(n)),
+            _ => Err(format!("Invalid '--threads {}'. Use a positive number of threads or 'auto'.", threads_str)),
+        }
+    }
+}
+
+/// Settings for the background governor that throttles worker threads during a mining cycle.
+#[derive(Debug, Clone, Default)]
+pub struct ThrottleSettings {
+    pub power_cap_watts: Option<f64>,
+    pub peak_hours: Option<HourWindow>,
+    pub max_temp_celsius: Option<f64>,
+}
+
+// ===============================================
+// RAPL POWER METER
+// ===============================================
+
+/// Estimates CPU package power from the difference between two RAPL energy readings.
+struct RaplMeter {
+    last_energy_uj: u64,
+    last_read: Instant,
+    max_range_uj: u64,
+}
+
+fn read_u64(path: &str) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+impl RaplMeter {
+    fn new() -> Option<Self> {
+        Some(Self {
+            last_energy_uj: read_u64(RAPL_ENERGY_PATH)?,
+            last_read: Instant::now(),
+            max_range_uj: read_u64(RAPL_RANGE_PATH).unwrap_or(u64::MAX),
+        })
+    }
+
+    fn sample_watts(&mut self) -> Option<f64> {
+        let energy_uj = read_u64(RAPL_ENERGY_PATH)?;
+        let elapsed = self.last_read.elapsed().as_secs_f64();
+
+        // The counter wraps at max_energy_range_uj
+        let delta_uj = if energy_uj >= self.last_energy_uj {
+            energy_uj - self.last_energy_uj
+        } else {
+            self.max_range_uj - self.last_energy_uj + energy_uj
+        };
+
+        self.last_energy_uj = energy_uj;
+        self.last_read = Instant::now();
+
+        if elapsed > 0.0 { Some(delta_uj as f64 / 1_000_000.0 / elapsed) } else { None }
+    }
+}
+
+// ===============================================
+// CPU THERMOMETER
+// ===============================================
+
+/// Reads the hottest CPU temperature sensor.
+enum Thermometer {
+    /// temp*_input files (millidegrees Celsius) of the CPU hwmon chips
+    Hwmon(Vec<std::path::PathBuf>),
+    /// Shell command printing the temperature in Celsius
+    Command(&'static str),
+}
+
+impl Thermometer {
+    fn detect() -> Option<Self> {
+        let hwmon_inputs: Vec<_> = std::fs::read_dir(HWMON_PATH).ok()
+            .map(|chips| chips.filter_map(|c| c.ok()).map(|c| c.path())
+                .filter(|chip| std::fs::read_to_string(chip.join("name")).is_ok_and(|name| CPU_HWMON_NAMES.contains(&name.trim())))
+                .filter_map(|chip| std::fs::read_dir(chip).ok())
+                .flatten()
+                .filter_map(|f| f.ok()).map(|f| f.path())
+                .filter(|f| f.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("temp") && n.ends_with("_input")))
+                .collect())
+            .unwrap_or_default();
+        if !hwmon_inputs.is_empty() {
+            return Some(Thermometer::Hwmon(hwmon_inputs));
+        }
+
+        if cfg!(target_os = "macos") {
+            return MACOS_TEMP_COMMANDS.iter()
+                .map(|command| Thermometer::Command(command))
+                .find(|thermometer| thermometer.read_celsius().is_some());
+        }
+        None
+    }
+
+    fn read_celsius(&self) -> Option<f64> {
+        match self {
+            Thermometer::Hwmon(inputs) => inputs.iter()
+                .filter_map(|input| read_u64(input.to_str()?))
+                .map(|millidegrees| millidegrees as f64 / 1000.0)
+                .reduce(f64::max),
+            Thermometer::Command(command) => {
+                let output = std::process::Command::new("sh").arg("-c").arg(command).output().ok()?;
+                if !output.status.success() {
+                    return None;
+                }
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let number: String = stdout.trim().chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+                // Some tools print 0.0 when the SMC key is unknown
+                number.parse().ok().filter(|&celsius: &f64| celsius > 0.0)
+            }
+        }
+    }
+}
+
+// ===============================================
+// GOVERNOR THREAD
+// ===============================================
+
+/// Background thread adjusting `control.active_threads` for the duration of one mining cycle.
+pub struct Governor {
+    stop_signal: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Governor {
+    /// Starts the governor, or returns None if no throttling is configured.
+    pub fn spawn(settings: &ThrottleSettings, control: Arc<ScavengeControl>, threads: u32) -> Option<Self> {
+        let peak_hours = settings.peak_hours;
+
+        let mut power = settings.power_cap_watts.and_then(|cap| match RaplMeter::new() {
+            Some(meter) => Some((meter, cap)),
+            None => {
+                eprintln!("⚠️ '--power-cap' requires Linux RAPL ({}) to be readable. Power cap disabled.", RAPL_ENERGY_PATH);
+                None
+            }
+        });
+        let mut thermal = settings.max_temp_celsius.and_then(|max_temp| match Thermometer::detect() {
+            Some(thermometer) => Some((thermometer, max_temp)),
+            None => {
+                eprintln!("⚠️ '--max-temp' needs a CPU temperature sensor (Linux hwmon, or {} on macOS). Thermal throttling disabled.", MACOS_TEMP_COMMANDS.join(" / "));
+                None
+            }
+        });
+        if power.is_none() && thermal.is_none() {
+            return None;
+        }
+
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let stop_clone = stop_signal.clone();
+
+        let handle = thread::spawn(move || {
+            let mut last_adjustment = Instant::now();
+            while !stop_clone.load(Ordering::Relaxed) {
+                // Poll the stop signal often so the cycle isn't held up when it ends
+                thread::sleep(GOVERNOR_POLL);
+                if last_adjustment.elapsed() < GOVERNOR_INTERVAL {
+                    continue;
+                }
+                last_adjustment = Instant::now();
+
+                let active = control.active_threads.load(Ordering::Relaxed);
+                let mut status = Vec::new();
+                // Step down if any limit is exceeded, step up only once every limit is comfortably below
+                let mut over = false;
+                let mut all_below = true;
+
+                if let Some((meter, power_cap)) = power.as_mut()
+                    && let Some(watts) = meter.sample_watts()
+                {
+                    let in_peak = peak_hours.is_none_or(|w| w.is_now());
+                    over |= in_peak && watts > *power_cap;
+                    all_below &= !in_peak || watts < *power_cap * POWER_CAP_HYSTERESIS;
+                    status.push(format!("{:.1} W (cap {:.0} W{})", watts, power_cap, if in_peak { ", peak" } else { "" }));
+                }
+                if let Some((thermometer, max_temp)) = thermal.as_mut() {
+                    match thermometer.read_celsius() {
+                        Some(celsius) => {
+                            over |= celsius > *max_temp;
+                            all_below &= celsius < *max_temp - THERMAL_HYSTERESIS_CELSIUS;
+                            status.push(format!("{:.0}°C (max {:.0}°C)", celsius, max_temp));
+                        },
+                        None => all_below = false,
+                    }
+                }
+
+                // The thermal limit may pause every worker (0 threads) until the CPU cools down
+                let floor = if thermal.is_some() { 0 } else { 1 };
+                let new_active = if over {
+                    active.saturating_sub(1).max(floor)
+                } else if all_below {
+                    (active + 1).min(threads)
+                } else {
+                    active
+                };
+                control.set_active_threads(new_active);
+
+                if let Ok(mut s) = control.status.lock() {
+                    let state = match new_active {
+                        0 => "paused to cool down".to_string(),
+                        n if n < threads => format!("throttled {}/{} threads", n, threads),
+                        n => format!("threads {}/{}", n, threads),
+                    };
+                    status.push(state);
+                    *s = status.join(" | ");
+                }
+            }
+        });
+
+        Some(Self { stop_signal, handle: Some(handle) })
+    }
+}
+
+impl Drop for Governor {
+    fn drop(&mut self) {
+        self.stop_signal.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// ===============================================
+// RUNTIME THREAD TARGET (SIGNALS)
+// ===============================================
+
+/// Lets the user step the worker count down (SIGUSR1) or up (SIGUSR2) while mining, without restarting the cycle.
+#[cfg(unix)]
+pub fn spawn_thread_signal_listener(control: Arc<ScavengeControl>, max_threads: u32) -> Result<(), String> {
+    use signal_hook::consts::{SIGUSR1, SIGUSR2};
+
+    let mut signals = signal_hook::iterator::Signals::new([SIGUSR1, SIGUSR2])
+        .map_err(|e| format!("Could not register SIGUSR1/SIGUSR2 handlers: {}", e))?;
+
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            let current = control.target_threads.load(Ordering::Relaxed);
+            let target = match signal {
+                SIGUSR1 => current.saturating_sub(1).max(1),
+                SIGUSR2 => (current + 1).min(max_threads),
+                _ => continue,
+            };
+            control.set_target_threads(target);
+            println!("\n🎚️ Worker threads set to {}/{} (SIGUSR1: fewer, SIGUSR2: more).", target, max_threads);
+        }
+    });
+
+    println!("🎚️ Send SIGUSR1/SIGUSR2 to PID {} to step worker threads down/up while mining.", std::process::id());
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn spawn_thread_signal_listener(_control: Arc<ScavengeControl>, _max_threads: u32) -> Result<(), String> {
+    Ok(())
+}
+
+// ===============================================
+// RUNTIME PAUSE / RESUME
+// ===============================================
+
+fn hold_workers(control: &ScavengeControl, held: bool, how: &str) {
+    if !control.set_held(held) {
+        return;
+    }
+    if held {
+        println!("\n⏸️ Mining paused ({}). Challenge and ROM stay loaded; resume at any time.", how);
+    } else {
+        println!("\n▶️ Mining resumed ({}).", how);
+    }
+}
+
+/// Pauses every worker on SIGTSTP (Ctrl+Z, 'kill -TSTP') and resumes on SIGCONT ('kill -CONT', 'fg'). The
+/// process keeps running, so queued submissions, the heartbeat and the loaded ROM are unaffected.
+#[cfg(unix)]
+pub fn spawn_pause_signal_listener(control: Arc<ScavengeControl>) -> Result<(), String> {
+    use signal_hook::consts::{SIGCONT, SIGTSTP};
+
+    let mut signals = signal_hook::iterator::Signals::new([SIGTSTP, SIGCONT])
+        .map_err(|e| format!("Could not register SIGTSTP/SIGCONT handlers: {}", e))?;
+
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            match signal {
+                SIGTSTP => hold_workers(&control, true, "SIGTSTP"),
+                SIGCONT => hold_workers(&control, false, "SIGCONT"),
+                _ => {}
+            }
+        }
+    });
+
+    println!("⏯️ Send SIGTSTP/SIGCONT to PID {} to pause/resume mining.", std::process::id());
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn spawn_pause_signal_listener(_control: Arc<ScavengeControl>) -> Result<(), String> {
+    Ok(())
+}
+
+/// When stdin is a terminal, typing 'p' + Enter toggles pause.
+pub fn spawn_pause_key_listener(control: Arc<ScavengeControl>) {
+    use std::io::{BufRead, IsTerminal};
+
+    if !std::io::stdin().is_terminal() {
+        return;
+    }
+
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().eq_ignore_ascii_case("p") {
+                hold_workers(&control, !control.is_held(), "keyboard");
+            }
+        }
+    });
+
+    println!("⏯️ Type 'p' and press Enter to pause/resume mining.");
+}
+
+// ===============================================
+// THREAD AUTO-TUNING
+// ===============================================
+
+enum Sample {
+    Rate(f64),
+    // No hashes for a whole tick: ROM generation between cycles, or waiting for a challenge
+    Stalled,
+    // The target changed under us (e.g. SIGUSR1/SIGUSR2)
+    Overridden,
+}
+
+fn sample_hash_rate(control: &ScavengeControl, nb_threads: u32) -> Sample {
+    control.set_target_threads(nb_threads);
+    thread::sleep(AUTO_TUNE_WARMUP);
+
+    let start = Instant::now();
+    let first_hashes = control.hashes.load(Ordering::Relaxed);
+    let mut last_hashes = first_hashes;
+    for _ in 0..AUTO_TUNE_SAMPLE_TICKS {
+        thread::sleep(AUTO_TUNE_TICK);
+        if control.target_threads.load(Ordering::Relaxed) != nb_threads {
+            return Sample::Overridden;
+        }
+        let hashes = control.hashes.load(Ordering::Relaxed);
+        if hashes == last_hashes {
+            return Sample::Stalled;
+        }
+        last_hashes = hashes;
+    }
+    Sample::Rate((last_hashes - first_hashes) as f64 / start.elapsed().as_secs_f64())
+}
+
+/// '--threads auto': starts with every worker, measures the hash rate of a few thread counts (all logical
+/// cores, physical cores only, half of them) and refines around the best one. SMT and ROM memory bandwidth
+/// often make fewer threads as fast as all of them; the smallest count within the noise tolerance is kept.
+pub fn spawn_thread_auto_tuner(control: Arc<ScavengeControl>, max_threads: u32, physical_cores: u32) {
+    thread::spawn(move || {
+        let mut rates: Vec<(u32, f64)> = Vec::new();
+        // Returns false once the user took over the thread count
+        let measure = |nb_threads: u32, rates: &mut Vec<(u32, f64)>| -> bool {
+            if rates.iter().any(|&(n, _)| n == nb_threads) {
+                return true;
+            }
+            if let Ok(mut status) = control.status.lock() {
+                *status = format!("auto-tune: trying {} threads", nb_threads);
+            }
+            loop {
+                match sample_hash_rate(&control, nb_threads) {
+                    Sample::Rate(rate) => {
+                        println!("\n🔬 Auto-tune: {} threads -> {:.2} H/s", nb_threads, rate);
+                        rates.push((nb_threads, rate));
+                        return true;
+                    },
+                    Sample::Stalled => continue,
+                    Sample::Overridden => return false,
+                }
+            }
+        };
+        let better = |candidate: (u32, f64), best: (u32, f64)| {
+            candidate.1 > best.1 * (1.0 + AUTO_TUNE_TOLERANCE)
+                || (candidate.0 < best.0 && candidate.1 >= best.1 * (1.0 - AUTO_TUNE_TOLERANCE))
+        };
+
+        // Coarse pass, then hill-climb with a halving step around the best count
+        let mut completed = [max_threads, physical_cores.min(max_threads), (physical_cores / 2).clamp(1, max_threads)]
+            .into_iter()
+            .all(|n| measure(n, &mut rates));
+        if completed {
+            let mut best = rates.iter().copied().reduce(|best, c| if better(c, best) { c } else { best }).unwrap_or((max_threads, 0.0));
+            let mut step = (best.0 / 8).max(1);
+            'refine: loop {
+                for neighbour in [best.0.saturating_sub(step).max(1), (best.0 + step).min(max_threads)] {
+                    if !measure(neighbour, &mut rates) {
+                        completed = false;
+                        break 'refine;
+                    }
+                    let candidate = *rates.iter().find(|&&(n, _)| n == neighbour).unwrap_or(&best);
+                    if better(candidate, best) {
+                        best = candidate;
+                        continue 'refine;
+                    }
+                }
+                if step == 1 {
+                    break;
+                }
+                step /= 2;
+            }
+
+            if completed {
+                control.set_target_threads(best.0);
+                println!("\n🎯 Auto-tune converged on {} worker threads ({:.2} H/s).", best.0, best.1);
+            }
+        }
+        if !completed {
+            println!("\n🎚️ Worker threads changed manually. Auto-tuning stopped.");
+        }
+        if let Ok(mut status) = control.status.lock() {
+            status.clear();
+        }
+    });
+
+    println!("🔬 '--threads auto': tuning worker threads while mining (starting with {}).", max_threads);
+}
+
+// ===============================================
+// PROCESS PRIORITY ('--nice')
+// ===============================================
+
+/// Lowers the priority of the mining threads so interactive work always goes first. On Linux this applies to
+/// the hashing worker threads only, so the submitter and the API calls keep their priority; elsewhere to the
+/// whole process. Returns a description of what was applied.
+#[cfg(target_os = "linux")]
+pub fn lower_priority() -> Result<&'static str, String> {
+    // Tried on a scratch thread first, so a failure is reported once rather than by every worker
+    let applied = thread::spawn(lower_thread_priority).join()
+        .map_err(|_| "Could not switch to SCHED_IDLE".to_string())??;
+    shadow_harvester_lib::set_worker_thread_setup(|| {
+        let _ = lower_thread_priority();
+    });
+    Ok(applied)
+}
+
+#[cfg(target_os = "linux")]
+fn lower_thread_priority() -> Result<&'static str, String> {
+    let param = libc::sched_param { sched_priority: 0 };
+    // SAFETY: plain syscalls on the calling thread with a valid sched_param
+    unsafe {
+        if libc::sched_setscheduler(0, libc::SCHED_IDLE, &param) != 0 {
+            return Err(format!("Could not switch to SCHED_IDLE: {}", std::io::Error::last_os_error()));
+        }
+        // Still counts where SCHED_IDLE is ignored (e.g. some container runtimes)
+        libc::setpriority(libc::PRIO_PROCESS, 0, 19);
+    }
+    Ok("SCHED_IDLE, nice 19")
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn lower_priority() -> Result<&'static str, String> {
+    // SAFETY: plain syscall on the current process
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 19) } != 0 {
+        return Err(format!("Could not lower the process priority: {}", std::io::Error::last_os_error()));
+    }
+    Ok("nice 19")
+}
+
+#[cfg(windows)]
+pub fn lower_priority() -> Result<&'static str, String> {
+    const IDLE_PRIORITY_CLASS: u32 = 0x0000_0040;
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetCurrentProcess() -> *mut std::ffi::c_void;
+        fn SetPriorityClass(process: *mut std::ffi::c_void, priority_class: u32) -> i32;
+    }
+    // SAFETY: GetCurrentProcess returns a pseudo handle that is always valid
+    if unsafe { SetPriorityClass(GetCurrentProcess(), IDLE_PRIORITY_CLASS) } == 0 {
+        return Err(format!("Could not set the idle priority class: {}", std::io::Error::last_os_error()));
+    }
+    Ok("idle priority class")
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn lower_priority() -> Result<&'static str, String> {
+    Err(format!("'--nice' is not supported on {}", std::env::consts::OS))
+}
+
+// ===============================================
+// USER ACTIVITY ('--pause-when-active')
+// ===============================================
+
+/// Tells how long ago the user last typed or moved the mouse.
+struct ActivityProbe {
+    // Linux: keyboard/touchpad interrupt count, and when it last changed (None: not since startup)
+    input_interrupts: Option<(u64, Option<Instant>)>,
+}
+
+impl ActivityProbe {
+    fn new() -> Self {
+        Self { input_interrupts: read_input_interrupts().map(|count| (count, None)) }
+    }
+
+    /// Linux: the newest of the terminal input times (as `w` reports them) and the last change of the
+    /// keyboard/touchpad interrupt count. macOS: the HID idle time. Windows: the last input event.
+    #[cfg(target_os = "linux")]
+    fn idle_time(&mut self) -> Option<Duration> {
+        if let (Some(count), Some((last_count, changed_at))) = (read_input_interrupts(), self.input_interrupts.as_mut())
+            && count != *last_count
+        {
+            *last_count = count;
+            *changed_at = Some(Instant::now());
+        }
+        let interrupts_idle = self.input_interrupts.and_then(|(_, changed_at)| changed_at).map(|changed_at| changed_at.elapsed());
+
+        let terminals = ["/dev/pts", "/dev"].iter()
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().starts_with("/dev/pts") || entry.file_name().to_str().is_some_and(|name| name.starts_with("tty")))
+            .filter_map(|entry| entry.metadata().ok()?.accessed().ok());
+        let terminals_idle = terminals.max().map(|accessed| accessed.elapsed().unwrap_or(Duration::ZERO));
+
+        [interrupts_idle, terminals_idle].into_iter().flatten().min()
+    }
+
+    #[cfg(target_os = "macos")]
+    fn idle_time(&mut self) -> Option<Duration> {
+        let output = std::process::Command::new("ioreg").args(["-c", "IOHIDSystem", "-d", "4"]).output().ok()?;
+        let listing = String::from_utf8_lossy(&output.stdout);
+        let nanos: u64 = listing.lines()
+            .find(|line| line.contains("\"HIDIdleTime\""))?
+            .rsplit('=').next()?.trim().parse().ok()?;
+        Some(Duration::from_nanos(nanos))
+    }
+
+    #[cfg(windows)]
+    fn idle_time(&mut self) -> Option<Duration> {
+        #[repr(C)]
+        struct LastInputInfo {
+            size: u32,
+            time: u32,
+        }
+        #[link(name = "user32")]
+        unsafe extern "system" {
+            fn GetLastInputInfo(info: *mut LastInputInfo) -> i32;
+        }
+        #[link(name = "kernel32")]
+        unsafe extern "system" {
+            fn GetTickCount() -> u32;
+        }
+        let mut info = LastInputInfo { size: std::mem::size_of::<LastInputInfo>() as u32, time: 0 };
+        // SAFETY: info is a valid LASTINPUTINFO with its size set
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return None;
+        }
+        // SAFETY: no arguments
+        let now = unsafe { GetTickCount() };
+        Some(Duration::from_millis(now.wrapping_sub(info.time) as u64))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    fn idle_time(&mut self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Total interrupts of the keyboard/touchpad controller across CPUs, if it has any.
+fn read_input_interrupts() -> Option<u64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let interrupts = std::fs::read_to_string("/proc/interrupts").ok()?;
+    let counts: Vec<u64> = interrupts.lines()
+        .filter(|line| line.contains(INPUT_INTERRUPT_NAME))
+        .flat_map(|line| line.split_whitespace().skip(1).map_while(|field| field.parse::<u64>().ok()))
+        .collect();
+    (!counts.is_empty()).then(|| counts.iter().sum())
+}
+
+/// Pauses every worker while the user was active within `idle_threshold`, for the lifetime of the process.
+pub fn spawn_activity_monitor(control: Arc<ScavengeControl>, idle_threshold: Duration) -> Result<(), String> {
+    let mut probe = ActivityProbe::new();
+    if probe.idle_time().is_none() {
+        return Err(format!("Can't tell when the user was last active on {}", std::env::consts::OS));
+    }
+
+    thread::spawn(move || {
+        let mut paused = false;
+        loop {
+            // Unknown idle time (e.g. a sensor went away) counts as idle, so mining doesn't stall forever
+            let active = probe.idle_time().is_some_and(|idle| idle < idle_threshold);
+            if active != paused {
+                paused = active;
+                control.set_paused(paused);
+                if paused {
+                    println!("\n⏸️ User active. Mining paused until {}s without input.", idle_threshold.as_secs());
+                } else {
+                    println!("\n▶️ User idle. Mining resumed.");
+                }
+            }
+            thread::sleep(ACTIVITY_POLL);
+        }
+    });
+
+    println!("👀 Pausing mining while the user is active (input within the last {}s).", idle_threshold.as_secs());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hour_windows() {
+        assert_eq!(HourWindow::parse("17-21"), Ok(HourWindow { start: 17, end: 21 }));
+        assert_eq!(HourWindow::parse(" 22 - 6 "), Ok(HourWindow { start: 22, end: 6 }));
+        assert_eq!(HourWindow::parse("0-24"), Ok(HourWindow { start: 0, end: 24 }));
+        for window in ["17", "17-", "a-21", "24-2", "3-25", "-1-4", ""] {
+            assert!(HourWindow::parse(window).is_err(), "'{}' should be rejected", window);
+        }
+    }
+
+    #[test]
+    fn hour_windows_end_before_their_end_hour() {
+        let evening = HourWindow { start: 17, end: 21 };
+        assert_eq!((0..24).filter(|&hour| evening.contains(hour)).collect::<Vec<_>>(), [17, 18, 19, 20]);
+        let all_day = HourWindow { start: 0, end: 24 };
+        assert!((0..24).all(|hour| all_day.contains(hour)));
+        let empty = HourWindow { start: 5, end: 5 };
+        assert!(!(0..24).any(|hour| empty.contains(hour)));
+    }
+
+    #[test]
+    fn hour_windows_wrap_around_midnight() {
+        let night = HourWindow { start: 22, end: 6 };
+        assert_eq!((0..24).filter(|&hour| night.contains(hour)).collect::<Vec<_>>(), [0, 1, 2, 3, 4, 5, 22, 23]);
+    }
+
+    #[test]
+    fn parses_thread_counts() {
+        assert_eq!(ThreadCount::parse("auto"), Ok(ThreadCount::Auto));
+        assert_eq!(ThreadCount::parse(" AUTO "), Ok(ThreadCount::Auto));
+        assert_eq!(ThreadCount::parse("8"), Ok(ThreadCount::Fixed(8)));
+        for threads in ["0", "-2", "1.5", "all", ""] {
+            assert!(ThreadCount::parse(threads).is_err(), "'{}' should be rejected", threads);
+        }
+    }
+}
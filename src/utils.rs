@@ -1,34 +1,148 @@
 // src/utils.rs
 
+pub mod parse;
+
 use crate::api;
-use crate::constants::USER_AGENT;
+use crate::error::ApiError;
+use crate::store::{FsStore, RedisStore, S3Store, StateStore};
+use crate::constants::{USER_AGENT, API_TIMEOUT_SECS, DEFAULT_THREADS, CONCURRENT_WALLETS, DEFAULT_OVERSUBSCRIPTION_FACTOR, NONCE_CHECKPOINT_SECS};
+use crate::notify::{Notifications, build_sinks};
+use crate::proxies::WalletClients;
+use crate::throttle::{Governor, HourWindow, ThreadCount, ThrottleSettings, lower_priority, spawn_activity_monitor, spawn_pause_key_listener, spawn_pause_signal_listener, spawn_thread_auto_tuner, spawn_thread_signal_listener};
 use crate::data_types::{
-    DataDir, DataDirMnemonic, MiningContext, MiningResult, FILE_NAME_RECEIPT,
-    ChallengeData, Statistics, TandCResponse, ChallengeResponse, PendingSolution, FILE_NAME_FOUND_SOLUTION
+    DataDir, DataDirMnemonic, MiningContext, MiningResult, NonceCheckpointRecord, FILE_NAME_RECEIPT,
+    ChallengeData, Statistics, TandCResponse, ChallengeResponse, HistoricalChallenge, PendingSolution, FILE_NAME_FOUND_SOLUTION,
+    append_challenge_note, challenge_checksum, count_receipts_for_challenge, load_cached_challenge, load_challenge_notes, new_idempotency_key, ROM_CACHE_DIR_NAME
 };
-use reqwest::blocking::{self, Client};
+use reqwest::Client;
+use std::collections::HashSet;
 use std::ffi::OsStr;
+use std::path::PathBuf;
 use std::thread;
 use std::time::Duration;
 use chrono::{DateTime, Utc};
 use std::process;
+use std::sync::Arc;
+use shadow_harvester_lib::{NonceCheckpoint, NoncePartition, ScavengeControl};
+use shadow_harvester_lib::numa;
 
 // ===============================================
 // HELPER FUNCTIONS
 // ===============================================
 
+/// Formats a duration as a humanized string, e.g. 3725.0 -> "1h 02m 05s".
 pub fn format_duration(seconds: f64) -> String {
-    let s = seconds.floor() as u64;
-    let h = s / 3600;
+    let s = seconds.max(0.0).floor() as u64;
+    let d = s / 86400;
+    let h = (s % 86400) / 3600;
     let m = (s % 3600) / 60;
     let s = s % 60;
-    format!("{}:{}:{}", h, m, s)
+    if d > 0 {
+        format!("{}d {}h {:02}m", d, h, m)
+    } else if h > 0 {
+        format!("{}h {:02}m {:02}s", h, m, s)
+    } else if m > 0 {
+        format!("{}m {:02}s", m, s)
+    } else {
+        format!("{}s", s)
+    }
+}
+
+/// Formats a hash rate with a metric prefix, e.g. 2410000.0 -> "2.41 MH/s".
+pub fn format_hashrate(hashes_per_sec: f64) -> String {
+    const PREFIXES: [&str; 5] = ["", "k", "M", "G", "T"];
+    let mut value = hashes_per_sec.max(0.0);
+    let mut prefix = 0;
+    while value >= 1000.0 && prefix < PREFIXES.len() - 1 {
+        value /= 1000.0;
+        prefix += 1;
+    }
+    format!("{:.2} {}H/s", value, PREFIXES[prefix])
+}
+
+/// Formats a count or NIGHT amount with thousands separators, e.g. 1234567 -> "1,234,567".
+pub fn format_thousands(value: u64) -> String {
+    let digits = value.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// This machine's host name, or "unknown".
+pub fn hostname() -> String {
+    std::fs::read_to_string("/etc/hostname").ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Spaces out wallet launches, and the restart after a challenge switch, by a fixed delay, so registration
+/// calls and ROM builds don't all hit at once. A launch is a wallet's first cycle on a challenge; cycles that
+/// re-mine a wallet already started don't wait. The first wallet of the run starts immediately.
+pub struct Stagger {
+    secs: u64,
+    last_challenge_id: Option<String>,
+    launched: HashSet<String>,
+}
+
+impl Stagger {
+    pub fn new(secs: u64) -> Self {
+        Self { secs, last_challenge_id: None, launched: HashSet::new() }
+    }
+
+    pub fn wait(&mut self, challenge_id: &str, address: &str) {
+        let first = self.last_challenge_id.is_none();
+        let switched = self.last_challenge_id.as_deref().is_some_and(|last| last != challenge_id);
+        if switched {
+            self.launched.clear();
+        }
+        let launch = self.launched.insert(address.to_string());
+        if self.secs > 0 && !first && (switched || launch) {
+            match switched {
+                true => println!("⏱️ Challenge switched. Staggering the next wallet start by {}s...", self.secs),
+                false => println!("⏱️ Staggering the launch of {} by {}s...", address, self.secs),
+            }
+            thread::sleep(Duration::from_secs(self.secs));
+        }
+        self.last_challenge_id = Some(challenge_id.to_string());
+    }
 }
 
-pub fn create_api_client() -> Result<Client, reqwest::Error> {
-    Client::builder()
+/// The API client, through `proxy` ('--proxy') if given.
+pub fn create_api_client(proxy: Option<&str>) -> Result<Client, String> {
+    let mut builder = Client::builder()
         .user_agent(USER_AGENT)
-        .build()
+        .timeout(Duration::from_secs(API_TIMEOUT_SECS))
+        .connect_timeout(api::timeouts().longest_connect());
+    let tls = api::tls();
+    for certificate in tls.ca_certificates {
+        builder = builder.add_root_certificate(certificate);
+    }
+    // Pins are checked against the certificate each response reports
+    if !tls.pins.is_empty() {
+        builder = builder.tls_info(true);
+    }
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(parse_proxy(proxy)?);
+    }
+    builder.build().map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// '--proxy' for any of the HTTP clients: http(s)://, or socks5h:// (e.g. Tor) to resolve host names through
+/// the proxy too. Without one, reqwest reads the *_PROXY environment variables.
+pub fn parse_proxy(url: &str) -> Result<reqwest::Proxy, String> {
+    let scheme = url.split_once("://").map_or("http", |(scheme, _)| scheme).to_ascii_lowercase();
+    if !matches!(scheme.as_str(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(format!("Unsupported '--proxy' URL '{}': expected http://, https://, socks5:// or socks5h://.", url));
+    }
+    reqwest::Proxy::all(url).map_err(|e| format!("Invalid '--proxy' URL '{}': {}", url, e))
 }
 
 /// Helper to print non-active challenge status
@@ -61,15 +175,43 @@ fn print_non_active_status(response: &ChallengeResponse) {
     println!("----------------------------------------------");
 }
 
+/// Prints the challenge history as a table, or as JSON with the local receipt count of each challenge.
+fn print_challenge_history(challenges: &[HistoricalChallenge], data_dir: Option<&str>, json: bool) -> Result<(), String> {
+    let local = |challenge: &HistoricalChallenge| data_dir.map(|base_dir| count_receipts_for_challenge(base_dir, &challenge.challenge_id));
+    if json {
+        let rows: Vec<serde_json::Value> = challenges.iter().map(|challenge| {
+            let mut row = serde_json::to_value(challenge).unwrap_or_default();
+            row["status"] = challenge.status().into();
+            row["local_receipts"] = local(challenge).into();
+            row
+        }).collect();
+        println!("{}", serde_json::to_string_pretty(&rows).map_err(|e| format!("Could not serialize the challenge history: {}", e))?);
+        return Ok(());
+    }
+
+    if challenges.is_empty() {
+        println!("No challenges match.");
+        return Ok(());
+    }
+    println!("{:<12} {:>4} {:>4}  {:<10} {:<8} {:<25} {:>9} {:>6}", "CHALLENGE", "DAY", "NO.", "DIFFICULTY", "STATUS", "LATEST SUBMISSION", "SOLUTIONS", "LOCAL");
+    for challenge in challenges {
+        println!("{:<12} {:>4} {:>4}  {:<10} {:<8} {:<25} {:>9} {:>6}",
+            challenge.challenge_id, challenge.day, challenge.challenge_number, challenge.difficulty, challenge.status(), challenge.latest_submission,
+            challenge.solutions.map_or("-".to_string(), format_thousands),
+            local(challenge).map_or("-".to_string(), |n| n.to_string()));
+    }
+    println!("{} challenge(s).", challenges.len());
+    Ok(())
+}
 
 /// Polls the API for the current challenge status and handles challenge change logic.
 pub fn poll_for_active_challenge(
-    client: &blocking::Client,
+    client: &Client,
     api_url: &str,
     current_id: &mut String,
-) -> Result<Option<ChallengeData>, String> {
+) -> Result<Option<ChallengeData>, ApiError> {
 
-    let challenge_response = api::fetch_challenge_status(client, api_url)?;
+    let challenge_response = api::block_on(api::fetch_challenge_status(client, api_url))?;
 
     match challenge_response.code.as_str() {
         "active" => {
@@ -106,21 +248,21 @@ pub fn poll_for_active_challenge(
             thread::sleep(Duration::from_secs(5 * 60));
             Ok(None)
         }
-        _ => Err(format!("Received unexpected challenge code: {}", challenge_response.code)),
+        _ => Err(ApiError::Decode(format!("unexpected challenge code: {}", challenge_response.code))),
     }
 }
 
 pub fn get_challenge_params(
-    client: &blocking::Client,
+    client: &Client,
     api_url: &str,
     cli_challenge: Option<&String>,
     current_id: &mut String,
-) -> Result<Option<ChallengeData>, String> {
+) -> Result<Option<ChallengeData>, ApiError> {
     if let Some(challenge_str) = cli_challenge {
         let cli_challenge_data = api::parse_cli_challenge_string(challenge_str)
-            .map_err(|e| format!("Challenge parameter parsing error: {}", e))?;
-        let live_params = api::get_active_challenge_data(client, api_url)
-            .map_err(|e| format!("Could not fetch live challenge status (required for submission deadline/hour): {}", e))?;
+            .map_err(|e| ApiError::Invalid(format!("Challenge parameter parsing error: {}", e)))?;
+        // The live status supplies the submission deadline and hour
+        let live_params = api::block_on(api::get_active_challenge_data(client, api_url))?;
 
         let mut fixed_challenge_params = live_params.clone();
         fixed_challenge_params.challenge_id = cli_challenge_data.challenge_id.clone();
@@ -155,30 +297,111 @@ pub fn get_challenge_params(
     }
 }
 
+/// Challenge parameters to keep mining with when the challenge poll fails with a network error.
+/// With a data dir the cached challenge.json is re-read and verified rather than trusted; if it can't be,
+/// the parameters polled last are used.
+pub fn resume_challenge(context: &MiningContext, current_id: &str, last_active: Option<&ChallengeData>) -> Result<ChallengeData, String> {
+    if let Some(base_dir) = context.data_dir {
+        match load_cached_challenge(base_dir, current_id, context.allow_stale_challenge) {
+            Ok(Some(cached)) => return Ok(cached),
+            Ok(None) => {},
+            Err(e) if last_active.is_some() => eprintln!("⚠️ {}. Continuing with the challenge parameters polled last.", e),
+            Err(e) => return Err(e),
+        }
+    }
+    last_active.cloned().ok_or_else(|| {
+        format!("FATAL LOGIC ERROR: Challenge ID {} is set but no previous challenge data was stored.", current_id)
+    })
+}
+
+// The ROM prefetcher re-checks the schedule at least this often, and this soon after a challenge was due
+const ROM_PREFETCH_POLL_MAX: Duration = Duration::from_secs(5 * 60);
+const ROM_PREFETCH_POLL_DUE: Duration = Duration::from_secs(30);
+
+/// Watches the challenge schedule and prepares the ROM of a newly published challenge while the workers
+/// are still on the previous one, so switching to it doesn't wait for the ROM build.
+pub fn spawn_rom_prefetcher(client: Client, api_url: String) {
+    thread::spawn(move || loop {
+        let wait = match api::block_on(api::fetch_challenge_status(&client, &api_url)) {
+            Ok(status) => {
+                if let Some(challenge) = status.challenge.as_ref().filter(|_| status.code == "active") {
+                    shadow_harvester_lib::prepare_challenge_rom(&challenge.no_pre_mine_key);
+                }
+                match status.next_challenge_starts_at.as_deref().and_then(|at| DateTime::parse_from_rfc3339(at).ok()) {
+                    // Due but not published yet: check again shortly
+                    Some(at) => (at.with_timezone(&Utc) - Utc::now()).to_std()
+                        .map_or(ROM_PREFETCH_POLL_DUE, |until| (until + ROM_PREFETCH_POLL_DUE).min(ROM_PREFETCH_POLL_MAX)),
+                    None => ROM_PREFETCH_POLL_MAX,
+                }
+            },
+            Err(_) => ROM_PREFETCH_POLL_MAX,
+        };
+        thread::sleep(wait);
+    });
+}
+
+/// Prints where rewards end up for this run, so a forgotten profile setting can't donate silently.
+pub fn print_donation_policy(donate_to_option: Option<&String>) {
+    match donate_to_option {
+        Some(address) => println!("🎁 Donation Target: {} (all accumulated rewards are assigned to it)", address),
+        None => println!("🎁 Donation Target: none (rewards stay with the mining wallets)"),
+    }
+}
+
+
+// A worker under this fraction of the median worker's rate is reported as slow
+const SLOW_THREAD_FRACTION: f64 = 0.5;
+const THREADS_PER_ROW: usize = 4;
+
+/// Per-worker hash rates of the last cycle, flagging workers far below the median.
+fn print_thread_rates(thread_hashes: &[u64], elapsed_secs: f64) {
+    if thread_hashes.len() < 2 || elapsed_secs <= 0.0 {
+        return;
+    }
+    let rates: Vec<f64> = thread_hashes.iter().map(|&h| h as f64 / elapsed_secs).collect();
+    for (row, chunk) in rates.chunks(THREADS_PER_ROW).enumerate() {
+        let cells: Vec<String> = chunk.iter().enumerate()
+            .map(|(i, rate)| format!("#{:<3} {:>12}", row * THREADS_PER_ROW + i, format_hashrate(*rate)))
+            .collect();
+        println!("  {}", cells.join("   "));
+    }
+
+    let mut sorted = rates.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+    let slow: Vec<String> = rates.iter().enumerate()
+        .filter(|&(_, &rate)| rate < median * SLOW_THREAD_FRACTION)
+        .map(|(i, _)| format!("#{}", i))
+        .collect();
+    if !slow.is_empty() {
+        println!("  ⚠️ Thread(s) {} below half the median rate ({}): throttled, paused or a stuck core.", slow.join(", "), format_hashrate(median));
+    }
+}
 
-pub fn print_statistics(stats_result: Result<Statistics, String>, total_hashes: u64, elapsed_secs: f64) {
+pub fn print_statistics(stats_result: Result<Statistics, ApiError>, total_hashes: u64, elapsed_secs: f64, thread_hashes: &[u64]) {
     println!("\n==============================================");
     println!("📈 Mining Statistics Summary");
     println!("==============================================");
     let hash_rate = if elapsed_secs > 0.0 { total_hashes as f64 / elapsed_secs } else { 0.0 };
     println!("** LAST MINING CYCLE PERFORMANCE **");
     println!("  Time Elapsed: {}", format_duration(elapsed_secs));
-    println!("  Total Hashes: {}", total_hashes);
-    println!("  Hash Rate: {:.2} H/s", hash_rate);
+    println!("  Total Hashes: {}", format_thousands(total_hashes));
+    println!("  Hash Rate: {}", format_hashrate(hash_rate));
+    print_thread_rates(thread_hashes, elapsed_secs);
     println!("----------------------------------------------");
     match stats_result {
         Ok(stats) => {
             println!("** YOUR ACCOUNT STATISTICS (Address: {}) **", stats.local_address);
-            println!("  Crypto Receipts (Solutions): {}", stats.crypto_receipts);
-            println!("  Night Allocation: {}", stats.night_allocation);
+            println!("  Crypto Receipts (Solutions): {}", format_thousands(stats.crypto_receipts as u64));
+            println!("  Night Allocation: {}", format_thousands(stats.night_allocation as u64));
             println!("----------------------------------------------");
             println!("** GLOBAL STATISTICS (All Miners) **");
             println!("  NOTE: These statistics are aggregated across all wallets globally.");
-            println!("  Total Wallets: {}", stats.wallets);
+            println!("  Total Wallets: {}", format_thousands(stats.wallets as u64));
             println!("  Current Challenges: {}", stats.challenges);
             println!("  Total Challenges Ever: {}", stats.total_challenges);
-            println!("  Total Crypto Receipts: {}", stats.total_crypto_receipts);
-            println!("  Recent Crypto Receipts: {}", stats.recent_crypto_receipts);
+            println!("  Total Crypto Receipts: {}", format_thousands(stats.total_crypto_receipts as u64));
+            println!("  Recent Crypto Receipts: {}", format_thousands(stats.recent_crypto_receipts as u64));
             println!("==============================================");
         }
         Err(e) => {
@@ -189,24 +412,90 @@ pub fn print_statistics(stats_result: Result<Statistics, String>, total_hashes:
     }
 }
 
+/// True when the challenge's submission deadline is closer than the deadline margin ('--deadline-margin').
+pub fn submission_deadline_near(challenge_params: &ChallengeData, control: &ScavengeControl) -> bool {
+    let Some(margin) = control.deadline_margin() else { return false };
+    DateTime::parse_from_rfc3339(&challenge_params.latest_submission)
+        .is_ok_and(|deadline| deadline.with_timezone(&Utc) - chrono::Duration::from_std(margin).unwrap_or_default() <= Utc::now())
+}
+
+/// `search_on`: the caller mines the same address and challenge again after a solution ('--solutions-per-challenge'),
+/// so the nonce checkpoint is kept and the next cycle continues after the found nonce.
+#[allow(clippy::too_many_arguments)]
 pub fn run_single_mining_cycle(
     mining_address: String,
     threads: u32,
     donate_to_option: Option<&String>,
     challenge_params: &ChallengeData,
-    data_dir_base: Option<&str>,
+    store: Option<&dyn StateStore>,
+    throttle: &ThrottleSettings,
+    control: &Arc<ScavengeControl>,
+    partition: NoncePartition,
+    search_on: bool,
 ) -> (MiningResult, u64, f64) {
-    let (found_nonce, total_hashes, elapsed_secs) = shadow_harvester_lib::scavenge(
-        mining_address.clone(),
-        challenge_params.challenge_id.clone(),
-        challenge_params.difficulty.clone(),
-        challenge_params.no_pre_mine_key.clone(),
-        challenge_params.latest_submission.clone(),
-        challenge_params.no_pre_mine_hour_str.clone(),
-        threads,
-    );
+    if submission_deadline_near(challenge_params, control) {
+        println!("\n⏰ Challenge {} closes for submissions in less than {}. Not mining it.",
+            challenge_params.challenge_id, format_duration(control.deadline_margin().unwrap_or_default().as_secs_f64()));
+        return (MiningResult::DeadlineReached, 0, 0.0);
+    }
+
+    // The governor throttles per cycle; the user's target (signals) carries over between cycles
+    control.set_active_threads(threads);
+    let control = control.clone();
+    // Dropped at the end of the cycle, which stops the governor thread
+    let _governor = Governor::spawn(throttle, control.clone(), threads);
+
+    // With a data dir, the workers' positions are checkpointed so a restart resumes the search
+    let checkpoint_dir = DataDir::Ephemeral(&mining_address);
+    let checkpoint = store.map(|store| {
+        let resume = store.load_nonce_checkpoint(&checkpoint_dir, &challenge_params.challenge_id)
+            .filter(|c| c.address == mining_address && c.challenge_id == challenge_params.challenge_id
+                && c.threads == threads && c.partition_worker_id == partition.worker_id && c.partition_worker_count == partition.worker_count)
+            .map(|c| c.nonces)
+            .unwrap_or_default();
+        let checkpoint_dir = &checkpoint_dir;
+        let mining_address = &mining_address;
+        NonceCheckpoint {
+            resume,
+            interval: Duration::from_secs(NONCE_CHECKPOINT_SECS),
+            save: Box::new(move |nonces: &[u64]| {
+                let record = NonceCheckpointRecord {
+                    address: mining_address.to_string(),
+                    challenge_id: challenge_params.challenge_id.clone(),
+                    threads,
+                    partition_worker_id: partition.worker_id,
+                    partition_worker_count: partition.worker_count,
+                    nonces: nonces.to_vec(),
+                    updated_at: Utc::now().to_rfc3339(),
+                };
+                if let Err(e) = store.save_nonce_checkpoint(checkpoint_dir, &record) {
+                    eprintln!("⚠️ {}", e);
+                }
+            }),
+        }
+    });
+
+    let (found_nonce, total_hashes, elapsed_secs) = match crate::cluster::coordinator() {
+        // '--coordinator': the workers search, from the start of the partition (no checkpoints)
+        Some(coordinator) => coordinator.mine(&mining_address, challenge_params, &control, partition),
+        None => shadow_harvester_lib::scavenge(
+            mining_address.clone(),
+            challenge_params.challenge_id.clone(),
+            challenge_params.difficulty.clone(),
+            challenge_params.no_pre_mine_key.clone(),
+            challenge_params.latest_submission.clone(),
+            challenge_params.no_pre_mine_hour_str.clone(),
+            threads,
+            control.clone(),
+            partition,
+            checkpoint,
+        ),
+    };
 
     let mining_result = match found_nonce {
+        None if control.time_slice_expired() => MiningResult::SliceExpired,
+        None if submission_deadline_near(challenge_params, &control) => MiningResult::DeadlineReached,
+        None if control.challenge_superseded(&challenge_params.challenge_id) => MiningResult::ChallengeSuperseded,
         None => {
             println!("\n⚠️ Scavenging finished, but no solution was found.");
             MiningResult::MiningFailed
@@ -220,13 +509,15 @@ pub fn run_single_mining_cycle(
                 challenge_id: challenge_params.challenge_id.clone(),
                 nonce: nonce.clone(),
                 donation_address: donate_to_option.cloned(),
+                challenge_checksum: challenge_checksum(challenge_params),
+                idempotency_key: Some(new_idempotency_key()),
             };
 
 
             // CRITICAL STEP 1: Save to a temporary 'found' file first for crash recovery
-            if let Some(base_dir) = data_dir_base {
-                let temp_data_dir = DataDir::Ephemeral(&mining_address);
-                if let Err(e) = temp_data_dir.save_found_solution(base_dir, &challenge_params.challenge_id, &pending_solution) {
+            let temp_data_dir = DataDir::Ephemeral(&mining_address);
+            if let Some(store) = store {
+                if let Err(e) = store.save_found_solution(&temp_data_dir, &pending_solution) {
                      eprintln!("FATAL: Solution found but could not save recovery file {}: {}", FILE_NAME_FOUND_SOLUTION, e);
                      return (MiningResult::MiningFailed, total_hashes, elapsed_secs);
                 }
@@ -237,18 +528,23 @@ pub fn run_single_mining_cycle(
             }
 
             // CRITICAL STEP 2: Move from temporary file to persistent queue
-            if let Some(base_dir) = data_dir_base {
-                let temp_data_dir = DataDir::Ephemeral(&mining_address);
-                if let Err(e) = temp_data_dir.save_pending_solution(base_dir, &pending_solution) {
+            if let Some(store) = store {
+                if let Err(e) = store.save_pending_solution(&pending_solution) {
                      eprintln!("FATAL: Solution found but could not save to queue: {}", e);
                      // If queue save fails, the recovery file is still there, so we return MiningFailed.
                      return (MiningResult::MiningFailed, total_hashes, elapsed_secs);
                 }
 
                 // CRITICAL STEP 3: If save to queue is successful, delete the temporary file
-                if let Err(e) = temp_data_dir.delete_found_solution(base_dir, &challenge_params.challenge_id) {
+                if let Err(e) = store.delete_found_solution(&temp_data_dir, &challenge_params.challenge_id) {
                     eprintln!("WARNING: Failed to delete recovery file {}: {}", FILE_NAME_FOUND_SOLUTION, e);
                 }
+                // Unless more solutions are wanted, the search for this address and challenge is over
+                if !search_on
+                    && let Err(e) = store.delete_nonce_checkpoint(&temp_data_dir, &challenge_params.challenge_id)
+                {
+                    eprintln!("WARNING: {}", e);
+                }
 
                 println!("🚀 Solution queued successfully. Mining continues.");
             }
@@ -260,6 +556,60 @@ pub fn run_single_mining_cycle(
     (mining_result, total_hashes, elapsed_secs)
 }
 
+/// Logical and physical core counts of this machine.
+#[derive(Debug, Clone, Copy)]
+pub struct CpuTopology {
+    pub logical: u32,
+    pub physical: u32,
+}
+
+impl CpuTopology {
+    pub fn detect() -> Self {
+        let logical = thread::available_parallelism().map(|n| n.get() as u32).unwrap_or(1);
+        // Physical cores are the distinct (physical id, core id) pairs of /proc/cpuinfo (Linux only)
+        let physical = std::fs::read_to_string("/proc/cpuinfo").ok()
+            .map(|cpuinfo| {
+                let mut cores = std::collections::HashSet::new();
+                let mut physical_id = "";
+                for line in cpuinfo.lines() {
+                    if let Some((key, value)) = line.split_once(':') {
+                        match key.trim() {
+                            "physical id" => physical_id = value.trim(),
+                            "core id" => { cores.insert((physical_id, value.trim())); },
+                            _ => {},
+                        }
+                    }
+                }
+                cores.len() as u32
+            })
+            .filter(|&n| n > 0)
+            .unwrap_or(logical);
+        Self { logical, physical }
+    }
+}
+
+/// Clamps the worker threads so threads x concurrent wallets stays within `factor` x logical cores.
+pub fn plan_threads(requested: u32, concurrent_wallets: u32, factor: f64, topology: CpuTopology) -> Result<u32, String> {
+    if factor <= 0.0 {
+        return Err("'--oversubscription' must be a positive factor.".to_string());
+    }
+    let max_total = ((topology.logical as f64 * factor).floor() as u32).max(1);
+    let planned_total = requested.saturating_mul(concurrent_wallets);
+
+    if planned_total > max_total {
+        let clamped = (max_total / concurrent_wallets).max(1);
+        eprintln!(
+            "⚠️ {} threads x {} wallet(s) = {} planned threads exceeds {} ({} logical cores x {} oversubscription). Clamping to {} threads per wallet.",
+            requested, concurrent_wallets, planned_total, max_total, topology.logical, factor, clamped
+        );
+        return Ok(clamped);
+    }
+    if planned_total > topology.physical {
+        println!("ℹ️ {} planned threads on {} physical cores: relying on SMT/hyper-threading.", planned_total, topology.physical);
+    }
+    Ok(requested)
+}
+
 pub fn print_mining_setup(
     api_url: &str,
     address: Option<&str>,
@@ -272,24 +622,32 @@ pub fn print_mining_setup(
     println!("==============================================");
     println!("API URL: {}", api_url);
     println!("Mining Address: {}", address_display);
-    println!("Worker Threads: {}", threads);
+    let topology = CpuTopology::detect();
+    println!("Worker Threads: {} x {} wallet(s) = {} total on {} logical / {} physical cores", threads, CONCURRENT_WALLETS, threads * CONCURRENT_WALLETS, topology.logical, topology.physical);
     println!("----------------------------------------------");
     println!("CHALLENGE DETAILS:");
     println!("  ID:               {}", challenge_params.challenge_id);
     println!("  Day:              {}", challenge_params.day);
     println!("  Difficulty Mask:  {}", challenge_params.difficulty);
+    let zero_bits = shadow_harvester_lib::difficulty_to_zero_bits(&challenge_params.difficulty);
+    println!("  Zero Bits:        {} (~{} hashes per solution)", zero_bits, format_thousands(shadow_harvester_lib::expected_hashes(zero_bits) as u64));
     println!("  Submission Deadline: {}", challenge_params.latest_submission);
     println!("  ROM Key (no_pre_mine): {}", challenge_params.no_pre_mine_key);
     println!("  Hash Input Hour:  {}", challenge_params.no_pre_mine_hour_str);
     println!("----------------------------------------------");
 }
 
-// New function to check if a specific index already has a receipt
-pub fn receipt_exists_for_index(base_dir: &str, challenge_id: &str, wallet_config: &DataDirMnemonic) -> Result<bool, String> {
-    let data_dir = DataDir::Mnemonic(*wallet_config);
-    let mut path = data_dir.receipt_dir(base_dir, challenge_id)?;
-    path.push(FILE_NAME_RECEIPT);
-    Ok(path.exists())
+/// Number of wallets of a mnemonic account holding a local receipt for a challenge.
+pub fn count_mnemonic_receipts_for_challenge(base_dir: &str, challenge_id: &str, wallet: &DataDirMnemonic) -> Result<usize, String> {
+    let mut account_dir = DataDir::Mnemonic(DataDirMnemonic { deriv_index: 0, ..*wallet }).receipt_dir(base_dir, challenge_id)?;
+    account_dir.pop();
+
+    Ok(match std::fs::read_dir(&account_dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok())
+            .filter(|e| e.path().join(FILE_NAME_RECEIPT).exists())
+            .count(),
+        Err(_) => 0,
+    })
 }
 
 pub fn next_wallet_deriv_index_for_challenge(
@@ -364,6 +722,99 @@ pub fn next_wallet_deriv_index_for_challenge(
 /// Handles the initial setup, argument validation, T&C, and pre-mining command dispatch.
 /// Returns the necessary context for the main mining loop functions.
 pub fn setup_app(cli: &crate::cli::Cli) -> Result<MiningContext<'_>, String> {
+    // Every path below the data dir is resolved through this layout
+    crate::data_types::set_dir_overrides(crate::data_types::DirOverrides {
+        receipts_dir: cli.receipts_dir.clone(),
+        queue_dir: cli.queue_dir.clone(),
+    });
+    let rom_cache_dir = match (&cli.rom_cache_dir, &cli.data_dir) {
+        (Some(dir), _) => Some(PathBuf::from(dir)),
+        (None, Some(data_dir)) if cli.rom_cache => Some(PathBuf::from(data_dir).join(ROM_CACHE_DIR_NAME)),
+        _ => None,
+    };
+    if let Some(dir) = rom_cache_dir {
+        shadow_harvester_lib::set_rom_cache_dir(dir);
+    }
+    shadow_harvester_lib::set_low_memory(cli.low_memory);
+    shadow_harvester_lib::set_huge_pages(cli.huge_pages);
+    if let Some(mode_str) = cli.numa.as_deref() {
+        let mode = numa::NumaMode::parse(mode_str)?;
+        let nodes = numa::detect_nodes();
+        if mode != numa::NumaMode::Off {
+            if nodes.len() > 1 {
+                println!("🧭 NUMA: {} nodes, workers pinned per node ({}).", nodes.len(), mode_str.trim().to_ascii_lowercase());
+            } else {
+                eprintln!("⚠️ '--numa {}' ignored: {} NUMA node(s) found.", mode_str, nodes.len());
+            }
+        }
+        numa::set_placement(numa::NumaPlacement { mode, nodes });
+    }
+
+    // 0. Commands that work without an API connection
+    if let Some(crate::cli::Commands::Challenges { action: Some(crate::cli::ChallengesCommands::Note { challenge_id, note }) }) = &cli.command {
+        let data_dir = cli.data_dir.as_deref()
+            .ok_or("The 'challenges note' command requires '--data-dir'.")?;
+        let path = append_challenge_note(data_dir, challenge_id, note)?;
+        println!("📝 Note added to challenge {} ({:?}).", challenge_id, path);
+        return Err("COMMAND EXECUTED".to_string());
+    }
+
+    if let Some(crate::cli::Commands::Wallets { action: crate::cli::WalletsCommands::Note { wallet, note, wallets_file } }) = &cli.command {
+        crate::receipts::run_wallet_note(wallets_file, wallet, note)?;
+        return Err("COMMAND EXECUTED".to_string());
+    }
+
+    if let Some(crate::cli::Commands::Doctor) = cli.command {
+        crate::doctor::run_doctor(cli);
+        return Err("COMMAND EXECUTED".to_string());
+    }
+    if let Some(crate::cli::Commands::Benchmark { duration, thread_counts, challenge_id }) = &cli.command {
+        crate::benchmark::run_benchmark(cli, *duration, thread_counts.as_deref(), challenge_id.as_deref())?;
+        return Err("COMMAND EXECUTED".to_string());
+    }
+    if let Some(crate::cli::Commands::Validate { config, wallets_file, data_dir }) = &cli.command {
+        crate::validate::run_validate(
+            config.as_deref().or(cli.config.as_deref()),
+            wallets_file.as_deref(),
+            data_dir.as_deref().or(cli.data_dir.as_deref()),
+        )?;
+        return Err("COMMAND EXECUTED".to_string());
+    }
+    if let Some(crate::cli::Commands::Init) = cli.command {
+        crate::init::run_init(cli)?;
+        return Err("COMMAND EXECUTED".to_string());
+    }
+    if let Some(crate::cli::Commands::Diagnostics { action: crate::cli::DiagnosticsCommands::Bundle { output } }) = &cli.command {
+        crate::diagnostics::run_bundle(cli, output.as_deref())?;
+        return Err("COMMAND EXECUTED".to_string());
+    }
+
+    // A cluster worker needs no API connection, keys or T&C; its coordinator has them
+    if let Some(coordinator) = &cli.worker {
+        let coordinator = match (coordinator.as_deref(), cli.auto_discover) {
+            (Some(_), true) => return Err("Pass either the coordinator's address to '--worker' or '--auto-discover', not both.".to_string()),
+            (None, false) => return Err("'--worker' needs the coordinator's address (host:port), or '--auto-discover'.".to_string()),
+            (coordinator, _) => coordinator,
+        };
+        let token = crate::cluster::load_token(cli.cluster_token.as_deref(), cli.cluster_token_file.as_deref())?;
+        // Any host on the LAN can answer the mDNS query, and would get the token from a plain connection
+        if cli.auto_discover && token.is_some() && !cli.worker_tls {
+            return Err("'--auto-discover' with a cluster token needs '--worker-tls', so the token only goes to a coordinator holding a trusted certificate.".to_string());
+        }
+        let threads = match cli.threads.as_deref().map(ThreadCount::parse).transpose()?.unwrap_or(ThreadCount::Fixed(DEFAULT_THREADS)) {
+            ThreadCount::Fixed(n) => n,
+            ThreadCount::Auto => CpuTopology::detect().logical,
+        };
+        crate::cluster::run_worker(crate::cluster::WorkerOptions {
+            coordinator,
+            threads,
+            token,
+            tls: cli.worker_tls,
+            tls_ca: cli.worker_tls_ca.as_deref(),
+        })?;
+        return Err("COMMAND EXECUTED".to_string());
+    }
+
     // 1. Check for --api-url
     let api_url: String = match cli.api_url.clone() {
         Some(url) => url,
@@ -392,22 +843,146 @@ pub fn setup_app(cli: &crate::cli::Cli) -> Result<MiningContext<'_>, String> {
         }
     }
 
-    let client = create_api_client()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    if cli.canary.is_some() {
+        if cli.mnemonic.is_none() && cli.mnemonic_file.is_none() {
+            return Err("'--canary' selects a mnemonic wallet and requires '--mnemonic' or '--mnemonic-file'.".to_string());
+        }
+        if cli.data_dir.is_none() {
+            return Err("'--canary' requires '--data-dir' to submit the canary solution and record the verified version.".to_string());
+        }
+    }
+
+    if cli.solutions_per_challenge > 1 && cli.data_dir.is_none() {
+        return Err("'--solutions-per-challenge' requires '--data-dir' to keep the search position between solutions.".to_string());
+    }
+
+    if let Some(slice) = cli.time_slice {
+        if cli.mnemonic.is_none() && cli.mnemonic_file.is_none() {
+            return Err("'--time-slice' rotates mnemonic wallets and requires '--mnemonic' or '--mnemonic-file'.".to_string());
+        }
+        if slice < Duration::from_secs(1) || cli.time_slice_wallets == 0 {
+            return Err("'--time-slice' must be at least 1 second and '--time-slice-wallets' at least 1.".to_string());
+        }
+    }
+
+    if cli.stop_pool_after.is_some() && cli.mnemonic.is_none() && cli.mnemonic_file.is_none() {
+        return Err("'--stop-pool-after' counts mnemonic wallets and requires '--mnemonic' or '--mnemonic-file'.".to_string());
+    }
+    if cli.coordinator.is_some() && cli.worker_count > 1 {
+        return Err("'--coordinator' splits the nonce space among its workers and can't be combined with '--worker-count'.".to_string());
+    }
+    if cli.stop_pool_after == Some(0) {
+        return Err("'--stop-pool-after' must be at least 1.".to_string());
+    }
+
+    if cli.power_cap.is_some_and(|w| w <= 0.0) {
+        return Err("'--power-cap' must be a positive number of watts.".to_string());
+    }
+    if cli.max_temp.is_some_and(|c| c <= 0.0) {
+        return Err("'--max-temp' must be a positive temperature in Celsius.".to_string());
+    }
+    if cli.max_hashrate.is_some_and(|h| h < 1.0) {
+        return Err("'--max-hashrate' must be at least 1 hash per second.".to_string());
+    }
+    let peak_hours = cli.peak_hours.as_deref().map(HourWindow::parse).transpose()?;
+    let nonce_partition = NoncePartition::new(cli.worker_id, cli.worker_count)?;
+    let nonce_partition = match cli.nonce_seed {
+        Some(parse::NonceSeed::Random) => {
+            let seed = rand_core::RngCore::next_u64(&mut rand_core::OsRng);
+            println!("🎲 Random nonce seed {} (repeat this run with '--nonce-seed fixed:{}').", seed, seed);
+            nonce_partition.with_seed(seed)
+        }
+        Some(parse::NonceSeed::Fixed(seed)) => nonce_partition.with_seed(seed),
+        None => nonce_partition,
+    };
+    if nonce_partition.worker_count > 1 || nonce_partition.seed != 0 {
+        println!("🧩 Mining nonce range {}/{} (starting at {:016x}).", nonce_partition.worker_id, nonce_partition.worker_count, nonce_partition.base());
+    }
+    let thread_count = cli.threads.as_deref().map(ThreadCount::parse).transpose()?.unwrap_or(ThreadCount::Fixed(DEFAULT_THREADS));
+    if thread_count == ThreadCount::Auto && (cli.power_cap.is_some() || cli.max_temp.is_some()) {
+        return Err("'--threads auto' cannot be combined with '--power-cap' or '--max-temp': they all adjust the worker threads.".to_string());
+    }
+    if thread_count == ThreadCount::Auto && cli.pause_when_active.is_some() {
+        return Err("'--threads auto' cannot be combined with '--pause-when-active': paused workers can't be used to tune the thread count.".to_string());
+    }
+    if cli.pause_when_active.is_some_and(|idle| idle < Duration::from_secs(1)) {
+        return Err("'--pause-when-active' must be at least 1 second.".to_string());
+    }
+    if thread_count == ThreadCount::Auto && cli.max_hashrate.is_some() {
+        return Err("'--threads auto' cannot be combined with '--max-hashrate': a capped hash rate can't be used to tune the thread count.".to_string());
+    }
+    let topology = CpuTopology::detect();
+    let threads = plan_threads(
+        match thread_count {
+            ThreadCount::Fixed(n) => n,
+            // Auto-tuning starts with every core and only ever steps down from there
+            ThreadCount::Auto => topology.logical,
+        },
+        CONCURRENT_WALLETS,
+        cli.oversubscription.unwrap_or(DEFAULT_OVERSUBSCRIPTION_FACTOR),
+        topology,
+    )?;
+
+    api::set_retry_policy(api::RetryPolicy { retries: cli.api_retries, base_delay: cli.api_retry_delay });
+    api::set_timeouts(crate::config::load_api_timeouts(cli)?);
+    api::set_tls(api::ApiTls {
+        ca_certificates: cli.api_ca_file.as_deref().map(api::load_ca_file).transpose()?.unwrap_or_default(),
+        pins: cli.api_pin.iter().map(|pin| api::parse_pin(pin)).collect::<Result<_, _>>()?,
+    });
+    if let Some(path) = cli.api_trace.as_deref() {
+        crate::api_trace::open(path)?;
+    }
+    let client = create_api_client(cli.proxy.as_deref())?;
+    if cli.proxy.as_deref().is_some_and(|proxy| proxy.to_ascii_lowercase().starts_with("socks5://")) {
+        println!("⚠️ '--proxy socks5://' resolves host names locally. Use socks5h:// to resolve them through the proxy (e.g. Tor).");
+    }
+    let wallet_clients = match cli.proxies_file.as_deref() {
+        Some(path) => WalletClients::load(path, client.clone())?,
+        None => WalletClients::single(client.clone()),
+    };
 
     // --- COMMAND HANDLERS ---
-    if let Some(crate::cli::Commands::Challenges) = cli.command {
-        let challenge_response = api::fetch_challenge_status(&client, &api_url)
+    if let Some(crate::cli::Commands::Challenges { action: None }) = cli.command {
+        let challenge_response = api::block_on(api::fetch_challenge_status(&client, &api_url))
             .map_err(|e| format!("Could not fetch challenge status: {}", e))?;
         // FIX: Print full detailed status info from the ChallengeResponse object
         print_non_active_status(&challenge_response);
         println!("Challenge status fetched: {:?}", challenge_response);
+        if let (Some(base_dir), Some(challenge)) = (cli.data_dir.as_deref(), challenge_response.challenge.as_ref()) {
+            for note in load_challenge_notes(base_dir, &challenge.challenge_id) {
+                println!("📝 {}", note);
+            }
+        }
         // We use a specific error string to signal successful execution and exit in run_app
         return Err("COMMAND EXECUTED".to_string());
     }
 
+    if let Some(crate::cli::Commands::Challenges { action: Some(crate::cli::ChallengesCommands::History { day, status, limit, json }) }) = &cli.command {
+        let challenges = api::block_on(api::fetch_challenge_history(&client, &api_url, *day, status.as_deref(), *limit))
+            .map_err(|e| format!("Could not fetch the challenge history: {}", e))?;
+        print_challenge_history(&challenges, cli.data_dir.as_deref(), *json)?;
+        return Err("COMMAND EXECUTED".to_string());
+    }
+
+    if let Some(crate::cli::Commands::Stats { action: crate::cli::StatsCommands::Network { wallets_file } }) = &cli.command {
+        crate::stats::run_network_stats(&wallet_clients, &api_url, cli.address.iter().cloned().collect(), wallets_file.as_deref())?;
+        return Err("COMMAND EXECUTED".to_string());
+    }
+
+    if let Some(crate::cli::Commands::ApiCheck) = cli.command {
+        crate::api_check::run_api_check(&client, &api_url, cli.address.as_deref())?;
+        return Err("COMMAND EXECUTED".to_string());
+    }
+
+    if let Some(crate::cli::Commands::Receipts { action: crate::cli::ReceiptsCommands::Backfill { wallets_file } }) = &cli.command {
+        let data_dir = cli.data_dir.as_deref()
+            .ok_or("The 'receipts backfill' command requires '--data-dir'.")?;
+        crate::receipts::run_backfill(&wallet_clients, &api_url, data_dir, wallets_file)?;
+        return Err("COMMAND EXECUTED".to_string());
+    }
+
     // 3. Fetch T&C message (always required for registration payload)
-    let tc_response: TandCResponse = match api::fetch_tandc(&client, &api_url) {
+    let tc_response: TandCResponse = match api::block_on(api::fetch_tandc(&client, &api_url)) {
         Ok(t) => t,
         Err(e) => return Err(format!("Could not fetch T&C from API URL: {}. Details: {}", api_url, e)),
     };
@@ -419,13 +994,97 @@ pub fn setup_app(cli: &crate::cli::Cli) -> Result<MiningContext<'_>, String> {
         return Err("You must pass the '--accept-tos' flag to proceed with mining.".to_string());
     }
 
+    // Restored from '--state s3://' before the data dir is read
+    let store: Option<Arc<dyn StateStore>> = match (cli.data_dir.as_deref().filter(|_| !cli.watch), cli.state.as_deref()) {
+        (Some(base_dir), Some(url)) if url.starts_with("s3://") => Some(Arc::new(S3Store::connect(url, base_dir, cli.proxy.as_deref())?)),
+        (Some(base_dir), Some(url)) => Some(Arc::new(RedisStore::connect(url, base_dir)?)),
+        (Some(base_dir), None) => Some(Arc::new(FsStore::new(base_dir))),
+        (None, _) => None,
+    };
+
+    // Finish a challenge switch the previous run died in, before the submitter reads the queue
+    if let Some(base_dir) = cli.data_dir.as_deref().filter(|_| !cli.watch) {
+        crate::journal::repair_interrupted_switch(base_dir)?;
+    }
+
+    // 5. Resolve the hashing backend
+    if cli.force_scalar {
+        println!("ℹ️ '--force-scalar': the hash only has the portable scalar path; nothing to disable.");
+    }
+
+    // Before anything else reads stdin (the pause key listener below)
+    crate::checklist::run_first_run_checklist(cli)?;
+
+    // 6. Shared worker control, adjustable at runtime
+    let control = Arc::new(ScavengeControl::new(threads));
+    control.set_live(!cli.no_live);
+    control.set_deadline_margin(Some(cli.deadline_margin));
+    if let Some(max_hashrate) = cli.max_hashrate {
+        control.set_max_hash_rate(max_hashrate as u64);
+        println!("🐢 Hash rate capped at {} H/s across {} thread(s).", max_hashrate as u64, threads);
+    }
+    if let Err(e) = spawn_thread_signal_listener(control.clone(), threads) {
+        eprintln!("⚠️ {}. Runtime thread adjustment disabled.", e);
+    }
+    if let Err(e) = spawn_pause_signal_listener(control.clone()) {
+        eprintln!("⚠️ {}. Pausing by signal disabled.", e);
+    }
+    spawn_pause_key_listener(control.clone());
+    if let Some(bind) = cli.coordinator.as_deref() {
+        crate::cluster::start_coordinator(crate::cluster::CoordinatorOptions {
+            bind,
+            grpc_bind: cli.coordinator_grpc.as_deref(),
+            stratum_bind: cli.coordinator_stratum.as_deref(),
+            announce: cli.coordinator_announce,
+            token: crate::cluster::load_token(cli.cluster_token.as_deref(), cli.cluster_token_file.as_deref())?,
+            tls: cli.coordinator_tls_cert.as_deref().zip(cli.coordinator_tls_key.as_deref()),
+            queue: store.clone(),
+            donate_to: cli.donate_to.clone(),
+        }, control.clone())?;
+    }
+    if let Some(bind) = cli.health_listen.as_deref() {
+        crate::health::spawn_health_server(bind, client.clone(), api_url.clone(), cli.data_dir.clone(), control.clone())?;
+    }
+    if thread_count == ThreadCount::Auto {
+        spawn_thread_auto_tuner(control.clone(), threads, topology.physical);
+    }
+    if cli.nice {
+        match lower_priority() {
+            Ok(applied) => println!("🐌 Mining at low priority ({}).", applied),
+            Err(e) => eprintln!("⚠️ {}. Mining at normal priority.", e),
+        }
+    }
+    if let Some(idle) = cli.pause_when_active
+        && let Err(e) = spawn_activity_monitor(control.clone(), idle)
+    {
+        eprintln!("⚠️ {}. '--pause-when-active' disabled.", e);
+    }
+
+    // 7. Start the notification dispatcher
+    let notifier_configs = crate::config::load_notifiers(cli)?;
+    let notifications = Notifications::start(build_sinks(&client, cli.webhook_url.as_ref(), &notifier_configs));
+
     Ok(MiningContext {
         client,
+        wallet_clients,
         api_url,
         tc_response,
         donate_to_option: cli.donate_to.as_ref(),
-        threads: cli.threads,
+        threads,
         cli_challenge: cli.challenge.as_ref(),
         data_dir: cli.data_dir.as_deref(),
+        store,
+        notifications,
+        stagger_secs: cli.stagger.as_secs(),
+        throttle: ThrottleSettings {
+            power_cap_watts: cli.power_cap,
+            peak_hours,
+            max_temp_celsius: cli.max_temp,
+        },
+        pending_queue: None,
+        control,
+        nonce_partition,
+        allow_stale_challenge: cli.allow_stale_challenge,
+        solutions_per_challenge: cli.solutions_per_challenge,
     })
 }
@@ -0,0 +1,134 @@
+// src/utils/parse.rs
+
+use std::time::Duration;
+
+// ===============================================
+// DURATIONS
+// ===============================================
+
+const DURATION_EXAMPLES: &str = "Use e.g. 45s, 90m, 2h30m or 1d";
+
+fn unit_seconds(unit: &str) -> Option<f64> {
+    match unit.to_ascii_lowercase().as_str() {
+        "s" | "sec" | "secs" | "second" | "seconds" => Some(1.0),
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(60.0),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(3600.0),
+        "d" | "day" | "days" => Some(86400.0),
+        _ => None,
+    }
+}
+
+/// Parses `90m`, `2h30m`, `1h 15m`, `1.5h` or `1,5h` (decimal comma). A bare number is read in `bare_unit`
+/// seconds, which keeps flags that used to take a plain number of minutes or seconds working.
+pub fn parse_duration(input: &str, bare_unit: u64) -> Result<Duration, String> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err(format!("Empty duration. {}.", DURATION_EXAMPLES));
+    }
+
+    let mut total_secs = 0.0;
+    let mut rest = trimmed;
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ',')).unwrap_or(rest.len());
+        let (number, after) = rest.split_at(number_len);
+        if number.is_empty() {
+            return Err(format!("Invalid duration '{}': expected a number before '{}'. {}.", input, after, DURATION_EXAMPLES));
+        }
+        let value: f64 = number.replace(',', ".").parse()
+            .map_err(|_| format!("Invalid duration '{}': '{}' is not a number. {}.", input, number, DURATION_EXAMPLES))?;
+
+        let unit_len = after.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(after.len());
+        let (unit, after) = after.split_at(unit_len);
+        let multiplier = match unit {
+            "" if number_len == trimmed.len() => bare_unit as f64,
+            "" => return Err(format!("Invalid duration '{}': '{}' has no unit. {}.", input, number, DURATION_EXAMPLES)),
+            unit => unit_seconds(unit)
+                .ok_or_else(|| format!("Invalid duration '{}': unknown unit '{}'. {}.", input, unit, DURATION_EXAMPLES))?,
+        };
+        total_secs += value * multiplier;
+        rest = after.trim_start();
+    }
+
+    Duration::try_from_secs_f64(total_secs)
+        .map_err(|_| format!("Invalid duration '{}': too long. {}.", input, DURATION_EXAMPLES))
+}
+
+/// clap parser for flags whose bare numbers are seconds.
+pub fn duration_secs(input: &str) -> Result<Duration, String> {
+    parse_duration(input, 1)
+}
+
+/// clap parser for flags whose bare numbers are minutes.
+pub fn duration_mins(input: &str) -> Result<Duration, String> {
+    parse_duration(input, 60)
+}
+
+// ===============================================
+// NONCE SEEDS
+// ===============================================
+
+/// Where the nonce search starts within this machine's range ('--nonce-seed').
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NonceSeed {
+    Random,
+    Fixed(u64),
+}
+
+/// clap parser for `random` or `fixed:<u64>` (decimal, or hex with `0x`).
+pub fn nonce_seed(input: &str) -> Result<NonceSeed, String> {
+    let trimmed = input.trim();
+    if trimmed.eq_ignore_ascii_case("random") {
+        return Ok(NonceSeed::Random);
+    }
+    let value = trimmed.strip_prefix("fixed:")
+        .ok_or_else(|| format!("Invalid nonce seed '{}'. Use 'random' or 'fixed:<number>'.", input))?;
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map(NonceSeed::Fixed)
+        .map_err(|_| format!("Invalid nonce seed '{}': '{}' is not a 64-bit number.", input, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_unit() {
+        assert_eq!(parse_duration("45s", 1), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("90m", 1), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_duration("3hours", 1), Ok(Duration::from_secs(3 * 3600)));
+        assert_eq!(parse_duration("1d", 1), Ok(Duration::from_secs(86400)));
+        assert_eq!(parse_duration("2MIN", 1), Ok(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn reads_bare_numbers_in_the_flag_unit() {
+        assert_eq!(duration_secs("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(duration_mins("30"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(duration_secs(" 0 "), Ok(Duration::ZERO));
+    }
+
+    #[test]
+    fn sums_compound_and_decimal_durations() {
+        assert_eq!(parse_duration("2h30m", 1), Ok(Duration::from_secs(2 * 3600 + 30 * 60)));
+        assert_eq!(parse_duration("1h 15m", 1), Ok(Duration::from_secs(3600 + 15 * 60)));
+        assert_eq!(parse_duration("1.5h", 1), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1,5h", 1), Ok(Duration::from_secs(5400)));
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        for input in ["", "  ", "h", "10x", "1h30", "1..5h", "-5m", "1e400s"] {
+            assert!(parse_duration(input, 1).is_err(), "'{}' should be rejected", input);
+        }
+    }
+
+    #[test]
+    fn rejects_durations_that_overflow() {
+        let huge = format!("{}d", "9".repeat(20));
+        assert!(parse_duration(&huge, 1).is_err());
+        assert!(duration_mins(&"9".repeat(400)).is_err());
+    }
+}
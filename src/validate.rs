@@ -0,0 +1,446 @@
+// src/validate.rs
+
+use crate::api;
+use crate::config::{ConfigFile, NotifierBackend, ProfileThreads, DEFAULT_CONFIG_FILE};
+use crate::data_types::{
+    BackfillReceipt, ChallengeData, NonceCheckpointRecord, PendingSolution, QueueEntry, WalletStatsDiff,
+    BACKFILL_DIR_NAME, FILE_NAME_CHALLENGE, FILE_NAME_FOUND_SOLUTION, FILE_NAME_NONCE_CHECKPOINT, is_receipt_file_name,
+    FILE_NAME_STATS_DIFF, is_queue_file, load_cached_challenge, queue_dir, read_pending_solution, receipts_root,
+};
+use crate::notify::NotifyEvent;
+use crate::receipts::parse_wallets_line;
+use crate::throttle::{HourWindow, ThreadCount};
+use pallas::ledger::addresses::Address;
+use serde::de::DeserializeOwned;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+// ===============================================
+// FINDINGS
+// ===============================================
+
+/// A problem in one input file, with the line it is on when known.
+struct Finding {
+    path: PathBuf,
+    line: Option<usize>,
+    message: String,
+}
+
+#[derive(Default)]
+struct Report {
+    files_checked: usize,
+    findings: Vec<Finding>,
+}
+
+impl Report {
+    fn error(&mut self, path: &Path, line: Option<usize>, message: impl Into<String>) {
+        self.findings.push(Finding { path: path.to_path_buf(), line, message: message.into() });
+    }
+}
+
+/// 1-based line number of a byte offset.
+fn line_at(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+/// Line of the first `key = ...` after the `[header]` line, falling back to the header itself.
+fn line_of_key(text: &str, header: &str, key: &str) -> Option<usize> {
+    let lines: Vec<&str> = text.lines().collect();
+    let header_index = lines.iter().position(|line| line.trim() == header)?;
+    let key_index = lines[header_index + 1..].iter()
+        .take_while(|line| !line.trim_start().starts_with('['))
+        .position(|line| line.trim_start().strip_prefix(key).is_some_and(|rest| rest.trim_start().starts_with('=')))
+        .map(|index| header_index + 1 + index)
+        .unwrap_or(header_index);
+    Some(key_index + 1)
+}
+
+fn is_bech32_address(address: &str) -> bool {
+    Address::from_bech32(address).is_ok()
+}
+
+// ===============================================
+// CONFIG FILE
+// ===============================================
+
+fn validate_config(report: &mut Report, path: &Path) {
+    report.files_checked += 1;
+    let config_toml = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => return report.error(path, None, format!("Could not read config file: {}", e)),
+    };
+    let config: ConfigFile = match toml::from_str(&config_toml) {
+        Ok(config) => config,
+        Err(e) => {
+            let line = e.span().map(|span| line_at(&config_toml, span.start));
+            return report.error(path, line, e.message().trim().to_string());
+        }
+    };
+
+    let mut profile_names: Vec<&String> = config.profile.keys().collect();
+    profile_names.sort();
+    for name in profile_names {
+        let profile = &config.profile[name];
+        let header = format!("[profile.{}]", name);
+        let mut check = |key: &str, problem: Option<String>| {
+            if let Some(message) = problem {
+                report.error(path, line_of_key(&config_toml, &header, key), format!("profile '{}': {}", name, message));
+            }
+        };
+
+        check("api-url", profile.api_url.as_deref().map(api::endpoints).unwrap_or_default().iter()
+            .find_map(|url| reqwest::Url::parse(url).err().map(|e| format!("api-url '{}' is not a URL: {}", url, e))));
+        check("threads", match &profile.threads {
+            Some(ProfileThreads::Keyword(keyword)) => ThreadCount::parse(keyword).err(),
+            Some(ProfileThreads::Count(0)) => Some("threads must be at least 1".to_string()),
+            _ => None,
+        });
+        check("oversubscription", profile.oversubscription.filter(|factor| *factor <= 0.0)
+            .map(|_| "oversubscription must be a positive factor".to_string()));
+        check("mnemonic-file", profile.mnemonic_file.as_deref().filter(|file| !Path::new(file).is_file())
+            .map(|file| format!("mnemonic-file '{}' does not exist", file)));
+        for (key, value) in [("mnemonic-account", profile.mnemonic_account), ("mnemonic-starting-index", profile.mnemonic_starting_index)] {
+            check(key, match value {
+                Some(_) if profile.mnemonic_file.is_none() => Some(format!("{} has no effect without mnemonic-file", key)),
+                // Larger values would collide with hardened derivation indices
+                Some(n) if n >= 0x8000_0000 => Some(format!("{} must be below 2^31", key)),
+                _ => None,
+            });
+        }
+        check("donate-to", profile.donate_to.as_deref().filter(|address| !is_bech32_address(address))
+            .map(|address| format!("donate-to '{}' is not a valid bech32 Cardano address", address)));
+        check("power-cap", profile.power_cap.filter(|watts| *watts <= 0.0)
+            .map(|_| "power-cap must be a positive number of watts".to_string()));
+        check("peak-hours", profile.peak_hours.as_deref().and_then(|hours| HourWindow::parse(hours).err()));
+        check("max-temp", profile.max_temp.filter(|celsius| *celsius <= 0.0)
+            .map(|_| "max-temp must be a positive temperature in Celsius".to_string()));
+        check("max-hashrate", profile.max_hashrate.filter(|rate| *rate < 1.0)
+            .map(|_| "max-hashrate must be at least 1 hash per second".to_string()));
+    }
+
+    for (name, timeouts) in config.timeouts.endpoints() {
+        let Some(timeouts) = timeouts else { continue };
+        let header = format!("[timeouts.{}]", name);
+        for (key, secs) in [("connect-secs", timeouts.connect_secs), ("read-secs", timeouts.read_secs)] {
+            if secs.is_some_and(|secs| !(secs > 0.0 && secs.is_finite())) {
+                report.error(path, line_of_key(&config_toml, &header, key), format!("timeouts.{}: {} must be a positive number of seconds", name, key));
+            }
+        }
+    }
+
+    // [[notifier]] tables have no name; point at the n-th one
+    let notifier_lines: Vec<usize> = config_toml.lines().enumerate()
+        .filter(|(_, line)| line.trim() == "[[notifier]]")
+        .map(|(index, _)| index + 1)
+        .collect();
+    for (index, notifier) in config.notifier.iter().enumerate() {
+        let line = notifier_lines.get(index).copied();
+        for event in notifier.events.iter().filter(|e| !NotifyEvent::KINDS.contains(&e.as_str())) {
+            report.error(path, line, format!("notifier #{}: unknown event '{}'. Known events: {:?}", index + 1, event, NotifyEvent::KINDS));
+        }
+        if let NotifierBackend::Webhook { url } = &notifier.backend
+            && let Err(e) = reqwest::Url::parse(url)
+        {
+            report.error(path, line, format!("notifier #{}: url '{}' is not a URL: {}", index + 1, url, e));
+        }
+    }
+}
+
+// ===============================================
+// WALLETS FILE
+// ===============================================
+
+fn validate_wallets_file(report: &mut Report, path: &Path) {
+    report.files_checked += 1;
+    let wallets = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => return report.error(path, None, format!("Could not read wallets file: {}", e)),
+    };
+
+    let mut seen = HashSet::new();
+    for (index, line) in wallets.lines().enumerate() {
+        let Some((address, _)) = parse_wallets_line(line) else { continue };
+        if !is_bech32_address(address) {
+            report.error(path, Some(index + 1), format!("'{}' is not a valid bech32 Cardano address", address));
+        } else if !seen.insert(address) {
+            report.error(path, Some(index + 1), format!("address {} is listed twice", address));
+        }
+    }
+    if seen.is_empty() {
+        report.error(path, None, "the wallets file contains no addresses");
+    }
+}
+
+// ===============================================
+// DATA DIR
+// ===============================================
+
+fn check_json<T: DeserializeOwned>(report: &mut Report, path: &Path) -> Option<T> {
+    report.files_checked += 1;
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) => {
+            report.error(path, None, format!("Could not read: {}", e));
+            return None;
+        }
+    };
+    match serde_json::from_str(&json) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            report.error(path, Some(e.line()), e.to_string());
+            None
+        }
+    }
+}
+
+fn check_solution_fields(report: &mut Report, path: &Path, solution: &PendingSolution) {
+    if !is_bech32_address(&solution.address) {
+        report.error(path, None, format!("address '{}' is not a valid bech32 Cardano address", solution.address));
+    }
+    if solution.nonce.len() != 16 || !solution.nonce.chars().all(|c| c.is_ascii_hexdigit()) {
+        report.error(path, None, format!("nonce '{}' is not 16 hex digits", solution.nonce));
+    }
+    if let Some(donation_address) = solution.donation_address.as_deref().filter(|a| !is_bech32_address(a)) {
+        report.error(path, None, format!("donation_address '{}' is not a valid bech32 Cardano address", donation_address));
+    }
+}
+
+fn validate_queue(report: &mut Report, base_dir: &str) {
+    let queue_path = queue_dir(base_dir);
+    let Ok(entries) = std::fs::read_dir(&queue_path) else { return };
+
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_file()) {
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+        if !is_queue_file(&file_name) {
+            continue;
+        }
+        report.files_checked += 1;
+        let Some(entry) = QueueEntry::from_file_name(&file_name) else {
+            report.error(&path, None, "file name is not '<address>_<challenge_id>_<nonce>.json.zst'");
+            continue;
+        };
+        match read_pending_solution(&path) {
+            Ok(solution) => {
+                check_solution_fields(report, &path, &solution);
+                if solution.address != entry.address || solution.challenge_id != entry.challenge_id || solution.nonce != entry.nonce {
+                    report.error(&path, None, "contents do not match the address, challenge and nonce in the file name");
+                }
+            },
+            Err(e) => report.error(&path, None, e),
+        }
+    }
+}
+
+/// Checks every file the miner writes below a challenge directory, whatever the wallet layout.
+fn validate_receipt_tree(report: &mut Report, dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.is_dir() {
+            validate_receipt_tree(report, &path);
+            continue;
+        }
+        match path.file_name().and_then(|n| n.to_str()).unwrap_or_default() {
+            name if is_receipt_file_name(name) => {
+                let receipt = check_json::<serde_json::Value>(report, &path);
+                if receipt.is_some_and(|receipt| !receipt.is_object()) {
+                    report.error(&path, None, "a receipt must be a JSON object");
+                }
+            },
+            FILE_NAME_FOUND_SOLUTION => {
+                if let Some(solution) = check_json::<PendingSolution>(report, &path) {
+                    check_solution_fields(report, &path, &solution);
+                }
+            },
+            FILE_NAME_NONCE_CHECKPOINT => {
+                if let Some(checkpoint) = check_json::<NonceCheckpointRecord>(report, &path)
+                    && checkpoint.nonces.len() != checkpoint.threads as usize
+                {
+                    report.error(&path, None, format!("{} nonces for {} threads", checkpoint.nonces.len(), checkpoint.threads));
+                }
+            },
+            FILE_NAME_STATS_DIFF => { check_json::<Vec<WalletStatsDiff>>(report, &path); },
+            _ => {},
+        }
+    }
+}
+
+fn validate_receipts(report: &mut Report, base_dir: &str) {
+    let root = receipts_root(base_dir);
+    let Ok(entries) = std::fs::read_dir(&root) else { return };
+
+    for challenge_dir in entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()) {
+        let name = challenge_dir.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+        if name == BACKFILL_DIR_NAME {
+            for path in std::fs::read_dir(&challenge_dir).into_iter().flatten().filter_map(|e| e.ok()).map(|e| e.path()) {
+                if let Some(receipt) = check_json::<BackfillReceipt>(report, &path)
+                    && path.file_stem().and_then(|s| s.to_str()) != Some(receipt.address.as_str())
+                {
+                    report.error(&path, None, format!("address {} does not match the file name", receipt.address));
+                }
+            }
+            continue;
+        }
+
+        // Every challenge directory starts with its challenge.json; anything else isn't the miner's
+        let challenge_path = challenge_dir.join(FILE_NAME_CHALLENGE);
+        if !challenge_path.exists() {
+            continue;
+        }
+        if let Some(challenge) = check_json::<ChallengeData>(report, &challenge_path) {
+            if challenge.challenge_id != name {
+                report.error(&challenge_path, None, format!("challenge_id {} does not match its directory", challenge.challenge_id));
+            } else if let Err(e) = load_cached_challenge(base_dir, &name, false) {
+                report.error(&challenge_path, None, e);
+            }
+        }
+        validate_receipt_tree(report, &challenge_dir);
+    }
+}
+
+// ===============================================
+// VALIDATE COMMAND
+// ===============================================
+
+/// Checks the config file, wallets file and data dir against the shapes the miner reads and writes.
+/// Every problem is printed as `file:line: message`; any problem makes the command fail.
+pub fn run_validate(config: Option<&str>, wallets_file: Option<&str>, data_dir: Option<&str>) -> Result<(), String> {
+    let config = config.or(Path::new(DEFAULT_CONFIG_FILE).exists().then_some(DEFAULT_CONFIG_FILE));
+    if config.is_none() && wallets_file.is_none() && data_dir.is_none() {
+        return Err("Nothing to validate. Pass '--config', '--wallets-file' and/or '--data-dir'.".to_string());
+    }
+
+    println!("\n==============================================");
+    println!("🔎 Shadow Harvester: Validating inputs");
+    println!("==============================================");
+
+    let mut report = Report::default();
+    if let Some(path) = config {
+        println!("Config file:   {}", path);
+        validate_config(&mut report, Path::new(path));
+    }
+    if let Some(path) = wallets_file {
+        println!("Wallets file:  {}", path);
+        validate_wallets_file(&mut report, Path::new(path));
+    }
+    if let Some(base_dir) = data_dir {
+        println!("Data dir:      {}", base_dir);
+        if !Path::new(base_dir).is_dir() {
+            report.error(Path::new(base_dir), None, "data dir does not exist");
+        }
+        validate_queue(&mut report, base_dir);
+        validate_receipts(&mut report, base_dir);
+    }
+
+    println!("----------------------------------------------");
+    for finding in &report.findings {
+        match finding.line {
+            Some(line) => eprintln!("❌ {}:{}: {}", finding.path.display(), line, finding.message),
+            None => eprintln!("❌ {}: {}", finding.path.display(), finding.message),
+        }
+    }
+    if !report.findings.is_empty() {
+        return Err(format!("Validation failed: {} problem(s) in {} file(s) checked.", report.findings.len(), report.files_checked));
+    }
+    println!("✅ {} file(s) checked, no problems found.", report.files_checked);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "addr1qq4dl3nhr0axurgcrpun9xyp04pd2r2dwu5x7eeam98psv6dhxlde8ucclv2p46hm077ds4vzelf5565fg3ky794uhrq5up0he";
+
+    /// A scratch directory holding `files`, removed again by the caller.
+    fn scratch(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("shadow-harvester-validate-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (file, contents) in files {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    fn findings(report: &Report) -> Vec<(Option<usize>, &str)> {
+        report.findings.iter().map(|finding| (finding.line, finding.message.as_str())).collect()
+    }
+
+    #[test]
+    fn accepts_a_valid_config() {
+        let dir = scratch("config-ok", &[("config.toml", &format!(
+            "[profile.home]\napi-url = \"https://a.example,https://b.example\"\nthreads = \"auto\"\ndonate-to = \"{}\"\npeak-hours = \"17-21\"\n\n\
+             [[notifier]]\nkind = \"webhook\"\nurl = \"https://hooks.example/x\"\nevents = [\"solution_submitted\"]\n", ADDRESS))]);
+        let mut report = Report::default();
+        validate_config(&mut report, &dir.join("config.toml"));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(report.files_checked, 1);
+        assert!(report.findings.is_empty(), "{:?}", findings(&report));
+    }
+
+    #[test]
+    fn reports_config_problems_on_their_lines() {
+        let dir = scratch("config-bad", &[("config.toml",
+            "[profile.home]\nthreads = 0\npeak-hours = \"25-3\"\nmnemonic-account = 1\n\n\
+             [[notifier]]\nkind = \"webhook\"\nurl = \"not a url\"\nevents = [\"solution_submitted\", \"lunch\"]\n")]);
+        let mut report = Report::default();
+        validate_config(&mut report, &dir.join("config.toml"));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let findings = findings(&report);
+        assert_eq!(findings.len(), 5, "{:?}", findings);
+        assert!(findings.contains(&(Some(2), "profile 'home': threads must be at least 1")), "{:?}", findings);
+        assert!(findings.iter().any(|(line, message)| *line == Some(3) && message.starts_with("profile 'home':")), "{:?}", findings);
+        assert!(findings.contains(&(Some(4), "profile 'home': mnemonic-account has no effect without mnemonic-file")), "{:?}", findings);
+        assert!(findings.iter().any(|(line, message)| *line == Some(6) && message.starts_with("notifier #1: url 'not a url' is not a URL")), "{:?}", findings);
+        assert!(findings.iter().any(|(line, message)| *line == Some(6) && message.starts_with("notifier #1: unknown event 'lunch'")), "{:?}", findings);
+    }
+
+    #[test]
+    fn reports_toml_syntax_errors_with_their_line() {
+        let dir = scratch("config-syntax", &[("config.toml", "[profile.home]\nthreads = \"auto\"\npower-cap = \n")]);
+        let mut report = Report::default();
+        validate_config(&mut report, &dir.join("config.toml"));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(findings(&report).iter().map(|(line, _)| *line).collect::<Vec<_>>(), [Some(3)]);
+    }
+
+    #[test]
+    fn reports_bad_and_duplicate_wallet_addresses() {
+        let dir = scratch("wallets", &[("wallets.txt", &format!("# fleet\n{}\nnot-an-address\n{} # again\n", ADDRESS, ADDRESS))]);
+        let mut report = Report::default();
+        validate_wallets_file(&mut report, &dir.join("wallets.txt"));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let findings = findings(&report);
+        assert_eq!(findings.len(), 2, "{:?}", findings);
+        assert_eq!(findings[0], (Some(3), "'not-an-address' is not a valid bech32 Cardano address"));
+        assert_eq!(findings[1].0, Some(4));
+        assert!(findings[1].1.ends_with("is listed twice"));
+    }
+
+    #[test]
+    fn reports_a_checkpoint_whose_nonces_do_not_match_its_threads() {
+        let checkpoint = |threads: u32, nonces: &str| format!(
+            r#"{{"address":"{}","challenge_id":"**D01C05","threads":{},"partition_worker_id":0,"partition_worker_count":1,"nonces":{},"updated_at":"2025-11-01T00:00:00Z"}}"#,
+            ADDRESS, threads, nonces);
+        let (good, bad) = (checkpoint(2, "[1, 2]"), checkpoint(4, "[1, 2]"));
+        let dir = scratch("checkpoint", &[
+            (&format!("good/{}", FILE_NAME_NONCE_CHECKPOINT), &good),
+            (&format!("bad/{}", FILE_NAME_NONCE_CHECKPOINT), &bad),
+            (&format!("broken/{}", FILE_NAME_NONCE_CHECKPOINT), "{\"address\":"),
+        ]);
+        let mut report = Report::default();
+        validate_receipt_tree(&mut report, &dir);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(report.files_checked, 3);
+        let mut paths: Vec<String> = report.findings.iter()
+            .map(|finding| finding.path.parent().unwrap().file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        paths.sort();
+        assert_eq!(paths, ["bad", "broken"]);
+        assert!(report.findings.iter().any(|finding| finding.message == "2 nonces for 4 threads"));
+    }
+}
@@ -0,0 +1,150 @@
+// src/watch.rs
+
+use crate::api;
+use crate::data_types::{ChallengeResponse, Statistics};
+use crate::utils::{format_duration, format_thousands};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+// Refresh schedule: slow while nothing is about to happen, faster as the next deadline approaches
+const WATCH_POLL_IDLE: Duration = Duration::from_secs(60);
+const WATCH_POLL_SOON: Duration = Duration::from_secs(15);
+const WATCH_POLL_IMMINENT: Duration = Duration::from_secs(5);
+const SOON_SECS: i64 = 15 * 60;
+const IMMINENT_SECS: i64 = 60;
+// Failed polls back off up to this interval
+const WATCH_POLL_MAX: Duration = Duration::from_secs(300);
+
+fn seconds_until(timestamp: Option<&str>, now: DateTime<Utc>) -> Option<i64> {
+    let at = DateTime::parse_from_rfc3339(timestamp?).ok()?;
+    Some((at.with_timezone(&Utc) - now).num_seconds())
+}
+
+fn countdown(seconds: Option<i64>) -> String {
+    match seconds {
+        Some(s) if s > 0 => format!("in {}", format_duration(s as f64)),
+        Some(_) => "passed".to_string(),
+        None => "unknown".to_string(),
+    }
+}
+
+/// Network solutions per hour from two samples of the global receipt count.
+struct SolutionRate {
+    first: Option<(Instant, u32)>,
+}
+
+impl SolutionRate {
+    fn per_hour(&mut self, total_receipts: u32) -> Option<f64> {
+        let (since, first_total) = *self.first.get_or_insert((Instant::now(), total_receipts));
+        let elapsed = since.elapsed().as_secs_f64();
+        (elapsed > 0.0).then(|| total_receipts.saturating_sub(first_total) as f64 * 3600.0 / elapsed)
+    }
+}
+
+/// Prints one refresh of the ticker and returns the time until the nearest upcoming deadline.
+fn render(status: &ChallengeResponse, stats: Option<&Statistics>, rate: &mut SolutionRate) -> Option<i64> {
+    let now = Utc::now();
+    let mut deadlines = Vec::new();
+
+    println!("==============================================");
+    println!("👀 Shadow Harvester: WATCH ({})", now.format("%Y-%m-%d %H:%M:%S UTC"));
+    println!("==============================================");
+    match (status.code.as_str(), status.challenge.as_ref()) {
+        ("active", Some(challenge)) => {
+            let submission_closes = seconds_until(Some(&challenge.latest_submission), now);
+            deadlines.push(submission_closes);
+            println!("Challenge:         {} (#{}, day {})", challenge.challenge_id, challenge.challenge_number, challenge.day);
+            println!("Difficulty:        {}", challenge.difficulty);
+            println!("Submissions close: {}", countdown(submission_closes));
+        }
+        ("before", _) => {
+            let starts = seconds_until(status.starts_at.as_deref(), now);
+            deadlines.push(starts);
+            println!("Mining not started yet. Starts {}.", countdown(starts));
+        }
+        ("after", _) => println!("Mining period has ended."),
+        (code, _) => println!("Challenge status: {}", code),
+    }
+    let next_challenge = seconds_until(status.next_challenge_starts_at.as_deref(), now);
+    let period_ends = seconds_until(status.mining_period_ends.as_deref(), now);
+    deadlines.extend([next_challenge, period_ends]);
+    if status.next_challenge_starts_at.is_some() {
+        println!("Next challenge:    {}", countdown(next_challenge));
+    }
+    if status.mining_period_ends.is_some() {
+        println!("Mining period end: {} (day {} of {})", countdown(period_ends),
+            status.current_day.map_or("?".to_string(), |d| d.to_string()),
+            status.max_day.map_or("?".to_string(), |d| d.to_string()));
+    }
+
+    println!("----------------------------------------------");
+    match stats {
+        Some(stats) => {
+            println!("Network receipts:  {} total, {} recent, {} wallets",
+                format_thousands(stats.total_crypto_receipts as u64),
+                format_thousands(stats.recent_crypto_receipts as u64),
+                format_thousands(stats.wallets as u64));
+            match rate.per_hour(stats.total_crypto_receipts) {
+                Some(per_hour) if per_hour > 0.0 => println!("Solution rate:     {:.0} / hour (since the ticker started)", per_hour),
+                _ => println!("Solution rate:     measuring..."),
+            }
+            if stats.crypto_receipts > 0 {
+                println!("NIGHT / solution:  {:.2} ({} NIGHT over {} receipts for {})",
+                    stats.night_allocation as f64 / stats.crypto_receipts as f64,
+                    format_thousands(stats.night_allocation as u64), stats.crypto_receipts, stats.local_address);
+            } else {
+                println!("NIGHT / solution:  no receipts yet for {}", stats.local_address);
+            }
+        }
+        None => println!("Network statistics need '--address' (any registered address; nothing is signed or sent)."),
+    }
+
+    deadlines.into_iter().flatten().filter(|&s| s > 0).min()
+}
+
+/// The refresh interval for the nearest upcoming deadline.
+fn next_poll(nearest_deadline: Option<i64>) -> Duration {
+    match nearest_deadline {
+        Some(s) if s <= IMMINENT_SECS => WATCH_POLL_IMMINENT,
+        Some(s) if s <= SOON_SECS => WATCH_POLL_SOON,
+        _ => WATCH_POLL_IDLE,
+    }
+}
+
+/// '--watch': a read-only ticker of the challenge schedule and network statistics. No key is loaded and
+/// nothing is written to the API. Runs until interrupted.
+pub fn run_watch(client: &Client, api_url: &str, address: Option<&str>) -> Result<(), String> {
+    let redraw = std::io::stdout().is_terminal();
+    let mut rate = SolutionRate { first: None };
+    let mut failure_wait = WATCH_POLL_IMMINENT;
+
+    loop {
+        let status = match api::block_on(api::fetch_challenge_status(client, api_url)) {
+            Ok(status) => status,
+            Err(e) => {
+                eprintln!("⚠️ Challenge poll failed: {}. Retrying in {}...", e, format_duration(failure_wait.as_secs_f64()));
+                std::thread::sleep(failure_wait);
+                failure_wait = (failure_wait * 2).min(WATCH_POLL_MAX);
+                continue;
+            }
+        };
+        failure_wait = WATCH_POLL_IMMINENT;
+        let stats = address.and_then(|address| match api::block_on(api::fetch_statistics(client, api_url, address)) {
+            Ok(stats) => Some(stats),
+            Err(e) => {
+                eprintln!("⚠️ Statistics poll failed: {}", e);
+                None
+            }
+        });
+
+        if redraw {
+            // Clear the screen and move the cursor home
+            print!("\x1b[2J\x1b[H");
+        }
+        let wait = next_poll(render(&status, stats.as_ref(), &mut rate));
+        println!("Refreshing in {}. Ctrl+C to stop.", format_duration(wait.as_secs_f64()));
+        std::thread::sleep(wait);
+    }
+}
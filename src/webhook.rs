@@ -0,0 +1,19 @@
+// src/webhook.rs
+
+use reqwest::Client;
+use serde::Serialize;
+
+/// POSTs a JSON payload (e.g. a finished challenge summary) to the configured webhook URL.
+pub async fn post_json<T: Serialize>(client: &Client, webhook_url: &str, payload: &T) -> Result<(), String> {
+    let response = client
+        .post(webhook_url)
+        .header("Content-Type", "application/json; charset=utf-8")
+        .json(payload)
+        .send().await.map_err(|e| format!("Network/Client Error: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Webhook returned non-success status: {}", status));
+    }
+    Ok(())
+}
@@ -0,0 +1,22 @@
+#[cfg(test)]
+mod challenge_push_tests {
+    use shadow_harvester_lib::ScavengeControl;
+
+    #[test]
+    fn nothing_is_superseded_until_a_challenge_is_pushed() {
+        let control = ScavengeControl::new(4);
+        assert!(!control.challenge_superseded("**D01C01"));
+        control.set_latest_challenge("**D01C01");
+        assert!(!control.challenge_superseded("**D01C01"));
+    }
+
+    #[test]
+    fn a_newer_challenge_supersedes_every_older_one() {
+        let control = ScavengeControl::new(4);
+        control.set_latest_challenge("**D01C02");
+        control.set_latest_challenge("**D01C03");
+        assert!(control.challenge_superseded("**D01C01"));
+        assert!(control.challenge_superseded("**D01C02"));
+        assert!(!control.challenge_superseded("**D01C03"));
+    }
+}
@@ -72,3 +72,30 @@ mod cip8_tests {
         );
     }
 }
+
+#[cfg(test)]
+mod cose_key_tests {
+    use shadow_harvester_lib::cardano::cose_key_public_key;
+
+    const PUBKEY_HEX: &str = "4497c0ef04fd9dd9b9d9abc2d8f19d8d09e69ae335c4355b7764c67e167d7f8e";
+
+    #[test]
+    /// A raw 32-byte key is returned unchanged.
+    fn test_raw_public_key() {
+        assert_eq!(cose_key_public_key(PUBKEY_HEX).unwrap(), PUBKEY_HEX);
+    }
+
+    #[test]
+    /// The key is read from label -2 of a COSE_Key as output by cardano-signer and CIP-30 wallets.
+    fn test_cose_key_public_key() {
+        // {1: 1 (OKP), 3: -8 (EdDSA), -1: 6 (Ed25519), -2: h'<pubkey>'}
+        let cose_key_hex = format!("a4010103272006215820{}", PUBKEY_HEX);
+        assert_eq!(cose_key_public_key(&cose_key_hex).unwrap(), PUBKEY_HEX);
+    }
+
+    #[test]
+    fn test_cose_key_without_public_key() {
+        assert!(cose_key_public_key("a201010327").is_err());
+        assert!(cose_key_public_key("not hex").is_err());
+    }
+}
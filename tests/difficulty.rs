@@ -0,0 +1,25 @@
+#[cfg(test)]
+mod difficulty_tests {
+    use shadow_harvester_lib::{difficulty_to_zero_bits, expected_hashes, solution_probability};
+
+    #[test]
+    fn counts_leading_zero_bits_of_the_mask() {
+        assert_eq!(difficulty_to_zero_bits("000FFFFF"), 12);
+        assert_eq!(difficulty_to_zero_bits("00007FFF"), 17);
+        assert_eq!(difficulty_to_zero_bits("FFFFFFFF"), 0);
+        assert_eq!(expected_hashes(12), 4096.0);
+    }
+
+    #[test]
+    fn probability_grows_with_time_towards_one() {
+        let bits = difficulty_to_zero_bits("00007FFF");
+        let rate = 1000.0;
+        // One expected solution's worth of hashing finds one about 63% of the time (1 - 1/e)
+        let one_eta = expected_hashes(bits) / rate;
+        assert!((solution_probability(bits, rate, one_eta) - 0.632).abs() < 0.01);
+        assert!(solution_probability(bits, rate, 10.0 * one_eta) > 0.9999);
+        assert!(solution_probability(bits, rate, one_eta / 10.0) < solution_probability(bits, rate, one_eta));
+        assert_eq!(solution_probability(bits, 0.0, one_eta), 0.0);
+        assert_eq!(solution_probability(bits, rate, -1.0), 0.0);
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod hashcore_tests {
+    use shadow_harvester_lib::hashcore::{NonceRange, Preimage, Searcher, Target};
+    use shadow_harvester_lib::{build_preimage, hash, Rom, RomGenerationType};
+    use std::sync::atomic::AtomicBool;
+
+    const FIELDS: [&str; 6] = ["addr1qtest", "**D05C10", "000FFFFF", "ab", "2026-01-01T00:00:00Z", "1"];
+
+    fn preimage() -> Preimage {
+        let [address, challenge_id, difficulty, no_pre_mine, latest_submission, hour] = FIELDS;
+        Preimage::new(address, challenge_id, difficulty, no_pre_mine, latest_submission, hour)
+    }
+
+    fn small_rom() -> Rom {
+        Rom::new(b"hashcore", RomGenerationType::TwoStep { pre_size: 1 << 16, mixing_numbers: 4 }, 1 << 20)
+    }
+
+    #[test]
+    fn preimage_matches_build_preimage() {
+        let mut preimage = preimage();
+        let [address, challenge_id, difficulty, no_pre_mine, latest_submission, hour] = FIELDS;
+        for nonce in [0, 1, 0xff, 0xdead_beef_cafe_f00d, u64::MAX] {
+            let expected = build_preimage(nonce, address, challenge_id, difficulty, no_pre_mine, latest_submission, hour);
+            assert_eq!(preimage.with_nonce(nonce), expected.as_bytes());
+        }
+    }
+
+    #[test]
+    fn searcher_hashes_like_hash() {
+        let rom = small_rom();
+        let mut searcher = Searcher::new(preimage());
+        let mut expected = preimage();
+        for nonce in [3, 42] {
+            assert_eq!(searcher.hash(&rom, nonce), hash(expected.with_nonce(nonce), &rom, 8, 256));
+        }
+    }
+
+    #[test]
+    fn search_stops_at_the_first_solution_or_when_cancelled() {
+        let rom = small_rom();
+        let mut searcher = Searcher::new(preimage());
+        let range = NonceRange { start: 10, step: 3, count: 5 };
+        // Every hash has at least zero leading zero bits
+        let solution = searcher.search(&rom, Target { zero_bits: 0 }, range, &AtomicBool::new(false)).unwrap();
+        assert_eq!(solution.nonce, 10);
+        assert_eq!(solution.hash, searcher.hash(&rom, 10));
+        assert_eq!(searcher.search(&rom, Target { zero_bits: 0 }, range, &AtomicBool::new(true)), None);
+        assert_eq!(searcher.search(&rom, Target { zero_bits: 512 }, range, &AtomicBool::new(false)), None);
+    }
+}
@@ -0,0 +1,33 @@
+#[cfg(test)]
+mod huge_pages_tests {
+    use shadow_harvester_lib::hugepages::HugePageBuffer;
+    use shadow_harvester_lib::{hash, Rom, RomGenerationType};
+
+    const ROM_SIZE: usize = 1024 * 1024;
+
+    #[test]
+    fn huge_page_rom_hashes_like_regular_rom() {
+        let gen_type = RomGenerationType::TwoStep { pre_size: 64 * 1024, mixing_numbers: 4 };
+        let regular = Rom::new(b"huge pages key", gen_type, ROM_SIZE);
+        // Neither reserved nor transparent huge pages are guaranteed on the test machine
+        let Ok(huge) = Rom::new_huge_pages(b"huge pages key", gen_type, ROM_SIZE) else { return };
+
+        assert!(huge.huge_pages().is_some());
+        assert_eq!(huge.digest.0, regular.digest.0);
+        let copy = regular.to_huge_pages().unwrap();
+        for nonce in 0u32..64 {
+            let salt = nonce.to_le_bytes();
+            assert_eq!(hash(&salt, &huge, 8, 256), hash(&salt, &regular, 8, 256));
+            assert_eq!(hash(&salt, &copy, 8, 256), hash(&salt, &regular, 8, 256));
+        }
+    }
+
+    #[test]
+    fn buffer_is_zeroed_and_writable() {
+        let Ok(mut buffer) = HugePageBuffer::alloc(3 * 1024 * 1024 + 5) else { return };
+        assert_eq!(buffer.len(), 3 * 1024 * 1024 + 5);
+        assert!(buffer.iter().all(|&b| b == 0));
+        buffer[3 * 1024 * 1024 + 4] = 0xff;
+        assert_eq!(buffer[3 * 1024 * 1024 + 4], 0xff);
+    }
+}
@@ -0,0 +1,33 @@
+#[cfg(test)]
+mod key_cache_tests {
+    use shadow_harvester_lib::cardano::*;
+    use std::sync::Arc;
+
+    // BIP39 test vector mnemonic
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art";
+
+    fn address(key_pair: &KeyPairAndAddress) -> String {
+        key_pair.2.to_bech32().unwrap()
+    }
+
+    #[test]
+    fn cached_keys_match_direct_derivation() {
+        let keys = KeyCache::new(MNEMONIC, 0, 2);
+        for index in [0, 1, 2, 3, 7] {
+            assert_eq!(address(&keys.key_pair(index)), address(&derive_key_pair_from_mnemonic(MNEMONIC, 0, index)));
+        }
+    }
+
+    #[test]
+    fn forgotten_indices_are_derived_again() {
+        let keys = KeyCache::new(MNEMONIC, 0, 0);
+        let first = keys.key_pair(3);
+        assert!(Arc::ptr_eq(&first, &keys.key_pair(3)));
+        keys.forget_below(3);
+        assert!(Arc::ptr_eq(&first, &keys.key_pair(3)));
+        keys.forget_below(4);
+        let again = keys.key_pair(3);
+        assert!(!Arc::ptr_eq(&first, &again));
+        assert_eq!(address(&first), address(&again));
+    }
+}
@@ -0,0 +1,27 @@
+#[cfg(test)]
+mod low_memory_rom_tests {
+    use shadow_harvester_lib::{hash, Rom, RomGenerationType};
+
+    const ROM_SIZE: usize = 1024 * 1024;
+
+    fn assert_same_rom(gen_type: RomGenerationType) {
+        let full = Rom::new(b"low memory key", gen_type, ROM_SIZE);
+        let low = Rom::new_low_memory(b"low memory key", gen_type, ROM_SIZE);
+
+        assert_eq!(low.digest.0, full.digest.0);
+        for nonce in 0u32..64 {
+            let salt = nonce.to_le_bytes();
+            assert_eq!(hash(&salt, &low, 8, 256), hash(&salt, &full, 8, 256));
+        }
+    }
+
+    #[test]
+    fn two_step_low_memory_rom_hashes_like_full_rom() {
+        assert_same_rom(RomGenerationType::TwoStep { pre_size: 64 * 1024, mixing_numbers: 4 });
+    }
+
+    #[test]
+    fn full_random_low_memory_rom_hashes_like_full_rom() {
+        assert_same_rom(RomGenerationType::FullRandom);
+    }
+}
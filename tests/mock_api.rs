@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod mock_api_tests {
+    use std::io::{BufRead, BufReader};
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    // Every hash meets it, so the cycle only waits for the ROM
+    const SCENARIO: &str = r#"{ "challenges": [{ "difficulty": "FFFFFFFF", "duration_secs": 3600 }] }"#;
+    const PAYMENT_KEY: &str = "abababababababababababababababababababababababababababababababab";
+    // A debug build generates the ROM slowly
+    const CYCLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+    const RECEIPT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn receipts(dir: &Path) -> Vec<String> {
+        let Ok(wallets) = std::fs::read_dir(dir.join("**D01C01").join("persistent")) else { return Vec::new() };
+        wallets.filter_map(|wallet| wallet.ok())
+            .filter_map(|wallet| std::fs::read_dir(wallet.path()).ok())
+            .flatten()
+            .filter_map(|file| file.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with("receipt"))
+            .collect()
+    }
+
+    #[test]
+    fn mines_and_submits_a_solution_against_the_mock_api() {
+        let dir = std::env::temp_dir().join(format!("shadow-harvester-mock-api-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let scenario = dir.join("scenario.json");
+        std::fs::write(&scenario, SCENARIO).unwrap();
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_shadow-harvester"))
+            .args(["--mock-api", scenario.to_str().unwrap(), "--data-dir", dir.to_str().unwrap()])
+            .args(["--payment-key", PAYMENT_KEY, "--accept-tos", "--headless", "--threads", "1", "--low-memory", "--no-rom-prefetch"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (lines, output) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if lines.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let deadline = Instant::now() + CYCLE_TIMEOUT;
+        let mut seen = Vec::new();
+        let submitted = loop {
+            match output.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(line) if line.contains("Successfully submitted solution") => break true,
+                Ok(line) => seen.push(line),
+                // Exited or timed out
+                Err(_) => break false,
+            }
+        };
+        // The receipt is saved right after the submission is reported
+        let saved_by = Instant::now() + RECEIPT_TIMEOUT;
+        while submitted && receipts(&dir).is_empty() && Instant::now() < saved_by {
+            thread::sleep(Duration::from_millis(100));
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+
+        let receipts = receipts(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(submitted, "no solution submitted; output:\n{}", seen.join("\n"));
+        assert_eq!(receipts, ["receipt.json"]);
+    }
+}
@@ -0,0 +1,34 @@
+#[cfg(test)]
+mod nonce_partition_tests {
+    use shadow_harvester_lib::NoncePartition;
+    use std::collections::HashSet;
+
+    #[test]
+    fn seed_offsets_the_start_within_the_worker_range() {
+        let unseeded = NoncePartition::new(1, 4).unwrap();
+        assert_eq!(unseeded.base(), 0x4000_0000_0000_0000);
+        assert_eq!(unseeded.with_seed(0).base(), 0x4000_0000_0000_0000);
+        // Offsets wrap inside the range instead of running into the next worker's
+        for seed in [1, 0x1234, 0x4000_0000_0000_0300, u64::MAX] {
+            let base = unseeded.with_seed(seed).base();
+            assert_eq!(base >> 62, 1, "seed {:#x} gave {:#x}", seed, base);
+            assert_eq!(base & 0xff, 0);
+        }
+    }
+
+    #[test]
+    fn small_fixed_seeds_start_at_different_nonces() {
+        let partition = NoncePartition::new(2, 3).unwrap();
+        let bases: HashSet<u64> = (1..=255).map(|seed| partition.with_seed(seed).base()).collect();
+        assert_eq!(bases.len(), 255);
+        assert!(!bases.contains(&partition.base()));
+    }
+
+    #[test]
+    fn a_single_worker_can_start_anywhere() {
+        let base = NoncePartition::default().with_seed(7).base();
+        assert_eq!(base & 0xff, 0);
+        assert!(base > u64::MAX / 256, "{:#x}", base);
+        assert_eq!(NoncePartition::default().with_seed(7).base(), base);
+    }
+}
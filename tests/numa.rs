@@ -0,0 +1,27 @@
+#[cfg(test)]
+mod numa_tests {
+    use shadow_harvester_lib::numa::{node_for_worker, parse_cpu_list, NumaMode};
+
+    #[test]
+    fn parses_sysfs_cpu_lists() {
+        assert_eq!(parse_cpu_list("0-3,8-9\n"), vec![0, 1, 2, 3, 8, 9]);
+        assert_eq!(parse_cpu_list("5"), vec![5]);
+        assert_eq!(parse_cpu_list("\n"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn workers_are_split_evenly_across_nodes() {
+        let nodes: Vec<usize> = (0..8).map(|w| node_for_worker(w, 8, 2)).collect();
+        assert_eq!(nodes, vec![0, 0, 0, 0, 1, 1, 1, 1]);
+
+        // More nodes than workers: every worker still gets a valid node
+        assert!((0..3).all(|w| node_for_worker(w, 3, 4) < 4));
+    }
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!(NumaMode::parse("Replicate").unwrap(), NumaMode::Replicate);
+        assert_eq!(NumaMode::parse("off").unwrap(), NumaMode::Off);
+        assert!(NumaMode::parse("interleave").is_err());
+    }
+}
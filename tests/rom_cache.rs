@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod rom_cache_tests {
+    use shadow_harvester_lib::{hash, Rom, RomGenerationType};
+    use std::path::PathBuf;
+
+    const ROM_SIZE: usize = 1024 * 1024;
+    const GEN_TYPE: RomGenerationType = RomGenerationType::TwoStep { pre_size: 64 * 1024, mixing_numbers: 4 };
+
+    fn cache_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("shadow-harvester-{}-{}.rom", name, std::process::id()))
+    }
+
+    #[test]
+    fn mapped_rom_hashes_like_generated_rom() {
+        let rom = Rom::new(b"rom cache key", GEN_TYPE, ROM_SIZE);
+        let path = cache_path("roundtrip");
+        rom.save(&path).unwrap();
+
+        let mapped = Rom::open_mapped(&path, ROM_SIZE).unwrap();
+        assert_eq!(mapped.digest.0, rom.digest.0);
+        for salt in [&b"nonce 0"[..], b"nonce 1", b"nonce 2"] {
+            assert_eq!(hash(salt, &mapped, 8, 256), hash(salt, &rom, 8, 256));
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn open_mapped_rejects_corrupt_cache() {
+        let rom = Rom::new(b"rom cache key", GEN_TYPE, ROM_SIZE);
+        let path = cache_path("corrupt");
+        rom.save(&path).unwrap();
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0x01;
+        std::fs::write(&path, bytes).unwrap();
+
+        assert!(Rom::open_mapped(&path, ROM_SIZE).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn open_mapped_rejects_wrong_size() {
+        let rom = Rom::new(b"rom cache key", GEN_TYPE, ROM_SIZE);
+        let path = cache_path("wrong-size");
+        rom.save(&path).unwrap();
+
+        assert!(Rom::open_mapped(&path, 2 * ROM_SIZE).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}